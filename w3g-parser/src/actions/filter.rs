@@ -0,0 +1,449 @@
+//! Action stream filtering.
+//!
+//! An [`ActionFilter`] selects actions by player, action type, time and the
+//! `FourCC` code of the ability, unit or item they use. Filters are built with
+//! the builder methods or parsed from a query such as
+//! `type=UnitAbilityNoTarget and player=2 and time<300000`.
+//!
+//! # Query Syntax
+//!
+//! A query is a list of conditions joined with `and`. Each condition is a
+//! field, an operator and a value:
+//!
+//! | Field | Operators | Value |
+//! |-------|-----------|-------|
+//! | `player` | `=` `!=` `<` `<=` `>` `>=` | Player ID |
+//! | `type` | `=` `!=` | Action type name, e.g. `UnitAbilityNoTarget` (case-insensitive) |
+//! | `time` | `=` `!=` `<` `<=` `>` `>=` | Milliseconds, or `MM:SS` / `HH:MM:SS` |
+//! | `code` | `=` `!=` | Object code, e.g. `hpea` or `0x000D0003` |
+//!
+//! With `=` and `!=`, the player, type and code fields take a comma-separated
+//! list of values: `type=UnitAbilityNoTarget,SelectSubgroup` matches either type
+//! and `player!=1,2` matches neither player.
+//!
+//! Codes are written as in the [`export`](crate::export) tables: `FourCC`
+//! codes in canonical form and numeric order IDs as `0x%08X`.
+//!
+//! # Example
+//!
+//! ```
+//! use w3g_parser::actions::{Action, ActionFilter, ActionType};
+//!
+//! let filter: ActionFilter = "type=TriggerEscapeKey and player=2 and time<5:00".parse().unwrap();
+//! assert!(filter.matches(&Action::new(2, ActionType::TriggerEscapeKey, 60_000)));
+//! assert!(!filter.matches(&Action::new(2, ActionType::TriggerEscapeKey, 300_000)));
+//! assert!(!filter.matches(&Action::new(1, ActionType::TriggerEscapeKey, 60_000)));
+//! ```
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::ability::AbilityCode;
+use super::types::{Action, ActionType};
+
+/// Result type of filter parsing.
+type Result<T> = std::result::Result<T, FilterError>;
+
+/// An action filter query or builder argument that could not be parsed.
+///
+/// See the [module documentation](self) for the query syntax.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid action filter: {reason}")]
+pub struct FilterError {
+    /// Description of the problem.
+    pub reason: String,
+}
+
+/// Operators in the order they are tried; two-character operators first.
+const OPERATORS: [(&str, Comparison); 7] = [
+    ("!=", Comparison::Ne),
+    ("<=", Comparison::Le),
+    (">=", Comparison::Ge),
+    ("==", Comparison::Eq),
+    ("=", Comparison::Eq),
+    ("<", Comparison::Lt),
+    (">", Comparison::Gt),
+];
+
+/// Comparison operator of a condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// Returns whether `value <op> operand` holds.
+    fn holds<T: Ord>(self, value: &T, operand: &T) -> bool {
+        match self {
+            Comparison::Eq => value == operand,
+            Comparison::Ne => value != operand,
+            Comparison::Lt => value < operand,
+            Comparison::Le => value <= operand,
+            Comparison::Gt => value > operand,
+            Comparison::Ge => value >= operand,
+        }
+    }
+
+    /// Returns whether the operator is `=` or `!=`.
+    fn is_equality(self) -> bool {
+        matches!(self, Comparison::Eq | Comparison::Ne)
+    }
+}
+
+/// A single condition of a filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    /// Player ID compared with one or more IDs.
+    Player(Comparison, Vec<u8>),
+    /// Action type is (or, if negated, is not) one of the names.
+    Type { negated: bool, names: Vec<&'static str> },
+    /// Timestamp compared with a time in milliseconds.
+    Time(Comparison, u32),
+    /// Object code is (or, if negated, is not) one of the codes.
+    Code { negated: bool, codes: Vec<String> },
+}
+
+impl Condition {
+    fn matches(&self, action: &Action) -> bool {
+        match self {
+            Condition::Player(op, ids) => match op {
+                Comparison::Eq => ids.contains(&action.player_id),
+                Comparison::Ne => !ids.contains(&action.player_id),
+                _ => ids.iter().all(|id| op.holds(&action.player_id, id)),
+            },
+            Condition::Type { negated, names } => {
+                names.contains(&action.action_type.type_name()) != *negated
+            }
+            Condition::Time(op, ms) => op.holds(&action.timestamp_ms, ms),
+            Condition::Code { negated, codes } => {
+                let code = action.action_type.object_code().map(AbilityCode::label);
+                code.is_some_and(|code| codes.contains(&code)) != *negated
+            }
+        }
+    }
+}
+
+/// A filter selecting actions by player, type, time and object code.
+///
+/// All conditions must hold for an action to match; an empty filter matches
+/// every action. See the [module documentation](self) for the query syntax.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionFilter {
+    conditions: Vec<Condition>,
+}
+
+impl ActionFilter {
+    /// Creates a filter that matches every action.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a filter query.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FilterError`] if a condition has an unknown
+    /// field, operator or action type, or a value that cannot be parsed.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut filter = Self::new();
+        for condition in split_conditions(query) {
+            filter.conditions.push(parse_condition(condition)?);
+        }
+        Ok(filter)
+    }
+
+    /// Only matches actions of the given players.
+    ///
+    /// An empty list leaves the filter unchanged.
+    #[must_use]
+    pub fn players(self, ids: impl IntoIterator<Item = u8>) -> Self {
+        let ids: Vec<u8> = ids.into_iter().collect();
+        self.push_if(!ids.is_empty(), Condition::Player(Comparison::Eq, ids))
+    }
+
+    /// Only matches actions of the given types, compared case-insensitively
+    /// with [`ActionType::type_name`].
+    ///
+    /// An empty list leaves the filter unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FilterError`] for names that are not in
+    /// [`ActionType::NAMES`].
+    pub fn action_types<S: AsRef<str>>(self, names: impl IntoIterator<Item = S>) -> Result<Self> {
+        let names = names
+            .into_iter()
+            .map(|name| type_name(name.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self.push_if(!names.is_empty(), Condition::Type { negated: false, names }))
+    }
+
+    /// Only matches actions using one of the given codes, written in canonical
+    /// form such as `hpea`, or as `0x%08X` for numeric order IDs.
+    ///
+    /// An empty list leaves the filter unchanged.
+    #[must_use]
+    pub fn codes<S: Into<String>>(self, codes: impl IntoIterator<Item = S>) -> Self {
+        let codes: Vec<String> = codes.into_iter().map(Into::into).collect();
+        self.push_if(!codes.is_empty(), Condition::Code { negated: false, codes })
+    }
+
+    /// Only matches actions at or after `ms`.
+    #[must_use]
+    pub fn from_ms(self, ms: u32) -> Self {
+        self.push_if(true, Condition::Time(Comparison::Ge, ms))
+    }
+
+    /// Only matches actions before `ms`.
+    #[must_use]
+    pub fn to_ms(self, ms: u32) -> Self {
+        self.push_if(true, Condition::Time(Comparison::Lt, ms))
+    }
+
+    /// Combines two filters; the result matches actions matched by both.
+    #[must_use]
+    pub fn and(mut self, other: ActionFilter) -> Self {
+        self.conditions.extend(other.conditions);
+        self
+    }
+
+    /// Returns `true` if the filter has no conditions.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// Returns whether `action` satisfies all conditions.
+    #[must_use]
+    pub fn matches(&self, action: &Action) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(action))
+    }
+
+    fn push_if(mut self, push: bool, condition: Condition) -> Self {
+        if push {
+            self.conditions.push(condition);
+        }
+        self
+    }
+}
+
+impl FromStr for ActionFilter {
+    type Err = FilterError;
+
+    fn from_str(query: &str) -> Result<Self> {
+        Self::parse(query)
+    }
+}
+
+fn invalid(reason: String) -> FilterError {
+    FilterError { reason }
+}
+
+/// Splits a query at the word `and`, in any case.
+fn split_conditions(query: &str) -> Vec<&str> {
+    let mut conditions = Vec::new();
+    let mut start = 0;
+    let mut words = query.split_whitespace().peekable();
+    let mut offset = 0;
+    while let Some(word) = words.next() {
+        let word_start = offset + query[offset..].find(word).unwrap_or(0);
+        offset = word_start + word.len();
+        if word.eq_ignore_ascii_case("and") {
+            conditions.push(query[start..word_start].trim());
+            start = offset;
+        }
+        if words.peek().is_none() {
+            conditions.push(query[start..].trim());
+        }
+    }
+    conditions
+}
+
+fn parse_condition(condition: &str) -> Result<Condition> {
+    if condition.is_empty() {
+        return Err(invalid("empty condition".to_string()));
+    }
+    let Some(position) = condition.find(['=', '!', '<', '>']) else {
+        return Err(invalid(format!("{condition:?} has no operator")));
+    };
+    let field = condition[..position].trim();
+    let rest = &condition[position..];
+    let Some(&(symbol, op)) = OPERATORS.iter().find(|(symbol, _)| rest.starts_with(symbol))
+    else {
+        return Err(invalid(format!("{condition:?} has no operator")));
+    };
+    let value = rest[symbol.len()..].trim();
+    if value.is_empty() {
+        return Err(invalid(format!("{condition:?} has no value")));
+    }
+    let values: Vec<&str> = value.split(',').map(str::trim).collect();
+    if values.len() > 1 && !op.is_equality() {
+        return Err(invalid(format!("{symbol} takes a single value in {condition:?}")));
+    }
+
+    match field.to_ascii_lowercase().as_str() {
+        "player" => {
+            let ids = values
+                .iter()
+                .map(|id| id.parse().map_err(|_| invalid(format!("invalid player ID {id:?}"))))
+                .collect::<Result<Vec<u8>>>()?;
+            Ok(Condition::Player(op, ids))
+        }
+        "type" => {
+            let names = values.iter().map(|name| type_name(name)).collect::<Result<_>>()?;
+            Ok(Condition::Type {
+                negated: equality_negated(op, field)?,
+                names,
+            })
+        }
+        "time" => Ok(Condition::Time(op, parse_time(value)?)),
+        "code" => Ok(Condition::Code {
+            negated: equality_negated(op, field)?,
+            codes: values.iter().map(|code| (*code).to_string()).collect(),
+        }),
+        _ => Err(invalid(format!(
+            "unknown field {field:?}, expected player, type, time or code"
+        ))),
+    }
+}
+
+/// Returns whether an `=`/`!=` operator is negated, rejecting ordering operators.
+fn equality_negated(op: Comparison, field: &str) -> Result<bool> {
+    match op {
+        Comparison::Eq => Ok(false),
+        Comparison::Ne => Ok(true),
+        _ => Err(invalid(format!("{field} only supports = and !="))),
+    }
+}
+
+/// Looks up an action type name case-insensitively.
+fn type_name(name: &str) -> Result<&'static str> {
+    ActionType::NAMES
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or_else(|| invalid(format!("unknown action type {name:?}")))
+}
+
+/// Parses a time in milliseconds, or as `MM:SS` or `HH:MM:SS`.
+fn parse_time(value: &str) -> Result<u32> {
+    let error = || invalid(format!("invalid time {value:?}"));
+    if !value.contains(':') {
+        return value.parse().map_err(|_| error());
+    }
+
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return Err(error());
+    }
+    let mut seconds: u32 = 0;
+    for part in parts {
+        let part: u32 = part.parse().map_err(|_| error())?;
+        seconds = seconds
+            .checked_mul(60)
+            .and_then(|s| s.checked_add(part))
+            .ok_or_else(error)?;
+    }
+    seconds.checked_mul(1000).ok_or_else(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(player_id: u8, unit_code: [u8; 4], timestamp_ms: u32) -> Action {
+        let mut raw = unit_code;
+        raw.reverse();
+        let order = ActionType::UnitAbilityNoTarget {
+            flags: 0,
+            ability_code: raw,
+        };
+        Action::new(player_id, order, timestamp_ms)
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let filter = ActionFilter::parse("type=UnitAbilityNoTarget and player=2 and time<300000").unwrap();
+        assert!(filter.matches(&build(2, *b"hpea", 1000)));
+        assert!(!filter.matches(&build(1, *b"hpea", 1000)));
+        assert!(!filter.matches(&build(2, *b"hpea", 300_000)));
+        assert!(!filter.matches(&Action::new(2, ActionType::TriggerEscapeKey, 1000)));
+
+        let filter: ActionFilter = "TYPE != triggerescapekey AND code=hpea,hfoo".parse().unwrap();
+        assert!(filter.matches(&build(1, *b"hfoo", 0)));
+        assert!(!filter.matches(&build(1, *b"hkni", 0)));
+        assert!(!filter.matches(&Action::new(1, ActionType::TriggerEscapeKey, 0)));
+
+        let filter = ActionFilter::parse("player!=1,2 and time>=1:00").unwrap();
+        assert!(filter.matches(&build(3, *b"hpea", 60_000)));
+        assert!(!filter.matches(&build(2, *b"hpea", 60_000)));
+        assert!(!filter.matches(&build(3, *b"hpea", 59_999)));
+
+        assert!(ActionFilter::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        for query in [
+            "speed=fast",
+            "type=Buildtrains",
+            "type=BuildTrain",
+            "type=Movement",
+            "type<UnitAbilityNoTarget",
+            "player=abc",
+            "player<1,2",
+            "time<5:00:00:00",
+            "player",
+            "player=",
+            "player=1 and",
+        ] {
+            let err = ActionFilter::parse(query).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid action filter"), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_numeric_code_matches_export_label() {
+        let order = Action::new(
+            1,
+            ActionType::UnitAbilityNoTarget {
+                flags: 0,
+                ability_code: 0x000D_0003_u32.to_le_bytes(),
+            },
+            0,
+        );
+        let filter = ActionFilter::parse("code=0x000D0003").unwrap();
+        assert!(filter.matches(&order));
+        assert!(!filter.matches(&build(1, *b"hpea", 0)));
+    }
+
+    #[test]
+    fn test_builder() {
+        let filter = ActionFilter::new()
+            .players([1, 3])
+            .action_types(["unitabilitynotarget"])
+            .unwrap()
+            .codes(["hpea"])
+            .from_ms(1000)
+            .to_ms(2000);
+        assert!(filter.matches(&build(3, *b"hpea", 1000)));
+        assert!(!filter.matches(&build(3, *b"hpea", 2000)));
+        assert!(!filter.matches(&build(2, *b"hpea", 1500)));
+        assert!(!filter.matches(&build(1, *b"hfoo", 1500)));
+
+        let empty = ActionFilter::new().players([]).codes(Vec::<String>::new());
+        assert!(empty.is_empty());
+        assert!(ActionFilter::new().action_types(["Bogus"]).is_err());
+        assert_eq!(
+            ActionFilter::parse("player=1").unwrap().and(ActionFilter::new().from_ms(5)),
+            ActionFilter::parse("player=1 and time>=5").unwrap()
+        );
+    }
+}
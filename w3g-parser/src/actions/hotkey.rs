@@ -20,6 +20,7 @@ use std::fmt;
 /// Note: The exact format and additional data is still under investigation.
 /// Some hotkey actions may include unit IDs for assign operations.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HotkeyAction {
     /// Control group number (0-9).
     pub group: u8,
@@ -47,7 +48,7 @@ impl HotkeyAction {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if marker doesn't match
+    /// - `ParserError::UnexpectedRecord` if marker doesn't match
     /// - `ParserError::UnexpectedEof` if data is truncated
     pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
         if data.len() < Self::MIN_SIZE {
@@ -55,13 +56,11 @@ impl HotkeyAction {
         }
 
         if data[0] != Self::MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid hotkey marker: expected 0x{:02X}, found 0x{:02X}",
-                    Self::MARKER,
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{:02X}", Self::MARKER),
+            ));
         }
 
         let group = data[1];
@@ -111,6 +110,7 @@ impl fmt::Display for HotkeyAction {
 
 /// Type of hotkey operation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HotkeyOperation {
     /// Assign current selection to group (Ctrl+N).
     Assign,
@@ -199,7 +199,7 @@ mod tests {
         let data: &[u8] = &[0x16, 0x00, 0x00]; // Wrong marker
 
         let result = HotkeyAction::parse(data);
-        assert!(matches!(result, Err(ParserError::InvalidHeader { .. })));
+        assert!(matches!(result, Err(ParserError::UnexpectedRecord { .. })));
     }
 
     #[test]
@@ -15,12 +15,26 @@
 //!
 //! | Type | Subcommand | Description |
 //! |------|------------|-------------|
-//! | 0x00 | 0x0D | Move/Attack command with coordinates |
-//! | 0x0F | 0x00 | Instant ability |
+//! | 0x01 | - | Pause game |
+//! | 0x02 | - | Resume game |
+//! | 0x03 | speed | Set game speed |
+//! | 0x04 / 0x05 | - | Increase / decrease game speed |
+//! | 0x06 | - | Save game |
+//! | 0x60 | - | Map trigger chat command |
+//! | 0x61 | - | ESC pressed in trigger |
+//! | 0x62 | - | Scenario trigger |
+//! | 0x66 / 0x67 | - | Hero skill / building submenu |
+//! | 0x6B | - | Game cache sync (W3MMD) |
+//! | 0x75 | event | Arrow key pressed or released |
+//! | 0x10 | - | Unit order without target |
+//! | 0x11 | - | Unit order with ground target |
+//! | 0x12 | - | Unit order with unit target |
+//! | 0x13 | - | Give or drop item |
+//! | 0x14 | - | Unit order with two targets |
 //! | 0x16 | - | Unit selection |
 //! | 0x17 | - | Control group hotkey |
-//! | 0x1A | 0x00 | Ability with selection |
-//! | 0x1A | 0x19 | Direct ability |
+//! | 0x19 | - | Select subgroup |
+//! | 0x1A | - | Pre-subselection |
 //!
 //! # Example
 //!
@@ -49,15 +63,23 @@
 //! ```
 
 mod ability;
+mod filter;
 mod hotkey;
 mod movement;
 mod parser;
+mod pause;
 mod selection;
 mod types;
+mod w3mmd;
 
-pub use ability::{AbilityAction, AbilityCode, AbilityWithSelectionAction, InstantAbilityAction, Race};
+pub use ability::{AbilityCode, Race};
+pub use filter::{ActionFilter, FilterError};
 pub use hotkey::{HotkeyAction, HotkeyOperation};
-pub use movement::{MovementAction, MovementType, Position};
+pub use movement::Position;
 pub use parser::{ActionContext, ActionIterator, ActionStatistics};
+pub use pause::{PauseInterval, PauseTimeline};
 pub use selection::{SelectionAction, SelectionMode};
-pub use types::{Action, ActionType};
+pub use types::{Action, ActionType, ArrowKey, GameSpeed};
+pub use w3mmd::{
+    MmdEvent, MmdEventDef, MmdFlag, MmdPlayer, MmdValue, MmdVarDef, W3mmdStats, W3MMD_FILENAME,
+};
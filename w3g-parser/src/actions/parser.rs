@@ -3,11 +3,10 @@
 //! This module provides the `ActionIterator` for parsing individual player actions
 //! from `TimeFrame` action data.
 
-use super::ability::{AbilityAction, AbilityWithSelectionAction, InstantAbilityAction};
-use super::hotkey::HotkeyAction;
-use super::movement::MovementAction;
+use super::hotkey::{HotkeyAction, HotkeyOperation};
 use super::selection::SelectionAction;
-use super::types::{Action, ActionType};
+use super::types::{Action, ActionType, ArrowKey, GameSpeed};
+use crate::binary::read_u16_le;
 use crate::error::{ParserError, Result};
 
 /// Context for parsing actions within a `TimeFrame`.
@@ -38,10 +37,16 @@ impl ActionContext {
 ///
 /// # W3G Format Structure
 ///
-/// The action data contains sequential player actions:
-/// - 1 byte: PlayerID (1-15)
-/// - 1 byte: ActionType
-/// - n bytes: Action data (varies by action type)
+/// The action data is a sequence of command blocks, one per player:
+/// - 1 byte: Player ID (1-15)
+/// - 2 bytes: Block length (u16 LE)
+/// - n bytes: The player's actions, back to back
+///
+/// Each action starts with its action type byte, followed by data that
+/// varies by action type. Blocks are framed by their length, so an action
+/// parsed with the wrong length only affects the rest of its block. After an
+/// action fails to parse, the iterator yields the error and continues with
+/// the next block.
 ///
 /// # Example
 ///
@@ -70,6 +75,15 @@ pub struct ActionIterator<'a> {
 
     /// Whether iteration has finished.
     finished: bool,
+
+    /// Player ID and end offset of the command block being parsed.
+    block: Option<(u8, usize)>,
+
+    /// Offset of the most recently parsed action.
+    action_offset: usize,
+
+    /// Number of actions yielded so far.
+    action_count: usize,
 }
 
 impl<'a> ActionIterator<'a> {
@@ -86,15 +100,35 @@ impl<'a> ActionIterator<'a> {
             offset: 0,
             context,
             finished: false,
+            block: None,
+            action_offset: 0,
+            action_count: 0,
         }
     }
 
     /// Returns the current offset in the data.
+    ///
+    /// After an action, this is the offset after its last byte. After an
+    /// error, this is the end of the command block that was skipped, or the
+    /// end of the data when a block header was invalid.
     #[must_use]
     pub fn current_offset(&self) -> usize {
         self.offset
     }
 
+    /// Returns the offset of the most recently parsed action, or of the
+    /// action that failed to parse.
+    #[must_use]
+    pub fn action_offset(&self) -> usize {
+        self.action_offset
+    }
+
+    /// Returns the player ID and end offset of the current command block.
+    #[must_use]
+    pub fn current_block(&self) -> Option<(u8, usize)> {
+        self.block
+    }
+
     /// Returns the remaining bytes to parse.
     #[must_use]
     pub fn remaining_bytes(&self) -> usize {
@@ -107,628 +141,486 @@ impl<'a> ActionIterator<'a> {
         self.finished
     }
 
-    /// Parses the next action from the data.
-    fn parse_next(&mut self) -> Result<Action> {
-        if self.offset >= self.data.len() {
-            return Err(ParserError::unexpected_eof(1, 0));
-        }
-
+    /// Reads the command block header at the current offset.
+    ///
+    /// Returns the player ID and the end offset of the block.
+    fn read_block_header(&self) -> Result<(u8, usize)> {
         let data = &self.data[self.offset..];
 
-        // First byte should be player ID (1-15)
         let player_id = data[0];
         if player_id == 0 || player_id > 15 {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid player ID {} at offset {}, expected 1-15",
-                    player_id, self.offset
-                ),
+            return Err(ParserError::InvalidAction {
+                offset: 0,
+                path: String::new(),
+                player_id,
+                type_byte: data.get(3).copied().unwrap_or(0),
             });
         }
 
-        // Check we have at least 2 bytes for action type
-        if data.len() < 2 {
-            return Err(ParserError::unexpected_eof(2, data.len()));
+        let len = usize::from(read_u16_le(data, 1)?);
+        if data.len() < 3 + len {
+            return Err(ParserError::unexpected_eof(3 + len, data.len()));
         }
+        Ok((player_id, self.offset + 3 + len))
+    }
 
-        let action_type_byte = data[1];
-        let subcommand = data.get(2).copied();
-
-        // Dispatch based on action type
+    /// Parses the next action of the current command block.
+    ///
+    /// An action that does not fit in its block is reported as
+    /// `InvalidAction` at offset 0, the start of the action.
+    fn parse_next(&mut self, player_id: u8, block_end: usize) -> Result<Action> {
+        let data = &self.data[self.offset..block_end];
         let (action_type, bytes_consumed) =
-            Self::parse_action_type(action_type_byte, subcommand, &data[1..])?;
-
-        // Total consumed includes player ID byte
-        let total_consumed = 1 + bytes_consumed;
-        self.offset += total_consumed;
-
+            Self::parse_action_type(data).map_err(|_| ParserError::InvalidAction {
+                offset: 0,
+                path: String::new(),
+                player_id,
+                type_byte: data[0],
+            })?;
+
+        self.offset += bytes_consumed.max(1);
         Ok(Action::new(player_id, action_type, self.context.timestamp_ms))
     }
 
-    /// Parses the action type from the remaining data.
-    fn parse_action_type(
-        action_type: u8,
-        subcommand: Option<u8>,
-        data: &[u8],
-    ) -> Result<(ActionType, usize)> {
-        match (action_type, subcommand) {
-            // Pattern: 0x01 with various subcommands - Reforged markers
-            (0x01, Some(0x00)) if data.len() >= 3 && data.get(2) == Some(&0x67) => {
-                // Structure: 0x01 0x00 0x67 (3 bytes)
+    /// Parses the action at the start of `data`, which ends with its block.
+    ///
+    /// Returns the action type and the size of the action in bytes. Action
+    /// types with a known layout but no variant of their own are returned as
+    /// [`ActionType::Unknown`] of that size; any other type byte takes up the
+    /// rest of the block.
+    #[allow(clippy::too_many_lines)]
+    fn parse_action_type(data: &[u8]) -> Result<(ActionType, usize)> {
+        match data[0] {
+            // Game control actions (0x01-0x07)
+            0x01 => Ok((ActionType::PauseGame, 1)),
+            0x02 => Ok((ActionType::ResumeGame, 1)),
+            0x03 => {
+                expect_size(data, 2)?;
+                match GameSpeed::from_byte(data[1]) {
+                    Some(speed) => Ok((ActionType::SetGameSpeed { speed }, 2)),
+                    None => Ok(unknown_action(data, Some(2))),
+                }
+            }
+            0x04 => Ok((ActionType::IncreaseGameSpeed, 1)),
+            0x05 => Ok((ActionType::DecreaseGameSpeed, 1)),
+            // Structure: 0x06 [filename\0]
+            0x06 => {
+                let (filename, consumed) = read_cstr(data, 1)?;
+                Ok((ActionType::SaveGame { filename }, consumed))
+            }
+            // Structure: 0x07 [unknown u32]
+            0x07 => {
+                expect_size(data, 5)?;
+                let unknown = u32_at(data, 1);
+                Ok((ActionType::SaveGameFinished { unknown }, 5))
+            }
+
+            // Unit orders (0x10-0x14) share a common prefix:
+            // [flags u16] [order ID u32] [unknown 8 bytes]
+            // Order IDs are FourCCs (e.g. "hpea") or numeric (e.g. 0x000D0003)
+            0x10 => {
+                expect_size(data, 15)?;
                 Ok((
-                    ActionType::BasicCommand {
-                        command_id: 0x01006700,
+                    ActionType::UnitAbilityNoTarget {
+                        flags: u16_at(data, 1),
+                        ability_code: code_at(data, 3),
                     },
-                    3,
+                    15,
                 ))
             }
-            // 0x01 0x0D pattern (2 bytes) - Reforged marker
-            (0x01, Some(0x0D)) => {
+            // Structure: [prefix] [x f32] [y f32]
+            0x11 => {
+                expect_size(data, 23)?;
                 Ok((
-                    ActionType::BasicCommand {
-                        command_id: 0x01000D00,
+                    ActionType::UnitAbilityGroundTarget {
+                        flags: u16_at(data, 1),
+                        ability_code: code_at(data, 3),
+                        x: f32_at(data, 15),
+                        y: f32_at(data, 19),
                     },
-                    2,
+                    23,
                 ))
             }
-            // 0x01 0x00 with selection (0x66 = 102 decimal, check for 0x16)
-            (0x01, Some(0x00)) if data.len() >= 3 => {
-                // Short form: 0x01 0x00 [byte] (3 bytes)
+            // Structure: [prefix] [x f32] [y f32] [target object ID, 8 bytes]
+            0x12 => {
+                expect_size(data, 31)?;
                 Ok((
-                    ActionType::BasicCommand {
-                        command_id: 0x01000000 | (data.get(2).copied().unwrap_or(0) as u32),
+                    ActionType::UnitAbilityUnitTarget {
+                        flags: u16_at(data, 1),
+                        ability_code: code_at(data, 3),
+                        target_unit: u32_at(data, 23),
+                        x: f32_at(data, 15),
+                        y: f32_at(data, 19),
                     },
-                    3,
+                    31,
                 ))
             }
-
-            // Pattern 1: 0x0C (Selection Shortcut)
-            (0x0C, Some(0x00)) => {
-                // Check if data[2] == 0x16 for long form selection
-                if data.len() >= 3 && data.get(2) == Some(&0x16) {
-                    // Long form: 0x0C 0x00 0x16 [count] [mode] 0x00 [unit_ids...]
-                    // Parse as Selection starting at offset 2
-                    let (sel, consumed) = SelectionAction::parse(&data[2..])?;
-                    Ok((ActionType::Selection(sel), 2 + consumed))
-                } else {
-                    // Short form: 0x0C 0x00 (2 bytes) - parse as BasicCommand
-                    Ok((
-                        ActionType::BasicCommand {
-                            command_id: 0x0C000000, // Unique ID for short form
-                        },
-                        2,
-                    ))
-                }
-            }
-
-            // Pattern 3: 0x2C (Movement Wrapper) - 24 bytes
-            (0x2C, Some(0x00)) => {
-                // Structure: 0x2C 0x00 0x14 0x00 0x00 0x03 0x00 0x0D 0x00 [8 bytes target] [8 bytes coords]
-                // Check for expected header structure
-                if data.len() >= 24
-                    && data.get(2) == Some(&0x14)
-                    && data.get(3) == Some(&0x00)
-                    && data.get(4) == Some(&0x00)
-                    && data.get(5) == Some(&0x03)
-                    && data.get(6) == Some(&0x00)
-                    && data.get(7) == Some(&0x0D)
-                    && data.get(8) == Some(&0x00)
-                {
-                    // Extract embedded movement data starting at offset 7
-                    // The embedded movement is: 0x00 0x0D [2 flags] [8 target] [8 coords]
-                    // We need to construct a proper movement buffer for parsing
-                    let mut mov_buffer = Vec::with_capacity(28);
-                    mov_buffer.push(0x00); // Movement marker
-                    mov_buffer.push(0x0D); // Move subcommand
-                    mov_buffer.extend_from_slice(&[0x00, 0x00]); // Flags placeholder
-                    mov_buffer.extend_from_slice(&data[9..17]); // Target (8 bytes)
-                    mov_buffer.extend_from_slice(&data[17..24]); // Coords (7 bytes visible, pad to 8)
-                    // Pad remaining bytes for full movement structure
-                    while mov_buffer.len() < 28 {
-                        mov_buffer.push(0x00);
-                    }
-
-                    // Parse the movement from constructed buffer
-                    let (mov, _) = MovementAction::parse_with_subcommand(&mov_buffer, 0x0D)?;
-                    Ok((ActionType::Movement(mov), 24))
-                } else {
-                    // Doesn't match expected structure, fall through to unknown
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
-            }
-
-            // Empty/keepalive 0x00 action (no subcommand or single byte)
-            // This appears in Reforged replays as a sync/heartbeat marker
-            (0x00, None) | (0x00, Some(0x00)) if data.len() <= 2 => {
-                // Consume just the type byte (or type + zero byte)
-                let consumed = data.len().min(2);
+            // Structure: [prefix] [x f32] [y f32] [target object ID, 8 bytes]
+            // [item object ID, 8 bytes]
+            0x13 => {
+                expect_size(data, 39)?;
                 Ok((
-                    ActionType::BasicCommand {
-                        command_id: 0x00000000, // Keepalive/sync
+                    ActionType::GiveDropItem {
+                        flags: u16_at(data, 1),
+                        item_code: code_at(data, 3),
+                        target_unit: u32_at(data, 23),
+                        x: f32_at(data, 15),
+                        y: f32_at(data, 19),
                     },
-                    consumed,
+                    39,
                 ))
             }
-
-            // Movement commands (0x00 with various subcommands)
-            // 0x0D = Move, 0x0E = Attack-move, 0x0F = Patrol, 0x10 = Hold, 0x12 = Smart-click
-            (0x00, Some(sub @ (0x0D | 0x0E | 0x0F | 0x10 | 0x12))) => {
-                let (mov, consumed) = MovementAction::parse_with_subcommand(data, sub)?;
-                Ok((ActionType::Movement(mov), consumed))
-            }
-
-            // Instant ability (0x0F 0x00)
-            (0x0F, Some(0x00)) => {
-                let (ab, consumed) = InstantAbilityAction::parse(data)?;
-                Ok((ActionType::InstantAbility(ab), consumed))
+            // Structure: [prefix] [x1 f32] [y1 f32] [order ID 2 u32]
+            // [unknown 9 bytes] [x2 f32] [y2 f32]
+            0x14 => {
+                expect_size(data, 44)?;
+                Ok((
+                    ActionType::UnitAbilityTwoTargets {
+                        flags: u16_at(data, 1),
+                        ability_code: code_at(data, 3),
+                        x1: f32_at(data, 15),
+                        y1: f32_at(data, 19),
+                        x2: f32_at(data, 36),
+                        y2: f32_at(data, 40),
+                    },
+                    44,
+                ))
             }
 
             // Selection (0x16)
-            (0x16, _) => {
+            0x16 => {
                 let (sel, consumed) = SelectionAction::parse(data)?;
                 Ok((ActionType::Selection(sel), consumed))
             }
 
-            // Hotkey (0x17)
-            (0x17, _) => {
-                let (hk, consumed) = HotkeyAction::parse(data)?;
-                Ok((ActionType::Hotkey(hk), consumed))
-            }
-
-            // Ability with selection (0x1A 0x00)
-            (0x1A, Some(0x00)) => {
-                let (ab, consumed) = AbilityWithSelectionAction::parse(data)?;
-                Ok((ActionType::AbilityWithSelection(ab), consumed))
-            }
-
-            // Direct ability (0x1A 0x19)
-            (0x1A, Some(0x19)) => {
-                let (ab, consumed) = AbilityAction::parse(data)?;
-                Ok((ActionType::Ability(ab), consumed))
-            }
-
-            // ESC key (0x18) - cancel current action
-            (0x18, _) => {
-                // ESC consumes just the action type byte
-                Ok((ActionType::EscapeKey, 1))
-            }
-
-            // Item action (0x1B)
-            (0x1B, _) => {
-                // Item actions: type(1) + flags(1) + item_id(4) + target(8) = 14 bytes
-                let consumed = 14.min(data.len());
-                let item_id = if data.len() >= 6 {
-                    u32::from_le_bytes([data[2], data[3], data[4], data[5]])
-                } else {
-                    0
-                };
-                Ok((ActionType::ItemAction { item_id }, consumed))
-            }
-
-            // Basic command (0x1C) - stop, hold position, patrol
-            (0x1C, _) => {
-                // Basic commands: type(1) + flags(1) + command_id(4) + target(8) = 14 bytes
-                let consumed = 14.min(data.len());
-                let command_id = if data.len() >= 6 {
-                    u32::from_le_bytes([data[2], data[3], data[4], data[5]])
-                } else {
-                    0
+            // Assign control group (0x17)
+            // Structure: 0x17 [group] [count u16] [object IDs, 8 bytes each]
+            0x17 => {
+                expect_size(data, 4)?;
+                let consumed = 4 + usize::from(u16_at(data, 2)) * 8;
+                expect_size(data, consumed)?;
+                let hotkey = HotkeyAction {
+                    group: data[1],
+                    operation: HotkeyOperation::Assign,
                 };
-                Ok((ActionType::BasicCommand { command_id }, consumed))
+                Ok((ActionType::Hotkey(hotkey), consumed))
             }
-
-            // Build/Train command (0x1D)
-            (0x1D, _) => {
-                // Build/train: type(1) + flags(1) + unit_code(4) + position(8+) = ~14 bytes
-                let consumed = 14.min(data.len());
-                let unit_code = if data.len() >= 6 {
-                    [data[2], data[3], data[4], data[5]]
-                } else {
-                    [0, 0, 0, 0]
+            // Select control group (0x18)
+            // Structure: 0x18 [group] [unknown]
+            0x18 => {
+                expect_size(data, 3)?;
+                let hotkey = HotkeyAction {
+                    group: data[1],
+                    operation: HotkeyOperation::Select,
                 };
-                Ok((ActionType::BuildTrain { unit_code }, consumed))
+                Ok((ActionType::Hotkey(hotkey), 3))
             }
 
             // Select subgroup (0x19)
-            (0x19, _) => {
-                // SelectSubgroup: type(1) + item(1) + object_id1(4) + object_id2(4) + unknown(3) = 13 bytes
-                let consumed = 13.min(data.len());
-                if data.len() >= 10 {
-                    let item = data[1];
-                    let object_id1 = u32::from_le_bytes([data[2], data[3], data[4], data[5]]);
-                    let object_id2 = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
-                    Ok((
-                        ActionType::SelectSubgroup {
-                            item,
-                            object_id1,
-                            object_id2,
-                        },
-                        consumed,
-                    ))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Structure: 0x19 [unit type u32] [object ID 1 u32] [object ID 2 u32]
+            0x19 => {
+                expect_size(data, 13)?;
+                Ok((
+                    ActionType::SelectSubgroup {
+                        unit_code: code_at(data, 1),
+                        object_id1: u32_at(data, 5),
+                        object_id2: u32_at(data, 9),
+                    },
+                    13,
+                ))
+            }
+            // Pre-subselection (0x1A), no data
+            0x1A => Ok((ActionType::PreSubselection, 1)),
+
+            // Select ground item (0x1C)
+            // Structure: 0x1C [flags] [object ID 1 u32] [object ID 2 u32]
+            0x1C => {
+                expect_size(data, 10)?;
+                let item_id = u32_at(data, 2);
+                Ok((ActionType::ItemAction { item_id }, 10))
             }
 
             // Remove from queue (0x1E)
-            (0x1E, _) => {
-                // RemoveFromQueue: type(1) + slot(1) + unit_id(4) = 6 bytes
-                let consumed = 6.min(data.len());
-                if data.len() >= 6 {
-                    let slot = data[1];
-                    let unit_id = u32::from_le_bytes([data[2], data[3], data[4], data[5]]);
-                    Ok((ActionType::RemoveFromQueue { slot, unit_id }, consumed))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Structure: 0x1E [slot] [unit type u32]
+            0x1E => {
+                expect_size(data, 6)?;
+                Ok((
+                    ActionType::RemoveFromQueue {
+                        slot: data[1],
+                        unit_id: u32_at(data, 2),
+                    },
+                    6,
+                ))
             }
 
             // Change ally options (0x50)
-            (0x50, _) => {
-                // ChangeAllyOptions: type(1) + slot(1) + flags(4) = 6 bytes
-                let consumed = 6.min(data.len());
-                if data.len() >= 6 {
-                    let slot = data[1];
-                    let flags = u32::from_le_bytes([data[2], data[3], data[4], data[5]]);
-                    Ok((ActionType::ChangeAllyOptions { slot, flags }, consumed))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Structure: 0x50 [slot] [flags u32]
+            0x50 => {
+                expect_size(data, 6)?;
+                Ok((
+                    ActionType::ChangeAllyOptions {
+                        slot: data[1],
+                        flags: u32_at(data, 2),
+                    },
+                    6,
+                ))
             }
-
             // Transfer resources (0x51)
-            (0x51, _) => {
-                // TransferResources: type(1) + slot(1) + gold(4) + lumber(4) = 10 bytes
-                let consumed = 10.min(data.len());
-                if data.len() >= 10 {
-                    let slot = data[1];
-                    let gold = u32::from_le_bytes([data[2], data[3], data[4], data[5]]);
-                    let lumber = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
-                    Ok((
-                        ActionType::TransferResources { slot, gold, lumber },
-                        consumed,
-                    ))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
-            }
-
-            // Minimap ping (0x68)
-            (0x68, _) => {
-                // MinimapPing: type(1) + x(4) + y(4) + unknown(4) = 13 bytes
-                let consumed = 13.min(data.len());
-                if data.len() >= 13 {
-                    let x = f32::from_le_bytes([data[1], data[2], data[3], data[4]]);
-                    let y = f32::from_le_bytes([data[5], data[6], data[7], data[8]]);
-                    let unknown = u32::from_le_bytes([data[9], data[10], data[11], data[12]]);
-                    Ok((ActionType::MinimapPing { x, y, unknown }, consumed))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
-            }
-
-            // Battle.net sync (0x15) - Reforged only
-            // Structure: type(1) + marker(2) + separator(1) + base64_data(~16) + padding(~4) = 24 bytes
-            (0x15, _) => {
-                let consumed = 24.min(data.len());
-                if data.len() >= 24 {
-                    let marker = u16::from_le_bytes([data[1], data[2]]);
-                    // Skip separator at data[3], capture Base64-like data from 4 onwards
-                    let sync_data = data[4..24].to_vec();
-                    Ok((ActionType::BattleNetSync { marker, data: sync_data }, consumed))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
-            }
-
-            // Reforged Wrapped Ability (0x11) - Reforged only
-            // Structure: type(1) + flags(1) + marker(1) + counter(1) + inner_flag(1) + inner_action
-            // For direct ability inner: 0x1A 0x19 + fourcc(4) + unit_ids(8) = 14 bytes
-            // Total: 5 + 14 = 19 bytes
-            (0x11, Some(0x00)) => {
-                // Check for 0x18 marker at byte 2 (Reforged format)
-                if data.get(2) == Some(&0x18) {
-                    // Long form: 19 bytes with inner 0x1A 0x19 ability
-                    if data.len() >= 19
-                        && data.get(5) == Some(&0x1A)
-                        && data.get(6) == Some(&0x19)
-                    {
-                        let ability_code = [data[7], data[8], data[9], data[10]];
-                        let target_unit = if data.len() >= 15 {
-                            Some(u32::from_le_bytes([data[11], data[12], data[13], data[14]]))
-                        } else {
-                            None
-                        };
-                        let ab = AbilityAction::from_raw(ability_code, target_unit);
-                        return Ok((ActionType::Ability(ab), 19));
-                    }
-                    // Short form: 0x11 0x00 0x18 0x00 (4 bytes) - sync/marker
-                    if data.len() >= 4 && data.get(3) == Some(&0x00) {
-                        return Ok((
-                            ActionType::BasicCommand {
-                                command_id: 0x11001800, // Reforged sync marker
-                            },
-                            4,
-                        ));
-                    }
-                    // Medium form: just 0x11 0x00 0x18 (3 bytes)
-                    if data.len() >= 3 {
-                        return Ok((
-                            ActionType::BasicCommand {
-                                command_id: 0x11001800,
-                            },
-                            3,
-                        ));
-                    }
-                }
-                // Check for 0x7B (123) marker at byte 2 (Classic/BattleNet format)
-                // These are BattleNet sync packets with variable structure
-                if data.get(2) == Some(&0x7B) {
-                    // Variable length: find end by scanning for known patterns
-                    // Common sizes: 4, 8, 10, 14, 16, 18 bytes after type+flags
-                    // Structure: 0x11 0x00 0x7B [coordinate data or ability]
-                    let consumed = if data.len() >= 18 {
-                        // Check if this contains an ability (FourCC pattern at bytes 12-15)
-                        // FourCC typically has ASCII chars like 'A' (65), 'E' (69), 'U' (85)
-                        if data.len() >= 18
-                            && (data[12..16].iter().any(|&b| (65..=90).contains(&b)
-                                || (97..=122).contains(&b)))
-                        {
-                            18 // Long form with FourCC
-                        } else if data.len() >= 14 && data.get(11) == Some(&0x00) {
-                            14 // Medium form with coordinates
-                        } else if data.len() >= 10 {
-                            10
-                        } else if data.len() >= 8 {
-                            8
-                        } else {
-                            4.min(data.len())
-                        }
-                    } else if data.len() >= 8 {
-                        8
-                    } else {
-                        4.min(data.len())
-                    };
-                    let sync_data = data[3..consumed].to_vec();
-                    return Ok((
-                        ActionType::BattleNetSync {
-                            marker: 0x117B, // 0x11 + 0x7B marker
-                            data: sync_data,
-                        },
-                        consumed,
-                    ));
-                }
-                // Fall through to unknown if structure doesn't match
-                Ok(Self::parse_unknown_action(action_type, subcommand, data))
-            }
-
-            // Reforged Queue/Repeat Action (0x03) - Reforged only
-            // Structure: type(1) + flags(1) + marker(1) + counter(1) + terminator(1) = 5 bytes
-            // This appears to be a "repeat last action" or "queue" marker
-            (0x03, Some(0x00)) => {
-                // Check for 0x18 marker at byte 2 and 0x03 terminator at byte 4
-                if data.len() >= 5 && data.get(2) == Some(&0x18) && data.get(4) == Some(&0x03) {
-                    // Simple 5-byte action - treat as a repeat/queue command
-                    // We'll classify this as a BasicCommand for stats purposes
-                    let counter = data.get(3).copied().unwrap_or(0);
-                    return Ok((
-                        ActionType::BasicCommand {
-                            command_id: 0x03001803 | ((counter as u32) << 24),
-                        },
-                        5,
-                    ));
-                }
-                Ok(Self::parse_unknown_action(action_type, subcommand, data))
-            }
-
-            // 0x03 with 0x1A (26) subcommand - Classic wrapped ability
-            // Pattern: 0x03 0x1A 0x19 [fourcc 4 bytes] [target 4 bytes] = 12 bytes
-            (0x03, Some(0x1A)) => {
-                if data.len() >= 12 && data.get(2) == Some(&0x19) {
-                    let ability_code = [data[3], data[4], data[5], data[6]];
-                    let target_unit = if data.len() >= 11 {
-                        Some(u32::from_le_bytes([data[7], data[8], data[9], data[10]]))
-                    } else {
-                        None
-                    };
-                    let ab = AbilityAction::from_raw(ability_code, target_unit);
-                    return Ok((ActionType::Ability(ab), 12));
-                }
-                Ok(Self::parse_unknown_action(action_type, subcommand, data))
-            }
-
-            // Note: Action types 0x10-0x14 are theoretically unit ability types
-            // according to W3G documentation, but in practice they seem to
-            // appear as data bytes within other actions in most replays.
-            // They are left to fall through to the Unknown handler to avoid
-            // desynchronization. The ActionType enum still has these variants
-            // defined for future use when we can properly validate them.
-
-            // Reforged Wrapped Selection (0x26, 0x36, 0x46, 0x56, 0x2E, 0x3E, 0x4E, 0x5E)
-            // Pattern: [type] 0x00 0x16 [selection_data...]
-            // These are base Selection (0x16) with modifier flags in upper nibble
-            (0x26 | 0x36 | 0x46 | 0x56 | 0x2E | 0x3E | 0x4E | 0x5E, Some(0x00)) => {
-                if data.len() >= 3 && data.get(2) == Some(&0x16) {
-                    // Long form: Parse selection starting from embedded 0x16
-                    let (sel, consumed) = SelectionAction::parse(&data[2..])?;
-                    Ok((ActionType::Selection(sel), 2 + consumed))
-                } else if data.len() >= 2 {
-                    // Short form: 2-byte marker (type + 0x00)
-                    // Treat as sync/marker command
-                    Ok((
-                        ActionType::BasicCommand {
-                            command_id: (action_type as u32) << 8,
-                        },
-                        2,
-                    ))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Structure: 0x51 [slot] [gold u32] [lumber u32]
+            0x51 => {
+                expect_size(data, 10)?;
+                Ok((
+                    ActionType::TransferResources {
+                        slot: data[1],
+                        gold: u32_at(data, 2),
+                        lumber: u32_at(data, 6),
+                    },
+                    10,
+                ))
             }
 
-            // Reforged short-form wrapped types (0x20-0x7F range with 0x00 subcommand)
-            // Many Reforged actions use [type, 0x00] as 2-byte sync/state markers
-            // Or [type, 0x00, 0x16, count] for wrapped selection variants
-            (0x20..=0x7F, Some(0x00)) => {
-                // Check for embedded selection (0x16 at offset 2)
-                // Note: 0x16 = 22 decimal
-                if data.len() >= 4 && data.get(2) == Some(&0x16) {
-                    let (sel, consumed) = SelectionAction::parse(&data[2..])?;
-                    Ok((ActionType::Selection(sel), 2 + consumed))
-                } else if data.len() >= 2 {
-                    // Short form: treat as sync marker
-                    Ok((
-                        ActionType::BasicCommand {
-                            command_id: (action_type as u32) << 8,
-                        },
-                        2,
-                    ))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Map trigger chat command (0x60)
+            // Structure: 0x60 [unknown u32] [unknown u32] [message\0]
+            0x60 => {
+                expect_size(data, 9)?;
+                let (message, consumed) = read_cstr(data, 9)?;
+                Ok((
+                    ActionType::TriggerChatCommand {
+                        unknown_1: u32_at(data, 1),
+                        unknown_2: u32_at(data, 5),
+                        message,
+                    },
+                    consumed,
+                ))
             }
-
-            // Reforged high-range types (0x80-0xFF with 0x00 or specific subcommands)
-            // 0xA0 (160) with subcommand 0x02 = Reforged sync/state marker
-            (0xA0, Some(0x02)) => {
+            // ESC pressed in trigger (0x61), hero skill / building submenu (0x66 / 0x67)
+            0x61 => Ok((ActionType::TriggerEscapeKey, 1)),
+            0x66 => Ok((ActionType::HeroSkillSubmenu, 1)),
+            0x67 => Ok((ActionType::BuildingSubmenu, 1)),
+            // Scenario trigger (0x62)
+            // Structure: 0x62 [unknown u32] [unknown u32] [unknown u32]
+            0x62 => {
+                expect_size(data, 13)?;
                 Ok((
-                    ActionType::BasicCommand {
-                        command_id: 0xA0020000, // Reforged state sync
+                    ActionType::ScenarioTrigger {
+                        unknown_1: u32_at(data, 1),
+                        unknown_2: u32_at(data, 5),
+                        unknown_3: u32_at(data, 9),
                     },
-                    2,
+                    13,
                 ))
             }
-            // Generic high-range handler
-            (0x80..=0xFF, Some(0x00)) => {
-                if data.len() >= 4 && data.get(2) == Some(&0x16) {
-                    let (sel, consumed) = SelectionAction::parse(&data[2..])?;
-                    Ok((ActionType::Selection(sel), 2 + consumed))
-                } else if data.len() >= 2 {
-                    Ok((
-                        ActionType::BasicCommand {
-                            command_id: (action_type as u32) << 8,
-                        },
-                        2,
-                    ))
-                } else {
-                    Ok(Self::parse_unknown_action(action_type, subcommand, data))
-                }
+            // Minimap ping (0x68)
+            // Structure: 0x68 [x f32] [y f32] [unknown u32]
+            0x68 => {
+                expect_size(data, 13)?;
+                Ok((
+                    ActionType::MinimapPing {
+                        x: f32_at(data, 1),
+                        y: f32_at(data, 5),
+                        unknown: u32_at(data, 9),
+                    },
+                    13,
+                ))
             }
+            // Game cache sync (0x6B), used by W3MMD
+            0x6B => match parse_game_cache_sync(data) {
+                Some(result) => Ok(result),
+                None => Ok(unknown_action(data, None)),
+            },
 
-            // 0x0E (Attack-move) with embedded ability (0x1A 0x19)
-            // Pattern: 0x0E 0x00 0x1A 0x19 [fourcc] [target]
-            (0x0E, Some(0x00)) if data.len() >= 3 && data.get(2) == Some(&0x1A) => {
-                if data.len() >= 14 && data.get(3) == Some(&0x19) {
-                    // Extract ability data
-                    let ability_code = [data[4], data[5], data[6], data[7]];
-                    let target_unit = if data.len() >= 12 {
-                        Some(u32::from_le_bytes([data[8], data[9], data[10], data[11]]))
-                    } else {
-                        None
-                    };
-                    let ab = AbilityAction::from_raw(ability_code, target_unit);
-                    return Ok((ActionType::Ability(ab), 14));
+            // Arrow key event (0x75)
+            // Structure: 0x75 [event]
+            0x75 => {
+                expect_size(data, 2)?;
+                match ArrowKey::from_event(data[1]) {
+                    Some((key, pressed)) => Ok((ActionType::ArrowKeyEvent { key, pressed }, 2)),
+                    None => Ok(unknown_action(data, Some(2))),
                 }
-                Ok(Self::parse_unknown_action(action_type, subcommand, data))
             }
-
-            // 0x14 (20) - Short marker patterns
-            (0x14, Some(0x00)) => {
-                if data.len() >= 4 && data.get(2) == Some(&0x16) {
-                    let (sel, consumed) = SelectionAction::parse(&data[2..])?;
-                    Ok((ActionType::Selection(sel), 2 + consumed))
-                } else {
-                    Ok((
-                        ActionType::BasicCommand {
-                            command_id: 0x14000000,
-                        },
-                        2,
-                    ))
-                }
+            // Battle.net sync (0x78) - Reforged only
+            // Structure: 0x78 [identifier\0] [value\0] [unknown u32]
+            0x78 => {
+                let (identifier, offset) = read_cstr(data, 1)?;
+                let (value, offset) = read_cstr(data, offset)?;
+                expect_size(data, offset + 4)?;
+                Ok((ActionType::BattleNetSync { identifier, value }, offset + 4))
             }
 
-            // Unknown action types - try to find the next action boundary
-            _ => Ok(Self::parse_unknown_action(action_type, subcommand, data)),
+            action_type => Ok(unknown_action(data, unknown_action_size(action_type))),
         }
     }
-
-    /// Handles unknown action types by finding the next action boundary.
-    fn parse_unknown_action(
-        type_id: u8,
-        subcommand: Option<u8>,
-        data: &[u8],
-    ) -> (ActionType, usize) {
-        // Try to find the next valid action start
-        // Look for a byte 1-15 followed by a known action type
-        let mut end_offset = 1;
-
-        while end_offset < data.len() {
-            let potential_player = data[end_offset];
-            if (1..=15).contains(&potential_player) {
-                // Check if next byte looks like a valid action type
-                if let Some(&next_type) = data.get(end_offset + 1) {
-                    if is_known_action_type(next_type) {
-                        // Found what looks like next action
-                        break;
-                    }
-                }
-            }
-            end_offset += 1;
-        }
-
-        // Capture the unknown action data
-        let action_data = data[1..end_offset].to_vec();
-
-        (
-            ActionType::Unknown {
-                type_id,
-                subcommand,
-                data: action_data,
-            },
-            end_offset,
-        )
-    }
 }
 
 impl Iterator for ActionIterator<'_> {
     type Item = Result<Action>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.finished || self.offset >= self.data.len() {
+        if self.finished {
             return None;
         }
 
-        match self.parse_next() {
+        // Move on to the next command block, skipping empty ones
+        while self.block.is_none_or(|(_, end)| self.offset >= end) {
+            if self.offset >= self.data.len() {
+                self.finished = true;
+                return None;
+            }
+            match self.read_block_header() {
+                Ok((player_id, end)) => {
+                    self.block = Some((player_id, end));
+                    self.offset += 3;
+                }
+                Err(e) => {
+                    // Without a length the following blocks cannot be found
+                    let start = self.offset;
+                    self.finished = true;
+                    self.action_offset = start;
+                    self.offset = self.data.len();
+                    let segment = format!("command block at 0x{start:X}");
+                    return Some(Err(e.with_context(start, &segment)));
+                }
+            }
+        }
+
+        let (player_id, block_end) = self.block?;
+        let start = self.offset;
+        self.action_offset = start;
+        self.action_count += 1;
+        match self.parse_next(player_id, block_end) {
             Ok(action) => Some(Ok(action)),
             Err(e) => {
-                self.finished = true;
-                Some(Err(e))
+                // Errors carry the offset within the action data and the
+                // action's path within the TimeFrame; the rest of the block
+                // is skipped
+                self.offset = block_end;
+                let segment = format!("player {player_id} / action #{}", self.action_count);
+                Some(Err(e.with_context(start, &segment)))
             }
         }
     }
 }
 
-/// Returns whether the byte is a known action type marker.
-///
-/// Note: 0x10-0x14 are unit ability types but are NOT included here
-/// because they can also appear as data bytes within other actions,
-/// making boundary detection unreliable. They are still parsed when
-/// encountered, but we don't use them for finding action boundaries.
-fn is_known_action_type(byte: u8) -> bool {
-    matches!(
-        byte,
-        // Core action types
-        0x00 | 0x03 | 0x0C | 0x0F | 0x11 | 0x15 | 0x16 | 0x17 | 0x18 | 0x19 | 0x1A | 0x1B | 0x1C | 0x1D | 0x1E
-        // Reforged wrapped selections (0x16 + flags)
-        | 0x26 | 0x2E | 0x36 | 0x3E | 0x46 | 0x4E | 0x56 | 0x5E
-        // Reforged short-form markers
-        | 0x20 | 0x24 | 0x28 | 0x2C | 0x30 | 0x34 | 0x38 | 0x3C | 0x40 | 0x44 | 0x48 | 0x4C
-        | 0x50 | 0x51 | 0x52 | 0x54 | 0x58 | 0x5C | 0x60 | 0x64 | 0x68 | 0x6C | 0x70 | 0x74 | 0x76 | 0x78 | 0x7C
+/// Returns the size of an action type that has a known layout but no
+/// variant of its own, including the type byte.
+fn unknown_action_size(action_type: u8) -> Option<usize> {
+    match action_type {
+        // Cheats without arguments
+        0x20 | 0x22..=0x26 | 0x29..=0x2C | 0x2F..=0x32 => Some(1),
+        // Cheats with a resource type and amount
+        0x27 | 0x28 | 0x2D => Some(6),
+        // Set time of day cheat
+        0x2E => Some(5),
+        // Cancel hero revival, unknown (0x21)
+        0x1D | 0x21 => Some(9),
+        // Unknown [flags] [object ID 1 u32] [object ID 2 u32]
+        0x1B => Some(10),
+        // Continue game, and a Reforged action with two object IDs
+        0x69 | 0x6A | 0x7B => Some(17),
+        _ => None,
+    }
+}
+
+/// Returns an unknown action of `size` bytes, or of the rest of `data`.
+fn unknown_action(data: &[u8], size: Option<usize>) -> (ActionType, usize) {
+    let size = size.unwrap_or(data.len()).min(data.len()).max(1);
+    (
+        ActionType::Unknown {
+            type_id: data[0],
+            subcommand: data.get(1).copied(),
+            data: data[1..size].to_vec(),
+        },
+        size,
     )
 }
 
+/// Returns an error unless `data` holds at least `size` bytes.
+fn expect_size(data: &[u8], size: usize) -> Result<()> {
+    if data.len() < size {
+        return Err(ParserError::unexpected_eof(size, data.len()));
+    }
+    Ok(())
+}
+
+/// Reads a u16 at `offset`, which must be in bounds.
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Reads a u32 at `offset`, which must be in bounds.
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// Reads an f32 at `offset`, which must be in bounds.
+fn f32_at(data: &[u8], offset: usize) -> f32 {
+    f32::from_bits(u32_at(data, offset))
+}
+
+/// Reads a `FourCC` or order ID at `offset`, which must be in bounds.
+fn code_at(data: &[u8], offset: usize) -> [u8; 4] {
+    [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]
+}
+
+/// Reads a null-terminated string at `offset`.
+///
+/// Returns the string and the offset after the terminator.
+fn read_cstr(data: &[u8], offset: usize) -> Result<(String, usize)> {
+    let rest = data.get(offset..).unwrap_or_default();
+    let len = rest
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| ParserError::unexpected_eof(offset + rest.len() + 1, data.len()))?;
+    Ok((String::from_utf8_lossy(&rest[..len]).into_owned(), offset + len + 1))
+}
+
+/// Reads a null-terminated string of printable characters at `offset`.
+///
+/// Returns the string and the offset after the terminator.
+fn read_printable_cstr(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let rest = data.get(offset..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    if rest[..len].iter().any(|&b| b < 0x20 || b == 0x7F) {
+        return None;
+    }
+    let s = std::str::from_utf8(&rest[..len]).ok()?.to_string();
+    Some((s, offset + len + 1))
+}
+
+/// Parses a game cache sync action (0x6B) at the start of `data`.
+///
+/// Structure: 0x6B [filename\0] [mission key\0] [key\0] [value u32]
+///
+/// Returns the action and the number of bytes consumed, or `None` if the data
+/// does not look like a game cache sync.
+pub(crate) fn parse_game_cache_sync(data: &[u8]) -> Option<(ActionType, usize)> {
+    if data.first() != Some(&0x6B) {
+        return None;
+    }
+
+    let (filename, offset) = read_printable_cstr(data, 1)?;
+    if filename.is_empty() {
+        return None;
+    }
+    let (mission_key, offset) = read_printable_cstr(data, offset)?;
+    let (key, offset) = read_printable_cstr(data, offset)?;
+    let value = data.get(offset..offset + 4)?;
+    let value = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+
+    Some((
+        ActionType::GameCacheSync {
+            filename,
+            mission_key,
+            key,
+            value,
+        },
+        offset + 4,
+    ))
+}
+
 /// Statistics about actions parsed from a replay.
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActionStatistics {
     /// Total number of actions parsed.
     pub total_actions: u32,
@@ -769,45 +661,15 @@ impl ActionStatistics {
 
         match &action.action_type {
             ActionType::Selection(_) => self.selection_actions += 1,
-            ActionType::Ability(ab) => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(ab.ability_code.raw_bytes());
-            }
-            ActionType::AbilityWithSelection(ab) => {
-                self.ability_actions += 1;
-                self.unique_ability_codes
-                    .insert(ab.ability.ability_code.raw_bytes());
-            }
-            ActionType::InstantAbility(ab) => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(ab.ability_code.raw_bytes());
-            }
-            ActionType::Movement(_) => self.movement_actions += 1,
             ActionType::Hotkey(_) => self.hotkey_actions += 1,
-            ActionType::EscapeKey => {
-                // ESC is a known action type, counts toward total
-            }
             ActionType::ItemAction { .. } => {
                 // Item usage counts as ability-like
                 self.ability_actions += 1;
             }
-            ActionType::BasicCommand { .. } => {
-                // Basic commands (stop, hold) count as movement-like
-                self.movement_actions += 1;
-            }
-            ActionType::BuildTrain { unit_code } => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(*unit_code);
-            }
-            ActionType::UnitAbilityNoTarget { ability_code, .. } => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(*ability_code);
-            }
-            ActionType::UnitAbilityGroundTarget { ability_code, .. } => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(*ability_code);
-            }
-            ActionType::UnitAbilityUnitTarget { ability_code, .. } => {
+            ActionType::UnitAbilityNoTarget { ability_code, .. }
+            | ActionType::UnitAbilityGroundTarget { ability_code, .. }
+            | ActionType::UnitAbilityUnitTarget { ability_code, .. }
+            | ActionType::UnitAbilityTwoTargets { ability_code, .. } => {
                 self.ability_actions += 1;
                 self.unique_ability_codes.insert(*ability_code);
             }
@@ -815,10 +677,6 @@ impl ActionStatistics {
                 self.ability_actions += 1;
                 self.unique_ability_codes.insert(*item_code);
             }
-            ActionType::UnitAbilityTwoTargets { ability_code, .. } => {
-                self.ability_actions += 1;
-                self.unique_ability_codes.insert(*ability_code);
-            }
             ActionType::SelectSubgroup { .. } => {
                 // SelectSubgroup is selection-like
                 self.selection_actions += 1;
@@ -827,20 +685,34 @@ impl ActionStatistics {
                 // Removing from queue is ability-like
                 self.ability_actions += 1;
             }
-            ActionType::ChangeAllyOptions { .. } => {
-                // Ally options are ESC-like (meta actions)
-            }
             ActionType::TransferResources { .. } => {
                 // Resource transfer is ability-like
                 self.ability_actions += 1;
             }
+            ActionType::PauseGame
+            | ActionType::ResumeGame
+            | ActionType::SetGameSpeed { .. }
+            | ActionType::IncreaseGameSpeed
+            | ActionType::DecreaseGameSpeed
+            | ActionType::SaveGame { .. }
+            | ActionType::SaveGameFinished { .. }
+            | ActionType::TriggerChatCommand { .. }
+            | ActionType::TriggerEscapeKey
+            | ActionType::ScenarioTrigger { .. }
+            | ActionType::HeroSkillSubmenu
+            | ActionType::BuildingSubmenu
+            | ActionType::ArrowKeyEvent { .. }
+            | ActionType::PreSubselection
+            | ActionType::ChangeAllyOptions { .. }
+            | ActionType::GameCacheSync { .. }
+            | ActionType::BattleNetSync { .. } => {
+                // Game control, map trigger input, pre-subselections, ally
+                // options and sync data are meta actions, no category
+            }
             ActionType::MinimapPing { .. } => {
                 // Pings are movement-like (map interaction)
                 self.movement_actions += 1;
             }
-            ActionType::BattleNetSync { .. } => {
-                // BattleNet sync is meta/network action, no category
-            }
             ActionType::Unknown { .. } => self.unknown_actions += 1,
         }
     }
@@ -865,13 +737,22 @@ impl ActionStatistics {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::actions::AbilityCode;
+
+    /// Builds a command block for `player_id` holding `actions`.
+    fn block(player_id: u8, actions: &[u8]) -> Vec<u8> {
+        let mut data = vec![player_id];
+        data.extend_from_slice(&u16::try_from(actions.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(actions);
+        data
+    }
 
     #[test]
     fn test_action_iterator_single_selection() {
         // Player 4, Selection with 1 unit
         let data: &[u8] = &[
-            0x04, // Player 4
-            0x16, 0x01, 0x01, 0x00, // Selection: 1 unit, mode 1
+            0x04, 0x0C, 0x00, // Player 4, 12 bytes
+            0x16, 0x01, 0x01, 0x00, // Selection: mode 1, 1 unit
             0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Unit ID
         ];
 
@@ -887,57 +768,75 @@ mod tests {
     }
 
     #[test]
-    fn test_action_iterator_direct_ability() {
-        // Player 3, Direct ability
+    fn test_action_iterator_subgroup_selection() {
+        // Player 3, pre-subselection followed by a subgroup selection
         let data: &[u8] = &[
-            0x03, // Player 3
-            0x1A, 0x19, // Ability command
-            0x77, 0x6F, 0x74, 0x68, // "woth"
-            0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Target
+            0x03, 0x0E, 0x00, // Player 3, 14 bytes
+            0x1A, // Pre-subselection
+            0x19, 0x77, 0x6F, 0x74, 0x68, // Subgroup "woth"
+            0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Object IDs
         ];
 
         let ctx = ActionContext::new(2000, 2);
-        let mut iter = ActionIterator::new(data, ctx);
+        let actions: Vec<_> = ActionIterator::new(data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
 
-        let action = iter.next().unwrap().unwrap();
-        assert_eq!(action.player_id, 3);
-        if let ActionType::Ability(ab) = &action.action_type {
-            assert_eq!(ab.ability_code.as_string(), "htow");
-        } else {
-            panic!("Expected Ability action type");
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0].action_type, ActionType::PreSubselection));
+        match &actions[1].action_type {
+            ActionType::SelectSubgroup {
+                unit_code,
+                object_id1,
+                ..
+            } => {
+                assert_eq!(AbilityCode::from_raw(*unit_code).as_string(), "htow");
+                assert_eq!(*object_id1, 0x3A3B);
+            }
+            other => panic!("Expected SelectSubgroup, got {other:?}"),
         }
     }
 
     #[test]
-    fn test_action_iterator_movement() {
-        // Player 5, Movement command
-        let data: &[u8] = &[
-            0x05, // Player 5
-            0x00, 0x0D, 0x00, 0x00, // Move command
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // No target
-            0x00, 0x00, 0xB0, 0xC5, // X
-            0x00, 0x00, 0x60, 0x45, // Y
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Extra
-        ];
+    fn test_action_iterator_unit_target_order() {
+        // Player 5, right-click (order 0x000D0003) on the ground
+        let data = block(
+            5,
+            &[
+                0x12, 0x00, 0x00, // Unit target order, flags
+                0x03, 0x00, 0x0D, 0x00, // Order ID
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // Unknown
+                0x00, 0x00, 0xB0, 0xC5, // X = -5632.0
+                0x00, 0x00, 0x60, 0x45, // Y = 3584.0
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // No target
+            ],
+        );
 
         let ctx = ActionContext::new(3000, 3);
-        let mut iter = ActionIterator::new(data, ctx);
-
-        let action = iter.next().unwrap().unwrap();
+        let action = ActionIterator::new(&data, ctx).next().unwrap().unwrap();
         assert_eq!(action.player_id, 5);
-        assert!(matches!(action.action_type, ActionType::Movement(_)));
+        match action.action_type {
+            ActionType::UnitAbilityUnitTarget {
+                ability_code, x, y, ..
+            } => {
+                assert_eq!(ability_code, [0x03, 0x00, 0x0D, 0x00]);
+                assert!((x - (-5632.0)).abs() < 0.1);
+                assert!((y - 3584.0).abs() < 0.1);
+            }
+            other => panic!("Expected UnitAbilityUnitTarget, got {other:?}"),
+        }
     }
 
     #[test]
     fn test_action_iterator_multiple_players() {
-        // From Rex's analysis: two instant abilities from different players
+        // From Rex's analysis: two orders without target from different players
         let data: &[u8] = &[
-            // Player 5's instant ability
-            0x05, 0x0F, 0x00, 0x10, 0x42, 0x00, 0x00, 0x70, 0x73, 0x77, 0x65, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-            // Player 4's instant ability
-            0x04, 0x0F, 0x00, 0x10, 0x42, 0x00, 0x00, 0x61, 0x65, 0x70, 0x68, 0xFF, 0xFF, 0xFF,
-            0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            // Player 5 trains a wisp
+            0x05, 0x0F, 0x00, 0x10, 0x42, 0x00, 0x70, 0x73, 0x77, 0x65, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            // Player 4 trains an acolyte
+            0x04, 0x0F, 0x00, 0x10, 0x42, 0x00, 0x61, 0x65, 0x70, 0x68, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF, 0xFF, 0xFF,
         ];
 
         let ctx = ActionContext::new(4000, 4);
@@ -947,6 +846,10 @@ mod tests {
         assert_eq!(actions.len(), 2);
         assert_eq!(actions[0].player_id, 5);
         assert_eq!(actions[1].player_id, 4);
+        assert_eq!(
+            actions[0].action_type.object_code().map(|c| c.as_string()),
+            Some("ewsp".to_string())
+        );
     }
 
     #[test]
@@ -960,13 +863,198 @@ mod tests {
 
     #[test]
     fn test_action_iterator_invalid_player() {
-        let data: &[u8] = &[0x00, 0x16, 0x01, 0x01, 0x00]; // Player 0 is invalid
+        let data: &[u8] = &[0x00, 0x01, 0x00, 0x16]; // Player 0 is invalid
 
         let ctx = ActionContext::default();
         let mut iter = ActionIterator::new(data, ctx);
 
         let result = iter.next().unwrap();
-        assert!(matches!(result, Err(ParserError::InvalidHeader { .. })));
+        assert!(matches!(
+            result,
+            Err(ParserError::InvalidAction {
+                offset: 0,
+                player_id: 0,
+                type_byte: 0x16,
+                ..
+            })
+        ));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_action_iterator_skips_rest_of_block_after_error() {
+        let mut data = block(1, &[0x10, 0x42, 0x00, 0x01]); // Truncated order
+        data.extend(block(2, &[0x01])); // Pause
+
+        let ctx = ActionContext::default();
+        let mut iter = ActionIterator::new(&data, ctx);
+
+        match iter.next().unwrap() {
+            Err(ParserError::InvalidAction {
+                offset,
+                path,
+                player_id: 1,
+                type_byte: 0x10,
+            }) => {
+                assert_eq!(offset, 3);
+                assert_eq!(path, "player 1 / action #1");
+            }
+            other => panic!("Expected InvalidAction, got {other:?}"),
+        }
+        assert_eq!(iter.current_offset(), 7);
+
+        let action = iter.next().unwrap().unwrap();
+        assert_eq!(action.player_id, 2);
+        assert!(matches!(action.action_type, ActionType::PauseGame));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_action_iterator_unknown_action_sizes() {
+        // A cheat with a known size is followed by a pause in the same block;
+        // an unknown type byte takes up the rest of its block
+        let mut data = block(1, &[0x2E, 0x00, 0x00, 0x40, 0x41, 0x01]);
+        data.extend(block(2, &[0xEE, 0x01, 0x02]));
+        data.extend(block(3, &[0x02]));
+
+        let ctx = ActionContext::default();
+        let actions: Vec<_> = ActionIterator::new(&data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(actions.len(), 4);
+        assert!(matches!(
+            &actions[0].action_type,
+            ActionType::Unknown { type_id: 0x2E, data, .. } if data.len() == 4
+        ));
+        assert!(matches!(actions[1].action_type, ActionType::PauseGame));
+        assert!(matches!(
+            &actions[2].action_type,
+            ActionType::Unknown { type_id: 0xEE, data, .. } if data == &[0x01, 0x02]
+        ));
+        assert_eq!(actions[3].player_id, 3);
+        assert!(matches!(actions[3].action_type, ActionType::ResumeGame));
+    }
+
+    #[test]
+    fn test_action_iterator_game_control() {
+        let mut data = block(1, &[0x01]); // Pause
+        data.extend(block(2, &[0x02])); // Resume
+        data.extend(block(1, &[0x03, 0x01])); // Set game speed normal
+        data.extend(block(2, &[0x06, b'a', b'.', b'w', b'3', b'z', 0x00])); // Save game
+        data.extend(block(1, &[0x04])); // Increase game speed
+
+        let ctx = ActionContext::new(1000, 0);
+        let actions: Vec<_> = ActionIterator::new(&data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(actions.len(), 5);
+        assert!(matches!(actions[0].action_type, ActionType::PauseGame));
+        assert!(matches!(actions[1].action_type, ActionType::ResumeGame));
+        assert!(matches!(
+            actions[2].action_type,
+            ActionType::SetGameSpeed {
+                speed: GameSpeed::Normal
+            }
+        ));
+        match &actions[3].action_type {
+            ActionType::SaveGame { filename } => assert_eq!(filename, "a.w3z"),
+            other => panic!("Expected SaveGame, got {other:?}"),
+        }
+        assert!(matches!(actions[4].action_type, ActionType::IncreaseGameSpeed));
+    }
+
+    #[test]
+    fn test_action_iterator_hotkeys() {
+        let data = block(
+            2,
+            &[
+                0x17, 0x03, 0x01, 0x00, // Assign group 3, 1 unit
+                0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Unit ID
+                0x18, 0x03, 0x03, // Select group 3
+            ],
+        );
+
+        let ctx = ActionContext::default();
+        let actions: Vec<_> = ActionIterator::new(&data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(actions.len(), 2);
+        match (&actions[0].action_type, &actions[1].action_type) {
+            (ActionType::Hotkey(assign), ActionType::Hotkey(select)) => {
+                assert_eq!(assign.group, 3);
+                assert!(assign.is_assign());
+                assert_eq!(select.group, 3);
+                assert!(select.is_select());
+            }
+            other => panic!("Expected two hotkeys, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_action_iterator_game_cache_sync_run() {
+        let mut actions = Vec::new();
+        for key in [&b"init pid 0 Alice"[..], &b"FlagP 0 winner"[..]] {
+            actions.push(0x6B);
+            actions.extend_from_slice(b"MMD.Dat\x00val:1\x00");
+            actions.extend_from_slice(key);
+            actions.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00]);
+        }
+        let data = block(5, &actions);
+
+        let ctx = ActionContext::new(0, 0);
+        let actions: Vec<_> = ActionIterator::new(&data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        let syncs: Vec<_> = actions
+            .iter()
+            .filter_map(|a| match &a.action_type {
+                ActionType::GameCacheSync { key, .. } => Some((a.player_id, key.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(syncs, vec![(5, "init pid 0 Alice"), (5, "FlagP 0 winner")]);
+    }
+
+    #[test]
+    fn test_parse_game_cache_sync_rejects_garbage() {
+        assert!(parse_game_cache_sync(&[0x6B, 0x00, 0x00, 0x00]).is_none());
+        assert!(parse_game_cache_sync(&[0x6B, 0x41, 0x01, 0x00]).is_none());
+        assert!(parse_game_cache_sync(b"\x6BMMD.Dat\x00a\x00b\x00\x01").is_none());
+    }
+
+    #[test]
+    fn test_action_iterator_trigger_actions() {
+        let mut actions = vec![0x60];
+        actions.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00]);
+        actions.extend_from_slice(b"-swap 3\x00");
+        actions.extend_from_slice(&[0x75, 0x06]); // Up pressed
+        actions.push(0x66); // Hero skill submenu
+        actions.push(0x61); // ESC in trigger
+        let data = block(2, &actions);
+
+        let ctx = ActionContext::new(0, 0);
+        let actions: Vec<_> = ActionIterator::new(&data, ctx)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(actions.len(), 4);
+        match &actions[0].action_type {
+            ActionType::TriggerChatCommand { message, .. } => assert_eq!(message, "-swap 3"),
+            other => panic!("Expected TriggerChatCommand, got {other:?}"),
+        }
+        assert!(matches!(
+            actions[1].action_type,
+            ActionType::ArrowKeyEvent {
+                key: ArrowKey::Up,
+                pressed: true
+            }
+        ));
+        assert!(matches!(actions[2].action_type, ActionType::HeroSkillSubmenu));
+        assert!(matches!(actions[3].action_type, ActionType::TriggerEscapeKey));
     }
 
     #[test]
@@ -989,7 +1077,6 @@ mod tests {
             ActionType::Selection(SelectionAction {
                 unit_count: 1,
                 mode: 1,
-                flags: 0,
                 unit_ids: vec![0x1234],
             }),
             1000,
@@ -997,10 +1084,10 @@ mod tests {
 
         let action2 = Action::new(
             2,
-            ActionType::Ability(AbilityAction {
-                ability_code: crate::actions::AbilityCode::from_raw([0x77, 0x6F, 0x74, 0x68]),
-                target_unit: None,
-            }),
+            ActionType::UnitAbilityNoTarget {
+                flags: 0,
+                ability_code: [0x77, 0x6F, 0x74, 0x68],
+            },
             2000,
         );
 
@@ -1014,30 +1101,4 @@ mod tests {
         assert_eq!(stats.actions_per_player.get(&1), Some(&1));
         assert_eq!(stats.actions_per_player.get(&2), Some(&1));
     }
-
-    #[test]
-    fn test_is_known_action_type() {
-        assert!(is_known_action_type(0x00)); // Movement
-        assert!(is_known_action_type(0x0F)); // InstantAbility
-        // Note: 0x10, 0x12-0x14 are NOT in is_known_action_type because they
-        // can appear as data bytes, making boundary detection unreliable
-        // However, 0x11 IS known (Reforged wrapped ability with distinct 0x18/0x7B markers)
-        assert!(!is_known_action_type(0x10)); // Not used for boundary detection
-        assert!(is_known_action_type(0x11)); // Reforged wrapped ability
-        assert!(!is_known_action_type(0x12)); // Not used for boundary detection
-        assert!(!is_known_action_type(0x13)); // Not used for boundary detection
-        assert!(!is_known_action_type(0x14)); // Not used for boundary detection
-        assert!(is_known_action_type(0x15)); // BattleNetSync
-        assert!(is_known_action_type(0x16)); // Selection
-        assert!(is_known_action_type(0x17)); // Hotkey
-        assert!(is_known_action_type(0x18)); // ESC
-        assert!(is_known_action_type(0x1A)); // Ability
-        assert!(is_known_action_type(0x1B)); // Item
-        assert!(is_known_action_type(0x1C)); // BasicCommand
-        assert!(is_known_action_type(0x1D)); // BuildTrain
-        // Reforged wrapped types
-        assert!(is_known_action_type(0x26)); // Wrapped selection
-        assert!(is_known_action_type(0x36)); // Wrapped selection
-        assert!(!is_known_action_type(0xFF)); // Unknown
-    }
 }
@@ -0,0 +1,254 @@
+//! Pause tracking for W3G replays.
+//!
+//! This module builds a timeline of pause (0x01) and resume (0x02) actions so
+//! the time each player kept the game paused can be measured, e.g. to enforce
+//! tournament pause limits.
+//!
+//! Durations are measured on the replay's time axis, i.e. the accumulated
+//! `TimeFrame` time deltas.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use w3g_parser::actions::PauseTimeline;
+//!
+//! let timeline = PauseTimeline::from_iterator(game.timeframes(&decompressed))?;
+//! for (player_id, paused_ms) in timeline.paused_ms_per_player() {
+//!     println!("Player {player_id}: {paused_ms}ms paused");
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use super::types::{Action, ActionType};
+use crate::error::Result;
+use crate::records::TimeFrameIterator;
+
+/// A single period during which the game was paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PauseInterval {
+    /// Player who paused the game.
+    pub paused_by: u8,
+
+    /// Player who resumed the game, or `None` if the replay ended while paused.
+    pub resumed_by: Option<u8>,
+
+    /// Time in milliseconds when the game was paused.
+    pub start_ms: u32,
+
+    /// Time in milliseconds when the game was resumed (or the replay ended).
+    pub end_ms: u32,
+}
+
+impl PauseInterval {
+    /// Returns the length of the pause in milliseconds.
+    #[must_use]
+    pub fn duration_ms(&self) -> u32 {
+        self.end_ms.saturating_sub(self.start_ms)
+    }
+}
+
+/// Timeline of all pauses in a replay.
+///
+/// Pausing an already paused game is counted as a pause action but does not
+/// start a new interval. The interval is attributed to the player who started it.
+#[derive(Debug, Clone, Default)]
+pub struct PauseTimeline {
+    /// Completed pause intervals in stream order.
+    intervals: Vec<PauseInterval>,
+
+    /// The currently open pause as (player ID, start time).
+    open: Option<(u8, u32)>,
+
+    /// Number of pause actions issued per player.
+    pause_counts: HashMap<u8, u32>,
+
+    /// Latest action or frame time seen.
+    last_time_ms: u32,
+}
+
+impl PauseTimeline {
+    /// Creates a new empty timeline.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a pause timeline from an iterator of `TimeFrame` records.
+    ///
+    /// Actions that fail to parse are skipped. A pause that is still open at
+    /// the end of the replay is closed at the time of the last `TimeFrame`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any `TimeFrame` in the iterator fails to parse.
+    pub fn from_iterator(iter: TimeFrameIterator<'_>) -> Result<Self> {
+        let mut timeline = PauseTimeline::new();
+
+        for result in iter {
+            let frame = result?;
+            for action in frame.actions().flatten() {
+                timeline.record(&action);
+            }
+            timeline.advance(frame.accumulated_time_ms);
+        }
+
+        timeline.close(timeline.last_time_ms);
+        Ok(timeline)
+    }
+
+    /// Records an action in the timeline.
+    ///
+    /// Only pause and resume actions change the timeline; all other actions
+    /// just advance its clock.
+    pub fn record(&mut self, action: &Action) {
+        self.advance(action.timestamp_ms);
+
+        match action.action_type {
+            ActionType::PauseGame => {
+                *self.pause_counts.entry(action.player_id).or_insert(0) += 1;
+                if self.open.is_none() {
+                    self.open = Some((action.player_id, action.timestamp_ms));
+                }
+            }
+            ActionType::ResumeGame => {
+                if let Some((paused_by, start_ms)) = self.open.take() {
+                    self.intervals.push(PauseInterval {
+                        paused_by,
+                        resumed_by: Some(action.player_id),
+                        start_ms,
+                        end_ms: action.timestamp_ms,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Closes a pause that is still open at `end_ms`.
+    pub fn close(&mut self, end_ms: u32) {
+        if let Some((paused_by, start_ms)) = self.open.take() {
+            self.intervals.push(PauseInterval {
+                paused_by,
+                resumed_by: None,
+                start_ms,
+                end_ms: end_ms.max(start_ms),
+            });
+        }
+    }
+
+    /// Returns whether the game is currently paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Returns all completed pause intervals.
+    #[must_use]
+    pub fn intervals(&self) -> &[PauseInterval] {
+        &self.intervals
+    }
+
+    /// Returns the number of pause actions issued by a player.
+    #[must_use]
+    pub fn pause_count(&self, player_id: u8) -> u32 {
+        self.pause_counts.get(&player_id).copied().unwrap_or(0)
+    }
+
+    /// Returns the total paused time in milliseconds attributed to a player.
+    #[must_use]
+    pub fn paused_ms(&self, player_id: u8) -> u32 {
+        self.intervals
+            .iter()
+            .filter(|i| i.paused_by == player_id)
+            .map(PauseInterval::duration_ms)
+            .sum()
+    }
+
+    /// Returns the total paused time in milliseconds for every player that paused.
+    #[must_use]
+    pub fn paused_ms_per_player(&self) -> HashMap<u8, u32> {
+        let mut totals = HashMap::new();
+        for interval in &self.intervals {
+            *totals.entry(interval.paused_by).or_insert(0) += interval.duration_ms();
+        }
+        totals
+    }
+
+    /// Returns the total paused time in milliseconds across all players.
+    #[must_use]
+    pub fn total_paused_ms(&self) -> u32 {
+        self.intervals.iter().map(PauseInterval::duration_ms).sum()
+    }
+
+    fn advance(&mut self, time_ms: u32) {
+        self.last_time_ms = self.last_time_ms.max(time_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::TIMEFRAME_MARKER_1F;
+
+    fn action(player_id: u8, action_type: ActionType, timestamp_ms: u32) -> Action {
+        Action::new(player_id, action_type, timestamp_ms)
+    }
+
+    #[test]
+    fn test_pause_timeline_intervals() {
+        let mut timeline = PauseTimeline::new();
+        timeline.record(&action(1, ActionType::PauseGame, 1000));
+        timeline.record(&action(2, ActionType::PauseGame, 1500));
+        timeline.record(&action(2, ActionType::ResumeGame, 4000));
+        timeline.record(&action(2, ActionType::PauseGame, 5000));
+        timeline.record(&action(1, ActionType::ResumeGame, 6000));
+        timeline.close(7000);
+
+        assert_eq!(timeline.intervals().len(), 2);
+        assert_eq!(timeline.intervals()[0].resumed_by, Some(2));
+        assert_eq!(timeline.paused_ms(1), 3000);
+        assert_eq!(timeline.paused_ms(2), 1000);
+        assert_eq!(timeline.total_paused_ms(), 4000);
+        assert_eq!(timeline.pause_count(2), 2);
+        assert_eq!(timeline.paused_ms_per_player().get(&1), Some(&3000));
+    }
+
+    #[test]
+    fn test_pause_timeline_open_at_end() {
+        let mut timeline = PauseTimeline::new();
+        timeline.record(&action(3, ActionType::ResumeGame, 500));
+        timeline.record(&action(3, ActionType::PauseGame, 1000));
+        assert!(timeline.is_paused());
+
+        timeline.close(2500);
+
+        assert!(!timeline.is_paused());
+        assert_eq!(timeline.intervals().len(), 1);
+        assert_eq!(timeline.intervals()[0].resumed_by, None);
+        assert_eq!(timeline.paused_ms(3), 1500);
+    }
+
+    #[test]
+    fn test_pause_timeline_from_iterator() {
+        let mut data = Vec::new();
+        // Frame at 100ms: player 1 pauses
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x06, 0x00, 0x64, 0x00]);
+        data.extend_from_slice(&[0x01, 0x01, 0x00, 0x01]);
+        // Frame at 300ms: nothing
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00, 0xC8, 0x00]);
+        // Frame at 600ms: player 2 resumes
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x06, 0x00, 0x2C, 0x01]);
+        data.extend_from_slice(&[0x02, 0x01, 0x00, 0x02]);
+
+        let timeline = PauseTimeline::from_iterator(TimeFrameIterator::new(&data, 0)).unwrap();
+
+        assert_eq!(timeline.intervals().len(), 1);
+        assert_eq!(timeline.paused_ms(1), 500);
+        assert_eq!(timeline.pause_count(1), 1);
+    }
+}
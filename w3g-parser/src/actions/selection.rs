@@ -13,24 +13,21 @@ use crate::error::{ParserError, Result};
 /// # Format
 ///
 /// ```text
-/// 16 [count: 1] [mode: 1] [flags: 1] [unit_ids: 8*count]
+/// 16 [mode: 1] [count: 2] [unit_ids: 8*count]
 /// ```
 ///
 /// Each unit uses 8 bytes (4-byte ID appearing twice or ID + counter).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SelectionAction {
     /// Number of units selected (1-12 typical).
-    pub unit_count: u8,
+    pub unit_count: u16,
 
-    /// Selection mode (1-10 observed).
-    /// - Mode 1: Most common, single selection
-    /// - Mode 5: Multi-unit selection
-    /// - Other modes: Under investigation
+    /// Selection mode.
+    /// - Mode 1: Units are added to the selection
+    /// - Mode 2: Units are removed from the selection
     pub mode: u8,
 
-    /// Flags byte (usually 0x00).
-    pub flags: u8,
-
     /// Object IDs of selected units.
     /// Each ID is 4 bytes, originally stored in 8-byte blocks.
     pub unit_ids: Vec<u32>,
@@ -52,7 +49,7 @@ impl SelectionAction {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x16
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x16
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
         if data.is_empty() {
@@ -60,23 +57,20 @@ impl SelectionAction {
         }
 
         if data[0] != Self::MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid selection marker: expected 0x{:02X}, found 0x{:02X}",
-                    Self::MARKER,
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{:02X}", Self::MARKER),
+            ));
         }
 
-        // Minimum size: marker + count + mode + flags = 4 bytes
+        // Minimum size: marker + mode + count = 4 bytes
         if data.len() < 4 {
             return Err(ParserError::unexpected_eof(4, data.len()));
         }
 
-        let unit_count = data[1];
-        let mode = data[2];
-        let flags = data[3];
+        let mode = data[1];
+        let unit_count = u16::from_le_bytes([data[2], data[3]]);
 
         // Calculate expected data size: 4 header bytes + 8 bytes per unit
         let expected_size = 4 + (unit_count as usize) * 8;
@@ -91,7 +85,6 @@ impl SelectionAction {
             SelectionAction {
                 unit_count,
                 mode,
-                flags,
                 unit_ids,
             },
             expected_size,
@@ -115,7 +108,7 @@ impl SelectionAction {
 ///
 /// Each unit is stored as an 8-byte block. We extract the first 4 bytes
 /// as the unit ID (the second 4 bytes may be a duplicate or counter).
-fn extract_unit_ids(data: &[u8], count: u8) -> Vec<u32> {
+fn extract_unit_ids(data: &[u8], count: u16) -> Vec<u32> {
     let mut ids = Vec::with_capacity(count as usize);
     for i in 0..count as usize {
         let offset = i * 8;
@@ -137,6 +130,7 @@ fn extract_unit_ids(data: &[u8], count: u8) -> Vec<u32> {
 /// Different modes represent different selection behaviors,
 /// though the exact semantics are still under investigation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SelectionMode {
     /// Replace current selection entirely (mode 1).
     Replace,
@@ -180,7 +174,7 @@ mod tests {
     fn test_selection_single_unit() {
         // From Rex's analysis: single unit selection
         let data: &[u8] = &[
-            0x16, 0x01, 0x01, 0x00, // Selection: 1 unit, mode 1, flags 0
+            0x16, 0x01, 0x01, 0x00, // Selection: mode 1, 1 unit
             0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Unit ID 0x00003A3B
         ];
 
@@ -188,7 +182,6 @@ mod tests {
 
         assert_eq!(sel.unit_count, 1);
         assert_eq!(sel.mode, 1);
-        assert_eq!(sel.flags, 0);
         assert_eq!(sel.unit_ids, vec![0x0000_3A3B]);
         assert_eq!(consumed, 12);
         assert!(!sel.is_multi_select());
@@ -199,7 +192,7 @@ mod tests {
     fn test_selection_multiple_units() {
         // From Rex's analysis: multi-unit selection
         let data: &[u8] = &[
-            0x16, 0x02, 0x05, 0x00, // Selection: 2 units, mode 5
+            0x16, 0x02, 0x02, 0x00, // Selection: mode 2, 2 units
             0x23, 0x3B, 0x00, 0x00, 0x26, 0x3B, 0x00, 0x00, // Unit 1
             0x39, 0x3B, 0x00, 0x00, 0x3C, 0x3B, 0x00, 0x00, // Unit 2
         ];
@@ -207,7 +200,7 @@ mod tests {
         let (sel, consumed) = SelectionAction::parse(data).unwrap();
 
         assert_eq!(sel.unit_count, 2);
-        assert_eq!(sel.mode, 5);
+        assert_eq!(sel.mode, 2);
         assert_eq!(sel.unit_ids.len(), 2);
         assert_eq!(sel.unit_ids[0], 0x0000_3B23);
         assert_eq!(sel.unit_ids[1], 0x0000_3B39);
@@ -219,12 +212,12 @@ mod tests {
     fn test_selection_invalid_marker() {
         let data: &[u8] = &[0x15, 0x01, 0x01, 0x00]; // Wrong marker
         let result = SelectionAction::parse(data);
-        assert!(matches!(result, Err(ParserError::InvalidHeader { .. })));
+        assert!(matches!(result, Err(ParserError::UnexpectedRecord { .. })));
     }
 
     #[test]
     fn test_selection_truncated() {
-        let data: &[u8] = &[0x16, 0x01, 0x01]; // Missing flags byte
+        let data: &[u8] = &[0x16, 0x01, 0x01]; // Missing count high byte
         let result = SelectionAction::parse(data);
         assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
     }
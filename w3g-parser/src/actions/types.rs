@@ -3,9 +3,9 @@
 //! This module defines the main `Action` struct and `ActionType` enum that
 //! represent parsed player actions.
 
-use super::ability::{AbilityAction, AbilityWithSelectionAction, InstantAbilityAction};
+use super::ability::AbilityCode;
 use super::hotkey::HotkeyAction;
-use super::movement::MovementAction;
+use super::movement::Position;
 use super::selection::SelectionAction;
 use std::fmt;
 
@@ -14,6 +14,7 @@ use std::fmt;
 /// Actions represent individual commands issued by players during gameplay.
 /// Each action has a player ID, timestamp, and action-specific data.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Action {
     /// Player ID who issued this action (1-15).
     pub player_id: u8,
@@ -61,125 +62,264 @@ impl fmt::Display for Action {
     }
 }
 
+/// Game speed setting used by the set game speed action (0x03).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameSpeed {
+    /// Slow speed (0x00).
+    Slow,
+    /// Normal speed (0x01).
+    Normal,
+    /// Fast speed (0x02), the default for ladder games.
+    #[default]
+    Fast,
+}
+
+impl GameSpeed {
+    /// Creates a game speed from its raw byte value.
+    ///
+    /// Returns `None` for values other than 0x00-0x02.
+    #[must_use]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x00 => Some(GameSpeed::Slow),
+            0x01 => Some(GameSpeed::Normal),
+            0x02 => Some(GameSpeed::Fast),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw byte value of this game speed.
+    #[must_use]
+    pub fn as_byte(self) -> u8 {
+        match self {
+            GameSpeed::Slow => 0x00,
+            GameSpeed::Normal => 0x01,
+            GameSpeed::Fast => 0x02,
+        }
+    }
+
+    /// Returns how fast game time advances relative to wall-clock time.
+    ///
+    /// Fast speed runs in real time; normal and slow run at 80% and 60%.
+    #[must_use]
+    pub fn rate(self) -> f64 {
+        match self {
+            GameSpeed::Slow => 0.6,
+            GameSpeed::Normal => 0.8,
+            GameSpeed::Fast => 1.0,
+        }
+    }
+
+    /// Returns the next faster speed, or the same speed if already fastest.
+    #[must_use]
+    pub fn faster(self) -> Self {
+        match self {
+            GameSpeed::Slow => GameSpeed::Normal,
+            GameSpeed::Normal | GameSpeed::Fast => GameSpeed::Fast,
+        }
+    }
+
+    /// Returns the next slower speed, or the same speed if already slowest.
+    #[must_use]
+    pub fn slower(self) -> Self {
+        match self {
+            GameSpeed::Fast => GameSpeed::Normal,
+            GameSpeed::Normal | GameSpeed::Slow => GameSpeed::Slow,
+        }
+    }
+}
+
+impl fmt::Display for GameSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameSpeed::Slow => write!(f, "slow"),
+            GameSpeed::Normal => write!(f, "normal"),
+            GameSpeed::Fast => write!(f, "fast"),
+        }
+    }
+}
+
+/// Arrow key used in an arrow key action (0x75).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArrowKey {
+    /// Left arrow key.
+    Left,
+    /// Right arrow key.
+    Right,
+    /// Down arrow key.
+    Down,
+    /// Up arrow key.
+    Up,
+}
+
+impl ArrowKey {
+    /// Decodes an arrow key event byte into the key and whether it was pressed.
+    ///
+    /// Even values are key presses, odd values releases:
+    /// 0/1 left, 2/3 right, 4/5 down, 6/7 up. Returns `None` for other values.
+    #[must_use]
+    pub fn from_event(event: u8) -> Option<(Self, bool)> {
+        let key = match event >> 1 {
+            0 => ArrowKey::Left,
+            1 => ArrowKey::Right,
+            2 => ArrowKey::Down,
+            3 => ArrowKey::Up,
+            _ => return None,
+        };
+        Some((key, event & 1 == 0))
+    }
+}
+
+impl fmt::Display for ArrowKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrowKey::Left => write!(f, "left"),
+            ArrowKey::Right => write!(f, "right"),
+            ArrowKey::Down => write!(f, "down"),
+            ArrowKey::Up => write!(f, "up"),
+        }
+    }
+}
+
 /// Enumeration of all known action types.
 ///
 /// Each variant contains the parsed data specific to that action type.
 /// Unknown actions preserve their raw data for debugging and forward compatibility.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
 pub enum ActionType {
-    /// Unit selection (0x16).
-    Selection(SelectionAction),
+    /// Pause game (0x01).
+    PauseGame,
 
-    /// Direct ability use (0x1A 0x19).
-    Ability(AbilityAction),
+    /// Resume game (0x02).
+    ResumeGame,
 
-    /// Ability use with selection (0x1A 0x00).
-    AbilityWithSelection(AbilityWithSelectionAction),
+    /// Set game speed (0x03), only available in single player and LAN games.
+    SetGameSpeed {
+        /// The new game speed.
+        speed: GameSpeed,
+    },
 
-    /// Instant ability (0x0F 0x00).
-    InstantAbility(InstantAbilityAction),
+    /// Increase game speed (0x04).
+    IncreaseGameSpeed,
 
-    /// Move/Attack command (0x00 0x0D).
-    Movement(MovementAction),
+    /// Decrease game speed (0x05).
+    DecreaseGameSpeed,
+
+    /// Save game (0x06).
+    SaveGame {
+        /// Name of the saved game file.
+        filename: String,
+    },
+
+    /// Save game finished (0x07).
+    SaveGameFinished {
+        /// Unknown value (usually 0x00000001).
+        unknown: u32,
+    },
+
+    /// Unit selection (0x16).
+    Selection(SelectionAction),
 
     /// Control group hotkey (0x17).
     Hotkey(HotkeyAction),
 
-    /// ESC key press - cancel action (0x18).
-    EscapeKey,
-
-    /// Item usage or drop (0x1B).
+    /// Select ground item (0x1C).
     ItemAction {
-        /// The item's FourCC identifier.
+        /// The item's object ID.
         item_id: u32,
     },
 
-    /// Basic command like stop, hold position, patrol (0x1C).
-    BasicCommand {
-        /// The command FourCC identifier.
-        command_id: u32,
-    },
-
-    /// Build or train command (0x1D).
-    BuildTrain {
-        /// The unit type's FourCC code.
-        unit_code: [u8; 4],
-    },
-
     /// Unit ability without target (0x10).
     UnitAbilityNoTarget {
-        /// Flags byte.
-        flags: u8,
+        /// Order flags.
+        flags: u16,
         /// The ability's FourCC code.
         ability_code: [u8; 4],
     },
 
     /// Unit ability with ground target (0x11).
     UnitAbilityGroundTarget {
-        /// Flags byte.
-        flags: u8,
+        /// Order flags.
+        flags: u16,
         /// The ability's FourCC code.
         ability_code: [u8; 4],
         /// Target X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x: f32,
         /// Target Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y: f32,
     },
 
     /// Unit ability with unit target (0x12).
     UnitAbilityUnitTarget {
-        /// Flags byte.
-        flags: u8,
+        /// Order flags.
+        flags: u16,
         /// The ability's FourCC code.
         ability_code: [u8; 4],
         /// Target unit ID.
         target_unit: u32,
         /// Target X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x: f32,
         /// Target Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y: f32,
     },
 
     /// Give or drop item (0x13).
     GiveDropItem {
-        /// Flags byte.
-        flags: u8,
+        /// Order flags.
+        flags: u16,
         /// Item FourCC code.
         item_code: [u8; 4],
         /// Target unit ID.
         target_unit: u32,
         /// Target X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x: f32,
         /// Target Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y: f32,
     },
 
     /// Unit ability with two position targets (0x14).
     UnitAbilityTwoTargets {
-        /// Flags byte.
-        flags: u8,
+        /// Order flags.
+        flags: u16,
         /// The ability's FourCC code.
         ability_code: [u8; 4],
         /// First target X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x1: f32,
         /// First target Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y1: f32,
         /// Second target X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x2: f32,
         /// Second target Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y2: f32,
     },
 
     /// Select subgroup (0x19).
     SelectSubgroup {
-        /// Item/subgroup index.
-        item: u8,
+        /// `FourCC` of the selected unit type.
+        unit_code: [u8; 4],
         /// Object ID 1.
         object_id1: u32,
         /// Object ID 2.
         object_id2: u32,
     },
 
+    /// Pre-subselection (0x1A), sent before a subgroup is selected.
+    PreSubselection,
+
     /// Remove unit from queue (0x1E).
     RemoveFromQueue {
         /// Slot position in queue.
@@ -206,24 +346,78 @@ pub enum ActionType {
         lumber: u32,
     },
 
+    /// Game cache sync, i.e. `SyncStoredInteger` (0x6B).
+    ///
+    /// Custom maps use this to transmit data between clients; the W3MMD
+    /// protocol (see [`W3mmdStats`](super::W3mmdStats)) is built on top of it.
+    GameCacheSync {
+        /// Game cache file name (e.g. "MMD.Dat").
+        filename: String,
+        /// Mission key (category) within the cache.
+        mission_key: String,
+        /// Key within the mission.
+        key: String,
+        /// Stored integer value.
+        value: u32,
+    },
+
+    /// Map trigger chat command (0x60), e.g. "-ar" in custom games.
+    TriggerChatCommand {
+        /// Unknown value.
+        unknown_1: u32,
+        /// Unknown value.
+        unknown_2: u32,
+        /// The chat command string.
+        message: String,
+    },
+
+    /// ESC key pressed while a trigger was listening for it (0x61).
+    TriggerEscapeKey,
+
+    /// Scenario trigger (0x62).
+    ScenarioTrigger {
+        /// Unknown value.
+        unknown_1: u32,
+        /// Unknown value.
+        unknown_2: u32,
+        /// Unknown value.
+        unknown_3: u32,
+    },
+
+    /// Enter choose hero skill submenu (0x66).
+    HeroSkillSubmenu,
+
+    /// Enter choose building submenu (0x67).
+    BuildingSubmenu,
+
     /// Minimap ping (0x68).
     MinimapPing {
         /// X coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         x: f32,
         /// Y coordinate.
+        #[cfg_attr(feature = "serde", serde(with = "super::movement::coordinate_serde"))]
         y: f32,
         /// Unknown data.
         unknown: u32,
     },
 
-    /// Battle.net sync action (0x15) - Reforged only.
+    /// Arrow key pressed or released (0x75).
+    ArrowKeyEvent {
+        /// The arrow key.
+        key: ArrowKey,
+        /// `true` if the key was pressed, `false` if released.
+        pressed: bool,
+    },
+
+    /// Battle.net sync action (0x78) - Reforged only.
     /// This action appears frequently in Reforged replays and contains
     /// what appears to be Base64-encoded synchronization data.
     BattleNetSync {
-        /// Marker/subcommand byte.
-        marker: u16,
-        /// Base64-like encoded data payload.
-        data: Vec<u8>,
+        /// Identifier of the synchronized value.
+        identifier: String,
+        /// Base64-like encoded value.
+        value: String,
     },
 
     /// Unknown action type - preserved for forward compatibility.
@@ -238,30 +432,49 @@ pub enum ActionType {
 }
 
 impl ActionType {
+    /// Names of all action types, as returned by [`type_name`](Self::type_name).
+    pub const NAMES: [&str; 30] = [
+        "PauseGame", "ResumeGame", "SetGameSpeed", "IncreaseGameSpeed", "DecreaseGameSpeed",
+        "SaveGame", "SaveGameFinished", "Selection", "Hotkey", "ItemAction",
+        "UnitAbilityNoTarget", "UnitAbilityGroundTarget", "UnitAbilityUnitTarget",
+        "GiveDropItem", "UnitAbilityTwoTargets", "SelectSubgroup", "PreSubselection",
+        "RemoveFromQueue", "ChangeAllyOptions", "TransferResources", "TriggerChatCommand",
+        "TriggerEscapeKey", "ScenarioTrigger", "HeroSkillSubmenu", "BuildingSubmenu",
+        "GameCacheSync", "MinimapPing", "ArrowKeyEvent", "BattleNetSync", "Unknown",
+    ];
+
     /// Returns the name of this action type.
     #[must_use]
     pub fn type_name(&self) -> &'static str {
         match self {
+            ActionType::PauseGame => "PauseGame",
+            ActionType::ResumeGame => "ResumeGame",
+            ActionType::SetGameSpeed { .. } => "SetGameSpeed",
+            ActionType::IncreaseGameSpeed => "IncreaseGameSpeed",
+            ActionType::DecreaseGameSpeed => "DecreaseGameSpeed",
+            ActionType::SaveGame { .. } => "SaveGame",
+            ActionType::SaveGameFinished { .. } => "SaveGameFinished",
             ActionType::Selection(_) => "Selection",
-            ActionType::Ability(_) => "Ability",
-            ActionType::AbilityWithSelection(_) => "AbilityWithSelection",
-            ActionType::InstantAbility(_) => "InstantAbility",
-            ActionType::Movement(_) => "Movement",
             ActionType::Hotkey(_) => "Hotkey",
-            ActionType::EscapeKey => "EscapeKey",
             ActionType::ItemAction { .. } => "ItemAction",
-            ActionType::BasicCommand { .. } => "BasicCommand",
-            ActionType::BuildTrain { .. } => "BuildTrain",
             ActionType::UnitAbilityNoTarget { .. } => "UnitAbilityNoTarget",
             ActionType::UnitAbilityGroundTarget { .. } => "UnitAbilityGroundTarget",
             ActionType::UnitAbilityUnitTarget { .. } => "UnitAbilityUnitTarget",
             ActionType::GiveDropItem { .. } => "GiveDropItem",
             ActionType::UnitAbilityTwoTargets { .. } => "UnitAbilityTwoTargets",
             ActionType::SelectSubgroup { .. } => "SelectSubgroup",
+            ActionType::PreSubselection => "PreSubselection",
             ActionType::RemoveFromQueue { .. } => "RemoveFromQueue",
             ActionType::ChangeAllyOptions { .. } => "ChangeAllyOptions",
             ActionType::TransferResources { .. } => "TransferResources",
+            ActionType::TriggerChatCommand { .. } => "TriggerChatCommand",
+            ActionType::TriggerEscapeKey => "TriggerEscapeKey",
+            ActionType::ScenarioTrigger { .. } => "ScenarioTrigger",
+            ActionType::HeroSkillSubmenu => "HeroSkillSubmenu",
+            ActionType::BuildingSubmenu => "BuildingSubmenu",
+            ActionType::GameCacheSync { .. } => "GameCacheSync",
             ActionType::MinimapPing { .. } => "MinimapPing",
+            ActionType::ArrowKeyEvent { .. } => "ArrowKeyEvent",
             ActionType::BattleNetSync { .. } => "BattleNetSync",
             ActionType::Unknown { .. } => "Unknown",
         }
@@ -271,15 +484,17 @@ impl ActionType {
     #[must_use]
     pub fn type_byte(&self) -> u8 {
         match self {
+            ActionType::PauseGame => 0x01,
+            ActionType::ResumeGame => 0x02,
+            ActionType::SetGameSpeed { .. } => 0x03,
+            ActionType::IncreaseGameSpeed => 0x04,
+            ActionType::DecreaseGameSpeed => 0x05,
+            ActionType::SaveGame { .. } => 0x06,
+            ActionType::SaveGameFinished { .. } => 0x07,
             ActionType::Selection(_) => 0x16,
-            ActionType::Ability(_) | ActionType::AbilityWithSelection(_) => 0x1A,
-            ActionType::InstantAbility(_) => 0x0F,
-            ActionType::Movement(_) => 0x00,
+            ActionType::PreSubselection => 0x1A,
             ActionType::Hotkey(_) => 0x17,
-            ActionType::EscapeKey => 0x18,
-            ActionType::ItemAction { .. } => 0x1B,
-            ActionType::BasicCommand { .. } => 0x1C,
-            ActionType::BuildTrain { .. } => 0x1D,
+            ActionType::ItemAction { .. } => 0x1C,
             ActionType::UnitAbilityNoTarget { .. } => 0x10,
             ActionType::UnitAbilityGroundTarget { .. } => 0x11,
             ActionType::UnitAbilityUnitTarget { .. } => 0x12,
@@ -289,71 +504,101 @@ impl ActionType {
             ActionType::RemoveFromQueue { .. } => 0x1E,
             ActionType::ChangeAllyOptions { .. } => 0x50,
             ActionType::TransferResources { .. } => 0x51,
+            ActionType::TriggerChatCommand { .. } => 0x60,
+            ActionType::TriggerEscapeKey => 0x61,
+            ActionType::ScenarioTrigger { .. } => 0x62,
+            ActionType::HeroSkillSubmenu => 0x66,
+            ActionType::BuildingSubmenu => 0x67,
+            ActionType::GameCacheSync { .. } => 0x6B,
             ActionType::MinimapPing { .. } => 0x68,
-            ActionType::BattleNetSync { .. } => 0x15,
+            ActionType::ArrowKeyEvent { .. } => 0x75,
+            ActionType::BattleNetSync { .. } => 0x78,
             ActionType::Unknown { type_id, .. } => *type_id,
         }
     }
 
+    /// Returns `true` if this action controls the game itself (pause, speed, save)
+    /// rather than units.
+    #[must_use]
+    pub fn is_game_control(&self) -> bool {
+        matches!(
+            self,
+            ActionType::PauseGame
+                | ActionType::ResumeGame
+                | ActionType::SetGameSpeed { .. }
+                | ActionType::IncreaseGameSpeed
+                | ActionType::DecreaseGameSpeed
+                | ActionType::SaveGame { .. }
+                | ActionType::SaveGameFinished { .. }
+        )
+    }
+
     /// Returns `true` if this is an unknown action type.
     #[must_use]
     pub fn is_unknown(&self) -> bool {
         matches!(self, ActionType::Unknown { .. })
     }
+
+    /// Returns the `FourCC` code of the ability, unit or item this action uses.
+    #[must_use]
+    pub fn object_code(&self) -> Option<AbilityCode> {
+        match self {
+            ActionType::UnitAbilityNoTarget {
+                ability_code: code, ..
+            }
+            | ActionType::UnitAbilityGroundTarget {
+                ability_code: code, ..
+            }
+            | ActionType::UnitAbilityUnitTarget {
+                ability_code: code, ..
+            }
+            | ActionType::UnitAbilityTwoTargets {
+                ability_code: code, ..
+            }
+            | ActionType::GiveDropItem {
+                item_code: code, ..
+            } => Some(AbilityCode::from_raw(*code)),
+            _ => None,
+        }
+    }
+
+    /// Returns the map position this action targets.
+    ///
+    /// For actions with two targets this is the first one.
+    #[must_use]
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            ActionType::UnitAbilityGroundTarget { x, y, .. }
+            | ActionType::UnitAbilityUnitTarget { x, y, .. }
+            | ActionType::GiveDropItem { x, y, .. }
+            | ActionType::UnitAbilityTwoTargets { x1: x, y1: y, .. }
+            | ActionType::MinimapPing { x, y, .. } => Some(Position::new(*x, *y)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for ActionType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            ActionType::PauseGame => write!(f, "PauseGame"),
+            ActionType::ResumeGame => write!(f, "ResumeGame"),
+            ActionType::SetGameSpeed { speed } => write!(f, "SetGameSpeed: {speed}"),
+            ActionType::IncreaseGameSpeed => write!(f, "IncreaseGameSpeed"),
+            ActionType::DecreaseGameSpeed => write!(f, "DecreaseGameSpeed"),
+            ActionType::SaveGame { filename } => write!(f, "SaveGame: {filename}"),
+            ActionType::SaveGameFinished { unknown } => {
+                write!(f, "SaveGameFinished: 0x{unknown:08X}")
+            }
             ActionType::Selection(sel) => {
                 write!(f, "Selection: {} unit(s), mode {}", sel.unit_count, sel.mode)
             }
-            ActionType::Ability(ab) => {
-                write!(f, "Ability: {}", ab.ability_code)
-            }
-            ActionType::AbilityWithSelection(ab) => {
-                write!(
-                    f,
-                    "AbilityWithSelection: {} (with {} unit(s))",
-                    ab.ability.ability_code, ab.selection.unit_count
-                )
-            }
-            ActionType::InstantAbility(ab) => {
-                write!(f, "InstantAbility: {}", ab.ability_code)
-            }
-            ActionType::Movement(mov) => {
-                write!(
-                    f,
-                    "Movement: ({:.1}, {:.1}){}",
-                    mov.x,
-                    mov.y,
-                    if mov.target_unit.is_some() {
-                        " (target unit)"
-                    } else {
-                        ""
-                    }
-                )
-            }
             ActionType::Hotkey(hk) => {
                 write!(f, "Hotkey: group {} {:?}", hk.group, hk.operation)
             }
-            ActionType::EscapeKey => {
-                write!(f, "EscapeKey: cancel")
-            }
             ActionType::ItemAction { item_id } => {
                 write!(f, "ItemAction: 0x{item_id:08X}")
             }
-            ActionType::BasicCommand { command_id } => {
-                write!(f, "BasicCommand: 0x{command_id:08X}")
-            }
-            ActionType::BuildTrain { unit_code } => {
-                let code_str = std::str::from_utf8(unit_code)
-                    .unwrap_or("????")
-                    .chars()
-                    .rev()
-                    .collect::<String>();
-                write!(f, "BuildTrain: {code_str}")
-            }
             ActionType::UnitAbilityNoTarget { ability_code, .. } => {
                 let code_str = std::str::from_utf8(ability_code)
                     .unwrap_or("????")
@@ -418,16 +663,19 @@ impl fmt::Display for ActionType {
                 )
             }
             ActionType::SelectSubgroup {
-                item,
+                unit_code,
                 object_id1,
                 object_id2,
             } => {
                 write!(
                     f,
-                    "SelectSubgroup: item {} (0x{:08X}, 0x{:08X})",
-                    item, object_id1, object_id2
+                    "SelectSubgroup: {} (0x{:08X}, 0x{:08X})",
+                    AbilityCode::from_raw(*unit_code),
+                    object_id1,
+                    object_id2
                 )
             }
+            ActionType::PreSubselection => write!(f, "PreSubselection"),
             ActionType::RemoveFromQueue { slot, unit_id } => {
                 write!(f, "RemoveFromQueue: slot {} unit 0x{:08X}", slot, unit_id)
             }
@@ -441,11 +689,39 @@ impl fmt::Display for ActionType {
                     slot, gold, lumber
                 )
             }
+            ActionType::TriggerChatCommand { message, .. } => {
+                write!(f, "TriggerChatCommand: {message}")
+            }
+            ActionType::TriggerEscapeKey => write!(f, "TriggerEscapeKey"),
+            ActionType::ScenarioTrigger {
+                unknown_1,
+                unknown_2,
+                unknown_3,
+            } => {
+                write!(
+                    f,
+                    "ScenarioTrigger: 0x{unknown_1:08X} 0x{unknown_2:08X} 0x{unknown_3:08X}"
+                )
+            }
+            ActionType::HeroSkillSubmenu => write!(f, "HeroSkillSubmenu"),
+            ActionType::BuildingSubmenu => write!(f, "BuildingSubmenu"),
+            ActionType::GameCacheSync {
+                filename,
+                mission_key,
+                key,
+                value,
+            } => {
+                write!(f, "GameCacheSync: {filename} [{mission_key}] {key} = {value}")
+            }
             ActionType::MinimapPing { x, y, .. } => {
                 write!(f, "MinimapPing: ({x:.1}, {y:.1})")
             }
-            ActionType::BattleNetSync { marker, data } => {
-                write!(f, "BattleNetSync: marker 0x{:04X} ({} bytes)", marker, data.len())
+            ActionType::ArrowKeyEvent { key, pressed } => {
+                let event = if *pressed { "pressed" } else { "released" };
+                write!(f, "ArrowKeyEvent: {key} {event}")
+            }
+            ActionType::BattleNetSync { identifier, value } => {
+                write!(f, "BattleNetSync: {identifier} = {value}")
             }
             ActionType::Unknown {
                 type_id,
@@ -476,7 +752,6 @@ mod tests {
         let sel = ActionType::Selection(SelectionAction {
             unit_count: 1,
             mode: 1,
-            flags: 0,
             unit_ids: vec![0x1234],
         });
         assert_eq!(sel.type_name(), "Selection");
@@ -488,6 +763,9 @@ mod tests {
         };
         assert_eq!(unknown.type_name(), "Unknown");
         assert!(unknown.is_unknown());
+
+        assert!(ActionType::NAMES.contains(&sel.type_name()));
+        assert!(ActionType::NAMES.contains(&ActionType::TriggerEscapeKey.type_name()));
     }
 
     #[test]
@@ -497,7 +775,6 @@ mod tests {
             ActionType::Selection(SelectionAction {
                 unit_count: 2,
                 mode: 1,
-                flags: 0,
                 unit_ids: vec![0x1234, 0x5678],
             }),
             1000,
@@ -508,4 +785,36 @@ mod tests {
         assert!(display.contains("1000ms"));
         assert!(display.contains("Selection"));
     }
+
+    #[test]
+    fn test_game_control_actions() {
+        let speed = ActionType::SetGameSpeed {
+            speed: GameSpeed::Fast,
+        };
+        assert_eq!(speed.type_name(), "SetGameSpeed");
+        assert_eq!(speed.type_byte(), 0x03);
+        assert_eq!(format!("{speed}"), "SetGameSpeed: fast");
+        assert!(speed.is_game_control());
+        assert!(ActionType::PauseGame.is_game_control());
+        assert!(!ActionType::TriggerEscapeKey.is_game_control());
+
+        assert_eq!(GameSpeed::from_byte(0x01), Some(GameSpeed::Normal));
+        assert_eq!(GameSpeed::from_byte(0x03), None);
+        assert_eq!(GameSpeed::Slow.as_byte(), 0x00);
+    }
+
+    #[test]
+    fn test_arrow_key_from_event() {
+        assert_eq!(ArrowKey::from_event(0), Some((ArrowKey::Left, true)));
+        assert_eq!(ArrowKey::from_event(3), Some((ArrowKey::Right, false)));
+        assert_eq!(ArrowKey::from_event(6), Some((ArrowKey::Up, true)));
+        assert_eq!(ArrowKey::from_event(8), None);
+
+        let event = ActionType::ArrowKeyEvent {
+            key: ArrowKey::Down,
+            pressed: false,
+        };
+        assert_eq!(format!("{event}"), "ArrowKeyEvent: down released");
+        assert_eq!(event.type_byte(), 0x75);
+    }
 }
@@ -0,0 +1,537 @@
+//! W3MMD (Warcraft 3 Map Meta Data) decoding.
+//!
+//! Custom maps report statistics through game cache sync actions (0x6B) with
+//! the file name `MMD.Dat`. Each message is sent as a pair of syncs:
+//!
+//! - mission key `val:<id>`: the message text in the key field
+//! - mission key `chk:<id>`: a checksum of the message (ignored here)
+//!
+//! Message texts are space-separated tokens; spaces and backslashes inside a
+//! token are escaped with a backslash. Supported messages:
+//!
+//! | Message | Description |
+//! |---------|-------------|
+//! | `init version <min> <current>` | Protocol version |
+//! | `init pid <pid> <name>` | Player ID to name mapping |
+//! | `DefVarP <name> <type> <goal> <suggest>` | Player variable definition |
+//! | `VarP <pid> <name> <op> <value>` | Player variable update (`=`, `+=`, `-=`) |
+//! | `FlagP <pid> <flag>` | Player result (`winner`, `loser`, `drawer`, `leaver`, `practicing`) |
+//! | `DefEvent <name> <argc> [args...] <format>` | Event definition |
+//! | `Event <name> [args...]` | Event occurrence |
+//!
+//! Every client sends the same messages, so duplicates are dropped.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use w3g_parser::actions::W3mmdStats;
+//!
+//! let stats = W3mmdStats::from_iterator(game.timeframes(&decompressed))?;
+//! for player in stats.players.values() {
+//!     println!("{}: kills = {:?}", player.name, player.vars.get("kills"));
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use super::types::{Action, ActionType};
+use crate::error::Result;
+use crate::records::TimeFrameIterator;
+
+/// Game cache file name used by W3MMD.
+pub const W3MMD_FILENAME: &str = "MMD.Dat";
+
+/// A W3MMD variable value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MmdValue {
+    /// Integer value.
+    Int(i64),
+    /// Real (floating point) value.
+    Real(f64),
+    /// String value.
+    String(String),
+}
+
+impl MmdValue {
+    /// Parses a value, choosing the type from its text.
+    fn parse(text: &str) -> Self {
+        if let Ok(value) = text.parse::<i64>() {
+            MmdValue::Int(value)
+        } else if let Ok(value) = text.parse::<f64>() {
+            MmdValue::Real(value)
+        } else {
+            MmdValue::String(text.trim_matches('"').to_string())
+        }
+    }
+
+    /// Applies an update operator (`=`, `+=`, `-=`) with `operand` to this value.
+    ///
+    /// Arithmetic on mismatched types replaces the value. Integer arithmetic
+    /// saturates at the bounds of `i64`, as map scripts can send any value.
+    fn apply(&mut self, op: &str, operand: MmdValue) {
+        let subtract = match op {
+            "+=" => false,
+            "-=" => true,
+            _ => {
+                *self = operand;
+                return;
+            }
+        };
+
+        *self = match (self.as_real(), operand.as_real(), &*self, &operand) {
+            (_, _, MmdValue::Int(a), MmdValue::Int(b)) => {
+                MmdValue::Int(if subtract { a.saturating_sub(*b) } else { a.saturating_add(*b) })
+            }
+            (Some(a), Some(b), _, _) => MmdValue::Real(if subtract { a - b } else { a + b }),
+            _ => operand,
+        };
+    }
+
+    /// Returns the value as a real number, if it is numeric.
+    #[allow(clippy::cast_precision_loss)]
+    fn as_real(&self) -> Option<f64> {
+        match self {
+            MmdValue::Int(v) => Some(*v as f64),
+            MmdValue::Real(v) => Some(*v),
+            MmdValue::String(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for MmdValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MmdValue::Int(v) => write!(f, "{v}"),
+            MmdValue::Real(v) => write!(f, "{v}"),
+            MmdValue::String(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// A player result flag reported with `FlagP`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MmdFlag {
+    /// The player won.
+    Winner,
+    /// The player lost.
+    Loser,
+    /// The game was a draw for the player.
+    Drawer,
+    /// The player left the game.
+    Leaver,
+    /// The player was practicing; the result should not count.
+    Practicing,
+    /// A flag not defined by the protocol.
+    Other(String),
+}
+
+impl MmdFlag {
+    fn parse(text: &str) -> Self {
+        match text {
+            "winner" => MmdFlag::Winner,
+            "loser" => MmdFlag::Loser,
+            "drawer" => MmdFlag::Drawer,
+            "leaver" => MmdFlag::Leaver,
+            "practicing" => MmdFlag::Practicing,
+            other => MmdFlag::Other(other.to_string()),
+        }
+    }
+}
+
+/// A player variable definition (`DefVarP`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmdVarDef {
+    /// Variable type: `int`, `real` or `string`.
+    pub var_type: String,
+    /// Goal: `high`, `low` or `none`.
+    pub goal: String,
+    /// Suggested use: `none`, `track` or `leaderboard`.
+    pub suggest: String,
+}
+
+/// An event definition (`DefEvent`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmdEventDef {
+    /// Names of the event arguments.
+    pub arg_names: Vec<String>,
+    /// Display format with `{0}`, `{1}`, ... placeholders.
+    pub format: String,
+}
+
+/// An event occurrence (`Event`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmdEvent {
+    /// Game time in milliseconds the event was received.
+    pub timestamp_ms: u32,
+    /// Event name.
+    pub name: String,
+    /// Event arguments.
+    pub args: Vec<String>,
+}
+
+/// Statistics reported by the map for a single player.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmdPlayer {
+    /// Player name from `init pid`.
+    pub name: String,
+    /// Current variable values.
+    pub vars: HashMap<String, MmdValue>,
+    /// Result flags in the order they were reported.
+    pub flags: Vec<MmdFlag>,
+}
+
+impl MmdPlayer {
+    /// Returns the final result flag (the last one reported), if any.
+    #[must_use]
+    pub fn result(&self) -> Option<&MmdFlag> {
+        self.flags.last()
+    }
+}
+
+/// Statistics rebuilt from a replay's W3MMD messages.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct W3mmdStats {
+    /// Protocol version as (minimum, current), from `init version`.
+    pub version: Option<(u32, u32)>,
+
+    /// Players keyed by W3MMD player ID.
+    pub players: BTreeMap<u32, MmdPlayer>,
+
+    /// Player variable definitions keyed by name.
+    pub var_defs: HashMap<String, MmdVarDef>,
+
+    /// Event definitions keyed by name.
+    pub event_defs: HashMap<String, MmdEventDef>,
+
+    /// Events in stream order.
+    pub events: Vec<MmdEvent>,
+
+    /// Number of messages that could not be decoded.
+    pub unparsed_messages: usize,
+
+    /// Already processed messages as (mission key, message).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    seen: HashSet<(String, String)>,
+}
+
+impl W3mmdStats {
+    /// Creates new empty statistics.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds W3MMD statistics from an iterator of `TimeFrame` records.
+    ///
+    /// Actions that fail to parse are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any `TimeFrame` in the iterator fails to parse.
+    pub fn from_iterator(iter: TimeFrameIterator<'_>) -> Result<Self> {
+        let mut stats = W3mmdStats::new();
+
+        for result in iter {
+            let frame = result?;
+            for action in frame.actions().flatten() {
+                stats.record(&action);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Returns whether no W3MMD data was recorded.
+    ///
+    /// `Blank` and `Custom` messages carry no data and are not counted.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.players.is_empty()
+            && self.var_defs.is_empty()
+            && self.event_defs.is_empty()
+            && self.events.is_empty()
+            && self.unparsed_messages == 0
+    }
+
+    /// Records an action; everything except W3MMD value messages is ignored.
+    pub fn record(&mut self, action: &Action) {
+        let ActionType::GameCacheSync {
+            filename,
+            mission_key,
+            key,
+            ..
+        } = &action.action_type
+        else {
+            return;
+        };
+
+        if filename != W3MMD_FILENAME || !mission_key.starts_with("val:") {
+            return;
+        }
+
+        if !self.seen.insert((mission_key.clone(), key.clone())) {
+            return;
+        }
+
+        if self.apply_message(key, action.timestamp_ms).is_none() {
+            self.unparsed_messages += 1;
+        }
+    }
+
+    /// Returns the player ID of every player with the given result flag.
+    #[must_use]
+    pub fn players_with_flag(&self, flag: &MmdFlag) -> Vec<u32> {
+        self.players
+            .iter()
+            .filter(|(_, player)| player.result() == Some(flag))
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    /// Formats an event using its definition, if the definition is known.
+    #[must_use]
+    pub fn format_event(&self, event: &MmdEvent) -> Option<String> {
+        let def = self.event_defs.get(&event.name)?;
+        let mut text = def.format.clone();
+        for (i, arg) in event.args.iter().enumerate() {
+            text = text.replace(&format!("{{{i}}}"), arg);
+        }
+        Some(text)
+    }
+
+    /// Applies a single message. Returns `None` if it is malformed.
+    fn apply_message(&mut self, message: &str, timestamp_ms: u32) -> Option<()> {
+        let tokens = tokenize(message);
+        let args: Vec<&str> = tokens.iter().map(String::as_str).collect();
+
+        match args.as_slice() {
+            ["init", "version", min, current, ..] => {
+                self.version = Some((min.parse().ok()?, current.parse().ok()?));
+            }
+            ["init", "pid", pid, name, ..] => {
+                self.players.entry(pid.parse().ok()?).or_default().name = (*name).to_string();
+            }
+            ["DefVarP", name, var_type, goal, suggest, ..] => {
+                self.var_defs.insert(
+                    (*name).to_string(),
+                    MmdVarDef {
+                        var_type: (*var_type).to_string(),
+                        goal: (*goal).to_string(),
+                        suggest: (*suggest).to_string(),
+                    },
+                );
+            }
+            ["VarP", pid, name, op, value, ..] => {
+                let operand = MmdValue::parse(value);
+                let player = self.players.entry(pid.parse().ok()?).or_default();
+                player
+                    .vars
+                    .entry((*name).to_string())
+                    .or_insert(MmdValue::Int(0))
+                    .apply(op, operand);
+            }
+            ["FlagP", pid, flag, ..] => {
+                let player = self.players.entry(pid.parse().ok()?).or_default();
+                player.flags.push(MmdFlag::parse(flag));
+            }
+            ["DefEvent", name, arg_count, rest @ ..] => {
+                let arg_count: usize = arg_count.parse().ok()?;
+                if rest.len() < arg_count + 1 {
+                    return None;
+                }
+                self.event_defs.insert(
+                    (*name).to_string(),
+                    MmdEventDef {
+                        arg_names: rest[..arg_count].iter().map(|s| (*s).to_string()).collect(),
+                        format: rest[arg_count].to_string(),
+                    },
+                );
+            }
+            ["Event", name, args @ ..] => {
+                self.events.push(MmdEvent {
+                    timestamp_ms,
+                    name: (*name).to_string(),
+                    args: args.iter().map(|s| (*s).to_string()).collect(),
+                });
+            }
+            ["Blank" | "Custom", ..] => {}
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+/// Splits a message into tokens, honoring backslash escapes.
+fn tokenize(message: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = message.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ' ' => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mmd(id: u32, message: &str, timestamp_ms: u32) -> Action {
+        Action::new(
+            1,
+            ActionType::GameCacheSync {
+                filename: W3MMD_FILENAME.to_string(),
+                mission_key: format!("val:{id}"),
+                key: message.to_string(),
+                value: 0,
+            },
+            timestamp_ms,
+        )
+    }
+
+    #[test]
+    fn test_tokenize_escapes() {
+        assert_eq!(
+            tokenize(r"init pid 0 Some\ Name"),
+            vec!["init", "pid", "0", "Some Name"]
+        );
+        assert_eq!(tokenize(r"a\\b  c"), vec![r"a\b", "c"]);
+    }
+
+    #[test]
+    fn test_w3mmd_players_and_vars() {
+        let mut stats = W3mmdStats::new();
+        for (id, message) in [
+            "init version 0 1",
+            "init pid 0 Alice",
+            "init pid 1 Bob",
+            "DefVarP kills int high leaderboard",
+            "VarP 0 kills = 3",
+            "VarP 0 kills += 2",
+            "VarP 1 kills -= 1",
+            "VarP 1 hero = \"Pandaren\"",
+            "FlagP 0 winner",
+            "FlagP 1 loser",
+        ]
+        .iter()
+        .enumerate()
+        {
+            stats.record(&mmd(u32::try_from(id).unwrap(), message, 1000));
+        }
+
+        assert_eq!(stats.version, Some((0, 1)));
+        assert_eq!(stats.players[&0].name, "Alice");
+        assert_eq!(stats.players[&0].vars["kills"], MmdValue::Int(5));
+        assert_eq!(stats.players[&1].vars["kills"], MmdValue::Int(-1));
+        assert_eq!(
+            stats.players[&1].vars["hero"],
+            MmdValue::String("Pandaren".to_string())
+        );
+        assert_eq!(stats.var_defs["kills"].goal, "high");
+        assert_eq!(stats.players_with_flag(&MmdFlag::Winner), vec![0]);
+        assert_eq!(stats.players[&1].result(), Some(&MmdFlag::Loser));
+        assert_eq!(stats.unparsed_messages, 0);
+    }
+
+    #[test]
+    fn test_w3mmd_int_overflow_saturates() {
+        let mut stats = W3mmdStats::new();
+        for (id, message) in [
+            "init pid 0 Alice",
+            &format!("VarP 0 gold = {}", i64::MAX),
+            "VarP 0 gold += 1",
+            &format!("VarP 0 lumber = {}", i64::MIN),
+            "VarP 0 lumber -= 1",
+        ]
+        .iter()
+        .enumerate()
+        {
+            stats.record(&mmd(u32::try_from(id).unwrap(), message, 1000));
+        }
+
+        assert_eq!(stats.players[&0].vars["gold"], MmdValue::Int(i64::MAX));
+        assert_eq!(stats.players[&0].vars["lumber"], MmdValue::Int(i64::MIN));
+    }
+
+    #[test]
+    fn test_w3mmd_events() {
+        let mut stats = W3mmdStats::new();
+        stats.record(&mmd(0, r"DefEvent kill 2 killer victim {0}\ killed\ {1}", 0));
+        stats.record(&mmd(1, "Event kill 0 1", 5000));
+
+        assert_eq!(stats.events.len(), 1);
+        assert_eq!(stats.events[0].timestamp_ms, 5000);
+        assert_eq!(
+            stats.format_event(&stats.events[0]).as_deref(),
+            Some("0 killed 1")
+        );
+    }
+
+    #[test]
+    fn test_w3mmd_duplicates_and_checksums() {
+        let mut stats = W3mmdStats::new();
+        // Every client sends the same message
+        stats.record(&mmd(2, "VarP 0 kills += 1", 1000));
+        stats.record(&mmd(2, "VarP 0 kills += 1", 1000));
+        // The same message text with a new id is a new message
+        stats.record(&mmd(3, "VarP 0 kills += 1", 2000));
+        // Checksums are ignored
+        stats.record(&Action::new(
+            1,
+            ActionType::GameCacheSync {
+                filename: W3MMD_FILENAME.to_string(),
+                mission_key: "chk:3".to_string(),
+                key: "3".to_string(),
+                value: 0x1234,
+            },
+            2000,
+        ));
+        stats.record(&mmd(4, "garbage", 3000));
+
+        assert_eq!(stats.players[&0].vars["kills"], MmdValue::Int(2));
+        assert_eq!(stats.unparsed_messages, 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_w3mmd_serde_skips_seen() {
+        let mut stats = W3mmdStats::new();
+        assert!(stats.is_empty());
+        stats.record(&mmd(0, "init version 0 1", 0));
+        assert!(!stats.is_empty());
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert!(json.get("seen").is_none());
+        let restored: W3mmdStats = serde_json::from_value(json).unwrap();
+        assert!(!restored.is_empty());
+        assert_eq!(restored.version, Some((0, 1)));
+    }
+}
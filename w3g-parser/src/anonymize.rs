@@ -0,0 +1,591 @@
+//! Replay anonymisation.
+//!
+//! [`anonymize`] rewrites a replay so it can be published without player
+//! identities:
+//!
+//! - Host, player and game creator names are replaced by pseudonyms derived
+//!   from a salted SHA-256 hash. The same name always maps to the same
+//!   pseudonym for a given salt, so players can still be followed across a
+//!   dataset.
+//! - Names in the lobby name, in chat messages and in game cache syncs (0x6B,
+//!   used by W3MMD stats) are replaced the same way. W3MMD message checksums
+//!   are not updated.
+//! - `BattleTags`, known names and IP addresses are wiped from the Reforged
+//!   protobuf metadata: the GRBN metadata blob and the 0x39 extended metadata
+//!   records.
+//! - Chat can be kept, redacted or dropped (see [`ChatPolicy`]).
+//!
+//! The result is written with [`ReplayWriter`], so sizes and checksums are
+//! valid. For GRBN replays the original 128-byte header is kept and the
+//! metadata blob and embedded Classic replay are replaced.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::anonymize::{anonymize, AnonymizeOptions, ChatPolicy};
+//!
+//! let data = std::fs::read("replay.w3g")?;
+//! let options = AnonymizeOptions::new("dataset-salt").chat(ChatPolicy::Redact);
+//! std::fs::write("anonymous.w3g", anonymize(&data, &options)?)?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::net::IpAddr;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+
+use crate::actions::{ActionContext, ActionIterator, ActionType};
+use crate::binary::read_u16_le;
+use crate::decompress::grbn::{decompress_metadata_zlib, find_classic_header};
+use crate::error::{ParserError, Result};
+use crate::header::grbn::GRBN_DATA_OFFSET;
+use crate::header::Header;
+use crate::records::{GameRecordHeader, Record};
+use crate::replay::{ParseOptions, Replay};
+use crate::writer::ReplayWriter;
+
+/// Replacement text for redacted chat messages.
+pub const REDACTED_MESSAGE: &str = "[redacted]";
+
+/// Names shorter than this are not replaced inside longer text.
+const MIN_SUBSTRING_NAME_LEN: usize = 3;
+
+/// What to do with chat messages when anonymising.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChatPolicy {
+    /// Keep messages, replacing player names in the text.
+    #[default]
+    Keep,
+    /// Replace every message with [`REDACTED_MESSAGE`].
+    Redact,
+    /// Remove chat records entirely.
+    Drop,
+}
+
+/// Options controlling [`anonymize`].
+#[derive(Debug, Clone)]
+pub struct AnonymizeOptions {
+    salt: String,
+    chat: ChatPolicy,
+}
+
+impl AnonymizeOptions {
+    /// Creates options using `salt` for the pseudonym hash.
+    ///
+    /// Keep the salt secret: anyone who knows it can test candidate names
+    /// against the pseudonyms.
+    #[must_use]
+    pub fn new(salt: impl Into<String>) -> Self {
+        Self {
+            salt: salt.into(),
+            chat: ChatPolicy::default(),
+        }
+    }
+
+    /// Sets the chat policy.
+    #[must_use]
+    pub fn chat(mut self, policy: ChatPolicy) -> Self {
+        self.chat = policy;
+        self
+    }
+
+    /// Returns the chat policy.
+    #[must_use]
+    pub fn chat_policy(&self) -> ChatPolicy {
+        self.chat
+    }
+
+    /// Returns the pseudonym for `name`.
+    ///
+    /// Pseudonyms are `Player-` followed by the first 8 hex digits of
+    /// `SHA-256(salt || 0x00 || name)`, which fits the 15-character name limit.
+    #[must_use]
+    pub fn pseudonym(&self, name: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        let digest = hasher.finalize();
+
+        let mut pseudonym = String::from("Player-");
+        for byte in &digest[..4] {
+            let _ = write!(pseudonym, "{byte:02x}");
+        }
+        pseudonym
+    }
+}
+
+/// Anonymises a replay file and returns the rewritten file.
+///
+/// # Errors
+///
+/// - Any error from parsing the replay or framing its records
+/// - `ParserError::InvalidAction` or `ParserError::UnexpectedEof` if the
+///   actions of a `TimeFrame` do not decode, since they could hide names
+/// - `ParserError::WriteError` if Reforged metadata is not a protobuf
+///   message or no longer fits the GRBN metadata area
+pub fn anonymize(data: &[u8], options: &AnonymizeOptions) -> Result<Vec<u8>> {
+    match Header::parse(data)? {
+        Header::Classic(_) => Ok(anonymize_classic(data, options)?.0),
+        Header::Grbn(_) => anonymize_grbn(data, options),
+    }
+}
+
+/// Anonymises a Classic replay, returning it and the names that were replaced.
+fn anonymize_classic<'a>(
+    data: &[u8],
+    options: &'a AnonymizeOptions,
+) -> Result<(Vec<u8>, Names<'a>)> {
+    let parse_options = ParseOptions::new().actions(false).chat(false);
+    let replay = Replay::from_bytes_with(data, &parse_options)?;
+    let Header::Classic(header) = &replay.header else {
+        return Err(ParserError::WriteError {
+            reason: "expected a Classic format replay".to_string(),
+        });
+    };
+
+    let records = replay
+        .records()?
+        .map(|result| result.map(|(_, record)| record))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut known = vec![replay.game.header.host_name.clone()];
+    known.extend(replay.game.header.creator_name());
+    known.extend(records.iter().filter_map(|record| match record {
+        Record::Player(slot) => Some(slot.player_name.clone()),
+        _ => None,
+    }));
+    let names = Names::new(options, known);
+
+    let mut writer = ReplayWriter::new(header.clone());
+    writer.write_game_header(&names.game_header(&replay.game.header)?);
+    for record in records {
+        if let Some(record) = names.record(record)? {
+            writer.write_record(&record);
+        }
+    }
+
+    Ok((writer.finish()?, names))
+}
+
+/// Anonymises a GRBN replay: the embedded Classic replay and the metadata blob.
+fn anonymize_grbn(data: &[u8], options: &AnonymizeOptions) -> Result<Vec<u8>> {
+    let classic_offset = find_classic_header(data).ok_or_else(|| {
+        ParserError::DecompressionError {
+            reason: "No embedded Classic replay found in GRBN file".to_string(),
+        }
+    })?;
+    let (classic, names) = anonymize_classic(&data[classic_offset..], options)?;
+
+    let metadata = names.protobuf(&decompress_metadata_zlib(data)?)?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&metadata)?;
+    let compressed = encoder.finish()?;
+
+    if GRBN_DATA_OFFSET + compressed.len() > classic_offset {
+        return Err(ParserError::WriteError {
+            reason: format!(
+                "anonymised GRBN metadata ({} bytes) does not fit the {} byte metadata area",
+                compressed.len(),
+                classic_offset - GRBN_DATA_OFFSET
+            ),
+        });
+    }
+
+    let mut out = data[..GRBN_DATA_OFFSET].to_vec();
+    out.extend(compressed);
+    out.resize(classic_offset, 0);
+    out.extend(classic);
+    Ok(out)
+}
+
+/// Known player names and their replacement rules.
+struct Names<'a> {
+    options: &'a AnonymizeOptions,
+    /// Known names, longest first so that substring replacement prefers them.
+    known: Vec<String>,
+}
+
+impl<'a> Names<'a> {
+    fn new(options: &'a AnonymizeOptions, mut known: Vec<String>) -> Self {
+        known.retain(|name| !name.is_empty());
+        known.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        known.dedup();
+        Self { options, known }
+    }
+
+    /// Replaces every known name inside `text` in a single pass.
+    fn replace_in(&self, text: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len());
+        let mut i = 0;
+        'text: while i < text.len() {
+            for name in &self.known {
+                if name.len() >= MIN_SUBSTRING_NAME_LEN && text[i..].starts_with(name.as_bytes()) {
+                    out.extend(self.options.pseudonym(name).into_bytes());
+                    i += name.len();
+                    continue 'text;
+                }
+            }
+            out.push(text[i]);
+            i += 1;
+        }
+        out
+    }
+
+    /// Returns the replacement for a protobuf string field, if it identifies a player.
+    ///
+    /// IP addresses are wiped, known names and `BattleTags` are replaced as a
+    /// whole, and known names inside longer text are replaced in place.
+    fn rewrite_string(&self, text: &str) -> Option<String> {
+        if text.parse::<IpAddr>().is_ok() {
+            return Some("0.0.0.0".to_string());
+        }
+        if is_battle_tag(text) {
+            // The BattleTag of a known name maps to that name's pseudonym
+            let name = text.rsplit_once('#').map_or(text, |(name, _)| name);
+            let known = self.known.iter().any(|known| known == name);
+            return Some(self.options.pseudonym(if known { name } else { text }));
+        }
+        if self.known.iter().any(|name| name == text) {
+            return Some(self.options.pseudonym(text));
+        }
+        let replaced = self.replace_in(text.as_bytes());
+        (replaced != text.as_bytes()).then(|| String::from_utf8_lossy(&replaced).into_owned())
+    }
+
+    /// Rewrites the strings of a protobuf message.
+    fn protobuf(&self, message: &[u8]) -> Result<Vec<u8>> {
+        rewrite_message(message, &|text| self.rewrite_string(text)).ok_or_else(|| {
+            ParserError::WriteError {
+                reason: "Reforged metadata is not a protobuf message".to_string(),
+            }
+        })
+    }
+
+    fn game_header(&self, header: &GameRecordHeader) -> Result<GameRecordHeader> {
+        let mut header = header.clone();
+        header.host_name = self.options.pseudonym(&header.host_name);
+        header.additional_data =
+            String::from_utf8_lossy(&self.replace_in(header.additional_data.as_bytes()))
+                .into_owned();
+
+        if let Some(range) = header.settings_string_range() {
+            // Lobby name: everything before the encoded settings string
+            let lobby = self.replace_in(&header.encoded_settings[..range.start]);
+            header.encoded_settings.splice(..range.start, lobby);
+        }
+
+        if let Some(creator) = header.creator_name().filter(|name| !name.is_empty()) {
+            header.set_creator_name(&self.options.pseudonym(&creator))?;
+        }
+
+        Ok(header)
+    }
+
+    fn record(&self, record: Record) -> Result<Option<Record>> {
+        Ok(Some(match record {
+            Record::Player(mut slot) => {
+                slot.player_name = self.options.pseudonym(&slot.player_name);
+                slot.byte_length = slot.encode().len();
+                Record::Player(slot)
+            }
+            Record::TimeFrame {
+                marker,
+                time_increment_ms,
+                commands,
+            } => Record::TimeFrame {
+                marker,
+                time_increment_ms,
+                commands: self.commands(&commands)?,
+            },
+            Record::ExtendedMetadata { subtype, data } => Record::ExtendedMetadata {
+                subtype,
+                data: self.protobuf(&data)?,
+            },
+            Record::Chat {
+                player_id,
+                flags,
+                mode,
+                message,
+            } => {
+                let message = match self.options.chat {
+                    ChatPolicy::Keep => self.replace_in(&message),
+                    ChatPolicy::Redact => REDACTED_MESSAGE.as_bytes().to_vec(),
+                    ChatPolicy::Drop => return Ok(None),
+                };
+                Record::Chat {
+                    player_id,
+                    flags,
+                    mode,
+                    message,
+                }
+            }
+            other => other,
+        }))
+    }
+
+    /// Rewrites the command blocks of a `TimeFrame`.
+    ///
+    /// Each block is a player ID, a `u16` length and the player's actions.
+    /// Commands that do not decode are an error rather than copied, as they
+    /// may hide names.
+    fn commands(&self, commands: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(commands.len());
+        let mut offset = 0;
+        while offset < commands.len() {
+            let end = offset + 3 + usize::from(read_u16_le(commands, offset + 1)?);
+            let block = commands
+                .get(offset..end)
+                .ok_or_else(|| ParserError::unexpected_eof(end - offset, commands.len() - offset))?;
+            let segment = format!("command block at 0x{offset:X}");
+            let actions = self
+                .game_cache_syncs(block)
+                .map_err(|e| e.with_context(offset, &segment))?;
+            let len = u16::try_from(actions.len()).map_err(|_| ParserError::WriteError {
+                reason: format!("anonymised {segment} exceeds 65535 bytes"),
+            })?;
+            out.push(block[0]);
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend(actions);
+            offset = end;
+        }
+        Ok(out)
+    }
+
+    /// Returns the actions of a command block with known names replaced in
+    /// the strings of game cache syncs (0x6B).
+    ///
+    /// Structure: 0x6B [filename\0] [mission key\0] [key\0] [value u32]
+    fn game_cache_syncs(&self, block: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(block.len());
+        let mut actions = ActionIterator::new(block, ActionContext::default());
+        while let Some(result) = actions.next() {
+            let action = result?;
+            let bytes = &block[actions.action_offset()..actions.current_offset()];
+            if let ActionType::GameCacheSync { .. } = action.action_type {
+                let strings_end = bytes.len() - 4;
+                out.push(bytes[0]);
+                out.extend(self.replace_in(&bytes[1..strings_end]));
+                out.extend_from_slice(&bytes[strings_end..]);
+            } else {
+                out.extend_from_slice(bytes);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Returns whether `text` looks like a `BattleTag` (`Name#1234`).
+fn is_battle_tag(text: &str) -> bool {
+    let Some((name, number)) = text.rsplit_once('#') else {
+        return false;
+    };
+    !name.is_empty()
+        && !name.chars().any(char::is_whitespace)
+        && (3..=7).contains(&number.len())
+        && number.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Reads a protobuf varint, returning the value and the new offset.
+fn read_varint(data: &[u8], mut offset: usize) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(offset)?;
+        offset += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Some((value, offset));
+        }
+    }
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        #[allow(clippy::cast_possible_truncation)]
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    out.push(value as u8);
+}
+
+/// Returns whether a length-delimited field is better treated as text than as a message.
+fn is_text(bytes: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    (!text.chars().any(char::is_control)).then_some(text)
+}
+
+/// Rewrites the string fields of a protobuf message without a schema.
+///
+/// Length-delimited fields holding printable UTF-8 are passed to `rewrite`;
+/// other length-delimited fields are rewritten recursively if they parse as
+/// messages and copied otherwise. Returns `None` if `message` is not a
+/// well-formed protobuf message.
+fn rewrite_message(message: &[u8], rewrite: &dyn Fn(&str) -> Option<String>) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(message.len());
+    let mut offset = 0;
+
+    while offset < message.len() {
+        let (key, value_start) = read_varint(message, offset)?;
+        if key >> 3 == 0 {
+            return None;
+        }
+        out.extend_from_slice(&message[offset..value_start]);
+
+        offset = match key & 0x07 {
+            0 => read_varint(message, value_start)?.1,
+            1 => value_start + 8,
+            5 => value_start + 4,
+            2 => {
+                let (len, payload_start) = read_varint(message, value_start)?;
+                let payload_end = payload_start.checked_add(usize::try_from(len).ok()?)?;
+                let payload = message.get(payload_start..payload_end)?;
+
+                let replaced = match is_text(payload) {
+                    Some(text) => rewrite(text).map(String::into_bytes),
+                    None if payload.is_empty() => None,
+                    None => rewrite_message(payload, rewrite),
+                };
+                let payload = replaced.as_deref().unwrap_or(payload);
+                write_varint(&mut out, payload.len() as u64);
+                out.extend_from_slice(payload);
+                offset = payload_end;
+                continue;
+            }
+            _ => return None,
+        };
+
+        out.extend_from_slice(message.get(value_start..offset)?);
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonym_is_stable_and_salted() {
+        let options = AnonymizeOptions::new("salt");
+        let pseudonym = options.pseudonym("Grubby#1234");
+        assert_eq!(pseudonym, options.pseudonym("Grubby#1234"));
+        assert_eq!(pseudonym.len(), 15);
+        assert!(pseudonym.starts_with("Player-"));
+        assert_ne!(pseudonym, AnonymizeOptions::new("other").pseudonym("Grubby#1234"));
+        assert_ne!(pseudonym, options.pseudonym("Moon#1234"));
+    }
+
+    #[test]
+    fn test_is_battle_tag() {
+        assert!(is_battle_tag("kennyg6050#1543"));
+        assert!(is_battle_tag("전소인#3190"));
+        assert!(!is_battle_tag("Maps/W3Champions/#1"));
+        assert!(!is_battle_tag("p042"));
+        assert!(!is_battle_tag("#1234"));
+    }
+
+    #[test]
+    fn test_rewrite_message_nested_strings() {
+        // { 1: 7, 2: { 2: "Moon#1234", 4: "p042" } }
+        let mut inner = vec![0x12, 0x09];
+        inner.extend_from_slice(b"Moon#1234");
+        inner.extend_from_slice(&[0x22, 0x04]);
+        inner.extend_from_slice(b"p042");
+        let mut message = vec![0x08, 0x07, 0x12, u8::try_from(inner.len()).unwrap()];
+        message.extend_from_slice(&inner);
+
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, Vec::new());
+        let rewritten = names.protobuf(&message).unwrap();
+
+        let pseudonym = options.pseudonym("Moon#1234");
+        assert!(!rewritten.windows(9).any(|w| w == b"Moon#1234"));
+        assert!(rewritten
+            .windows(pseudonym.len())
+            .any(|w| w == pseudonym.as_bytes()));
+        assert_eq!(rewritten.len(), message.len() + pseudonym.len() - 9);
+        assert!(rewritten.ends_with(b"p042"));
+    }
+
+    #[test]
+    fn test_rewrite_message_rejects_garbage() {
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, Vec::new());
+        assert!(matches!(
+            names.protobuf(&[0x0F, 0x01]),
+            Err(ParserError::WriteError { .. })
+        ));
+        assert!(names.protobuf(&[0x12, 0x05, 0x41]).is_err());
+    }
+
+    #[test]
+    fn test_replace_in_prefers_longest_name() {
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, vec!["Moon".to_string(), "MoonLight".to_string()]);
+        let replaced = names.replace_in(b"MoonLight beat Moon");
+        let expected = format!(
+            "{} beat {}",
+            options.pseudonym("MoonLight"),
+            options.pseudonym("Moon")
+        );
+        assert_eq!(replaced, expected.into_bytes());
+    }
+
+    #[test]
+    fn test_rewrite_string_replaces_names_only() {
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, vec!["Moon".to_string()]);
+        let moon = options.pseudonym("Moon");
+        assert_eq!(names.rewrite_string("Moon"), Some(moon.clone()));
+        assert_eq!(names.rewrite_string("Moon#1234"), Some(moon.clone()));
+        assert_eq!(names.rewrite_string("Team of Moon"), Some(format!("Team of {moon}")));
+        assert_eq!(
+            names.rewrite_string("Grubby#1234"),
+            Some(options.pseudonym("Grubby#1234"))
+        );
+        assert_eq!(names.rewrite_string("10.0.0.1").as_deref(), Some("0.0.0.0"));
+        assert_eq!(names.rewrite_string("Maps/W3Champions"), None);
+    }
+
+    #[test]
+    fn test_commands_rewrite_game_cache_syncs_only() {
+        // A selection whose object ID bytes look like a game cache sync
+        let selection = [0x16, 0x01, 0x01, 0x00, 0x6B, b'M', b'o', b'o', b'n', 0, 0, 0];
+        let mut sync = vec![0x6B];
+        sync.extend_from_slice(b"MMD.Dat\0val:0\0Moon\0");
+        sync.extend_from_slice(&[1, 0, 0, 0]);
+        let mut commands = vec![0x01, 0x00, 0x00];
+        commands.extend_from_slice(&selection);
+        commands.extend_from_slice(&sync);
+        commands[1] = u8::try_from(selection.len() + sync.len()).unwrap();
+
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, vec!["Moon".to_string()]);
+        let out = names.commands(&commands).unwrap();
+
+        let pseudonym = options.pseudonym("Moon");
+        assert_eq!(&out[3..3 + selection.len()], &selection);
+        assert!(out.ends_with(format!("{pseudonym}\0\x01\0\0\0").as_bytes()));
+        assert_eq!(usize::from(read_u16_le(&out, 1).unwrap()), out.len() - 3);
+    }
+
+    #[test]
+    fn test_commands_fail_closed() {
+        let options = AnonymizeOptions::new("salt");
+        let names = Names::new(&options, Vec::new());
+        // Truncated order, block longer than the data, player ID 0
+        assert!(matches!(
+            names.commands(&[0x01, 0x01, 0x00, 0x10]),
+            Err(ParserError::InvalidAction { .. })
+        ));
+        assert!(names.commands(&[0x01, 0x09, 0x00, 0x01]).is_err());
+        assert!(names.commands(&[0x00, 0x01, 0x00, 0x01]).is_err());
+        assert_eq!(names.commands(&[0x01, 0x00, 0x00]).unwrap(), [0x01, 0x00, 0x00]);
+    }
+}
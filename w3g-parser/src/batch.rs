@@ -0,0 +1,553 @@
+//! Batch input discovery.
+//!
+//! [`collect_sources`] expands the inputs of a batch run (replay files,
+//! directories and archives) into a list of [`ReplaySource`]s, and
+//! [`read_sources`] reads them in order, opening each archive only once.
+//!
+//! - Directories are scanned one level deep, or recursively with
+//!   [`InputOptions::recursive`].
+//! - `.zip`, `.tar`, `.tar.gz` and `.tgz` archives are read without extracting
+//!   them (requires the `archives` feature). Archives inside archives
+//!   are not opened.
+//! - Replays found in directories and archives are filtered by glob patterns
+//!   matched against their path relative to the input directory, or their
+//!   entry name inside the archive. Without include patterns, files with the
+//!   `.w3g` extension are accepted. Replay files named explicitly are always
+//!   accepted.
+//! - Archive entries with an absolute path or a `..` component are skipped, so
+//!   [`ReplaySource::output_path`] always stays inside the output directory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use w3g_parser::batch::{collect_sources, read_sources, InputOptions};
+//!
+//! let options = InputOptions::new().recursive(true).exclude("**/test/**").unwrap();
+//! let sources = collect_sources(&[PathBuf::from("archive")], &options)?;
+//! read_sources(&sources, |index, data| {
+//!     println!("{}: {} bytes", sources[index], data.map_or(0, |d| d.len()));
+//!     true
+//! })?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern, PatternError};
+
+use crate::error::{ParserError, Result};
+
+/// File extension of replay files.
+pub const REPLAY_EXTENSION: &str = "w3g";
+
+/// Glob matching options: case-insensitive, `*` also matches path separators.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+/// Options controlling [`collect_sources`].
+#[derive(Debug, Clone, Default)]
+pub struct InputOptions {
+    recursive: bool,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl InputOptions {
+    /// Creates options for a non-recursive scan accepting `.w3g` files.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether directories are scanned recursively.
+    #[must_use]
+    pub fn recursive(mut self, enabled: bool) -> Self {
+        self.recursive = enabled;
+        self
+    }
+
+    /// Adds a glob pattern that replays must match.
+    ///
+    /// When any include pattern is given, the default `.w3g` extension filter
+    /// no longer applies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid glob.
+    pub fn include(mut self, pattern: &str) -> std::result::Result<Self, PatternError> {
+        self.include.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Adds a glob pattern excluding matching replays and archives.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid glob.
+    pub fn exclude(mut self, pattern: &str) -> std::result::Result<Self, PatternError> {
+        self.exclude.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Returns whether directories are scanned recursively.
+    #[must_use]
+    pub fn is_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Returns whether a replay at `relative` passes the include and exclude patterns.
+    #[must_use]
+    pub fn accepts(&self, relative: &Path) -> bool {
+        let included = if self.include.is_empty() {
+            has_replay_extension(relative)
+        } else {
+            self.include
+                .iter()
+                .any(|p| p.matches_path_with(relative, MATCH_OPTIONS))
+        };
+        included && !self.is_excluded(relative)
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|p| p.matches_path_with(relative, MATCH_OPTIONS))
+    }
+}
+
+/// Where a replay in a batch comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplaySource {
+    /// Archive containing the replay, if it was found in one.
+    pub archive: Option<PathBuf>,
+
+    /// Path of the replay file, or its entry name inside the archive.
+    pub path: PathBuf,
+
+    /// Path relative to the directory or archive it was found in.
+    pub relative: PathBuf,
+}
+
+impl ReplaySource {
+    /// Creates a source for a replay file on disk.
+    #[must_use]
+    pub fn file(path: PathBuf, relative: PathBuf) -> Self {
+        Self {
+            archive: None,
+            path,
+            relative,
+        }
+    }
+
+    /// Returns a relative path identifying this replay within a batch.
+    ///
+    /// Archive entries are placed under the archive's file name without its
+    /// extension, so replays from different archives do not collide.
+    #[must_use]
+    pub fn output_path(&self) -> PathBuf {
+        match &self.archive {
+            Some(archive) => Path::new(&archive_stem(archive)).join(&self.relative),
+            None => self.relative.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ReplaySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.archive {
+            Some(archive) => write!(f, "{}!{}", archive.display(), self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// Archive formats that can be read without extracting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Archive file name suffixes, checked in order.
+const ARCHIVE_SUFFIXES: [(&str, ArchiveKind); 4] = [
+    (".tar.gz", ArchiveKind::TarGz),
+    (".tgz", ArchiveKind::TarGz),
+    (".tar", ArchiveKind::Tar),
+    (".zip", ArchiveKind::Zip),
+];
+
+impl ArchiveKind {
+    /// Detects the archive format from a file name.
+    fn detect(path: &Path) -> Option<Self> {
+        archive_suffix(path).map(|(_, kind)| kind)
+    }
+}
+
+/// Returns the archive suffix of `path`'s file name, matched case-insensitively.
+fn archive_suffix(path: &Path) -> Option<(&'static str, ArchiveKind)> {
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    ARCHIVE_SUFFIXES
+        .into_iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+}
+
+/// Returns whether `path` is a zip or tar archive that batch input can read.
+#[must_use]
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::detect(path).is_some()
+}
+
+/// Expands batch inputs into the replays they contain.
+///
+/// Sources are returned in input order. Directory contents are sorted by
+/// path and archive entries keep their order in the archive. A replay reached
+/// through several inputs is listed once.
+///
+/// # Errors
+///
+/// - `ParserError::IoError` if an input does not exist or a directory cannot be read
+/// - `ParserError::DecompressionError` if an archive cannot be listed
+pub fn collect_sources(inputs: &[PathBuf], options: &InputOptions) -> Result<Vec<ReplaySource>> {
+    let mut sources = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut files = Vec::new();
+            walk(input, options.recursive, &mut files)?;
+            files.sort();
+            for file in files {
+                let relative = file.strip_prefix(input).unwrap_or(&file).to_path_buf();
+                if let Some(kind) = ArchiveKind::detect(&file) {
+                    if !options.is_excluded(&relative) {
+                        sources.extend(list_archive(&file, kind, options)?);
+                    }
+                } else if options.accepts(&relative) {
+                    sources.push(ReplaySource::file(file, relative));
+                }
+            }
+        } else if let Some(kind) = ArchiveKind::detect(input) {
+            sources.extend(list_archive(input, kind, options)?);
+        } else if input.is_file() {
+            let relative = PathBuf::from(input.file_name().unwrap_or(input.as_os_str()));
+            sources.push(ReplaySource::file(input.clone(), relative));
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", input.display()),
+            )
+            .into());
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    sources.retain(|source| seen.insert((source.archive.clone(), source.path.clone())));
+    Ok(sources)
+}
+
+/// Reads every source in order and passes its data to `visit`.
+///
+/// `visit` receives the index of the source in `sources` and returns whether
+/// to continue. Consecutive sources from the same archive are read in a single
+/// pass over it. Failing to read one source does not stop the others.
+///
+/// # Errors
+///
+/// Errors for individual sources are passed to `visit`; this function itself
+/// only fails if `visit` cannot be called.
+pub fn read_sources(
+    sources: &[ReplaySource],
+    mut visit: impl FnMut(usize, Result<Vec<u8>>) -> bool,
+) -> Result<()> {
+    let mut start = 0;
+    while start < sources.len() {
+        let Some(archive) = &sources[start].archive else {
+            if !visit(start, std::fs::read(&sources[start].path).map_err(Into::into)) {
+                return Ok(());
+            }
+            start += 1;
+            continue;
+        };
+
+        let end = sources[start..]
+            .iter()
+            .position(|source| source.archive.as_ref() != Some(archive))
+            .map_or(sources.len(), |len| start + len);
+        if !read_archive(archive, &sources[start..end], start, &mut visit) {
+            return Ok(());
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Collects all files below `dir`, descending into subdirectories if `recursive`.
+fn walk(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if recursive {
+                walk(&entry.path(), recursive, files)?;
+            }
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+fn has_replay_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(REPLAY_EXTENSION))
+}
+
+/// Returns an archive's file name without its archive extension.
+fn archive_stem(archive: &Path) -> String {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match archive_suffix(archive) {
+        Some((suffix, _)) => name[..name.len() - suffix.len()].to_string(),
+        None => name,
+    }
+}
+
+fn archive_error(archive: &Path, e: impl fmt::Display) -> ParserError {
+    ParserError::DecompressionError {
+        reason: format!("cannot read archive {}: {e}", archive.display()),
+    }
+}
+
+/// Lists the accepted replay entries of an archive.
+#[cfg(feature = "archives")]
+fn list_archive(
+    archive: &Path,
+    kind: ArchiveKind,
+    options: &InputOptions,
+) -> Result<Vec<ReplaySource>> {
+    let mut names = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let file = io::BufReader::new(std::fs::File::open(archive)?);
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| archive_error(archive, e))?;
+            for index in 0..zip.len() {
+                let entry = zip.by_index_raw(index).map_err(|e| archive_error(archive, e))?;
+                if entry.is_file() && entry.enclosed_name().is_some() {
+                    names.push(entry.name().to_string());
+                }
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut tar = open_tar(archive, kind)?;
+            for entry in tar.entries().map_err(|e| archive_error(archive, e))? {
+                let entry = entry.map_err(|e| archive_error(archive, e))?;
+                if entry.header().entry_type().is_file() {
+                    let path = entry.path().map_err(|e| archive_error(archive, e))?;
+                    names.push(path.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+
+    Ok(names
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|name| is_enclosed(name) && options.accepts(name))
+        .map(|name| ReplaySource {
+            archive: Some(archive.to_path_buf()),
+            relative: name.clone(),
+            path: name,
+        })
+        .collect())
+}
+
+/// Returns whether an archive entry name stays inside the directory it is
+/// extracted to: relative, and without `..` components.
+#[cfg(feature = "archives")]
+fn is_enclosed(name: &Path) -> bool {
+    name.components()
+        .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+#[cfg(not(feature = "archives"))]
+fn list_archive(
+    archive: &Path,
+    _kind: ArchiveKind,
+    _options: &InputOptions,
+) -> Result<Vec<ReplaySource>> {
+    Err(archive_error(
+        archive,
+        "archive support requires the `archives` feature",
+    ))
+}
+
+#[cfg(feature = "archives")]
+fn open_tar(archive: &Path, kind: ArchiveKind) -> Result<tar::Archive<Box<dyn io::Read>>> {
+    let file = io::BufReader::new(std::fs::File::open(archive)?);
+    let reader: Box<dyn io::Read> = if kind == ArchiveKind::TarGz {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+/// Reads `sources`, which all come from `archive`, passing `first + i` as index.
+///
+/// Returns `false` if `visit` asked to stop.
+#[cfg(feature = "archives")]
+fn read_archive(
+    archive: &Path,
+    sources: &[ReplaySource],
+    first: usize,
+    visit: &mut impl FnMut(usize, Result<Vec<u8>>) -> bool,
+) -> bool {
+    let mut pending: std::collections::HashMap<&Path, usize> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| (source.path.as_path(), first + i))
+        .collect();
+
+    let reason = match visit_archive(archive, &mut pending, visit) {
+        Ok(false) => return false,
+        Ok(true) => "entry not found".to_string(),
+        Err(e) => e.to_string(),
+    };
+
+    // Entries that could not be reached are reported individually
+    let mut missing: Vec<usize> = pending.into_values().collect();
+    missing.sort_unstable();
+    missing
+        .into_iter()
+        .all(|index| visit(index, Err(archive_error(archive, &reason))))
+}
+
+/// Visits the `pending` entries of `archive`, removing each one visited.
+///
+/// Returns `Ok(false)` if `visit` asked to stop.
+#[cfg(feature = "archives")]
+fn visit_archive(
+    archive: &Path,
+    pending: &mut std::collections::HashMap<&Path, usize>,
+    visit: &mut impl FnMut(usize, Result<Vec<u8>>) -> bool,
+) -> Result<bool> {
+    let kind = ArchiveKind::detect(archive)
+        .ok_or_else(|| archive_error(archive, "unknown archive format"))?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let file = io::BufReader::new(std::fs::File::open(archive)?);
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| archive_error(archive, e))?;
+            let mut entries: Vec<(&Path, usize)> = pending.iter().map(|(p, i)| (*p, *i)).collect();
+            entries.sort_by_key(|(_, index)| *index);
+            for (path, index) in entries {
+                let data = match zip.by_name(&path.to_string_lossy()) {
+                    Ok(mut entry) => read_all(&mut entry),
+                    Err(e) => Err(archive_error(archive, e)),
+                };
+                pending.remove(path);
+                if !visit(index, data) {
+                    return Ok(false);
+                }
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut tar = open_tar(archive, kind)?;
+            for entry in tar.entries().map_err(|e| archive_error(archive, e))? {
+                let mut entry = entry.map_err(|e| archive_error(archive, e))?;
+                let path = entry.path().map_err(|e| archive_error(archive, e))?;
+                let Some(index) = pending.remove(path.as_ref()) else {
+                    continue;
+                };
+                if !visit(index, read_all(&mut entry)) {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "archives")]
+fn read_all(reader: &mut impl io::Read) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "archives"))]
+fn read_archive(
+    archive: &Path,
+    sources: &[ReplaySource],
+    first: usize,
+    visit: &mut impl FnMut(usize, Result<Vec<u8>>) -> bool,
+) -> bool {
+    (0..sources.len()).all(|i| {
+        let reason = "archive support requires the `archives` feature";
+        visit(first + i, Err(archive_error(archive, reason)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_detection_and_stem() {
+        assert_eq!(ArchiveKind::detect(Path::new("a/2024.ZIP")), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::detect(Path::new("s1.tar.gz")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("s1.tgz")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::detect(Path::new("replay.w3g")), None);
+
+        assert_eq!(archive_stem(Path::new("dir/season-1.tar.gz")), "season-1");
+        assert_eq!(archive_stem(Path::new("May.Zip")), "May");
+    }
+
+    #[test]
+    fn test_input_options_patterns() {
+        let options = InputOptions::new();
+        assert!(options.accepts(Path::new("2024/05/game.W3G")));
+        assert!(!options.accepts(Path::new("notes.txt")));
+
+        let options = InputOptions::new()
+            .include("2024/**/*.w3g")
+            .unwrap()
+            .exclude("*ladder*")
+            .unwrap();
+        assert!(options.accepts(Path::new("2024/05/game.w3g")));
+        assert!(!options.accepts(Path::new("2023/05/game.w3g")));
+        assert!(!options.accepts(Path::new("2024/05/ladder_game.w3g")));
+
+        assert!(InputOptions::new().include("[").is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        let source = ReplaySource {
+            archive: Some(PathBuf::from("/data/2024-05.tar.gz")),
+            path: PathBuf::from("week1/game.w3g"),
+            relative: PathBuf::from("week1/game.w3g"),
+        };
+        assert_eq!(source.output_path(), PathBuf::from("2024-05/week1/game.w3g"));
+        assert_eq!(source.to_string(), "/data/2024-05.tar.gz!week1/game.w3g");
+    }
+
+    #[cfg(feature = "archives")]
+    #[test]
+    fn test_is_enclosed() {
+        assert!(is_enclosed(Path::new("week1/game.w3g")));
+        assert!(is_enclosed(Path::new("./game.w3g")));
+        assert!(!is_enclosed(Path::new("../game.w3g")));
+        assert!(!is_enclosed(Path::new("week1/../../game.w3g")));
+        assert!(!is_enclosed(Path::new("/tmp/game.w3g")));
+    }
+}
@@ -4,8 +4,7 @@
 
 use std::collections::HashMap;
 use std::env;
-use std::fs;
-use w3g_parser::{decompress, GameRecord, Header};
+use w3g_parser::{ParseOptions, Replay};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -18,13 +17,12 @@ fn main() {
     let input_path = &args[1];
     eprintln!("Reading: {}", input_path);
 
-    let data = fs::read(input_path).expect("Failed to read input file");
-    let header = Header::parse(&data).expect("Failed to parse header");
-    let decompressed = decompress(&data, &header).expect("Failed to decompress");
-    let game_record = GameRecord::parse(&decompressed).expect("Failed to parse game record");
+    // Only the raw TimeFrame data is analyzed, so skip decoding actions and chat
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = Replay::from_path_with(input_path, &options).expect("Failed to parse replay");
 
-    eprintln!("Host: {}", game_record.host_name());
-    eprintln!("Players: {:?}", game_record.player_names());
+    eprintln!("Host: {}", replay.game.host_name());
+    eprintln!("Players: {:?}", replay.game.player_names());
     eprintln!();
 
     let mut all_action_bytes: Vec<u8> = Vec::new();
@@ -36,7 +34,7 @@ fn main() {
     let mut frames_with_actions = 0;
 
     // Collect all action data
-    for result in game_record.timeframes(&decompressed) {
+    for result in replay.timeframes() {
         let frame = match result {
             Ok(f) => f,
             Err(e) => {
@@ -108,7 +106,7 @@ fn main() {
     // Byte frequency analysis
     println!("## First Byte Distribution (Action Blocks)\n");
     let mut first_bytes: HashMap<u8, u32> = HashMap::new();
-    for frame_result in game_record.timeframes(&decompressed) {
+    for frame_result in replay.timeframes() {
         if let Ok(frame) = frame_result {
             if !frame.action_data.is_empty() {
                 *first_bytes.entry(frame.action_data[0]).or_insert(0) += 1;
@@ -2,9 +2,9 @@
 
 use std::env;
 use std::fs;
-use w3g_parser::decompress::decompress;
 use w3g_parser::header::Header;
-use w3g_parser::records::{GameRecordHeader, PlayerRoster};
+use w3g_parser::records::find_game_record_start;
+use w3g_parser::{ParseOptions, Replay};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -13,82 +13,71 @@ fn main() {
         std::process::exit(1);
     }
 
+    // Only the record structure is analyzed, so skip decoding actions and chat
+    let options = ParseOptions::new().actions(false).chat(false);
     let data = fs::read(&args[1]).unwrap();
     println!("File size: {} bytes", data.len());
+    let replay = Replay::from_bytes_with(&data, &options).unwrap();
+    let header = &replay.header;
 
-    // Parse header
-    let header = Header::parse(&data).unwrap();
+    // Header
     println!("\n=== Header ===");
     println!("Format: {:?}", header.format());
     println!("Data offset: {}", header.data_offset());
     println!("Decompressed size: {}", header.decompressed_size());
 
-    if let Header::Classic(h) = &header {
+    if let Header::Classic(h) = header {
         println!("Build version: {}", h.build_version);
     }
 
-    // Decompress
-    let decompressed = decompress(&data, &header).unwrap();
+    // Decompressed data
+    let decompressed = replay.decompressed();
     println!("\n=== Decompressed ===");
     println!("Actual decompressed size: {} bytes", decompressed.len());
 
     // Hex dump first 512 bytes
     println!("\n=== First 512 bytes (hex) ===");
-    hex_dump(&decompressed, 0, 512.min(decompressed.len()));
-
-    // Parse game header
-    println!("\n=== Game Record Header ===");
-    match GameRecordHeader::parse(&decompressed) {
-        Ok(header) => {
-            println!("Host name: {}", header.host_name);
-            println!("Host slot: {}", header.host_slot);
-            println!("Additional data: {:?}", header.additional_data);
-            println!("Encoded settings length: {} bytes", header.encoded_settings.len());
-            println!("Byte length: {}", header.byte_length);
-
-            // Look at what comes after game header
-            let player_start = header.byte_length;
-            println!("\n=== After Game Header (offset {}) ===", player_start);
-            if player_start < decompressed.len() {
-                let after_header = &decompressed[player_start..];
-                println!("First 20 bytes: {:02X?}", &after_header[..20.min(after_header.len())]);
-                println!("First byte marker: 0x{:02X}", after_header[0]);
+    hex_dump(decompressed, 0, 512.min(decompressed.len()));
+
+    // Game header
+    let game_header = replay.settings();
+    let record_start = find_game_record_start(decompressed).unwrap_or(0);
+    println!("\n=== Game Record Header (offset {}) ===", record_start);
+    println!("Host name: {}", game_header.host_name);
+    println!("Host slot: {}", game_header.host_slot);
+    println!("Additional data: {:?}", game_header.additional_data);
+    println!("Encoded settings length: {} bytes", game_header.encoded_settings.len());
+    println!("Byte length: {}", game_header.byte_length);
+
+    // Look at what comes after game header
+    let player_start = record_start + game_header.byte_length;
+    println!("\n=== After Game Header (offset {}) ===", player_start);
+    let after_header = &decompressed[player_start..];
+    println!("First 20 bytes: {:02X?}", &after_header[..20.min(after_header.len())]);
+    if let Some(marker) = after_header.first() {
+        println!("First byte marker: 0x{:02X}", marker);
+    }
 
-                // Parse player roster
-                println!("\n=== Player Roster ===");
-                match PlayerRoster::parse(after_header) {
-                    Ok(roster) => {
-                        println!("Found {} players", roster.len());
-                        println!("Roster byte length: {}", roster.byte_length);
-                        for p in roster.players() {
-                            println!("  Slot {}: {} ({} bytes)", p.slot_id(), p.player_name(), p.byte_length());
-                        }
+    // Player roster
+    let roster = &replay.game.players;
+    println!("\n=== Player Roster ===");
+    println!("Found {} players", roster.len());
+    println!("Roster byte length: {}", roster.byte_length);
+    for p in roster.players() {
+        println!("  Slot {}: {} ({} bytes)", p.slot_id(), p.player_name(), p.byte_length());
+    }
 
-                        // Look at what comes after player roster
-                        let timeframe_start = player_start + roster.byte_length;
-                        println!("\n=== After Player Roster (offset {}) ===", timeframe_start);
-                        if timeframe_start < decompressed.len() {
-                            hex_dump(&decompressed, timeframe_start, 128.min(decompressed.len() - timeframe_start));
-                        }
-                    }
-                    Err(e) => {
-                        println!("Failed to parse player roster: {}", e);
-                        // Try to manually scan for patterns
-                        println!("\n=== Manual scan for markers ===");
-                        scan_for_markers(after_header, player_start);
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            println!("Failed to parse game header: {}", e);
-        }
+    // Look at what comes after player roster
+    let search_start = player_start + roster.byte_length;
+    println!("\n=== After Player Roster (offset {}) ===", search_start);
+    if search_start < decompressed.len() {
+        hex_dump(decompressed, search_start, 128.min(decompressed.len() - search_start));
     }
 
     // Scan for record markers throughout the file
     println!("\n=== Record Marker Distribution ===");
     let mut marker_counts: std::collections::HashMap<u8, usize> = std::collections::HashMap::new();
-    for &byte in &decompressed {
+    for &byte in decompressed {
         match byte {
             0x16 | 0x19 | 0x1E | 0x1F | 0x20 | 0x22 | 0x17 => {
                 *marker_counts.entry(byte).or_insert(0) += 1;
@@ -112,15 +101,6 @@ fn main() {
 
     // Find first TimeFrame AFTER player roster
     println!("\n=== TimeFrame Search (after player roster) ===");
-    let search_start = match GameRecordHeader::parse(&decompressed) {
-        Ok(h) => {
-            match PlayerRoster::parse(&decompressed[h.byte_length..]) {
-                Ok(r) => h.byte_length + r.byte_length,
-                Err(_) => h.byte_length,
-            }
-        }
-        Err(_) => 0,
-    };
     println!("Searching from offset {}", search_start);
 
     // Look for FIRST TimeFrame marker after player roster
@@ -139,7 +119,7 @@ fn main() {
                     first_tf_offset = Some(i);
                     println!("Found TimeFrame at offset {}", i);
                     println!("  Time delta: {}ms, Length hint: {}", time_delta, length_hint);
-                    hex_dump(&decompressed, i, 64.min(decompressed.len() - i));
+                    hex_dump(decompressed, i, 64.min(decompressed.len() - i));
                     break;
                 }
             }
@@ -150,13 +130,13 @@ fn main() {
     if let Some(tf_off) = first_tf_offset {
         println!("\n=== Data between player roster ({}) and TimeFrame ({}) ===", search_start, tf_off);
         println!("Length: {} bytes", tf_off - search_start);
-        hex_dump(&decompressed, search_start, (tf_off - search_start).min(256));
+        hex_dump(decompressed, search_start, (tf_off - search_start).min(256));
     }
 
     // Test TimeFrameIterator with detailed debugging
     println!("\n=== Testing TimeFrameIterator ===");
     use w3g_parser::records::TimeFrameIterator;
-    let mut iter = TimeFrameIterator::new(&decompressed, search_start);
+    let mut iter = TimeFrameIterator::new(decompressed, search_start);
     let mut frame_count = 0;
     let mut last_error: Option<String> = None;
     let mut last_offset = search_start;
@@ -182,7 +162,7 @@ fn main() {
                 // Show bytes around stop point
                 if current_offset < decompressed.len() {
                     println!("Bytes at stop point:");
-                    hex_dump(&decompressed, current_offset, 32.min(decompressed.len() - current_offset));
+                    hex_dump(decompressed, current_offset, 32.min(decompressed.len() - current_offset));
                 }
                 break;
             }
@@ -228,31 +208,3 @@ fn hex_dump(data: &[u8], offset: usize, len: usize) {
         println!("|");
     }
 }
-
-fn scan_for_markers(data: &[u8], base_offset: usize) {
-    println!("Scanning first 500 bytes after game header...");
-    for (i, window) in data.windows(3).enumerate().take(500) {
-        match window[0] {
-            0x16 => {
-                let slot = window[1];
-                let next = window[2];
-                if slot <= 24 && ((0x20..=0x7E).contains(&next) || next >= 0x80) {
-                    println!("  Possible PlayerSlot at offset {} (slot {})", base_offset + i, slot);
-                }
-            }
-            0x19 => {
-                println!("  Possible SlotRecord at offset {}", base_offset + i);
-            }
-            0x1F | 0x1E => {
-                println!("  TimeFrame marker at offset {}", base_offset + i);
-            }
-            0x20 => {
-                println!("  Chat marker at offset {}", base_offset + i);
-            }
-            0x22 => {
-                println!("  Checksum marker at offset {}", base_offset + i);
-            }
-            _ => {}
-        }
-    }
-}
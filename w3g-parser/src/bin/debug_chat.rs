@@ -2,11 +2,9 @@
 //! This tool hex dumps chat messages to reverse engineer the player_id field
 
 use std::env;
-use std::fs;
 use std::path::Path;
-use w3g_parser::decompress::decompress;
-use w3g_parser::header::Header;
-use w3g_parser::records::{GameRecord, ChatMessage, CHAT_MARKER};
+use w3g_parser::records::{ChatMessage, CHAT_MARKER};
+use w3g_parser::{ParseOptions, Replay};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -16,28 +14,24 @@ fn main() {
     }
 
     let replay_path = &args[1];
-    let data = fs::read(replay_path).expect("Failed to read replay file");
 
-    // Parse header
-    let header = Header::parse(&data).expect("Failed to parse header");
+    // Chat is scanned from the raw data below, without the library's validation
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = Replay::from_path_with(replay_path, &options).expect("Failed to parse replay");
     println!("Replay: {}", Path::new(replay_path).file_name().unwrap().to_string_lossy());
     println!();
 
-    // Decompress
-    let decompressed = decompress(&data, &header).expect("Failed to decompress");
-
-    // Parse game record to get player roster
-    let game_record = GameRecord::parse(&decompressed).expect("Failed to parse game record");
+    let decompressed = replay.decompressed();
 
     println!("=== PLAYER ROSTER ===");
-    for (i, player) in game_record.players.players().enumerate() {
+    for (i, player) in replay.game.players.players().enumerate() {
         println!("  [{}] Slot {:2}: {}", i, player.slot_id(), player.player_name());
     }
     println!();
 
     // Find chat messages using proper parser
     println!("=== CHAT MESSAGES ===");
-    let mut offset = game_record.timeframe_offset;
+    let mut offset = replay.game.timeframe_offset;
     let mut chat_count = 0;
 
     while offset < decompressed.len() {
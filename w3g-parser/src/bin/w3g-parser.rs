@@ -7,15 +7,49 @@
 //! - `info` - Display quick replay metadata
 //! - `parse` - Parse replay with output format options
 //! - `validate` - Validate replay format (exit codes for scripting)
-//! - `batch` - Process multiple replays from a directory
+//! - `batch` - Process replays from files, directories and archives in parallel
+//! - `anonymize` - Rewrite player names and chat for sharing
+//! - `trim` - Cut a replay to a game time range
+//! - `compare` - Check two replays of the same game against each other
+//! - `report` - Write a self-contained HTML match report
+//! - `export-sqlite` - Write replays into a SQLite database (`sqlite` feature)
+//! - `census` - Group unknown actions of many replays by pattern and build
+//! - `annotate` - Label every byte range of the decompressed data
+//! - `inspect` - Browse the record tree and bytes in a terminal UI (`tui` feature)
 
 use clap::{Parser, Subcommand, ValueEnum};
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+#[cfg(feature = "tui")]
+use std::ops::Range;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use w3g_parser::records::{ChatMessage, CHAT_MARKER};
-use w3g_parser::{decompress, GameRecord, Header};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use w3g_parser::actions::{AbilityCode, Action, ActionFilter, ActionType, FilterError, Race};
+use w3g_parser::batch::{collect_sources, read_sources, InputOptions, ReplaySource};
+use w3g_parser::cache::{content_hash, ResultCache};
+use w3g_parser::census::{BuildCounts, UnknownCensus, UnknownPattern};
+#[cfg(feature = "arrow")]
+use w3g_parser::export::ActionIpcWriter;
+use w3g_parser::export::{PlayerCsvWriter, ReplayTables};
+#[cfg(feature = "sqlite")]
+use w3g_parser::sqlite::{ReplayDatabase, Upsert};
+use w3g_parser::records::{PlayerRecord, Slot, SlotStatus, SlotTable};
+use w3g_parser::compare::{DivergenceKind, IdentityMismatch, ReplayComparison, Side};
+use w3g_parser::inspect::{Annotation, RecordTree};
+use w3g_parser::replay::ChatEvent;
+use w3g_parser::report::MatchReport;
+use w3g_parser::{
+    anonymize, compare, decompress, trim, AnonymizeOptions, ChatPolicy, DesyncReport, Diagnostic,
+    GameClock, GameRecord, Header, LeadIn, ParseMode, ParseOptions, Replay, TrimOptions,
+};
 
 /// Warcraft 3 replay (.w3g) parser
 #[derive(Parser)]
@@ -53,6 +87,33 @@ enum Commands {
         /// Include chat messages
         #[arg(long)]
         chat: bool,
+        /// Time base for timestamps and duration: game, wall
+        #[arg(long, default_value = "game")]
+        clock: ClockMode,
+        /// Fail on the first action or TimeFrame that cannot be parsed
+        #[arg(long)]
+        strict: bool,
+        /// Include parse diagnostics (skipped data, unknown actions)
+        #[arg(long)]
+        diagnostics: bool,
+        /// Only list actions of these player IDs (implies --actions)
+        #[arg(long, value_delimiter = ',')]
+        player: Vec<u8>,
+        /// Only list actions of these types, e.g. UnitAbilityNoTarget (implies --actions)
+        #[arg(long = "type", value_name = "TYPE", value_delimiter = ',')]
+        action_type: Vec<String>,
+        /// Only list actions at or after this game time (seconds, MM:SS or HH:MM:SS)
+        #[arg(long, value_parser = parse_game_time)]
+        from: Option<u32>,
+        /// Only list actions before this game time (seconds, MM:SS or HH:MM:SS)
+        #[arg(long, value_parser = parse_game_time)]
+        to: Option<u32>,
+        /// Only list actions using these FourCC codes, e.g. hpea (implies --actions)
+        #[arg(long, value_name = "CODE", value_delimiter = ',')]
+        ability_code: Vec<String>,
+        /// Only list actions matching a query, e.g. "type=UnitAbilityNoTarget and time<5:00"
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<ActionFilter>,
     },
     /// Validate replay format
     Validate {
@@ -64,20 +125,170 @@ enum Commands {
     },
     /// Parse multiple replay files
     Batch {
-        /// Directory containing replay files
-        directory: PathBuf,
+        /// Replay files, directories or .zip/.tar/.tar.gz archives
+        #[arg(required_unless_present = "stdin")]
+        inputs: Vec<PathBuf>,
+        /// Read additional input paths from stdin, one per line
+        #[arg(long)]
+        stdin: bool,
+        /// Scan directories recursively
+        #[arg(short, long)]
+        recursive: bool,
+        /// Only process replays whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip replays and archives whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
         /// Output directory for JSON files
         #[arg(short, long)]
         output: Option<PathBuf>,
-        /// Output format
+        /// Output format: json, pretty, ndjson, csv, arrow
         #[arg(short, long, default_value = "json")]
-        format: OutputFormat,
+        format: BatchFormat,
         /// Generate summary report
         #[arg(long)]
         summary: bool,
         /// Continue on errors
         #[arg(long)]
         continue_on_error: bool,
+        /// Number of worker threads (default: available CPU cores)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Per-file timeout in seconds (0 disables the timeout)
+        #[arg(long, default_value = "120")]
+        timeout: u64,
+        /// Results cache; replays already in it with the same output version are skipped
+        /// (not used by the csv and arrow formats)
+        #[arg(long, value_name = "FILE")]
+        cache: Option<PathBuf>,
+        /// Clear the results cache and parse every replay again
+        #[arg(long, requires = "cache")]
+        force: bool,
+    },
+    /// Replace player names with salted pseudonyms and write a new replay
+    Anonymize {
+        /// Path to the replay file
+        file: PathBuf,
+        /// Path to write the anonymised replay to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Secret salt for the pseudonyms; the same salt gives the same names
+        #[arg(long)]
+        salt: String,
+        /// Chat handling: keep, redact, drop
+        #[arg(long, default_value = "keep")]
+        chat: ChatMode,
+    },
+    /// Cut a replay to a game time range and write a new replay
+    Trim {
+        /// Path to the replay file
+        file: PathBuf,
+        /// Path to write the trimmed replay to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Start of the range (seconds, MM:SS or HH:MM:SS)
+        #[arg(long, default_value = "0", value_parser = parse_game_time)]
+        start: u32,
+        /// End of the range, inclusive (seconds, MM:SS or HH:MM:SS)
+        #[arg(long, value_parser = parse_game_time)]
+        end: u32,
+        /// Records before the start: keep, empty, drop
+        #[arg(long, default_value = "keep")]
+        lead_in: LeadInMode,
+    },
+    /// Compare two replays of the same game saved by different players
+    Compare {
+        /// Path to the first replay file
+        left: PathBuf,
+        /// Path to the second replay file
+        right: PathBuf,
+        /// Output format: json, pretty
+        #[arg(short, long, default_value = "pretty")]
+        output: OutputFormat,
+        /// Maximum number of divergences listed in pretty output
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Write a self-contained HTML match report
+    Report {
+        /// Path to the replay file
+        file: PathBuf,
+        /// Path to write the HTML file to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Page title; defaults to the replay's file name
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Write replays into a SQLite database, replacing games imported before
+    #[cfg(feature = "sqlite")]
+    ExportSqlite {
+        /// Replay files, directories or .zip/.tar/.tar.gz archives
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Path of the database; created if it does not exist
+        #[arg(long)]
+        db: PathBuf,
+        /// Scan directories recursively
+        #[arg(short, long)]
+        recursive: bool,
+        /// Only import replays whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip replays and archives whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Continue on errors
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// Group the unknown actions of many replays by pattern and build
+    Census {
+        /// Replay files, directories or .zip/.tar/.tar.gz archives
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Scan directories recursively
+        #[arg(short, long)]
+        recursive: bool,
+        /// Only include replays whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip replays and archives whose relative path matches this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Output format: json, pretty
+        #[arg(short, long, default_value = "pretty")]
+        output: OutputFormat,
+        /// Maximum number of patterns listed in pretty output
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Sample occurrences kept per pattern, each from a different replay
+        #[arg(long, default_value = "3")]
+        samples: usize,
+    },
+    /// Label every byte range of the decompressed data with its structure
+    Annotate {
+        /// Path to the replay file
+        file: PathBuf,
+        /// Output format: json, pretty
+        #[arg(short, long, default_value = "pretty")]
+        output: OutputFormat,
+        /// Only list unclaimed ranges and ranges of unknown meaning
+        #[arg(long)]
+        unknown_only: bool,
+        /// Write the decompressed data to this file, for loading into a hex editor
+        #[arg(long, value_name = "PATH")]
+        dump: Option<PathBuf>,
+    },
+    /// Browse the record tree and bytes of a replay in a terminal UI
+    #[cfg(feature = "tui")]
+    Inspect {
+        /// Path to the replay file
+        file: PathBuf,
+        /// Select the structure at this offset of the decompressed data
+        #[arg(long, value_name = "OFFSET", value_parser = parse_offset)]
+        offset: Option<usize>,
     },
 }
 
@@ -88,36 +299,126 @@ enum OutputFormat {
     Pretty,
 }
 
+/// Output format options for `batch`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BatchFormat {
+    /// One pretty JSON file per replay in the output directory
+    Json,
+    /// Same as json; batch output is always machine readable
+    Pretty,
+    /// One JSON object per replay per line
+    Ndjson,
+    /// One row per player: replay, player, race, team, APM, result
+    Csv,
+    /// Arrow IPC file with one row per action (requires --output)
+    Arrow,
+}
+
+impl BatchFormat {
+    /// Returns whether replays are written to one JSON file each.
+    fn writes_files(self) -> bool {
+        matches!(self, BatchFormat::Json | BatchFormat::Pretty)
+    }
+
+    /// Returns whether the player and action tables are needed.
+    fn needs_tables(self) -> bool {
+        matches!(self, BatchFormat::Csv | BatchFormat::Arrow)
+    }
+
+    /// Returns the file the combined output is written to in the output directory.
+    fn combined_file(self) -> Option<&'static str> {
+        match self {
+            BatchFormat::Json | BatchFormat::Pretty => None,
+            BatchFormat::Ndjson => Some("replays.ndjson"),
+            BatchFormat::Csv => Some("players.csv"),
+            BatchFormat::Arrow => Some("actions.arrow"),
+        }
+    }
+}
+
+/// Time base options
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ClockMode {
+    /// Game time as recorded in the replay
+    Game,
+    /// Wall-clock time, adjusted for pauses and game speed
+    Wall,
+}
+
+/// Chat handling options for `anonymize`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ChatMode {
+    /// Keep messages, replacing player names in them
+    Keep,
+    /// Replace every message with a placeholder
+    Redact,
+    /// Remove chat records entirely
+    Drop,
+}
+
+/// Lead-in handling options for `trim`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LeadInMode {
+    /// Keep everything before the range so playback stays in sync
+    Keep,
+    /// Replace the lead-in with empty frames of the same duration
+    Empty,
+    /// Remove the lead-in so the range starts at 00:00
+    Drop,
+}
+
+impl From<LeadInMode> for LeadIn {
+    fn from(mode: LeadInMode) -> Self {
+        match mode {
+            LeadInMode::Keep => LeadIn::Keep,
+            LeadInMode::Empty => LeadIn::Empty,
+            LeadInMode::Drop => LeadIn::Drop,
+        }
+    }
+}
+
+impl From<ChatMode> for ChatPolicy {
+    fn from(mode: ChatMode) -> Self {
+        match mode {
+            ChatMode::Keep => ChatPolicy::Keep,
+            ChatMode::Redact => ChatPolicy::Redact,
+            ChatMode::Drop => ChatPolicy::Drop,
+        }
+    }
+}
+
 // ============================================================================
 // Serializable Output Structures
 // ============================================================================
+//
+// Players, chat messages and actions are emitted as the library model types
+// (see the `serde` feature), so the JSON schema matches the library.
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct ParseOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    game_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_base: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     header: Option<HeaderInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    players: Option<Vec<PlayerInfo>>,
+    map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    players: Option<Vec<PlayerRecord>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    chat: Option<Vec<ChatInfo>>,
+    slots: Option<SlotTable>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    actions: Option<Vec<ActionInfo>>,
+    chat: Option<Vec<ChatEvent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actions: Option<Vec<Action>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     statistics: Option<Statistics>,
-}
-
-#[derive(Serialize)]
-struct ChatInfo {
-    /// Player slot ID (1-24) or None for system messages
     #[serde(skip_serializing_if = "Option::is_none")]
-    sender_slot: Option<u8>,
-    /// True if this is a system message (not from a player)
-    is_system: bool,
-    message_id: u16,
-    message: String,
+    diagnostics: Option<Vec<Diagnostic>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct HeaderInfo {
     format: String,
     file_size: usize,
@@ -134,20 +435,7 @@ struct HeaderInfo {
     duration: Option<String>,
 }
 
-#[derive(Serialize)]
-struct PlayerInfo {
-    slot_id: u8,
-    name: String,
-}
-
-#[derive(Serialize)]
-struct ActionInfo {
-    player_id: u8,
-    timestamp_ms: u32,
-    action_type: String,
-}
-
-#[derive(Serialize, Default)]
+#[derive(Serialize, Deserialize, Default)]
 struct Statistics {
     total_frames: usize,
     total_actions: usize,
@@ -158,7 +446,7 @@ struct Statistics {
 }
 
 /// Per-player action statistics matching warcraft3.info categories.
-#[derive(Serialize, Default, Clone)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 struct PlayerStats {
     /// Player name (if available).
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -195,9 +483,76 @@ struct BatchSummary {
     successful: usize,
     failed: usize,
     total_actions: usize,
-    format_distribution: HashMap<String, usize>,
+    format_distribution: BTreeMap<String, usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     average_duration_ms: Option<u32>,
+    /// Replays per game version and build
+    version_distribution: BTreeMap<String, usize>,
+    /// Replays per map file name
+    map_distribution: BTreeMap<String, usize>,
+    /// Replays per matchup, such as `HvO` or `HN v OU`
+    matchup_distribution: BTreeMap<String, usize>,
+    duration_histogram: Vec<DurationBucket>,
+    /// Average APM of players by the race picked in the lobby
+    average_apm_by_race: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    failures: Vec<FailureGroup>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    archives: BTreeMap<String, ArchiveTotals>,
+}
+
+/// Number of replays whose game length falls in a histogram bucket
+#[derive(Serialize)]
+struct DurationBucket {
+    label: String,
+    min_minutes: u32,
+    /// Exclusive upper bound; `None` for the last bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_minutes: Option<u32>,
+    count: usize,
+}
+
+/// Failed replays with the same kind of error
+#[derive(Serialize)]
+struct FailureGroup {
+    kind: String,
+    count: usize,
+    examples: Vec<FailureExample>,
+}
+
+#[derive(Serialize)]
+struct FailureExample {
+    file: String,
+    error: String,
+}
+
+/// Unknown action census of a corpus
+#[derive(Serialize)]
+struct CensusOutput<'a> {
+    failed: usize,
+    totals: BuildCounts,
+    builds: &'a BTreeMap<String, BuildCounts>,
+    patterns: Vec<&'a UnknownPattern>,
+}
+
+/// Byte-range map of the decompressed data
+#[derive(Serialize)]
+struct AnnotateOutput {
+    file: String,
+    decompressed_size: usize,
+    claimed_bytes: usize,
+    unclaimed_bytes: usize,
+    unknown_bytes: usize,
+    annotations: Vec<Annotation>,
+}
+
+/// Per-archive counts in the batch summary
+#[derive(Serialize, Default)]
+struct ArchiveTotals {
+    files: usize,
+    successful: usize,
+    failed: usize,
+    total_actions: usize,
 }
 
 // ============================================================================
@@ -234,15 +589,134 @@ fn main() -> ExitCode {
             players,
             stats,
             chat,
-        } => cmd_parse(&file, output, actions, players, stats, chat),
+            clock,
+            strict,
+            diagnostics,
+            player,
+            action_type,
+            from,
+            to,
+            ability_code,
+            filter,
+        } => {
+            let filter = match build_action_filter(player, &action_type, from, to, ability_code)
+            {
+                Ok(flags) => filter.unwrap_or_default().and(flags),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let sections = Sections {
+                actions: actions || !filter.is_empty(),
+                players,
+                stats,
+                chat,
+                diagnostics,
+            };
+            let mode = if strict {
+                ParseMode::Strict
+            } else {
+                ParseMode::Lenient
+            };
+            cmd_parse(&file, output, sections, &filter, clock, mode)
+        }
         Commands::Validate { file, verbose } => cmd_validate(&file, verbose),
         Commands::Batch {
-            directory,
+            inputs,
+            stdin,
+            recursive,
+            include,
+            exclude,
             output,
             format,
             summary,
             continue_on_error,
-        } => cmd_batch(&directory, output, format, summary, continue_on_error),
+            jobs,
+            timeout,
+            cache,
+            force,
+        } => {
+            let options = BatchOptions {
+                read_stdin: stdin,
+                recursive,
+                include,
+                exclude,
+                summary,
+                continue_on_error,
+                jobs: jobs.unwrap_or_else(|| {
+                    std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+                }),
+                timeout: (timeout > 0).then(|| Duration::from_secs(timeout)),
+                cache,
+                force,
+            };
+            cmd_batch(inputs, output, format, &options)
+        }
+        Commands::Anonymize {
+            file,
+            output,
+            salt,
+            chat,
+        } => cmd_anonymize(&file, &output, &salt, chat),
+        Commands::Trim {
+            file,
+            output,
+            start,
+            end,
+            lead_in,
+        } => cmd_trim(&file, &output, start..=end, lead_in),
+        Commands::Compare {
+            left,
+            right,
+            output,
+            limit,
+        } => cmd_compare(&left, &right, output, limit),
+        Commands::Report {
+            file,
+            output,
+            title,
+        } => cmd_report(&file, &output, title.as_deref()),
+        #[cfg(feature = "sqlite")]
+        Commands::ExportSqlite {
+            inputs,
+            db,
+            recursive,
+            include,
+            exclude,
+            continue_on_error,
+        } => match build_input_options(recursive, &include, &exclude) {
+            Ok(input_options) => {
+                cmd_export_sqlite(&inputs, &db, &input_options, continue_on_error)
+            }
+            Err(e) => {
+                eprintln!("Invalid glob pattern: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Commands::Census {
+            inputs,
+            recursive,
+            include,
+            exclude,
+            output,
+            limit,
+            samples,
+        } => match build_input_options(recursive, &include, &exclude) {
+            Ok(input_options) => cmd_census(&inputs, &input_options, output, limit, samples),
+            Err(e) => {
+                eprintln!("Invalid glob pattern: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Commands::Annotate {
+            file,
+            output,
+            unknown_only,
+            dump,
+        } => cmd_annotate(&file, output, unknown_only, dump.as_deref()),
+        #[cfg(feature = "tui")]
+        Commands::Inspect { file, offset } => cmd_inspect(&file, offset),
     }
 }
 
@@ -260,40 +734,30 @@ fn cmd_info(file: &Path) -> ExitCode {
         }
     };
 
-    // Parse header
-    let header = match Header::parse(&data) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("Error parsing header: {}", e);
-            return ExitCode::FAILURE;
-        }
-    };
-
-    // Decompress and parse game record
-    let decompressed = match decompress(&data, &header) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Error decompressing: {}", e);
-            return ExitCode::FAILURE;
-        }
-    };
-
-    let game_record = match GameRecord::parse(&decompressed) {
+    // Parse header and game record; actions and chat are not needed
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = match Replay::from_bytes_with(&data, &options) {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("Error parsing game record: {}", e);
+            eprintln!("Error: {}", e);
             return ExitCode::FAILURE;
         }
     };
 
     // Display info
-    print_info(&header, &game_record, data.len());
+    let game_id = replay.game_id().ok();
+    print_info(&replay.header, &replay.game, data.len(), game_id.as_deref());
 
     ExitCode::SUCCESS
 }
 
 #[allow(clippy::cast_precision_loss)]
-fn print_info(header: &Header, game_record: &GameRecord, file_size: usize) {
+fn print_info(
+    header: &Header,
+    game_record: &GameRecord,
+    file_size: usize,
+    game_id: Option<&str>,
+) {
     println!("=== Replay Information ===\n");
 
     // File information
@@ -347,19 +811,53 @@ fn print_info(header: &Header, game_record: &GameRecord, file_size: usize) {
     println!("  Header Size: {} bytes", header.header_size());
     println!("  Data Offset: 0x{:X}", header.data_offset());
     println!("  Decompressed Size: {} bytes", header.decompressed_size());
+    if let Some(game_id) = game_id {
+        println!("  Game ID: {}", game_id);
+    }
 }
 
 // ============================================================================
 // Parse Command Implementation
 // ============================================================================
 
+/// Optional sections of the parse output.
+#[derive(Debug, Clone, Copy)]
+struct Sections {
+    actions: bool,
+    players: bool,
+    stats: bool,
+    chat: bool,
+    diagnostics: bool,
+}
+
+/// Builds the action filter of the parse command's filter flags.
+fn build_action_filter(
+    players: Vec<u8>,
+    action_types: &[String],
+    from: Option<u32>,
+    to: Option<u32>,
+    codes: Vec<String>,
+) -> Result<ActionFilter, FilterError> {
+    let mut filter = ActionFilter::new()
+        .players(players)
+        .action_types(action_types)?
+        .codes(codes);
+    if let Some(from) = from {
+        filter = filter.from_ms(from);
+    }
+    if let Some(to) = to {
+        filter = filter.to_ms(to);
+    }
+    Ok(filter)
+}
+
 fn cmd_parse(
     file: &Path,
     output: OutputFormat,
-    include_actions: bool,
-    include_players: bool,
-    include_stats: bool,
-    include_chat: bool,
+    sections: Sections,
+    filter: &ActionFilter,
+    clock: ClockMode,
+    mode: ParseMode,
 ) -> ExitCode {
     // Read and parse
     let data = match std::fs::read(file) {
@@ -370,7 +868,11 @@ fn cmd_parse(
         }
     };
 
-    let (header, game_record, decompressed) = match parse_replay(&data) {
+    let options = ParseOptions::new()
+        .actions(sections.actions || sections.stats)
+        .chat(sections.chat)
+        .mode(mode);
+    let replay = match Replay::from_bytes_with(&data, &options) {
         Ok(r) => r,
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -378,102 +880,117 @@ fn cmd_parse(
         }
     };
 
+    let game_clock = match clock {
+        ClockMode::Game => None,
+        ClockMode::Wall => match GameClock::from_iterator(replay.timeframes()) {
+            Ok(game_clock) => Some(game_clock),
+            Err(e) => {
+                eprintln!("Error: Clock reconstruction failed: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
     // Build output
-    let output_data = build_output(
-        &header,
-        &game_record,
-        &decompressed,
-        data.len(),
-        include_actions,
-        include_players,
-        include_stats,
-        include_chat,
-    );
+    let mut output_data = build_output(replay, data.len(), sections);
+
+    // Filter on game time, before timestamps are converted
+    if let Some(actions) = &mut output_data.actions {
+        actions.retain(|action| filter.matches(action));
+    }
+
+    if let Some(game_clock) = &game_clock {
+        apply_wall_clock(&mut output_data, game_clock);
+    }
 
     // Format and print
     match output {
         OutputFormat::Json => print_json(&output_data),
-        OutputFormat::Pretty => print_pretty(&output_data),
+        OutputFormat::Pretty => print_pretty(&output_data, filter.is_empty()),
     }
 
     ExitCode::SUCCESS
 }
 
-fn parse_replay(data: &[u8]) -> Result<(Header, GameRecord, Vec<u8>), String> {
-    let header = Header::parse(data).map_err(|e| format!("Header parsing failed: {}", e))?;
-    let decompressed =
-        decompress(data, &header).map_err(|e| format!("Decompression failed: {}", e))?;
-    let game_record =
-        GameRecord::parse(&decompressed).map_err(|e| format!("Record parsing failed: {}", e))?;
-    Ok((header, game_record, decompressed))
+/// Converts all game timestamps in the output to wall-clock time.
+fn apply_wall_clock(output: &mut ParseOutput, clock: &GameClock) {
+    output.time_base = Some("wall".to_string());
+
+    if let Some(header) = &mut output.header {
+        if let Some(duration_ms) = header.duration_ms {
+            let wall_ms = clock.game_to_wall_ms(duration_ms);
+            header.duration_ms = Some(wall_ms);
+            header.duration = Some(format_duration(wall_ms));
+        }
+    }
+
+    if let Some(chat) = &mut output.chat {
+        for event in chat {
+            event.timestamp_ms = clock.game_to_wall_ms(event.timestamp_ms);
+        }
+    }
+
+    if let Some(actions) = &mut output.actions {
+        for action in actions {
+            action.timestamp_ms = clock.game_to_wall_ms(action.timestamp_ms);
+        }
+    }
 }
 
-fn build_output(
-    header: &Header,
-    game_record: &GameRecord,
-    decompressed: &[u8],
-    file_size: usize,
-    include_actions: bool,
-    include_players: bool,
-    include_stats: bool,
-    include_chat: bool,
-) -> ParseOutput {
+/// Builds the output from a replay parsed with the matching `ParseOptions`.
+fn build_output(replay: Replay, file_size: usize, sections: Sections) -> ParseOutput {
     // Always include header info (pass player count for game mode inference)
-    let header_info = Some(build_header_info(header, file_size, game_record.player_count()));
-
-    // Build player list if requested
-    let players = if include_players {
-        Some(build_player_info(game_record))
-    } else {
-        None
-    };
+    let header_info = build_header_info(&replay, file_size, replay.game.player_count());
 
-    // Build chat messages if requested
-    let chat = if include_chat {
-        Some(collect_chat_messages(decompressed, game_record.timeframe_offset))
+    // Build player list and lobby slots if requested
+    let (players, slots) = if sections.players {
+        let players = replay.game.players.players().cloned().collect();
+        (Some(players), replay.slot_table().ok().flatten())
     } else {
-        None
+        (None, None)
     };
 
-    // Build actions and stats if requested
-    let (actions, statistics) = if include_actions || include_stats {
+    // Build stats if requested
+    let statistics = if sections.stats {
         // Get duration for APM calculation
-        let duration_ms = match header {
-            Header::Classic(h) => Some(h.duration_ms),
-            Header::Grbn(_) => None,
-        };
+        let duration_ms = header_info.duration_ms;
         // Get player names for stats
-        let player_names: HashMap<u8, String> = game_record
+        let player_names: HashMap<u8, String> = replay
+            .game
             .players
             .players()
             .map(|p| (p.slot_id(), p.player_name().to_string()))
             .collect();
 
-        let (action_list, stats) =
-            collect_actions(game_record, decompressed, duration_ms, &player_names);
-        (
-            if include_actions {
-                Some(action_list)
-            } else {
-                None
-            },
-            if include_stats { Some(stats) } else { None },
-        )
+        Some(collect_statistics(&replay, duration_ms, &player_names))
     } else {
-        (None, None)
+        None
     };
 
     ParseOutput {
-        header: header_info,
+        game_id: replay.game_id().ok(),
+        time_base: None,
+        header: Some(header_info),
+        map: replay.settings().map_path(),
         players,
-        chat,
-        actions,
+        slots,
+        chat: if sections.chat { Some(replay.chat) } else { None },
+        actions: if sections.actions {
+            Some(replay.actions)
+        } else {
+            None
+        },
         statistics,
+        diagnostics: if sections.diagnostics {
+            Some(replay.diagnostics)
+        } else {
+            None
+        },
     }
 }
 
-fn build_header_info(header: &Header, file_size: usize, player_count: usize) -> HeaderInfo {
-    match header {
+fn build_header_info(replay: &Replay, file_size: usize, player_count: usize) -> HeaderInfo {
+    match &replay.header {
         Header::Classic(h) => HeaderInfo {
             format: "Classic".to_string(),
             file_size,
@@ -484,19 +1001,40 @@ fn build_header_info(header: &Header, file_size: usize, player_count: usize) ->
             duration_ms: Some(h.duration_ms),
             duration: Some(h.duration_string()),
         },
-        Header::Grbn(h) => HeaderInfo {
-            format: "Grbn".to_string(),
-            file_size,
-            decompressed_size: h.decompressed_size,
-            build_version: None,
-            version: None,
-            game_mode: Some(infer_game_mode(player_count)),
-            duration_ms: None,
-            duration: None,
-        },
+        Header::Grbn(h) => {
+            // The GRBN header has no duration; add up the TimeFrame increments
+            let duration_ms = replay.records().ok().map(|records| {
+                records
+                    .map_while(Result::ok)
+                    .fold(0u32, |total, (_, record)| {
+                        total.saturating_add(record.time_increment_ms())
+                    })
+            });
+            HeaderInfo {
+                format: "Grbn".to_string(),
+                file_size,
+                decompressed_size: h.decompressed_size,
+                build_version: None,
+                version: None,
+                game_mode: Some(infer_game_mode(player_count)),
+                duration_ms,
+                duration: duration_ms.map(format_duration),
+            }
+        }
     }
 }
 
+/// Formats a duration in milliseconds as HH:MM:SS, like `ClassicHeader::duration_string`.
+fn format_duration(ms: u32) -> String {
+    let total_seconds = ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}",
+        total_seconds / 3600,
+        (total_seconds / 60) % 60,
+        total_seconds % 60
+    )
+}
+
 /// Infers game mode from player count.
 fn infer_game_mode(player_count: usize) -> String {
     match player_count {
@@ -510,93 +1048,113 @@ fn infer_game_mode(player_count: usize) -> String {
     }
 }
 
-fn build_player_info(game_record: &GameRecord) -> Vec<PlayerInfo> {
-    let mut players = Vec::new();
+/// Order ID of a smart (right-click) order, 0x000D0003.
+const SMART_ORDER: [u8; 4] = [0x03, 0x00, 0x0D, 0x00];
 
-    // Add players from the roster using the public API
-    for record in game_record.players.players() {
-        players.push(PlayerInfo {
-            slot_id: record.slot_id(),
-            name: record.player_name().to_string(),
-        });
-    }
+/// Returns whether an order's code names a unit, building, upgrade or item
+/// rather than an ability (`A...`) or a numeric order ID.
+fn is_object_code(code: [u8; 4]) -> bool {
+    AbilityCode::from_raw(code).is_valid_fourcc() && code[3] != b'A'
+}
 
-    players
+/// Returns whether an action is sent by the client on its own, such as the
+/// pre-subselection before a subgroup switch or a cache or Battle.net sync.
+fn is_bookkeeping(action_type: &ActionType) -> bool {
+    matches!(
+        action_type,
+        ActionType::PreSubselection
+            | ActionType::GameCacheSync { .. }
+            | ActionType::BattleNetSync { .. }
+    )
 }
 
-fn collect_actions(
-    game_record: &GameRecord,
-    decompressed: &[u8],
+fn collect_statistics(
+    replay: &Replay,
     duration_ms: Option<u32>,
     player_names: &HashMap<u8, String>,
-) -> (Vec<ActionInfo>, Statistics) {
-    use w3g_parser::actions::{ActionType, HotkeyOperation};
+) -> Statistics {
+    use w3g_parser::actions::HotkeyOperation;
 
-    let mut actions = Vec::new();
-    let mut stats = Statistics::default();
+    let mut stats = Statistics {
+        total_frames: replay.frame_count,
+        ..Statistics::default()
+    };
     let mut player_stats: HashMap<u8, PlayerStats> = HashMap::new();
 
-    for frame_result in game_record.timeframes(decompressed) {
-        let frame = match frame_result {
-            Ok(f) => f,
-            Err(_) => continue,
-        };
+    for action in &replay.actions {
+        stats.total_actions += 1;
 
-        stats.total_frames += 1;
+        let action_type_str = format!("{:?}", action.action_type);
+        *stats.actions_by_type.entry(action_type_str).or_insert(0) += 1;
+        *stats
+            .actions_by_player
+            .entry(action.player_id)
+            .or_insert(0) += 1;
 
-        for action_result in frame.actions() {
-            let action = match action_result {
-                Ok(a) => a,
-                Err(_) => continue,
-            };
+        // Selection bookkeeping and sync packets are not commands of the
+        // player, so they count toward neither the total nor APM
+        if is_bookkeeping(&action.action_type) {
+            continue;
+        }
 
-            stats.total_actions += 1;
-
-            let action_type_str = format!("{:?}", action.action_type);
-            *stats
-                .actions_by_type
-                .entry(action_type_str.clone())
-                .or_insert(0) += 1;
-            *stats
-                .actions_by_player
-                .entry(action.player_id)
-                .or_insert(0) += 1;
-
-            // Update per-player stats
-            let ps = player_stats.entry(action.player_id).or_default();
-            ps.total += 1;
-
-            // Categorize action (matching warcraft3.info categories)
-            match &action.action_type {
-                ActionType::Movement(_) | ActionType::MinimapPing { .. } => ps.rightclick += 1,
-                ActionType::BasicCommand { .. } => ps.basic += 1,
-                ActionType::BuildTrain { .. } => ps.buildtrain += 1,
-                ActionType::Ability(_)
-                | ActionType::AbilityWithSelection(_)
-                | ActionType::InstantAbility(_)
-                | ActionType::UnitAbilityNoTarget { .. }
-                | ActionType::UnitAbilityGroundTarget { .. }
-                | ActionType::UnitAbilityUnitTarget { .. }
-                | ActionType::UnitAbilityTwoTargets { .. }
-                | ActionType::RemoveFromQueue { .. }
-                | ActionType::TransferResources { .. } => ps.ability += 1,
-                ActionType::ItemAction { .. } | ActionType::GiveDropItem { .. } => ps.item += 1,
-                ActionType::Selection(_) | ActionType::SelectSubgroup { .. } => ps.select += 1,
-                ActionType::Hotkey(hk) => match hk.operation {
-                    HotkeyOperation::Assign | HotkeyOperation::AddToGroup => ps.assigngroup += 1,
-                    HotkeyOperation::Select => ps.selecthotkey += 1,
-                    HotkeyOperation::Unknown(_) => ps.other += 1,
-                },
-                ActionType::EscapeKey | ActionType::ChangeAllyOptions { .. } => ps.esc += 1,
-                ActionType::BattleNetSync { .. } => ps.other += 1,
-                ActionType::Unknown { .. } => ps.other += 1,
-            }
+        // Update per-player stats
+        let ps = player_stats.entry(action.player_id).or_default();
+        ps.total += 1;
 
-            actions.push(ActionInfo {
-                player_id: action.player_id,
-                timestamp_ms: frame.accumulated_time_ms,
-                action_type: action_type_str,
-            });
+        // Categorize action (matching warcraft3.info categories)
+        match &action.action_type {
+            ActionType::MinimapPing { .. }
+            | ActionType::UnitAbilityGroundTarget {
+                ability_code: SMART_ORDER,
+                ..
+            }
+            | ActionType::UnitAbilityUnitTarget {
+                ability_code: SMART_ORDER,
+                ..
+            } => ps.rightclick += 1,
+            // Numeric order IDs without a target, such as stop and hold position
+            ActionType::UnitAbilityNoTarget {
+                ability_code: [_, _, 0x0D, 0x00],
+                ..
+            } => ps.basic += 1,
+            // Orders of a unit, building, upgrade or item carry its code
+            ActionType::UnitAbilityNoTarget { ability_code, .. }
+            | ActionType::UnitAbilityGroundTarget { ability_code, .. }
+                if is_object_code(*ability_code) =>
+            {
+                ps.buildtrain += 1;
+            }
+            ActionType::UnitAbilityNoTarget { .. }
+            | ActionType::UnitAbilityGroundTarget { .. }
+            | ActionType::UnitAbilityUnitTarget { .. }
+            | ActionType::UnitAbilityTwoTargets { .. }
+            | ActionType::RemoveFromQueue { .. }
+            | ActionType::TransferResources { .. } => ps.ability += 1,
+            ActionType::ItemAction { .. } | ActionType::GiveDropItem { .. } => ps.item += 1,
+            ActionType::Selection(_) | ActionType::SelectSubgroup { .. } => ps.select += 1,
+            ActionType::Hotkey(hk) => match hk.operation {
+                HotkeyOperation::Assign | HotkeyOperation::AddToGroup => ps.assigngroup += 1,
+                HotkeyOperation::Select => ps.selecthotkey += 1,
+                HotkeyOperation::Unknown(_) => ps.other += 1,
+            },
+            ActionType::TriggerEscapeKey
+            | ActionType::ChangeAllyOptions { .. } => ps.esc += 1,
+            ActionType::PauseGame
+            | ActionType::ResumeGame
+            | ActionType::SetGameSpeed { .. }
+            | ActionType::IncreaseGameSpeed
+            | ActionType::DecreaseGameSpeed
+            | ActionType::SaveGame { .. }
+            | ActionType::SaveGameFinished { .. }
+            | ActionType::TriggerChatCommand { .. }
+            | ActionType::ScenarioTrigger { .. }
+            | ActionType::HeroSkillSubmenu
+            | ActionType::BuildingSubmenu
+            | ActionType::ArrowKeyEvent { .. }
+            | ActionType::Unknown { .. } => ps.other += 1,
+            ActionType::PreSubselection
+            | ActionType::GameCacheSync { .. }
+            | ActionType::BattleNetSync { .. } => {}
         }
     }
 
@@ -618,60 +1176,7 @@ fn collect_actions(
 
     stats.player_stats = Some(player_stats);
 
-    (actions, stats)
-}
-
-/// Collects all chat messages from decompressed replay data.
-///
-/// Chat message format (reverse engineered):
-/// - Offset 0: 0x20 (marker)
-/// - Offset 1: flags (0x00-0x1F, typically 0x03 for system, 0x07 for player)
-/// - Offset 2-3: message_id (u16 little-endian)
-/// - Offset 4-8: padding signature (0x20 XX 0x00 0x00 0x00)
-/// - Offset 9+: null-terminated message
-fn collect_chat_messages(decompressed: &[u8], start_offset: usize) -> Vec<ChatInfo> {
-    let mut messages = Vec::new();
-    let mut offset = start_offset;
-
-    while offset + 9 < decompressed.len() {
-        if decompressed[offset] == CHAT_MARKER {
-            // Validate chat message structure to filter false positives
-            // Check: marker=0x20, flags in valid range, padding signature matches
-            let flags = decompressed[offset + 1];
-
-            // Flags should be small (0x00-0x1F range based on reverse engineering)
-            // and padding byte at offset 4 should be 0x20
-            if flags <= 0x1F
-                && offset + 8 < decompressed.len()
-                && decompressed[offset + 4] == 0x20
-                && decompressed[offset + 6] == 0x00
-                && decompressed[offset + 7] == 0x00
-                && decompressed[offset + 8] == 0x00
-            {
-                // Try to parse as a chat message
-                if let Ok(chat) = ChatMessage::parse(&decompressed[offset..]) {
-                    // Only include meaningful messages:
-                    // - At least 2 characters (filter out noise like single punctuation)
-                    // - Printable ASCII content
-                    if chat.message.len() >= 2
-                        && chat.message.chars().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
-                    {
-                        messages.push(ChatInfo {
-                            sender_slot: chat.sender_slot,
-                            is_system: chat.is_system_message(),
-                            message_id: chat.message_id,
-                            message: chat.message,
-                        });
-                    }
-                    offset += chat.byte_length;
-                    continue;
-                }
-            }
-        }
-        offset += 1;
-    }
-
-    messages
+    stats
 }
 
 fn print_json(output: &ParseOutput) {
@@ -681,7 +1186,13 @@ fn print_json(output: &ParseOutput) {
     }
 }
 
-fn print_pretty(output: &ParseOutput) {
+/// Prints the output for humans; `truncate_actions` limits the action list to
+/// its first 50 entries.
+fn print_pretty(output: &ParseOutput, truncate_actions: bool) {
+    if let Some(time_base) = &output.time_base {
+        println!("Time Base: {}", time_base);
+    }
+
     if let Some(header) = &output.header {
         println!("=== Header ===");
         println!("Format: {}", header.format);
@@ -699,26 +1210,30 @@ fn print_pretty(output: &ParseOutput) {
         if let Some(duration) = &header.duration {
             println!("Duration: {}", duration);
         }
+        if let Some(game_id) = &output.game_id {
+            println!("Game ID: {}", game_id);
+        }
         println!();
     }
 
     if let Some(players) = &output.players {
         println!("=== Players ({}) ===", players.len());
         for player in players {
-            println!("  Slot {}: {}", player.slot_id, player.name);
+            println!("  Slot {}: {}", player.slot_id(), player.player_name());
         }
         println!();
     }
 
     if let Some(chat) = &output.chat {
         println!("=== Chat Messages ({}) ===", chat.len());
-        for msg in chat {
-            if msg.is_system {
-                println!("  [SYSTEM] {}", msg.message);
+        for event in chat {
+            let msg = &event.message;
+            if msg.is_system_message() {
+                println!("  [{}ms] [SYSTEM] {}", event.timestamp_ms, msg.message);
             } else if let Some(slot) = msg.sender_slot {
-                println!("  [Slot {}] {}", slot, msg.message);
+                println!("  [{}ms] [Slot {}] {}", event.timestamp_ms, slot, msg.message);
             } else {
-                println!("  {}", msg.message);
+                println!("  [{}ms] {}", event.timestamp_ms, msg.message);
             }
         }
         println!();
@@ -765,17 +1280,76 @@ fn print_pretty(output: &ParseOutput) {
 
     if let Some(actions) = &output.actions {
         println!("=== Actions ({}) ===", actions.len());
-        // Only show first 50 actions in pretty mode to avoid spam
-        let display_count = std::cmp::min(actions.len(), 50);
+        // Only show first 50 actions in pretty mode to avoid spam, unless the
+        // actions were filtered
+        let display_count = if truncate_actions {
+            std::cmp::min(actions.len(), 50)
+        } else {
+            actions.len()
+        };
         for action in &actions[..display_count] {
             println!(
-                "  [{}ms] Player {}: {}",
-                action.timestamp_ms, action.player_id, action.action_type
+                "  [{}ms] Player {}: {}{}",
+                action.timestamp_ms,
+                action.player_id,
+                action.action_type.type_name(),
+                action_fields(&action.action_type)
             );
         }
-        if actions.len() > 50 {
-            println!("  ... and {} more actions", actions.len() - 50);
+        if actions.len() > display_count {
+            println!("  ... and {} more actions", actions.len() - display_count);
+        }
+    }
+
+    if let Some(diagnostics) = &output.diagnostics {
+        if output.actions.is_some() {
+            println!();
+        }
+        println!("=== Diagnostics ({}) ===", diagnostics.len());
+        // Same limit as actions
+        let display_count = std::cmp::min(diagnostics.len(), 50);
+        for diagnostic in &diagnostics[..display_count] {
+            println!("  {}", diagnostic);
+        }
+        if diagnostics.len() > 50 {
+            println!("  ... and {} more diagnostics", diagnostics.len() - 50);
+        }
+    }
+}
+
+/// Formats the decoded fields of an action as ` key=value` pairs, with
+/// `FourCC` codes in canonical form.
+fn action_fields(action_type: &ActionType) -> String {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(action_type) else {
+        return String::new();
+    };
+    fields
+        .iter()
+        .filter(|(key, _)| key.as_str() != "type")
+        .map(|(key, value)| format!(" {}={}", key, format_field(key, value)))
+        .collect()
+}
+
+fn format_field(key: &str, value: &serde_json::Value) -> String {
+    use serde_json::Value;
+
+    match value {
+        Value::Array(bytes) if key.ends_with("code") && bytes.len() == 4 => {
+            let mut raw = [0u8; 4];
+            for (byte, value) in raw.iter_mut().zip(bytes) {
+                *byte = value.as_u64().and_then(|v| u8::try_from(v).ok()).unwrap_or(b'?');
+            }
+            AbilityCode::from_raw(raw).to_string()
+        }
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, format_field(key, value)))
+                .collect();
+            format!("{{{}}}", fields.join(" "))
         }
+        Value::String(text) => format!("{:?}", text),
+        other => other.to_string(),
     }
 }
 
@@ -862,6 +1436,17 @@ fn validate_replay(file: &Path) -> ValidationResult {
                     .warnings
                     .push("No players found in replay".to_string());
             }
+
+            // Report out-of-sync records so desync disputes can be checked from the replay
+            if let Ok(report) = DesyncReport::from_iterator(record.timeframes(&decompressed)) {
+                if let Some(time) = report.first_divergence_string() {
+                    result.warnings.push(format!(
+                        "Replay contains {} desync record(s), first at {}",
+                        report.events.len(),
+                        time
+                    ));
+                }
+            }
         }
         Err(e) => {
             result
@@ -924,21 +1509,114 @@ fn status_icon(valid: bool) -> &'static str {
 // Batch Command Implementation
 // ============================================================================
 
-fn cmd_batch(
-    directory: &Path,
-    output_dir: Option<PathBuf>,
-    format: OutputFormat,
+/// Options for the batch command.
+struct BatchOptions {
+    /// Whether to read more input paths from stdin.
+    read_stdin: bool,
+    recursive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
     summary: bool,
     continue_on_error: bool,
+    /// Number of worker threads.
+    jobs: usize,
+    /// Time after which a single file is abandoned.
+    timeout: Option<Duration>,
+    /// Path of the results cache.
+    cache: Option<PathBuf>,
+    /// Whether to clear the results cache first.
+    force: bool,
+}
+
+/// A replay processed in a batch.
+struct BatchResult {
+    output: ParseOutput,
+    /// Content hash, set when a results cache is used.
+    hash: Option<String>,
+    /// Whether the output was taken from the results cache.
+    cached: bool,
+    /// Player and action rows, for the csv and arrow formats.
+    tables: Option<ReplayTables>,
+}
+
+/// A replay that failed in a batch.
+struct BatchError {
+    /// `ParserError` variant name, or `Timeout`, `Panic`, `Cache` or `Output`
+    /// for failures outside the parser.
+    kind: &'static str,
+    message: String,
+}
+
+impl BatchError {
+    fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<w3g_parser::ParserError> for BatchError {
+    fn from(e: w3g_parser::ParserError) -> Self {
+        Self::new(e.kind(), e.to_string())
+    }
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Result of processing one replay in a batch.
+type BatchOutcome = Result<BatchResult, BatchError>;
+
+fn cmd_batch(
+    mut inputs: Vec<PathBuf>,
+    output_dir: Option<PathBuf>,
+    format: BatchFormat,
+    options: &BatchOptions,
 ) -> ExitCode {
-    let replays = find_replays(directory);
+    if options.read_stdin {
+        inputs.extend(
+            std::io::stdin()
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| PathBuf::from(line.trim())),
+        );
+    }
+
+    let input_options = match build_input_options(
+        options.recursive,
+        &options.include,
+        &options.exclude,
+    ) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("Invalid glob pattern: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let sources = match collect_sources(&inputs, &input_options) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    if replays.is_empty() {
-        eprintln!("No .w3g files found in {}", directory.display());
+    if sources.is_empty() {
+        eprintln!("No replay files found");
         return ExitCode::FAILURE;
     }
 
-    eprintln!("Found {} replay files", replays.len());
+    let jobs = options.jobs.clamp(1, sources.len());
+    eprintln!(
+        "Found {} replay files, processing with {} worker(s)",
+        sources.len(),
+        jobs
+    );
 
     // Create output directory if specified and doesn't exist
     if let Some(ref dir) = output_dir {
@@ -950,165 +1628,1809 @@ fn cmd_batch(
         }
     }
 
-    let mut success_count = 0;
-    let mut error_count = 0;
-    let mut results: Vec<(PathBuf, ParseOutput)> = Vec::new();
-    let mut durations: Vec<u32> = Vec::new();
-    let mut format_counts: HashMap<String, usize> = HashMap::new();
+    let mut sink = match BatchSink::open(format, output_dir.as_deref()) {
+        Ok(sink) => sink,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    for replay in &replays {
-        eprint!(
-            "Processing {}... ",
-            replay.file_name().unwrap_or_default().to_string_lossy()
-        );
+    let cache = match options.cache.as_ref().map(|path| open_cache(path, options.force)) {
+        None => None,
+        Some(Ok(cache)) => Some(Arc::new(Mutex::new(cache))),
+        Some(Err(e)) => {
+            eprintln!("Failed to open cache: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-        match process_replay(replay, &output_dir, &format) {
-            Ok(output) => {
-                eprintln!("OK");
-                success_count += 1;
+    let total = sources.len();
+    let sources = Arc::new(sources);
+    let stop = Arc::new(AtomicBool::new(false));
+    // Parses still running, including the ones abandoned after a timeout
+    let running = Arc::new(AtomicUsize::new(0));
+
+    // A loader thread reads files and archive entries in order, opening each
+    // archive once, and hands the data to the workers
+    let (work_sender, work_receiver) = mpsc::sync_channel(jobs * 2);
+    {
+        let sources = Arc::clone(&sources);
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let _ = read_sources(&sources, |index, data| {
+                !stop.load(Ordering::Relaxed) && work_sender.send((index, data)).is_ok()
+            });
+        });
+    }
+
+    let work_receiver = Arc::new(Mutex::new(work_receiver));
+    let (sender, receiver) = mpsc::channel::<(usize, BatchOutcome)>();
+    for _ in 0..jobs {
+        let sources = Arc::clone(&sources);
+        let work_receiver = Arc::clone(&work_receiver);
+        let cache = cache.clone();
+        let sender = sender.clone();
+        let running = Arc::clone(&running);
+        let timeout = options.timeout;
+        thread::spawn(move || loop {
+            let work = work_receiver.lock().ok().and_then(|r| r.recv().ok());
+            let Some((index, data)) = work else {
+                break;
+            };
+            let outcome = data.map_err(BatchError::from).and_then(|data| {
+                let source = sources[index].clone();
+                let hash = cache.is_some().then(|| content_hash(&data));
+                // Cached outputs do not include the player and action tables
+                let cached = hash.as_ref().filter(|_| !format.needs_tables()).and_then(|hash| {
+                    let cache = cache.as_ref()?.lock().ok()?;
+                    cache.get(hash).map(str::to_string)
+                });
+                let mut result = match cached {
+                    Some(json) => reuse_cached(&json),
+                    None => process_with_timeout(source, data, format, timeout, &running),
+                }?;
+                result.hash = hash;
+                Ok(result)
+            });
+            if sender.send((index, outcome)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(sender);
+
+    let mut cached_count = 0;
+    let mut results: Vec<(ReplaySource, ParseOutput)> = Vec::new();
+    let mut failures: Vec<(ReplaySource, BatchError)> = Vec::new();
+    let mut archive_totals: BTreeMap<String, ArchiveTotals> = BTreeMap::new();
+
+    // Workers finish out of order; report results in input order
+    let mut pending: BTreeMap<usize, BatchOutcome> = BTreeMap::new();
+    let mut next_report = 0;
+    for (index, outcome) in receiver {
+        pending.insert(index, outcome);
+        while let Some(outcome) = pending.remove(&next_report) {
+            let source = &sources[next_report];
+            next_report += 1;
+            eprint!("[{}/{}] {}... ", next_report, total, source);
+
+            // Only reported results are written, never a parse abandoned
+            // after its timeout
+            let outcome = outcome.and_then(|result| {
+                write_output(source, &result.output, &output_dir, format)?;
+                Ok(result)
+            });
+
+            let archive = source.archive.as_ref().map(|archive| {
+                let totals = archive_totals
+                    .entry(archive.display().to_string())
+                    .or_default();
+                totals.files += 1;
+                totals
+            });
 
-                // Collect stats for summary
-                if let Some(ref header) = output.header {
-                    *format_counts.entry(header.format.clone()).or_insert(0) += 1;
-                    if let Some(duration) = header.duration_ms {
-                        durations.push(duration);
+            match outcome {
+                Ok(BatchResult {
+                    output,
+                    hash,
+                    cached,
+                    tables,
+                }) => {
+                    if cached {
+                        eprintln!("OK (cached)");
+                        cached_count += 1;
+                    } else {
+                        eprintln!("OK");
+                        if let (Some(cache), Some(hash)) = (&cache, hash) {
+                            let stored = serde_json::to_string(&output)
+                                .map_err(|e| e.to_string())
+                                .and_then(|json| {
+                                    let mut cache = cache.lock().map_err(|e| e.to_string())?;
+                                    cache.insert(&hash, &json).map_err(|e| e.to_string())
+                                });
+                            if let Err(e) = stored {
+                                eprintln!("Warning: failed to update cache: {}", e);
+                            }
+                        }
                     }
-                }
 
-                results.push((replay.clone(), output));
-            }
-            Err(e) => {
-                eprintln!("ERROR: {}", e);
-                error_count += 1;
-                if !continue_on_error {
-                    return ExitCode::FAILURE;
+                    if let Err(e) = sink.write(&output, tables.as_ref()) {
+                        eprintln!("Error: failed to write output: {}", e);
+                        return ExitCode::FAILURE;
+                    }
+                    if let Some(totals) = archive {
+                        totals.successful += 1;
+                        totals.total_actions +=
+                            output.statistics.as_ref().map_or(0, |s| s.total_actions);
+                    }
+
+                    results.push((source.clone(), output));
+                }
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    failures.push((source.clone(), e));
+                    if let Some(totals) = archive {
+                        totals.failed += 1;
+                    }
+                    if !options.continue_on_error {
+                        stop.store(true, Ordering::Relaxed);
+                        return ExitCode::FAILURE;
+                    }
                 }
             }
         }
     }
 
+    if let Err(e) = sink.finish() {
+        eprintln!("Error: failed to write output: {}", e);
+        return ExitCode::FAILURE;
+    }
+
     eprintln!(
-        "\nProcessed: {} success, {} errors",
-        success_count, error_count
+        "\nProcessed: {} success ({} cached), {} errors",
+        results.len(),
+        cached_count,
+        failures.len()
     );
+    let abandoned = running.load(Ordering::Relaxed);
+    if abandoned > 0 {
+        eprintln!("Warning: {} timed out parses were still running", abandoned);
+    }
 
-    if summary {
-        generate_summary(&results, &output_dir, &format_counts, &durations);
+    if options.summary {
+        // Keep stdout clean when it carries the ndjson or csv output
+        let to_stderr = output_dir.is_none() && format.combined_file().is_some();
+        generate_summary(&results, &failures, &output_dir, archive_totals, to_stderr);
     }
 
-    if error_count > 0 && !continue_on_error {
+    if !failures.is_empty() && !options.continue_on_error {
         ExitCode::FAILURE
     } else {
         ExitCode::SUCCESS
     }
 }
 
-fn find_replays(directory: &Path) -> Vec<PathBuf> {
-    let mut replays = Vec::new();
+/// Destination of the formats that combine every replay into one output.
+enum BatchSink {
+    /// Per-replay JSON files, written as each replay is reported.
+    Files,
+    /// One JSON object per line.
+    Ndjson(Box<dyn Write>),
+    /// One CSV row per player.
+    Csv(PlayerCsvWriter<Box<dyn Write>>),
+    /// Arrow IPC action table.
+    #[cfg(feature = "arrow")]
+    Arrow(Box<ActionIpcWriter<BufWriter<File>>>),
+}
+
+impl BatchSink {
+    /// Opens the combined output in `output_dir`, or stdout without one.
+    fn open(format: BatchFormat, output_dir: Option<&Path>) -> Result<Self, String> {
+        let Some(file_name) = format.combined_file() else {
+            return Ok(BatchSink::Files);
+        };
+        if format == BatchFormat::Arrow {
+            if cfg!(not(feature = "arrow")) {
+                return Err("built without the arrow feature".to_string());
+            }
+            if output_dir.is_none() {
+                return Err("the arrow format requires --output".to_string());
+            }
+        }
+        let file = output_dir
+            .map(|dir| {
+                let path = dir.join(file_name);
+                File::create(&path)
+                    .map(BufWriter::new)
+                    .map_err(|e| format!("{}: {}", path.display(), e))
+            })
+            .transpose()?;
+
+        match (format, file) {
+            #[cfg(feature = "arrow")]
+            (BatchFormat::Arrow, Some(file)) => ActionIpcWriter::new(file)
+                .map(|writer| BatchSink::Arrow(Box::new(writer)))
+                .map_err(|e| e.to_string()),
+            (format, file) => {
+                let writer: Box<dyn Write> = match file {
+                    Some(file) => Box::new(file),
+                    None => Box::new(BufWriter::new(std::io::stdout())),
+                };
+                if format == BatchFormat::Csv {
+                    PlayerCsvWriter::new(writer)
+                        .map(BatchSink::Csv)
+                        .map_err(|e| e.to_string())
+                } else {
+                    Ok(BatchSink::Ndjson(writer))
+                }
+            }
+        }
+    }
 
-    if let Ok(entries) = std::fs::read_dir(directory) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().is_some_and(|e| e == "w3g") {
-                replays.push(path);
+    /// Appends one replay's output.
+    fn write(&mut self, output: &ParseOutput, tables: Option<&ReplayTables>) -> Result<(), String> {
+        match (self, tables) {
+            (BatchSink::Ndjson(writer), _) => {
+                serde_json::to_writer(&mut *writer, output).map_err(|e| e.to_string())?;
+                writer.write_all(b"\n").map_err(|e| e.to_string())
             }
+            (BatchSink::Csv(writer), Some(tables)) => {
+                writer.write(tables).map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "arrow")]
+            (BatchSink::Arrow(writer), Some(tables)) => {
+                writer.write(tables).map_err(|e| e.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Flushes the combined output and writes any footer.
+    fn finish(self) -> Result<(), String> {
+        match self {
+            BatchSink::Files => Ok(()),
+            BatchSink::Ndjson(mut writer) => writer.flush().map_err(|e| e.to_string()),
+            BatchSink::Csv(writer) => writer.finish().map(drop).map_err(|e| e.to_string()),
+            #[cfg(feature = "arrow")]
+            BatchSink::Arrow(writer) => writer
+                .finish()
+                .map_err(|e| e.to_string())
+                .and_then(|mut file| file.flush().map_err(|e| e.to_string())),
         }
     }
+}
 
-    replays.sort();
-    replays
+/// Opens the results cache, clearing it first when `force` is set.
+fn open_cache(path: &Path, force: bool) -> w3g_parser::Result<ResultCache> {
+    let mut cache = ResultCache::open(path)?;
+    if force {
+        cache.clear()?;
+    }
+    Ok(cache)
 }
 
-fn process_replay(
-    replay: &Path,
-    output_dir: &Option<PathBuf>,
-    format: &OutputFormat,
-) -> Result<ParseOutput, String> {
+fn build_input_options(
+    recursive: bool,
+    include: &[String],
+    exclude: &[String],
+) -> Result<InputOptions, glob::PatternError> {
+    let mut input_options = InputOptions::new().recursive(recursive);
+    for pattern in include {
+        input_options = input_options.include(pattern)?;
+    }
+    for pattern in exclude {
+        input_options = input_options.exclude(pattern)?;
+    }
+    Ok(input_options)
+}
+
+/// Processes one replay on its own thread, giving up after `timeout`.
+///
+/// Threads cannot be cancelled, so a timed-out parse keeps running in the
+/// background, counted in `running`, until it finishes or the process exits.
+/// Its result is discarded and the worker moves on to the next file. A panic
+/// while parsing is reported as an error.
+fn process_with_timeout(
+    source: ReplaySource,
+    data: Vec<u8>,
+    format: BatchFormat,
+    timeout: Option<Duration>,
+    running: &Arc<AtomicUsize>,
+) -> BatchOutcome {
+    let (sender, receiver) = mpsc::channel();
+    let guard = RunningGuard::new(running);
+    thread::spawn(move || {
+        let outcome = process_replay(&source, &data, format);
+        // Stop counting before sending, so that a reported parse is never
+        // counted as still running
+        drop(guard);
+        let _ = sender.send(outcome);
+    });
+
+    match timeout {
+        Some(timeout) => receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                BatchError::new("Timeout", format!("timed out after {}s", timeout.as_secs()))
+            }
+            RecvTimeoutError::Disconnected => BatchError::new("Panic", "parser panicked"),
+        })?,
+        None => receiver
+            .recv()
+            .map_err(|_| BatchError::new("Panic", "parser panicked"))?,
+    }
+}
+
+/// Counts a parse thread in a shared counter for as long as it is alive.
+struct RunningGuard(Arc<AtomicUsize>);
+
+impl RunningGuard {
+    fn new(running: &Arc<AtomicUsize>) -> Self {
+        running.fetch_add(1, Ordering::Relaxed);
+        Self(Arc::clone(running))
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn process_replay(source: &ReplaySource, data: &[u8], format: BatchFormat) -> BatchOutcome {
     // Parse replay
-    let data = std::fs::read(replay).map_err(|e| e.to_string())?;
-    let (header, game_record, decompressed) = parse_replay(&data)?;
-
-    let output = build_output(
-        &header,
-        &game_record,
-        &decompressed,
-        data.len(),
-        false, // Don't include actions in batch (too large)
-        true,  // Include players
-        true,  // Include stats
-        false, // Don't include chat in batch (too large)
-    );
+    let options = ParseOptions::new().chat(false);
+    let parsed = Replay::from_bytes_with(data, &options)?;
+    let tables = if format.needs_tables() {
+        let replay_id = source.output_path().to_string_lossy().replace('\\', "/");
+        Some(ReplayTables::from_replay(&parsed, &replay_id)?)
+    } else {
+        None
+    };
+
+    let sections = Sections {
+        actions: false, // Don't include actions in batch (too large)
+        players: true,
+        stats: true,
+        chat: false, // Don't include chat in batch (too large)
+        diagnostics: false,
+    };
+    let output = build_output(parsed, data.len(), sections);
+    Ok(BatchResult {
+        output,
+        hash: None,
+        cached: false,
+        tables,
+    })
+}
+
+/// Takes a replay's output from the results cache instead of parsing it.
+fn reuse_cached(json: &str) -> BatchOutcome {
+    let output: ParseOutput = serde_json::from_str(json)
+        .map_err(|e| BatchError::new("Cache", format!("invalid cache entry: {}", e)))?;
+    Ok(BatchResult {
+        output,
+        hash: None,
+        cached: true,
+        tables: None,
+    })
+}
 
-    // Write to file if output directory specified
+/// Writes a replay's output if an output directory is specified, mirroring
+/// the input layout. Formats that combine all replays into one file are
+/// written by [`BatchSink`] instead.
+fn write_output(
+    source: &ReplaySource,
+    output: &ParseOutput,
+    output_dir: &Option<PathBuf>,
+    format: BatchFormat,
+) -> Result<(), BatchError> {
+    if !format.writes_files() {
+        return Ok(());
+    }
     if let Some(dir) = output_dir {
-        let output_file = dir
-            .join(replay.file_stem().unwrap_or_default())
-            .with_extension("json");
-
-        let content = match format {
-            OutputFormat::Json => serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?,
-            OutputFormat::Pretty => {
-                // For pretty format in batch, still write JSON for machine readability
-                serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?
-            }
-        };
+        let output_error = |e: &dyn std::fmt::Display| BatchError::new("Output", e.to_string());
+        let output_file = dir.join(source.output_path()).with_extension("json");
+        if let Some(parent) = output_file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| output_error(&e))?;
+        }
 
-        std::fs::write(&output_file, content).map_err(|e| e.to_string())?;
+        // For pretty format in batch, still write JSON for machine readability
+        let content = serde_json::to_string_pretty(output).map_err(|e| output_error(&e))?;
+        std::fs::write(&output_file, content).map_err(|e| output_error(&e))?;
     }
 
-    Ok(output)
+    Ok(())
 }
 
+/// Upper bounds in minutes of the game length histogram buckets; the last
+/// bucket has no upper bound.
+const DURATION_BUCKET_BOUNDS: [u32; 6] = [5, 10, 15, 20, 30, 45];
+
+/// Example files listed for each kind of failure in the batch summary.
+const FAILURE_EXAMPLES: usize = 3;
+
+/// Entries listed for each distribution in the printed batch summary.
+const SUMMARY_TOP_ENTRIES: usize = 10;
+
 fn generate_summary(
-    results: &[(PathBuf, ParseOutput)],
+    results: &[(ReplaySource, ParseOutput)],
+    failures: &[(ReplaySource, BatchError)],
     output_dir: &Option<PathBuf>,
-    format_counts: &HashMap<String, usize>,
-    durations: &[u32],
+    archives: BTreeMap<String, ArchiveTotals>,
+    to_stderr: bool,
 ) {
-    let total_actions: usize = results
-        .iter()
-        .filter_map(|(_, output)| output.statistics.as_ref())
-        .map(|s| s.total_actions)
-        .sum();
+    let summary = build_summary(results, failures, archives);
+    let report = format_summary(&summary);
+    if to_stderr {
+        eprint!("{}", report);
+    } else {
+        print!("{}", report);
+    }
+
+    if let Some(dir) = output_dir {
+        let summary_file = dir.join("summary.json");
+        if let Ok(json) = serde_json::to_string_pretty(&summary) {
+            if std::fs::write(&summary_file, json).is_ok() {
+                eprintln!("\nSummary written to: {}", summary_file.display());
+            }
+        }
+    }
+}
+
+fn build_summary(
+    results: &[(ReplaySource, ParseOutput)],
+    failures: &[(ReplaySource, BatchError)],
+    archives: BTreeMap<String, ArchiveTotals>,
+) -> BatchSummary {
+    let mut format_distribution = BTreeMap::new();
+    let mut version_distribution = BTreeMap::new();
+    let mut map_distribution = BTreeMap::new();
+    let mut matchup_distribution = BTreeMap::new();
+    let mut durations: Vec<u32> = Vec::new();
+    let mut race_apm: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+    let mut total_actions = 0;
+
+    for (_, output) in results {
+        if let Some(header) = &output.header {
+            *format_distribution.entry(header.format.clone()).or_insert(0) += 1;
+            *version_distribution.entry(version_key(header)).or_insert(0) += 1;
+            durations.extend(header.duration_ms);
+        }
+        let map = output.map.as_deref().map_or("unknown", map_file_name);
+        *map_distribution.entry(map.to_string()).or_insert(0) += 1;
+        if let Some(matchup) = output.slots.as_ref().and_then(matchup) {
+            *matchup_distribution.entry(matchup).or_insert(0) += 1;
+        }
+        if let Some(statistics) = &output.statistics {
+            total_actions += statistics.total_actions;
+        }
+
+        let player_stats = output.statistics.as_ref().and_then(|s| s.player_stats.as_ref());
+        if let (Some(slots), Some(player_stats)) = (&output.slots, player_stats) {
+            for slot in slots.slots.iter().filter(|s| s.is_player() && !slots.is_observer(s)) {
+                let Some(stats) = player_stats.get(&slot.player_id).filter(|s| s.apm > 0.0) else {
+                    continue;
+                };
+                let entry = race_apm.entry(race_name(slot)).or_default();
+                entry.0 += stats.apm;
+                entry.1 += 1;
+            }
+        }
+    }
 
     #[allow(clippy::cast_possible_truncation)]
-    let avg_duration = if durations.is_empty() {
+    let average_duration_ms = if durations.is_empty() {
         None
     } else {
         Some((durations.iter().map(|&d| u64::from(d)).sum::<u64>() / durations.len() as u64) as u32)
     };
 
-    let summary = BatchSummary {
-        total_files: results.len(),
+    #[allow(clippy::cast_precision_loss)]
+    let average_apm_by_race = race_apm
+        .into_iter()
+        .map(|(race, (total, players))| (race, (total / players as f64 * 10.0).round() / 10.0))
+        .collect();
+
+    BatchSummary {
+        total_files: results.len() + failures.len(),
         successful: results.len(),
-        failed: 0,
+        failed: failures.len(),
         total_actions,
-        format_distribution: format_counts.clone(),
-        average_duration_ms: avg_duration,
-    };
+        format_distribution,
+        average_duration_ms,
+        version_distribution,
+        map_distribution,
+        matchup_distribution,
+        duration_histogram: duration_histogram(&durations),
+        average_apm_by_race,
+        failures: group_failures(failures),
+        archives,
+    }
+}
+
+/// Returns the summary key of a replay's game version.
+fn version_key(header: &HeaderInfo) -> String {
+    match (&header.version, header.build_version) {
+        (Some(version), Some(build)) => format!("{} (build {})", version, build),
+        _ => header.format.clone(),
+    }
+}
+
+/// Returns the file name of a map path using either separator.
+fn map_file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
 
-    println!("\n=== Batch Summary ===");
-    println!("Files processed: {}", summary.total_files);
-    println!("Successful: {}", summary.successful);
-    println!("Total actions: {}", summary.total_actions);
+/// Returns the matchup of a game, with the race letters of each team sorted,
+/// such as `HvO` or `HN v OU`. Returns `None` for games with fewer than two teams.
+fn matchup(slots: &SlotTable) -> Option<String> {
+    let mut teams: BTreeMap<u8, Vec<char>> = BTreeMap::new();
+    for slot in &slots.slots {
+        if slot.status == SlotStatus::Used && !slots.is_observer(slot) {
+            teams.entry(slot.team).or_default().push(race_letter(slot));
+        }
+    }
+    if teams.len() < 2 {
+        return None;
+    }
+
+    let mut teams: Vec<String> = teams
+        .into_values()
+        .map(|mut races| {
+            races.sort_unstable();
+            races.into_iter().collect()
+        })
+        .collect();
+    teams.sort();
+    let separator = if teams.iter().all(|team| team.len() == 1) { "v" } else { " v " };
+    Some(teams.join(separator))
+}
+
+fn race_letter(slot: &Slot) -> char {
+    if slot.is_random_race() {
+        return 'R';
+    }
+    match slot.race() {
+        Some(Race::Human) => 'H',
+        Some(Race::Orc) => 'O',
+        Some(Race::NightElf) => 'N',
+        Some(Race::Undead) => 'U',
+        _ => '?',
+    }
+}
+
+fn race_name(slot: &Slot) -> String {
+    if slot.is_random_race() {
+        return "Random".to_string();
+    }
+    slot.race().map_or_else(|| "Unknown".to_string(), |race| race.to_string())
+}
+
+fn duration_histogram(durations: &[u32]) -> Vec<DurationBucket> {
+    let mut buckets: Vec<DurationBucket> = Vec::new();
+    let mut lower = 0;
+    for upper in DURATION_BUCKET_BOUNDS.into_iter().map(Some).chain([None]) {
+        buckets.push(DurationBucket {
+            label: match upper {
+                Some(upper) => format!("{}-{} min", lower, upper),
+                None => format!("{}+ min", lower),
+            },
+            min_minutes: lower,
+            max_minutes: upper,
+            count: 0,
+        });
+        lower = upper.unwrap_or(lower);
+    }
 
-    println!("\nFormat distribution:");
-    for (format, count) in &summary.format_distribution {
-        println!("  {}: {}", format, count);
+    for &duration in durations {
+        let minutes = duration / 60_000;
+        let index = DURATION_BUCKET_BOUNDS.partition_point(|&upper| upper <= minutes);
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+/// Groups failures by error kind, most frequent first.
+fn group_failures(failures: &[(ReplaySource, BatchError)]) -> Vec<FailureGroup> {
+    let mut groups: BTreeMap<&str, FailureGroup> = BTreeMap::new();
+    for (source, error) in failures {
+        let group = groups.entry(error.kind).or_insert_with(|| FailureGroup {
+            kind: error.kind.to_string(),
+            count: 0,
+            examples: Vec::new(),
+        });
+        group.count += 1;
+        if group.examples.len() < FAILURE_EXAMPLES {
+            group.examples.push(FailureExample {
+                file: source.to_string(),
+                error: error.message.clone(),
+            });
+        }
+    }
+
+    let mut groups: Vec<FailureGroup> = groups.into_values().collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    groups
+}
+
+fn format_summary(summary: &BatchSummary) -> String {
+    use std::fmt::Write as _;
+
+    let mut report = String::new();
+    let _ = writeln!(report, "\n=== Batch Summary ===");
+    let _ = writeln!(report, "Files processed: {}", summary.total_files);
+    let _ = writeln!(report, "Successful: {}", summary.successful);
+    let _ = writeln!(report, "Failed: {}", summary.failed);
+    let _ = writeln!(report, "Total actions: {}", summary.total_actions);
+
+    for (title, distribution) in [
+        ("Format distribution", &summary.format_distribution),
+        ("Versions", &summary.version_distribution),
+        ("Maps", &summary.map_distribution),
+        ("Matchups", &summary.matchup_distribution),
+    ] {
+        if distribution.is_empty() {
+            continue;
+        }
+        let _ = writeln!(report, "\n{}:", title);
+        let mut entries: Vec<(&String, &usize)> = distribution.iter().collect();
+        entries.sort_by(|a, b| b.1.cmp(a.1));
+        for (key, count) in entries.iter().take(SUMMARY_TOP_ENTRIES) {
+            let _ = writeln!(report, "  {}: {}", key, count);
+        }
+        if entries.len() > SUMMARY_TOP_ENTRIES {
+            let _ = writeln!(report, "  ... and {} more", entries.len() - SUMMARY_TOP_ENTRIES);
+        }
     }
 
     if let Some(avg) = summary.average_duration_ms {
         let minutes = avg / 60000;
         let seconds = (avg % 60000) / 1000;
-        println!("\nAverage duration: {:02}:{:02}", minutes, seconds);
+        let _ = writeln!(report, "\nAverage duration: {:02}:{:02}", minutes, seconds);
     }
 
-    if let Some(dir) = output_dir {
-        let summary_file = dir.join("summary.json");
-        if let Ok(json) = serde_json::to_string_pretty(&summary) {
-            if std::fs::write(&summary_file, json).is_ok() {
-                println!("\nSummary written to: {}", summary_file.display());
+    let longest = summary.duration_histogram.iter().map(|b| b.count).max().unwrap_or(0);
+    if longest > 0 {
+        let _ = writeln!(report, "\nGame length:");
+        for bucket in &summary.duration_histogram {
+            let bar = "#".repeat((bucket.count * 40).div_ceil(longest));
+            let line = format!("  {:>10}  {:>5}  {}", bucket.label, bucket.count, bar);
+            let _ = writeln!(report, "{}", line.trim_end());
+        }
+    }
+
+    if !summary.average_apm_by_race.is_empty() {
+        let _ = writeln!(report, "\nAverage APM by race:");
+        for (race, apm) in &summary.average_apm_by_race {
+            let _ = writeln!(report, "  {}: {:.1}", race, apm);
+        }
+    }
+
+    if !summary.failures.is_empty() {
+        let _ = writeln!(report, "\nFailures:");
+        for group in &summary.failures {
+            let _ = writeln!(report, "  {}: {}", group.kind, group.count);
+            for example in &group.examples {
+                let _ = writeln!(report, "    {}: {}", example.file, example.error);
             }
         }
     }
+
+    if !summary.archives.is_empty() {
+        let _ = writeln!(report, "\nArchives:");
+        for (archive, totals) in &summary.archives {
+            let _ = writeln!(
+                report,
+                "  {}: {} files, {} successful, {} failed, {} actions",
+                archive, totals.files, totals.successful, totals.failed, totals.total_actions
+            );
+        }
+    }
+    report
+}
+
+// ============================================================================
+// Anonymize Command Implementation
+// ============================================================================
+
+fn cmd_anonymize(file: &Path, output: &Path, salt: &str, chat: ChatMode) -> ExitCode {
+    let data = match std::fs::read(file) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = AnonymizeOptions::new(salt).chat(chat.into());
+    let anonymized = match anonymize(&data, &options) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = std::fs::write(output, &anonymized) {
+        eprintln!("Error writing file: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    println!("Anonymised replay written to: {}", output.display());
+    ExitCode::SUCCESS
+}
+
+// ============================================================================
+// Trim Command Implementation
+// ============================================================================
+
+/// Parses a game time given as seconds, `MM:SS` or `HH:MM:SS` into milliseconds.
+fn parse_game_time(value: &str) -> Result<u32, String> {
+    let mut seconds: u32 = 0;
+    for (i, part) in value.split(':').enumerate() {
+        if i > 2 {
+            return Err(format!("invalid time {value:?}: expected at most HH:MM:SS"));
+        }
+        let part: u32 = part
+            .parse()
+            .map_err(|_| format!("invalid time {value:?}: {part:?} is not a number"))?;
+        seconds = seconds
+            .checked_mul(60)
+            .and_then(|s| s.checked_add(part))
+            .ok_or_else(|| format!("invalid time {value:?}: too large"))?;
+    }
+    seconds
+        .checked_mul(1000)
+        .ok_or_else(|| format!("invalid time {value:?}: too large"))
+}
+
+fn cmd_trim(
+    file: &Path,
+    output: &Path,
+    range: RangeInclusive<u32>,
+    lead_in: LeadInMode,
+) -> ExitCode {
+    if range.start() >= range.end() {
+        eprintln!("Error: --start must be before --end");
+        return ExitCode::FAILURE;
+    }
+
+    let data = match std::fs::read(file) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = TrimOptions::new(range).lead_in(lead_in.into());
+    let trimmed = match trim(&data, &options) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = std::fs::write(output, &trimmed) {
+        eprintln!("Error writing file: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    println!("Trimmed replay written to: {}", output.display());
+    ExitCode::SUCCESS
+}
+
+// ============================================================================
+// Compare Command Implementation
+// ============================================================================
+
+fn cmd_compare(left: &Path, right: &Path, format: OutputFormat, limit: usize) -> ExitCode {
+    let load = |file: &Path| {
+        let options = ParseOptions::new().actions(false).chat(false);
+        Replay::from_path_with(file, &options)
+            .map_err(|e| eprintln!("Error reading {}: {}", file.display(), e))
+    };
+    let (Ok(left_replay), Ok(right_replay)) = (load(left), load(right)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let comparison = match compare(&left_replay, &right_replay) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(&comparison) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing to JSON: {}", e),
+        },
+        OutputFormat::Pretty => print_comparison(&comparison, limit),
+    }
+
+    if comparison.is_consistent() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_comparison(comparison: &ReplayComparison, limit: usize) {
+    println!("=== Replay Comparison ===\n");
+
+    println!("Same game: {}", status_icon(comparison.is_same_game()));
+    for mismatch in &comparison.identity_mismatches {
+        match mismatch {
+            IdentityMismatch::Settings => println!("  - Game settings differ"),
+            IdentityMismatch::RandomSeed { left, right } => {
+                let seed = |seed: &Option<u32>| {
+                    seed.map_or_else(|| "none".to_string(), |s| format!("0x{s:08X}"))
+                };
+                println!("  - Random seed differs: {} vs {}", seed(left), seed(right));
+            }
+            IdentityMismatch::Roster { left, right } => {
+                let names = |roster: &[(u8, String)]| {
+                    roster
+                        .iter()
+                        .map(|(id, name)| format!("{id}:{name}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                println!("  - Roster differs: [{}] vs [{}]", names(left), names(right));
+            }
+        }
+    }
+
+    println!("\nFrames:");
+    println!("  Left: {}", comparison.left_frame_count);
+    println!("  Right: {}", comparison.right_frame_count);
+    println!("  Compared: {}", comparison.frames_compared);
+    println!("  Overlap: {}", format_game_time(comparison.overlap_ms));
+
+    println!("\nDivergences: {}", comparison.divergences.len());
+    for divergence in comparison.divergences.iter().take(limit) {
+        let time = format_game_time(divergence.time_ms);
+        match &divergence.kind {
+            DivergenceKind::MissingFrame { missing_from } => {
+                let side = match missing_from {
+                    Side::Left => "left",
+                    Side::Right => "right",
+                };
+                println!("  [{time}] Frame missing from {side} replay");
+            }
+            DivergenceKind::ActionsDiffer { player_ids } => {
+                println!("  [{time}] Actions differ for players {player_ids:?}");
+            }
+            DivergenceKind::ChecksumMismatch { left, right } => {
+                println!("  [{time}] Checksum mismatch: 0x{left:08X} vs 0x{right:08X}");
+            }
+        }
+    }
+    if comparison.divergences.len() > limit {
+        println!("  ... and {} more", comparison.divergences.len() - limit);
+    }
+}
+
+/// Formats a game time in milliseconds as MM:SS.
+fn format_game_time(ms: u32) -> String {
+    let total_seconds = ms / 1000;
+    format!("{:02}:{:02}", total_seconds / 60, total_seconds % 60)
+}
+
+// ============================================================================
+// Export SQLite Command Implementation
+// ============================================================================
+
+#[cfg(feature = "sqlite")]
+fn cmd_export_sqlite(
+    inputs: &[PathBuf],
+    db: &Path,
+    input_options: &InputOptions,
+    continue_on_error: bool,
+) -> ExitCode {
+    let sources = match collect_sources(inputs, input_options) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if sources.is_empty() {
+        eprintln!("No replay files found");
+        return ExitCode::FAILURE;
+    }
+
+    let mut database = match ReplayDatabase::open(db) {
+        Ok(database) => database,
+        Err(e) => {
+            eprintln!("Failed to open database: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (mut inserted, mut replaced, mut kept, mut errors) = (0, 0, 0, 0);
+    let total = sources.len();
+    let read = read_sources(&sources, |index, data| {
+        let source = &sources[index];
+        eprint!("[{}/{}] {}... ", index + 1, total, source);
+
+        let replay_id = source.output_path().to_string_lossy().replace('\\', "/");
+        let outcome = data
+            .and_then(|data| Replay::from_bytes(&data))
+            .and_then(|replay| database.upsert(&replay, &replay_id));
+        match outcome {
+            Ok(Upsert::Inserted) => {
+                eprintln!("OK");
+                inserted += 1;
+            }
+            Ok(Upsert::Replaced) => {
+                eprintln!("OK (replaced)");
+                replaced += 1;
+            }
+            Ok(Upsert::Kept) => {
+                eprintln!("OK (longer perspective kept)");
+                kept += 1;
+            }
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                errors += 1;
+                return continue_on_error;
+            }
+        }
+        true
+    });
+    if let Err(e) = read {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    eprintln!(
+        "\nImported: {} new, {} replaced, {} kept, {} errors ({} games in {})",
+        inserted,
+        replaced,
+        kept,
+        errors,
+        database.replay_count().unwrap_or_default(),
+        db.display()
+    );
+
+    if errors > 0 && !continue_on_error {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// ============================================================================
+// Report Command Implementation
+// ============================================================================
+
+fn cmd_report(file: &Path, output: &Path, title: Option<&str>) -> ExitCode {
+    let replay = match Replay::from_path(file) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let file_name = file.file_name().map(|name| name.to_string_lossy());
+    let title = title.or(file_name.as_deref()).unwrap_or("Match report");
+    let report = match MatchReport::from_replay(&replay, title) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = std::fs::write(output, report.to_html()) {
+        eprintln!("Error writing {}: {}", output.display(), e);
+        return ExitCode::FAILURE;
+    }
+    println!("Wrote {}", output.display());
+    ExitCode::SUCCESS
+}
+
+// ============================================================================
+// Census Command Implementation
+// ============================================================================
+
+/// Neighbouring actions listed per pattern in pretty census output.
+const CENSUS_TOP_NEIGHBOURS: usize = 3;
+
+fn cmd_census(
+    inputs: &[PathBuf],
+    input_options: &InputOptions,
+    format: OutputFormat,
+    limit: usize,
+    samples: usize,
+) -> ExitCode {
+    let sources = match collect_sources(inputs, input_options) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if sources.is_empty() {
+        eprintln!("No replay files found");
+        return ExitCode::FAILURE;
+    }
+
+    let mut census = UnknownCensus::new().samples(samples);
+    let mut failed = 0;
+    let total = sources.len();
+    let read = read_sources(&sources, |index, data| {
+        let source = &sources[index];
+        eprint!("[{}/{}] {}... ", index + 1, total, source);
+
+        // The census decodes the actions itself, keeping their offsets
+        let options = ParseOptions::new().actions(false).chat(false);
+        match data.and_then(|data| Replay::from_bytes_with(&data, &options)) {
+            Ok(replay) => {
+                let name = source.output_path().to_string_lossy().replace('\\', "/");
+                census.add(&name, &replay);
+                eprintln!("OK");
+            }
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                failed += 1;
+            }
+        }
+        true
+    });
+    if let Err(e) = read {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    eprintln!();
+
+    let output = CensusOutput {
+        failed,
+        totals: census.totals,
+        builds: &census.builds,
+        patterns: census.patterns(),
+    };
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(&output) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing to JSON: {}", e),
+        },
+        OutputFormat::Pretty => print_census(&output, limit),
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_census(output: &CensusOutput, limit: usize) {
+    let totals = &output.totals;
+    println!("=== Unknown Action Census ===\n");
+    println!("Replays: {} ({} failed)", totals.replays, output.failed);
+    println!(
+        "Actions: {}, unknown: {} ({:.1}%)",
+        totals.actions,
+        totals.unknown_actions,
+        totals.unknown_rate() * 100.0
+    );
+    println!("Patterns: {}", output.patterns.len());
+
+    println!("\nBy build:");
+    for (build, counts) in output.builds {
+        println!(
+            "  {:<24} {:>5} replays {:>9} actions {:>8} unknown ({:.1}%)",
+            build,
+            counts.replays,
+            counts.actions,
+            counts.unknown_actions,
+            counts.unknown_rate() * 100.0
+        );
+    }
+
+    if output.patterns.is_empty() {
+        return;
+    }
+    println!("\nMost frequent patterns:");
+    for (rank, pattern) in output.patterns.iter().take(limit).enumerate() {
+        println!(
+            "\n  #{} {}: {} times in {} replays",
+            rank + 1,
+            pattern.key,
+            pattern.count,
+            pattern.replays
+        );
+        println!("     Builds: {}", top_counts(&pattern.builds, usize::MAX));
+        println!("     Before: {}", top_counts(&pattern.preceding, CENSUS_TOP_NEIGHBOURS));
+        println!("     After:  {}", top_counts(&pattern.following, CENSUS_TOP_NEIGHBOURS));
+        for sample in &pattern.samples {
+            println!("     Sample: {} ({} @ 0x{:X})", sample.hex, sample.source, sample.offset);
+        }
+    }
+    if output.patterns.len() > limit {
+        println!("\n  ... and {} more patterns", output.patterns.len() - limit);
+    }
+}
+
+/// Formats the largest counts of a map as `name count, ...`.
+fn top_counts(counts: &BTreeMap<String, usize>, limit: usize) -> String {
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by_key(|&(_, count)| std::cmp::Reverse(*count));
+    let mut parts: Vec<String> = entries
+        .iter()
+        .take(limit)
+        .map(|(name, count)| format!("{name} {count}"))
+        .collect();
+    if entries.len() > limit {
+        parts.push(format!("+{} more", entries.len() - limit));
+    }
+    parts.join(", ")
+}
+
+// ============================================================================
+// Annotate Command Implementation
+// ============================================================================
+
+/// Bytes of each range shown in pretty annotate output.
+const ANNOTATE_PREVIEW_BYTES: usize = 8;
+
+fn cmd_annotate(
+    file: &Path,
+    format: OutputFormat,
+    unknown_only: bool,
+    dump: Option<&Path>,
+) -> ExitCode {
+    let data = match std::fs::read(file) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = match Replay::from_bytes_with(&data, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(path) = dump {
+        if let Err(e) = std::fs::write(path, replay.decompressed()) {
+            eprintln!("Error writing {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut annotations = RecordTree::build(&data, &replay).stream.annotations();
+    let total = |annotations: &[Annotation], keep: fn(&Annotation) -> bool| -> usize {
+        annotations.iter().filter(|a| keep(a)).map(|a| a.len).sum()
+    };
+    let claimed_bytes = total(&annotations, |a| a.claimed);
+    let unknown_bytes = total(&annotations, |a| a.claimed && a.unknown);
+    if unknown_only {
+        annotations.retain(|a| !a.claimed || a.unknown);
+    }
+
+    let output = AnnotateOutput {
+        file: file.display().to_string(),
+        decompressed_size: replay.decompressed().len(),
+        claimed_bytes,
+        unclaimed_bytes: replay.decompressed().len() - claimed_bytes,
+        unknown_bytes,
+        annotations,
+    };
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(&output) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing to JSON: {}", e),
+        },
+        OutputFormat::Pretty => print_annotations(&output, replay.decompressed()),
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_annotations(output: &AnnotateOutput, data: &[u8]) {
+    let percent = |bytes: usize| bytes as f64 * 100.0 / output.decompressed_size.max(1) as f64;
+    println!("=== Annotated Byte Map ===\n");
+    println!("File: {}", output.file);
+    println!("Decompressed: {} bytes", output.decompressed_size);
+    println!(
+        "Claimed: {} bytes ({:.1}%), of which unknown: {} bytes ({:.1}%)",
+        output.claimed_bytes,
+        percent(output.claimed_bytes),
+        output.unknown_bytes,
+        percent(output.unknown_bytes)
+    );
+    println!(
+        "Unclaimed: {} bytes ({:.1}%)\n",
+        output.unclaimed_bytes,
+        percent(output.unclaimed_bytes)
+    );
+
+    println!("{:<10} {:>6}  {:<26} {:<40} Label", "Offset", "Length", "Bytes", "Path");
+    for annotation in &output.annotations {
+        let bytes = &data[annotation.offset..annotation.end()];
+        let mut preview: Vec<String> = bytes
+            .iter()
+            .take(ANNOTATE_PREVIEW_BYTES)
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        if bytes.len() > ANNOTATE_PREVIEW_BYTES {
+            preview.push("..".to_string());
+        }
+
+        // Field labels repeat the last path segment
+        let field = annotation.path.rsplit(['.', '/']).next().unwrap_or_default();
+        let label = if !annotation.claimed {
+            format!("UNCLAIMED ({})", annotation.label)
+        } else if annotation.label == field {
+            String::new()
+        } else {
+            annotation.label.clone()
+        };
+        let label = if annotation.unknown && annotation.claimed {
+            format!("{label} [unknown]").trim_start().to_string()
+        } else {
+            label
+        };
+
+        let line = format!(
+            "0x{:08X} {:>6}  {:<26} {:<40} {}",
+            annotation.offset,
+            annotation.len,
+            preview.join(" "),
+            annotation.path,
+            label
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+// ============================================================================
+// Inspect Command Implementation
+// ============================================================================
+
+#[cfg(feature = "tui")]
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    DefaultTerminal, Frame,
+};
+#[cfg(feature = "tui")]
+use std::collections::HashSet;
+#[cfg(feature = "tui")]
+use w3g_parser::inspect::Node;
+
+/// Bytes per line of the hex view.
+#[cfg(feature = "tui")]
+const HEX_WIDTH: usize = 16;
+
+/// Parses an offset given in decimal or as `0x` hex.
+#[cfg(feature = "tui")]
+fn parse_offset(value: &str) -> Result<usize, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("invalid offset {value:?}"))
+}
+
+#[cfg(feature = "tui")]
+fn cmd_inspect(file: &Path, offset: Option<usize>) -> ExitCode {
+    let data = match std::fs::read(file) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Error reading file: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = match Replay::from_bytes_with(&data, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tree = RecordTree::build(&data, &replay);
+    let mut inspector = Inspector::new(&tree, [&data, replay.decompressed()]);
+    if let Some(offset) = offset {
+        inspector.select_offset(offset);
+    }
+
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Error: Cannot open the terminal: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = inspector.run(&mut terminal);
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A search the inspector can repeat with `n` and `N`.
+#[cfg(feature = "tui")]
+enum Search {
+    /// Nodes whose kind or action type is the given name.
+    Type(String),
+    /// Nodes of a player.
+    Player(u8),
+    /// Nodes whose label contains the text.
+    Text(String),
+    /// Leaf nodes flagged unknown.
+    Unknown,
+}
+
+#[cfg(feature = "tui")]
+impl Search {
+    /// Parses a search prompt; offsets are handled separately.
+    fn parse(query: &str) -> Result<Self, String> {
+        if let Some(name) = query.strip_prefix("type=") {
+            return Ok(Search::Type(name.trim().to_ascii_lowercase()));
+        }
+        if let Some(id) = query.strip_prefix("player=") {
+            let id = id.trim().parse().map_err(|_| format!("invalid player ID {id:?}"))?;
+            return Ok(Search::Player(id));
+        }
+        if query == "unknown" {
+            return Ok(Search::Unknown);
+        }
+        Ok(Search::Text(query.to_ascii_lowercase()))
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        match self {
+            Search::Type(name) => {
+                node.kind.name().eq_ignore_ascii_case(name)
+                    || node.action_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(name))
+            }
+            Search::Player(id) => node.player_id == Some(*id),
+            Search::Text(text) => node.label.to_ascii_lowercase().contains(text),
+            Search::Unknown => node.unknown && node.children.is_empty(),
+        }
+    }
+}
+
+/// State of the inspect terminal UI.
+///
+/// Nodes are identified by their path of child indices, starting with the
+/// root: 0 for the file header, 1 for the decompressed stream. Preorder is
+/// the lexicographic order of these paths.
+#[cfg(feature = "tui")]
+struct Inspector<'a> {
+    roots: [&'a Node; 2],
+    /// Bytes of each root: the file and the decompressed data.
+    data: [&'a [u8]; 2],
+    expanded: HashSet<Vec<usize>>,
+    /// Paths of the visible rows of the tree.
+    rows: Vec<Vec<usize>>,
+    selected: usize,
+    /// First row shown in the tree pane.
+    top: usize,
+    /// Lines the hex view is scrolled past the start of the selection.
+    hex_scroll: usize,
+    /// Search prompt being typed, if any.
+    prompt: Option<String>,
+    search: Option<Search>,
+    status: String,
+}
+
+#[cfg(feature = "tui")]
+impl<'a> Inspector<'a> {
+    fn new(tree: &'a RecordTree, data: [&'a [u8]; 2]) -> Self {
+        let mut inspector = Self {
+            roots: [&tree.header, &tree.stream],
+            data,
+            expanded: [vec![0], vec![1]].into_iter().collect(),
+            rows: Vec::new(),
+            selected: 0,
+            top: 0,
+            hex_scroll: 0,
+            prompt: None,
+            search: None,
+            status: "/ search (offset, type=, player=, unknown, text)  n/N next/previous  \
+                     u next unknown  [ ] scroll hex  q quit"
+                .to_string(),
+        };
+        inspector.rebuild_rows();
+        inspector
+    }
+
+    fn node(&self, path: &[usize]) -> &'a Node {
+        let mut node = self.roots[path[0]];
+        for &index in &path[1..] {
+            node = &node.children[index];
+        }
+        node
+    }
+
+    fn selected_path(&self) -> &[usize] {
+        &self.rows[self.selected]
+    }
+
+    fn rebuild_rows(&mut self) {
+        fn visit(inspector: &Inspector<'_>, path: &mut Vec<usize>, rows: &mut Vec<Vec<usize>>) {
+            rows.push(path.clone());
+            if !inspector.expanded.contains(path) {
+                return;
+            }
+            for index in 0..inspector.node(path).children.len() {
+                path.push(index);
+                visit(inspector, path, rows);
+                path.pop();
+            }
+        }
+
+        let mut rows = Vec::new();
+        for root in 0..self.roots.len() {
+            visit(self, &mut vec![root], &mut rows);
+        }
+        self.rows = rows;
+    }
+
+    /// Expands the ancestors of `path` and selects it.
+    fn select_path(&mut self, path: &[usize]) {
+        for len in 1..path.len() {
+            self.expanded.insert(path[..len].to_vec());
+        }
+        self.rebuild_rows();
+        self.selected = self.rows.iter().position(|row| row == path).unwrap_or(0);
+        self.hex_scroll = 0;
+    }
+
+    /// Selects the innermost node of the decompressed stream at `offset`.
+    fn select_offset(&mut self, offset: usize) {
+        let mut path = vec![1];
+        let mut node = self.roots[1];
+        if !(node.offset..node.end()).contains(&offset) {
+            self.status = format!("Offset 0x{offset:X} is outside the decompressed data");
+            return;
+        }
+        while let Some(index) =
+            node.children.iter().position(|c| (c.offset..c.end()).contains(&offset))
+        {
+            path.push(index);
+            node = &node.children[index];
+        }
+        self.select_path(&path);
+        self.status = format!("0x{offset:X}: {}", node.label);
+    }
+
+    /// Selects the next (or previous) node in preorder matching the search,
+    /// wrapping around at the end.
+    fn find(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            self.status = "No search".to_string();
+            return;
+        };
+
+        let current = self.selected_path().to_vec();
+        let mut first = None;
+        let mut last = None;
+        let mut before = None;
+        let mut after = None;
+        let mut path = Vec::new();
+        let mut visit = |path: &[usize], node: &Node| {
+            if !search.matches(node) {
+                return;
+            }
+            first.get_or_insert_with(|| path.to_vec());
+            last = Some(path.to_vec());
+            if path < current.as_slice() {
+                before = Some(path.to_vec());
+            } else if path > current.as_slice() && after.is_none() {
+                after = Some(path.to_vec());
+            }
+        };
+        for (index, root) in self.roots.iter().enumerate() {
+            path.push(index);
+            walk_preorder(root, &mut path, &mut visit);
+            path.pop();
+        }
+
+        let found = if forward { after.or(first) } else { before.or(last) };
+        match found {
+            Some(path) => {
+                self.select_path(&path);
+                self.status = format!("Found: {}", self.node(&path).label);
+            }
+            None => self.status = "No match".to_string(),
+        }
+    }
+
+    fn submit_prompt(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        if query.starts_with(|c: char| c.is_ascii_digit()) {
+            match parse_offset(query) {
+                Ok(offset) => self.select_offset(offset),
+                Err(e) => self.status = e,
+            }
+            return;
+        }
+        match Search::parse(query) {
+            Ok(search) => {
+                self.search = Some(search);
+                self.find(true);
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(prompt) = &mut self.prompt {
+                match key.code {
+                    KeyCode::Enter => {
+                        let query = std::mem::take(prompt);
+                        self.prompt = None;
+                        self.submit_prompt(&query);
+                    }
+                    KeyCode::Esc => self.prompt = None,
+                    KeyCode::Backspace => {
+                        prompt.pop();
+                    }
+                    KeyCode::Char(c) => prompt.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            let page = terminal.size()?.height.saturating_sub(8) as usize;
+            let previous = self.selected;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => self.selected += 1,
+                KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+                KeyCode::PageDown => self.selected += page,
+                KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+                KeyCode::End | KeyCode::Char('G') => self.selected = self.rows.len(),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => self.expand(),
+                KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+                KeyCode::Char('/') => self.prompt = Some(String::new()),
+                KeyCode::Char('n') => self.find(true),
+                KeyCode::Char('N') => self.find(false),
+                KeyCode::Char('u') => {
+                    self.search = Some(Search::Unknown);
+                    self.find(true);
+                }
+                KeyCode::Char(']') => self.hex_scroll += 1,
+                KeyCode::Char('[') => self.hex_scroll = self.hex_scroll.saturating_sub(1),
+                _ => {}
+            }
+            self.selected = self.selected.min(self.rows.len() - 1);
+            if self.selected != previous {
+                self.hex_scroll = 0;
+            }
+        }
+    }
+
+    /// Expands the selected node, or moves to its first child if expanded.
+    fn expand(&mut self) {
+        let path = self.selected_path().to_vec();
+        if self.node(&path).children.is_empty() {
+            return;
+        }
+        if self.expanded.insert(path) {
+            self.rebuild_rows();
+        } else {
+            self.selected += 1;
+        }
+    }
+
+    /// Collapses the selected node, or moves to its parent if collapsed.
+    fn collapse(&mut self) {
+        let path = self.selected_path().to_vec();
+        if self.expanded.remove(&path) {
+            self.rebuild_rows();
+        } else if path.len() > 1 {
+            self.select_path(&path[..path.len() - 1]);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, details, status] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [tree, hex] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(78)])
+            .areas(main);
+
+        self.draw_tree(frame, tree);
+        self.draw_hex(frame, hex);
+        self.draw_details(frame, details);
+
+        let status_line = match &self.prompt {
+            Some(prompt) => format!("/{prompt}"),
+            None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_tree(&mut self, frame: &mut Frame, area: Rect) {
+        let height = usize::from(area.height.saturating_sub(2)).max(1);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + height {
+            self.top = self.selected + 1 - height;
+        }
+
+        let lines: Vec<Line> = self.rows[self.top..]
+            .iter()
+            .take(height)
+            .enumerate()
+            .map(|(index, path)| {
+                let node = self.node(path);
+                let marker = if node.children.is_empty() {
+                    "  "
+                } else if self.expanded.contains(path) {
+                    "▾ "
+                } else {
+                    "▸ "
+                };
+                let text = format!(
+                    "{}{}{}  0x{:X} +{}",
+                    "  ".repeat(path.len() - 1),
+                    marker,
+                    node.label,
+                    node.offset,
+                    node.len
+                );
+                // Roots are too large to search for unknown bytes on every frame
+                let style = if node.unknown {
+                    Style::default().fg(Color::Red)
+                } else if path.len() > 1 && node.has_unknown() {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                let style = if self.top + index == self.selected {
+                    style.add_modifier(Modifier::REVERSED)
+                } else {
+                    style
+                };
+                Line::styled(text, style)
+            })
+            .collect();
+
+        let title = format!(" Records ({}/{}) ", self.selected + 1, self.rows.len());
+        let block = Block::default().borders(Borders::ALL).title(title);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_hex(&mut self, frame: &mut Frame, area: Rect) {
+        let path = self.selected_path().to_vec();
+        let node = self.node(&path);
+        let data = self.data[path[0]];
+        let height = usize::from(area.height.saturating_sub(2));
+
+        let node_lines = node.len.div_ceil(HEX_WIDTH).max(1);
+        self.hex_scroll = self.hex_scroll.min(node_lines.saturating_sub(1));
+        let start = (node.offset / HEX_WIDTH + self.hex_scroll) * HEX_WIDTH;
+        let end = (start + height * HEX_WIDTH).min(data.len());
+        let unknown = unknown_ranges(self.roots[path[0]], start..end);
+
+        let lines: Vec<Line> = (start..end)
+            .step_by(HEX_WIDTH)
+            .map(|line_start| {
+                let line_end = (line_start + HEX_WIDTH).min(end);
+                let mut spans = vec![Span::raw(format!("{line_start:08X}  "))];
+                let mut ascii = String::new();
+                for (offset, &byte) in (line_start..).zip(&data[line_start..line_end]) {
+                    let mut style = Style::default();
+                    if unknown.iter().any(|range| range.contains(&offset)) {
+                        style = style.fg(Color::Red);
+                    }
+                    if (node.offset..node.end()).contains(&offset) {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    } else {
+                        style = style.add_modifier(Modifier::DIM);
+                    }
+                    spans.push(Span::styled(format!("{byte:02X}"), style));
+                    spans.push(Span::raw(" "));
+                    ascii.push(if byte.is_ascii_graphic() { char::from(byte) } else { '.' });
+                }
+                spans.push(Span::raw(format!(" {ascii}")));
+                Line::from(spans)
+            })
+            .collect();
+
+        let title = if path[0] == 0 { " File " } else { " Decompressed " };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let path = self.selected_path();
+        let node = self.node(path);
+        let breadcrumbs: Vec<&str> = (1..=path.len())
+            .map(|len| self.node(&path[..len]).kind.name())
+            .collect();
+
+        let mut facts = vec![
+            format!("{}", node.kind),
+            format!("0x{:X}..0x{:X} ({} bytes)", node.offset, node.end(), node.len),
+        ];
+        if let Some(player_id) = node.player_id {
+            facts.push(format!("player {player_id}"));
+        }
+        if let Some(action_type) = &node.action_type {
+            facts.push(action_type.clone());
+        }
+        if node.unknown {
+            facts.push("unknown".to_string());
+        }
+
+        let lines = vec![
+            Line::from(node.label.clone()),
+            Line::from(facts.join("  ·  ")),
+            Line::styled(breadcrumbs.join(" → "), Style::default().add_modifier(Modifier::DIM)),
+        ];
+        let block = Block::default().borders(Borders::ALL);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// Calls `visit` for `node` and every node below it, in preorder.
+#[cfg(feature = "tui")]
+fn walk_preorder<'n>(
+    node: &'n Node,
+    path: &mut Vec<usize>,
+    visit: &mut impl FnMut(&[usize], &'n Node),
+) {
+    visit(path, node);
+    for (index, child) in node.children.iter().enumerate() {
+        path.push(index);
+        walk_preorder(child, path, visit);
+        path.pop();
+    }
+}
+
+/// Returns the ranges of leaf nodes flagged unknown that overlap `window`.
+#[cfg(feature = "tui")]
+fn unknown_ranges(node: &Node, window: Range<usize>) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if node.end() <= window.start || node.offset >= window.end {
+        return ranges;
+    }
+    if node.children.is_empty() {
+        if node.unknown {
+            ranges.push(node.offset..node.end());
+        }
+        return ranges;
+    }
+    for child in &node.children {
+        ranges.extend(unknown_ranges(child, window.clone()));
+    }
+    ranges
 }
@@ -135,6 +135,38 @@ pub fn read_bytes(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
     Ok(&bytes[offset..offset + len])
 }
 
+/// Checks that a buffer starts with the expected marker bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The byte buffer to check
+/// * `expected` - The marker bytes expected at offset 0
+///
+/// # Errors
+///
+/// - Returns `ParserError::UnexpectedEof` if the buffer is shorter than `expected`
+/// - Returns `ParserError::UnexpectedRecord` at the first byte that differs
+///
+/// # Example
+///
+/// ```
+/// use w3g_parser::binary::expect_markers;
+///
+/// assert!(expect_markers(&[0x1A, 0x19, 0x00], &[0x1A, 0x19]).is_ok());
+/// assert!(expect_markers(&[0x1A, 0x00], &[0x1A, 0x19]).is_err());
+/// ```
+pub fn expect_markers(bytes: &[u8], expected: &[u8]) -> Result<()> {
+    let found = read_bytes(bytes, 0, expected.len())?;
+    match found.iter().zip(expected).position(|(a, b)| a != b) {
+        Some(i) => Err(ParserError::unexpected_record(
+            i,
+            found[i],
+            format!("0x{:02X}", expected[i]),
+        )),
+        None => Ok(()),
+    }
+}
+
 /// Reads a null-terminated string from the buffer at the given offset.
 ///
 /// The string is read until a null byte (0x00) is encountered or
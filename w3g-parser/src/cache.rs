@@ -0,0 +1,258 @@
+//! Batch result cache.
+//!
+//! [`ResultCache`] remembers the output of a batch run for each replay, keyed
+//! by the SHA-256 of the replay file and [`CACHE_VERSION`], so re-running a
+//! batch over an archive only parses replays that are new or changed.
+//!
+//! The cache is an append-only text index with one entry per line:
+//!
+//! ```text
+//! <cache version>\t<content hash>\t<output>
+//! ```
+//!
+//! Each [`ResultCache::insert`] appends a line, so an interrupted run keeps
+//! everything it finished. When the cache is opened, entries written with
+//! another cache version, entries superseded by a later line for the same replay and
+//! malformed lines (such as a line cut short by a crash) are dropped by
+//! rewriting the index.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::cache::{content_hash, ResultCache};
+//!
+//! let mut cache = ResultCache::open("batch-cache.tsv")?;
+//! let data = std::fs::read("replay.w3g")?;
+//! let hash = content_hash(&data);
+//! if cache.get(&hash).is_none() {
+//!     cache.insert(&hash, r#"{"parsed":true}"#)?;
+//! }
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+
+/// Version of the cached output, stored with every cache entry.
+///
+/// Bump it whenever a change to the parser or to the batch output changes
+/// what a replay's cached output would contain, so that stale entries are
+/// dropped instead of reused. Releases that leave the output unchanged keep
+/// their users' caches.
+pub const CACHE_VERSION: &str = "1";
+
+/// Separator between the fields of an index line.
+const FIELD_SEPARATOR: char = '\t';
+
+/// Returns the hex SHA-256 of a replay file's contents.
+#[must_use]
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hash = String::with_capacity(64);
+    for byte in Sha256::digest(data) {
+        let _ = write!(hash, "{byte:02x}");
+    }
+    hash
+}
+
+/// An on-disk cache of batch outputs keyed by replay content hash.
+#[derive(Debug)]
+pub struct ResultCache {
+    path: PathBuf,
+    entries: HashMap<String, String>,
+    file: File,
+}
+
+impl ResultCache {
+    /// Opens the cache index at `path`, creating it and its parent
+    /// directories if they do not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if the index cannot be read or written.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = HashMap::new();
+        let mut stale = 0;
+        for line in contents.split_inclusive('\n') {
+            // A line without its newline was cut short while being appended
+            match line.strip_suffix('\n').and_then(parse_line) {
+                Some((hash, output)) => {
+                    if entries.insert(hash.to_string(), output.to_string()).is_some() {
+                        stale += 1;
+                    }
+                }
+                None => stale += 1,
+            }
+        }
+
+        if stale > 0 {
+            write_index(&path, &entries)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            entries,
+            file,
+        })
+    }
+
+    /// Returns the path of the cache index.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the cached output for a replay with content hash `hash`.
+    #[must_use]
+    pub fn get(&self, hash: &str) -> Option<&str> {
+        self.entries.get(hash).map(String::as_str)
+    }
+
+    /// Stores `output` for the replay with content hash `hash`.
+    ///
+    /// The entry is appended to the index immediately. `output` must be a
+    /// single line, such as compact JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if `output` contains a line break or the
+    /// index cannot be written.
+    pub fn insert(&mut self, hash: &str, output: &str) -> Result<()> {
+        if output.contains(['\n', '\r']) || hash.contains([FIELD_SEPARATOR, '\n', '\r']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cache entries must be a single line",
+            )
+            .into());
+        }
+        self.file.write_all(format_line(hash, output).as_bytes())?;
+        self.entries.insert(hash.to_string(), output.to_string());
+        Ok(())
+    }
+
+    /// Removes every entry, so all replays are parsed again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if the index cannot be truncated.
+    pub fn clear(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.entries.clear();
+        Ok(())
+    }
+
+    /// Returns the number of cached replays.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Parses an index line, returning `None` for malformed lines and entries
+/// written with another cache version.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let mut fields = line.splitn(3, FIELD_SEPARATOR);
+    let (version, hash, output) = (fields.next()?, fields.next()?, fields.next()?);
+    (version == CACHE_VERSION && !hash.is_empty()).then_some((hash, output))
+}
+
+fn format_line(hash: &str, output: &str) -> String {
+    format!("{CACHE_VERSION}{FIELD_SEPARATOR}{hash}{FIELD_SEPARATOR}{output}\n")
+}
+
+/// Replaces the index with `entries`, writing a temporary file first so an
+/// interrupted rewrite leaves the old index in place.
+fn write_index(path: &Path, entries: &HashMap<String, String>) -> Result<()> {
+    let mut contents = String::new();
+    for (hash, output) in entries {
+        contents.push_str(&format_line(hash, output));
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("w3g-cache-{}-{}.tsv", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_entries_persist_across_opens() {
+        let path = temp_path("persist");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = ResultCache::open(&path).unwrap();
+        assert!(cache.is_empty());
+        cache.insert("aa", "{\"n\":1}").unwrap();
+        cache.insert("bb", "{\"n\":2}").unwrap();
+        assert!(cache.insert("cc", "two\nlines").is_err());
+        drop(cache);
+
+        let mut cache = ResultCache::open(&path).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("aa"), Some("{\"n\":1}"));
+
+        cache.clear().unwrap();
+        drop(cache);
+        assert!(ResultCache::open(&path).unwrap().is_empty());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stale_lines_are_dropped() {
+        let path = temp_path("stale");
+        let contents = format!(
+            "0.0.0-old\taa\told\n{}{}bad line\n{CACHE_VERSION}\tcc\ttrunc",
+            format_line("aa", "first"),
+            format_line("aa", "second"),
+        );
+        fs::write(&path, contents).unwrap();
+
+        let cache = ResultCache::open(&path).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("aa"), Some("second"));
+        assert_eq!(cache.get("cc"), None);
+        drop(cache);
+
+        // Only live entries remain in the rewritten index
+        let rewritten = fs::read_to_string(&path).unwrap();
+        assert_eq!(rewritten, format_line("aa", "second"));
+        assert!(!rewritten.contains("0.0.0-old"));
+        let _ = fs::remove_file(&path);
+    }
+}
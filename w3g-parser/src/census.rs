@@ -0,0 +1,364 @@
+//! Corpus-wide census of unknown actions.
+//!
+//! [`ActionStatistics::error_rate`](crate::ActionStatistics::error_rate) tells
+//! how many actions of one replay are unknown. [`UnknownCensus`] collects the
+//! [`ActionType::Unknown`](crate::ActionType::Unknown) actions of many
+//! replays and groups them by type byte, subcommand byte and length, so the
+//! most frequent patterns can be decoded first. For each pattern it keeps the
+//! builds it appears in, sample bytes, and the known actions next to it.
+//!
+//! Actions are decoded by [`ActionIterator`] from the `TimeFrame`s of
+//! [`Replay::timeframes`], like [`Replay::actions`], so the counts agree with
+//! the [`ActionStatistics`](crate::ActionStatistics) of the parsed actions.
+//! Command blocks are framed by their length, so a misparsed action cannot
+//! shift the actions of other players or frames.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::census::UnknownCensus;
+//! use w3g_parser::Replay;
+//!
+//! let mut census = UnknownCensus::new();
+//! for path in ["a.w3g", "b.w3g"] {
+//!     census.add(path, &Replay::from_path(path)?);
+//! }
+//! for pattern in census.patterns().iter().take(10) {
+//!     println!("{}: {} times", pattern.key, pattern.count);
+//! }
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+use crate::actions::{ActionContext, ActionIterator, ActionType};
+use crate::header::Header;
+use crate::replay::Replay;
+
+/// Default number of sample occurrences kept per pattern.
+pub const DEFAULT_SAMPLES: usize = 3;
+
+/// Bytes of an occurrence kept in a sample.
+const SAMPLE_BYTES: usize = 32;
+
+/// Neighbour recorded when no known action precedes or follows an unknown
+/// action in its command block.
+pub const NO_NEIGHBOUR: &str = "(none)";
+
+/// What unknown actions are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatternKey {
+    /// Action type byte.
+    pub type_id: u8,
+
+    /// Byte after the type byte, if the action has one.
+    pub subcommand: Option<u8>,
+
+    /// Length of the action in bytes, including the type byte.
+    pub len: usize,
+}
+
+impl fmt::Display for PatternKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:02X}", self.type_id)?;
+        if let Some(subcommand) = self.subcommand {
+            write!(f, " 0x{subcommand:02X}")?;
+        }
+        let unit = if self.len == 1 { "byte" } else { "bytes" };
+        write!(f, " ({} {unit})", self.len)
+    }
+}
+
+/// One occurrence of an unknown action pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnknownSample {
+    /// Replay the action was found in, as passed to [`UnknownCensus::add`].
+    pub source: String,
+
+    /// Offset of the action in the decompressed data.
+    pub offset: usize,
+
+    /// The first bytes of the action as space-separated hex.
+    pub hex: String,
+}
+
+/// Unknown actions sharing a [`PatternKey`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnknownPattern {
+    /// What the actions have in common.
+    pub key: PatternKey,
+
+    /// Number of occurrences.
+    pub count: usize,
+
+    /// Number of replays with at least one occurrence.
+    pub replays: usize,
+
+    /// Occurrences per build, keyed like [`UnknownCensus::builds`].
+    pub builds: BTreeMap<String, usize>,
+
+    /// Occurrences from different replays, up to the census' sample limit.
+    pub samples: Vec<UnknownSample>,
+
+    /// Nearest known action before each occurrence in its command block, by
+    /// action type name; [`NO_NEIGHBOUR`] if there is none.
+    pub preceding: BTreeMap<String, usize>,
+
+    /// Nearest known action after each occurrence in its command block.
+    pub following: BTreeMap<String, usize>,
+
+    /// Index of the last replay counted in `replays`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    last_replay: Option<usize>,
+}
+
+/// Action counts of the replays of one build.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildCounts {
+    /// Number of replays.
+    pub replays: usize,
+
+    /// Number of actions.
+    pub actions: usize,
+
+    /// Number of unknown actions.
+    pub unknown_actions: usize,
+}
+
+impl BuildCounts {
+    /// Returns the share of unknown actions, from 0.0 to 1.0.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn unknown_rate(&self) -> f64 {
+        if self.actions == 0 {
+            0.0
+        } else {
+            self.unknown_actions as f64 / self.actions as f64
+        }
+    }
+}
+
+/// Unknown actions of a corpus of replays, grouped by pattern.
+#[derive(Debug, Clone)]
+pub struct UnknownCensus {
+    /// Totals over all replays added.
+    pub totals: BuildCounts,
+
+    /// Counts per build, keyed like `"1.26 (build 6059)"`, or `"Reforged"`
+    /// for GRBN replays, whose header has no build number.
+    pub builds: BTreeMap<String, BuildCounts>,
+
+    patterns: HashMap<PatternKey, UnknownPattern>,
+    sample_limit: usize,
+}
+
+impl Default for UnknownCensus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnknownCensus {
+    /// Creates an empty census keeping [`DEFAULT_SAMPLES`] samples per pattern.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            totals: BuildCounts::default(),
+            builds: BTreeMap::new(),
+            patterns: HashMap::new(),
+            sample_limit: DEFAULT_SAMPLES,
+        }
+    }
+
+    /// Sets the number of samples kept per pattern.
+    #[must_use]
+    pub fn samples(mut self, limit: usize) -> Self {
+        self.sample_limit = limit;
+        self
+    }
+
+    /// Adds the actions of a replay; `source` names it in samples.
+    pub fn add(&mut self, source: &str, replay: &Replay) {
+        let build = build_key(&replay.header);
+        let before = self.totals;
+        let mut frames = replay.timeframes();
+        while let Some(result) = frames.next() {
+            // Records that fail to parse are skipped like in lenient parsing
+            let Ok(frame) = result else {
+                if frames.resync().is_none() {
+                    break;
+                }
+                continue;
+            };
+            let data_offset = frames.action_data_offset();
+            self.add_actions(source, &build, &frame.action_data, data_offset);
+        }
+
+        let counts = self.builds.entry(build).or_default();
+        counts.replays += 1;
+        counts.actions += self.totals.actions - before.actions;
+        counts.unknown_actions += self.totals.unknown_actions - before.unknown_actions;
+        self.totals.replays += 1;
+    }
+
+    /// Returns the patterns, most frequent first.
+    #[must_use]
+    pub fn patterns(&self) -> Vec<&UnknownPattern> {
+        let mut patterns: Vec<_> = self.patterns.values().collect();
+        patterns.sort_by(|a, b| b.count.cmp(&a.count).then(a.key.cmp(&b.key)));
+        patterns
+    }
+
+    /// Counts the actions of a `TimeFrame`'s action data, which starts at
+    /// `data_offset` in the decompressed data.
+    fn add_actions(&mut self, source: &str, build: &str, data: &[u8], data_offset: usize) {
+        let mut iter = ActionIterator::new(data, ActionContext::default());
+        let mut block = Vec::new();
+        let mut block_end = None;
+        while let Some(result) = iter.next() {
+            // Actions that fail to parse are not part of `Replay::actions` either
+            let Ok(action) = result else {
+                continue;
+            };
+            let end = iter.current_block().map(|(_, end)| end);
+            if end != block_end {
+                self.add_block(source, build, &block, data, data_offset);
+                block.clear();
+                block_end = end;
+            }
+            block.push((iter.action_offset()..iter.current_offset(), action.action_type));
+        }
+        self.add_block(source, build, &block, data, data_offset);
+    }
+
+    /// Counts the actions of one command block, given by their byte range in
+    /// `data`.
+    fn add_block(
+        &mut self,
+        source: &str,
+        build: &str,
+        actions: &[(Range<usize>, ActionType)],
+        data: &[u8],
+        data_offset: usize,
+    ) {
+        let is_unknown = |action: &&(Range<usize>, ActionType)| {
+            matches!(action.1, ActionType::Unknown { .. })
+        };
+        for (index, action) in actions.iter().enumerate() {
+            self.totals.actions += 1;
+            if !is_unknown(&action) {
+                continue;
+            }
+            self.totals.unknown_actions += 1;
+
+            let bytes = &data[action.0.clone()];
+            let key = PatternKey {
+                type_id: bytes[0],
+                subcommand: bytes.get(1).copied(),
+                len: bytes.len(),
+            };
+            let preceding = actions[..index].iter().rev().find(|a| !is_unknown(a));
+            let following = actions[index + 1..].iter().find(|a| !is_unknown(a));
+
+            let replay = self.totals.replays;
+            let sample_limit = self.sample_limit;
+            let pattern = self.patterns.entry(key).or_insert_with(|| UnknownPattern {
+                key,
+                count: 0,
+                replays: 0,
+                builds: BTreeMap::new(),
+                samples: Vec::new(),
+                preceding: BTreeMap::new(),
+                following: BTreeMap::new(),
+                last_replay: None,
+            });
+            pattern.count += 1;
+            *pattern.builds.entry(build.to_string()).or_default() += 1;
+            *pattern.preceding.entry(neighbour(preceding)).or_default() += 1;
+            *pattern.following.entry(neighbour(following)).or_default() += 1;
+            if pattern.last_replay != Some(replay) {
+                pattern.last_replay = Some(replay);
+                pattern.replays += 1;
+                if pattern.samples.len() < sample_limit {
+                    pattern.samples.push(UnknownSample {
+                        source: source.to_string(),
+                        offset: data_offset + action.0.start,
+                        hex: hex(bytes),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Returns the key of a replay's build in [`UnknownCensus::builds`].
+pub(crate) fn build_key(header: &Header) -> String {
+    match header {
+        Header::Classic(h) => format!("{} (build {})", h.version_string(), h.build_version),
+        Header::Grbn(_) => "Reforged".to_string(),
+    }
+}
+
+fn neighbour(action: Option<&(Range<usize>, ActionType)>) -> String {
+    action.map_or_else(|| NO_NEIGHBOUR.to_string(), |a| a.1.type_name().to_string())
+}
+
+/// Formats up to [`SAMPLE_BYTES`] bytes as space-separated hex.
+fn hex(bytes: &[u8]) -> String {
+    let mut hex: Vec<String> =
+        bytes.iter().take(SAMPLE_BYTES).map(|b| format!("{b:02X}")).collect();
+    if bytes.len() > SAMPLE_BYTES {
+        hex.push("..".to_string());
+    }
+    hex.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Player 1: `TriggerEscapeKey` (0x61), then an arrow key action with a bad event.
+    const COMMANDS: [u8; 6] = [0x01, 0x03, 0x00, 0x61, 0x75, 0xFF];
+
+    #[test]
+    fn test_add_actions() {
+        let mut census = UnknownCensus::new().samples(1);
+        for source in ["a.w3g", "b.w3g"] {
+            census.add_actions(source, "1.26 (build 6059)", &COMMANDS, 0);
+            census.add_actions(source, "1.26 (build 6059)", &COMMANDS, 0);
+            census.totals.replays += 1;
+        }
+
+        assert_eq!(census.totals.actions, 8);
+        assert_eq!(census.totals.unknown_actions, 4);
+        let patterns = census.patterns();
+        assert_eq!(patterns.len(), 1);
+
+        let pattern = patterns[0];
+        let key = PatternKey { type_id: 0x75, subcommand: Some(0xFF), len: 2 };
+        assert_eq!(pattern.key, key);
+        assert_eq!(key.to_string(), "0x75 0xFF (2 bytes)");
+        assert_eq!((pattern.count, pattern.replays), (4, 2));
+        assert_eq!(pattern.builds["1.26 (build 6059)"], 4);
+        assert_eq!(pattern.preceding["TriggerEscapeKey"], 4);
+        assert_eq!(pattern.following[NO_NEIGHBOUR], 4);
+        assert_eq!(
+            pattern.samples,
+            [UnknownSample { source: "a.w3g".to_string(), offset: 4, hex: "75 FF".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(&[0x10, 0x42]), "10 42");
+        let long = hex(&[0xAB; 40]);
+        assert!(long.ends_with("AB .."));
+        assert_eq!(long.matches("AB").count(), SAMPLE_BYTES);
+    }
+}
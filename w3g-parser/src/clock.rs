@@ -0,0 +1,313 @@
+//! Conversion between game time and wall-clock time.
+//!
+//! Replay timestamps (`TimeFrame::accumulated_time_ms`, `Action::timestamp_ms`)
+//! are sums of `TimeFrame` time deltas, i.e. game time. At normal or slow game
+//! speed game time runs slower than real time.
+//!
+//! [`GameClock`] reconstructs the wall-clock timeline from pause, resume and
+//! game speed actions so that game timestamps can be mapped to real-time
+//! offsets (e.g. for aligning a replay with a recorded video) and back.
+//!
+//! `TimeFrame` deltas recorded while the game is paused are counted 1:1 as
+//! wall-clock time, independent of the game speed.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use w3g_parser::clock::GameClock;
+//!
+//! let clock = GameClock::from_iterator(game.timeframes(&decompressed))?;
+//! let wall_ms = clock.game_to_wall_ms(action.timestamp_ms);
+//! println!("Action happened {wall_ms}ms into the recording");
+//! ```
+
+use crate::actions::{Action, ActionType, GameSpeed};
+use crate::error::Result;
+use crate::records::TimeFrameIterator;
+
+/// State of the game clock during a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClockState {
+    /// The game is running at the given speed.
+    Running(GameSpeed),
+
+    /// The game is paused.
+    ///
+    /// `TimeFrame`s keep arriving while paused, and their deltas are counted
+    /// 1:1 as wall-clock time whatever the game speed.
+    Paused,
+}
+
+/// A stretch of the replay during which the clock state did not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockSegment {
+    /// Game time in milliseconds at the start of the segment.
+    pub game_start_ms: u32,
+
+    /// Wall-clock time in milliseconds at the start of the segment.
+    pub wall_start_ms: u32,
+
+    /// Clock state during the segment.
+    pub state: ClockState,
+}
+
+impl ClockState {
+    /// Returns the wall-clock milliseconds that pass per game millisecond.
+    fn wall_per_game(self) -> f64 {
+        match self {
+            ClockState::Running(speed) => 1.0 / speed.rate(),
+            ClockState::Paused => 1.0,
+        }
+    }
+}
+
+/// Maps game time to wall-clock time and back.
+///
+/// Both time axes start at 0 when the game starts.
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    /// Segments in stream order; never empty.
+    segments: Vec<ClockSegment>,
+
+    /// Current state of the clock while building.
+    state: ClockState,
+
+    /// Latest game time seen.
+    game_ms: u32,
+
+    /// Wall-clock time at `game_ms`, unrounded.
+    wall_ms: f64,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new(GameSpeed::default())
+    }
+}
+
+impl GameClock {
+    /// Creates a clock for a game that starts at the given speed.
+    #[must_use]
+    pub fn new(initial_speed: GameSpeed) -> Self {
+        let state = ClockState::Running(initial_speed);
+        Self {
+            segments: vec![ClockSegment {
+                game_start_ms: 0,
+                wall_start_ms: 0,
+                state,
+            }],
+            state,
+            game_ms: 0,
+            wall_ms: 0.0,
+        }
+    }
+
+    /// Builds a clock from an iterator of `TimeFrame` records.
+    ///
+    /// The game is assumed to start at fast speed. Actions that fail to parse
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any `TimeFrame` in the iterator fails to parse.
+    pub fn from_iterator(iter: TimeFrameIterator<'_>) -> Result<Self> {
+        let mut clock = GameClock::default();
+
+        for result in iter {
+            let frame = result?;
+            clock.advance(frame.accumulated_time_ms);
+            for action in frame.actions().flatten() {
+                clock.record(&action);
+            }
+        }
+
+        Ok(clock)
+    }
+
+    /// Advances the clock to the given game time (accumulated `TimeFrame` time).
+    ///
+    /// Times earlier than the last seen time are ignored.
+    pub fn advance(&mut self, game_ms: u32) {
+        let delta = game_ms.saturating_sub(self.game_ms);
+        self.game_ms = self.game_ms.max(game_ms);
+        self.wall_ms += f64::from(delta) * self.state.wall_per_game();
+    }
+
+    /// Records an action, applying pause, resume and speed changes.
+    ///
+    /// The clock is advanced to the action's timestamp first.
+    pub fn record(&mut self, action: &Action) {
+        self.advance(action.timestamp_ms);
+
+        let new_state = match (&action.action_type, self.state) {
+            (ActionType::PauseGame, ClockState::Running(_)) => ClockState::Paused,
+            (ActionType::ResumeGame, ClockState::Paused) => {
+                ClockState::Running(self.running_speed())
+            }
+            (ActionType::SetGameSpeed { speed }, ClockState::Running(_)) => {
+                ClockState::Running(*speed)
+            }
+            (ActionType::IncreaseGameSpeed, ClockState::Running(speed)) => {
+                ClockState::Running(speed.faster())
+            }
+            (ActionType::DecreaseGameSpeed, ClockState::Running(speed)) => {
+                ClockState::Running(speed.slower())
+            }
+            _ => return,
+        };
+
+        if new_state != self.state {
+            self.state = new_state;
+            self.segments.push(ClockSegment {
+                game_start_ms: self.game_ms,
+                wall_start_ms: round_ms(self.wall_ms),
+                state: new_state,
+            });
+        }
+    }
+
+    /// Returns all clock segments in order.
+    #[must_use]
+    pub fn segments(&self) -> &[ClockSegment] {
+        &self.segments
+    }
+
+    /// Returns the total game time in milliseconds.
+    #[must_use]
+    pub fn game_duration_ms(&self) -> u32 {
+        self.game_ms
+    }
+
+    /// Returns the total wall-clock time in milliseconds.
+    #[must_use]
+    pub fn wall_duration_ms(&self) -> u32 {
+        round_ms(self.wall_ms)
+    }
+
+    /// Converts a game timestamp to a wall-clock offset in milliseconds.
+    ///
+    /// Timestamps past the end of the replay are extrapolated at the last
+    /// known speed.
+    #[must_use]
+    pub fn game_to_wall_ms(&self, game_ms: u32) -> u32 {
+        let index = self.segments.partition_point(|s| s.game_start_ms <= game_ms);
+        let segment = &self.segments[index.saturating_sub(1)];
+
+        let elapsed = f64::from(game_ms - segment.game_start_ms) * segment.state.wall_per_game();
+        round_ms(f64::from(segment.wall_start_ms) + elapsed)
+    }
+
+    /// Converts a wall-clock offset to a game timestamp in milliseconds.
+    ///
+    /// This is the inverse of [`GameClock::game_to_wall_ms`].
+    #[must_use]
+    pub fn wall_to_game_ms(&self, wall_ms: u32) -> u32 {
+        let index = self.segments.partition_point(|s| s.wall_start_ms <= wall_ms);
+        let segment = &self.segments[index.saturating_sub(1)];
+
+        let elapsed = f64::from(wall_ms - segment.wall_start_ms) / segment.state.wall_per_game();
+        round_ms(f64::from(segment.game_start_ms) + elapsed)
+    }
+
+    /// Returns the speed the game runs at (or will resume at, when paused).
+    fn running_speed(&self) -> GameSpeed {
+        self.segments
+            .iter()
+            .rev()
+            .find_map(|s| match s.state {
+                ClockState::Running(speed) => Some(speed),
+                ClockState::Paused => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Rounds a non-negative millisecond value to `u32`, saturating on overflow.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn round_ms(ms: f64) -> u32 {
+    ms.round().clamp(0.0, f64::from(u32::MAX)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(action_type: ActionType, timestamp_ms: u32) -> Action {
+        Action::new(1, action_type, timestamp_ms)
+    }
+
+    #[test]
+    fn test_clock_fast_is_real_time() {
+        let mut clock = GameClock::default();
+        clock.advance(60_000);
+
+        assert_eq!(clock.game_duration_ms(), 60_000);
+        assert_eq!(clock.wall_duration_ms(), 60_000);
+        assert_eq!(clock.game_to_wall_ms(30_000), 30_000);
+        assert_eq!(clock.wall_to_game_ms(30_000), 30_000);
+    }
+
+    #[test]
+    fn test_clock_pause() {
+        let mut clock = GameClock::default();
+        clock.record(&action(ActionType::PauseGame, 10_000));
+        // Frames recorded while paused run in real time, whatever the speed
+        clock.advance(15_000);
+        clock.record(&action(ActionType::ResumeGame, 15_000));
+        clock.advance(20_000);
+
+        assert_eq!(clock.segments().len(), 3);
+        assert_eq!(clock.segments()[1].state, ClockState::Paused);
+        assert_eq!(clock.game_duration_ms(), 20_000);
+        assert_eq!(clock.wall_duration_ms(), 20_000);
+        assert_eq!(clock.game_to_wall_ms(12_000), 12_000);
+        assert_eq!(clock.wall_to_game_ms(17_000), 17_000);
+    }
+
+    #[test]
+    fn test_clock_speed_change() {
+        let mut clock = GameClock::new(GameSpeed::Normal);
+        clock.record(&action(
+            ActionType::SetGameSpeed {
+                speed: GameSpeed::Fast,
+            },
+            8_000,
+        ));
+        clock.advance(18_000);
+
+        // 8s of game time at normal speed take 10s of real time
+        assert_eq!(clock.game_to_wall_ms(8_000), 10_000);
+        assert_eq!(clock.game_to_wall_ms(18_000), 20_000);
+        assert_eq!(clock.wall_to_game_ms(5_000), 4_000);
+        assert_eq!(clock.wall_to_game_ms(20_000), 18_000);
+    }
+
+    #[test]
+    fn test_clock_pause_at_normal_speed() {
+        let mut clock = GameClock::new(GameSpeed::Normal);
+        clock.record(&action(ActionType::PauseGame, 4_000));
+        clock.record(&action(ActionType::ResumeGame, 6_000));
+        clock.advance(10_000);
+
+        assert_eq!(clock.game_to_wall_ms(4_000), 5_000);
+        assert_eq!(clock.game_to_wall_ms(6_000), 7_000);
+        assert_eq!(clock.game_to_wall_ms(10_000), 12_000);
+        assert_eq!(clock.wall_to_game_ms(6_000), 5_000);
+        assert_eq!(clock.wall_to_game_ms(12_000), 10_000);
+    }
+
+    #[test]
+    fn test_clock_resume_keeps_speed() {
+        let mut clock = GameClock::default();
+        clock.record(&action(ActionType::DecreaseGameSpeed, 1_000));
+        clock.record(&action(ActionType::PauseGame, 2_000));
+        clock.record(&action(ActionType::ResumeGame, 3_000));
+
+        assert_eq!(
+            clock.segments().last().map(|s| s.state),
+            Some(ClockState::Running(GameSpeed::Normal))
+        );
+    }
+}
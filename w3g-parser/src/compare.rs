@@ -0,0 +1,389 @@
+//! Comparison of two replays of the same game.
+//!
+//! Every player in an online game saves their own replay. All clients receive
+//! the same actions in the same `TimeFrame`s and compute the same game state
+//! checksums, so two honest replays of one game agree frame by frame until the
+//! first of them ends. [`compare`] checks this:
+//!
+//! 1. The replays are confirmed to be the same game: same decoded game
+//!    settings, same random seed from the slot table and same roster.
+//! 2. `TimeFrame`s are aligned by accumulated game time.
+//! 3. Within the time both replays cover, every difference is reported as a
+//!    [`Divergence`]: a frame present in only one replay, differing command
+//!    data, or differing checksums.
+//!
+//! Frames after the end of the shorter replay are not divergences, since a
+//! replay ends when its owner leaves the game.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::compare::compare;
+//! use w3g_parser::Replay;
+//!
+//! let left = Replay::from_path("player1.w3g")?;
+//! let right = Replay::from_path("player2.w3g")?;
+//! let comparison = compare(&left, &right)?;
+//!
+//! if !comparison.is_consistent() {
+//!     for divergence in &comparison.divergences {
+//!         println!("{divergence:?}");
+//!     }
+//! }
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use crate::binary::{read_u16_le, read_u32_le};
+use crate::error::Result;
+use crate::records::{Record, SlotTable};
+use crate::replay::Replay;
+
+/// Which of the two compared replays something belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    /// The first replay passed to [`compare`].
+    Left,
+    /// The second replay passed to [`compare`].
+    Right,
+}
+
+/// A reason the two replays are not the same game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
+pub enum IdentityMismatch {
+    /// The decoded game settings (map, flags, creator) differ.
+    Settings,
+    /// The random seeds in the slot tables differ.
+    RandomSeed {
+        /// Seed of the left replay, if it has a slot table.
+        left: Option<u32>,
+        /// Seed of the right replay, if it has a slot table.
+        right: Option<u32>,
+    },
+    /// The player IDs and names differ.
+    Roster {
+        /// Players of the left replay, sorted by player ID.
+        left: Vec<(u8, String)>,
+        /// Players of the right replay, sorted by player ID.
+        right: Vec<(u8, String)>,
+    },
+}
+
+/// The kind of difference found between two aligned `TimeFrame`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
+pub enum DivergenceKind {
+    /// A `TimeFrame` at this game time exists in one replay only.
+    MissingFrame {
+        /// The replay that lacks the frame.
+        missing_from: Side,
+    },
+    /// The command data of the frame differs.
+    ActionsDiffer {
+        /// Player IDs whose command blocks differ, or are present on one side only.
+        ///
+        /// Empty if the command data could not be split into player blocks.
+        player_ids: Vec<u8>,
+    },
+    /// The game state checksums recorded after the frame differ.
+    ChecksumMismatch {
+        /// Checksum in the left replay.
+        left: u32,
+        /// Checksum in the right replay.
+        right: u32,
+    },
+}
+
+/// A difference between two replays at one point in the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Divergence {
+    /// Accumulated game time of the frame in milliseconds.
+    pub time_ms: u32,
+
+    /// Index of the frame (0-based) in the left replay, if present there.
+    pub left_frame: Option<usize>,
+
+    /// Index of the frame (0-based) in the right replay, if present there.
+    pub right_frame: Option<usize>,
+
+    /// What differs.
+    pub kind: DivergenceKind,
+}
+
+/// Result of comparing two replays.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayComparison {
+    /// Reasons the replays are not the same game; empty if they are.
+    pub identity_mismatches: Vec<IdentityMismatch>,
+
+    /// Differences within the time covered by both replays, in game time order.
+    pub divergences: Vec<Divergence>,
+
+    /// Number of `TimeFrame`s in the left replay.
+    pub left_frame_count: usize,
+
+    /// Number of `TimeFrame`s in the right replay.
+    pub right_frame_count: usize,
+
+    /// Number of frame pairs aligned and compared.
+    pub frames_compared: usize,
+
+    /// Game time up to which both replays were compared, in milliseconds.
+    pub overlap_ms: u32,
+}
+
+impl ReplayComparison {
+    /// Returns whether both replays are recordings of the same game.
+    #[must_use]
+    pub fn is_same_game(&self) -> bool {
+        self.identity_mismatches.is_empty()
+    }
+
+    /// Returns whether the replays are the same game and agree everywhere.
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.is_same_game() && self.divergences.is_empty()
+    }
+
+    /// Returns the game time in milliseconds of the first divergence.
+    #[must_use]
+    pub fn first_divergence_ms(&self) -> Option<u32> {
+        self.divergences.first().map(|d| d.time_ms)
+    }
+}
+
+/// Compares two replays of the same game.
+///
+/// # Errors
+///
+/// Returns an error if the record stream of either replay cannot be framed.
+pub fn compare(left: &Replay, right: &Replay) -> Result<ReplayComparison> {
+    let left_game = GameSummary::collect(left, usize::MAX)?;
+    let right_game = GameSummary::collect(right, usize::MAX)?;
+
+    let mut comparison = ReplayComparison {
+        identity_mismatches: identity_mismatches(left, right, &left_game, &right_game),
+        left_frame_count: left_game.frames.len(),
+        right_frame_count: right_game.frames.len(),
+        ..ReplayComparison::default()
+    };
+
+    let end_of = |frames: &[Frame]| frames.last().map_or(0, |f| f.time_ms);
+    let overlap_ms = end_of(&left_game.frames).min(end_of(&right_game.frames));
+    comparison.overlap_ms = overlap_ms;
+
+    let (mut i, mut j) = (0, 0);
+    let (left_frames, right_frames) = (&left_game.frames, &right_game.frames);
+    while i < left_frames.len() && j < right_frames.len() {
+        let (a, b) = (&left_frames[i], &right_frames[j]);
+        if a.time_ms.min(b.time_ms) > overlap_ms {
+            break;
+        }
+
+        if a.time_ms < b.time_ms {
+            comparison.divergences.push(Divergence {
+                time_ms: a.time_ms,
+                left_frame: Some(i),
+                right_frame: None,
+                kind: DivergenceKind::MissingFrame {
+                    missing_from: Side::Right,
+                },
+            });
+            i += 1;
+            continue;
+        }
+        if b.time_ms < a.time_ms {
+            comparison.divergences.push(Divergence {
+                time_ms: b.time_ms,
+                left_frame: None,
+                right_frame: Some(j),
+                kind: DivergenceKind::MissingFrame {
+                    missing_from: Side::Left,
+                },
+            });
+            j += 1;
+            continue;
+        }
+
+        comparison.frames_compared += 1;
+        let divergence = |kind| Divergence {
+            time_ms: a.time_ms,
+            left_frame: Some(i),
+            right_frame: Some(j),
+            kind,
+        };
+        if a.commands != b.commands {
+            let player_ids = differing_players(&a.commands, &b.commands);
+            comparison
+                .divergences
+                .push(divergence(DivergenceKind::ActionsDiffer { player_ids }));
+        }
+        if let (Some(left), Some(right)) = (a.checksum, b.checksum) {
+            if left != right {
+                comparison
+                    .divergences
+                    .push(divergence(DivergenceKind::ChecksumMismatch { left, right }));
+            }
+        }
+        i += 1;
+        j += 1;
+    }
+
+    Ok(comparison)
+}
+
+/// A `TimeFrame` with the checksum recorded after it.
+pub(crate) struct Frame {
+    /// Accumulated game time at the end of the frame.
+    pub(crate) time_ms: u32,
+    pub(crate) commands: Vec<u8>,
+    checksum: Option<u32>,
+}
+
+/// The perspective-independent parts of a replay's record stream.
+pub(crate) struct GameSummary {
+    /// Random seed from the slot table.
+    pub(crate) seed: Option<u32>,
+    /// Player IDs and names, sorted.
+    pub(crate) roster: Vec<(u8, String)>,
+    pub(crate) frames: Vec<Frame>,
+}
+
+impl GameSummary {
+    /// Collects the summary, stopping after `frame_limit` `TimeFrame`s.
+    pub(crate) fn collect(replay: &Replay, frame_limit: usize) -> Result<Self> {
+        let header = &replay.game.header;
+        let mut summary = GameSummary {
+            seed: None,
+            roster: vec![(header.host_slot, header.host_name.clone())],
+            frames: Vec::new(),
+        };
+
+        let mut time_ms = 0u32;
+        for result in replay.records()? {
+            match result?.1 {
+                Record::Player(slot) => summary.roster.push((slot.slot_id, slot.player_name)),
+                Record::SlotTable { data } => {
+                    summary.seed = SlotTable::parse(&data).ok().map(|t| t.random_seed);
+                }
+                Record::TimeFrame {
+                    time_increment_ms,
+                    commands,
+                    ..
+                } => {
+                    if summary.frames.len() == frame_limit {
+                        break;
+                    }
+                    time_ms = time_ms.saturating_add(u32::from(time_increment_ms));
+                    summary.frames.push(Frame {
+                        time_ms,
+                        commands,
+                        checksum: None,
+                    });
+                }
+                Record::Checksum { data } => {
+                    if let Some(frame) = summary.frames.last_mut() {
+                        frame.checksum = read_u32_le(&data, 0).ok();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        summary.roster.sort();
+        Ok(summary)
+    }
+}
+
+/// Returns the reasons two replays are not the same game.
+fn identity_mismatches(
+    left: &Replay,
+    right: &Replay,
+    left_game: &GameSummary,
+    right_game: &GameSummary,
+) -> Vec<IdentityMismatch> {
+    let mut mismatches = Vec::new();
+
+    if left.game.header.decoded_settings() != right.game.header.decoded_settings() {
+        mismatches.push(IdentityMismatch::Settings);
+    }
+    if left_game.seed != right_game.seed {
+        mismatches.push(IdentityMismatch::RandomSeed {
+            left: left_game.seed,
+            right: right_game.seed,
+        });
+    }
+    if left_game.roster != right_game.roster {
+        mismatches.push(IdentityMismatch::Roster {
+            left: left_game.roster.clone(),
+            right: right_game.roster.clone(),
+        });
+    }
+
+    mismatches
+}
+
+/// Splits `TimeFrame` command data into `(player ID, block)` pairs.
+///
+/// Each block is a player ID, a `u16` length and the player's actions.
+/// Returns `None` if the data does not frame exactly.
+fn command_blocks(commands: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    while offset < commands.len() {
+        let len = usize::from(read_u16_le(commands, offset + 1).ok()?);
+        let block = commands.get(offset + 3..offset + 3 + len)?;
+        blocks.push((commands[offset], block));
+        offset += 3 + len;
+    }
+    Some(blocks)
+}
+
+/// Returns the player IDs whose command blocks differ between two frames.
+fn differing_players(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let (Some(left), Some(right)) = (command_blocks(left), command_blocks(right)) else {
+        return Vec::new();
+    };
+
+    let blocks_of = |blocks: &[(u8, &[u8])], id: u8| -> Vec<Vec<u8>> {
+        blocks
+            .iter()
+            .filter(|(player_id, _)| *player_id == id)
+            .map(|(_, block)| block.to_vec())
+            .collect()
+    };
+
+    let mut ids: Vec<u8> = left.iter().chain(&right).map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.retain(|&id| blocks_of(&left, id) != blocks_of(&right, id));
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_blocks() {
+        let commands = [0x01, 0x02, 0x00, 0xAA, 0xBB, 0x03, 0x01, 0x00, 0xCC];
+        let blocks = command_blocks(&commands).unwrap();
+        assert_eq!(blocks, vec![(1, &[0xAA, 0xBB][..]), (3, &[0xCC][..])]);
+
+        assert!(command_blocks(&[0x01, 0x05, 0x00, 0xAA]).is_none());
+        assert_eq!(command_blocks(&[]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_differing_players() {
+        let left = [0x01, 0x01, 0x00, 0xAA, 0x03, 0x01, 0x00, 0xCC];
+        let right = [0x01, 0x01, 0x00, 0xAA, 0x03, 0x01, 0x00, 0xCD, 0x05, 0x00, 0x00];
+        assert_eq!(differing_players(&left, &right), vec![3, 5]);
+        assert!(differing_players(&[0x01], &right).is_empty());
+    }
+}
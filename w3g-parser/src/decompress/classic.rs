@@ -57,6 +57,7 @@ pub const BLOCK_DECOMPRESSED_SIZE: usize = 8192;
 
 /// Information parsed from a block header.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockHeader {
     /// The size of the compressed data following this header.
     pub compressed_size: u16,
@@ -159,7 +160,7 @@ impl BlockHeader {
 ///
 /// # Errors
 ///
-/// - `ParserError::UnexpectedEof` if a block extends beyond the file
+/// - `ParserError::TruncatedBlock` if a block extends beyond the file
 /// - `ParserError::DecompressionError` if a block's zlib data is invalid
 ///
 /// # Example
@@ -186,13 +187,11 @@ pub fn decompress_classic(data: &[u8], header: &ClassicHeader) -> Result<Vec<u8>
 
     for block_index in 0..block_count {
         // Parse block header
-        let block_header = BlockHeader::parse(data, offset, version).map_err(|e| {
-            ParserError::DecompressionError {
-                reason: format!(
-                    "Failed to parse block header {block_index} at offset 0x{offset:X}: {e}"
-                ),
-            }
-        })?;
+        let block_header =
+            BlockHeader::parse(data, offset, version).map_err(|_| ParserError::TruncatedBlock {
+                block_index: block_index as usize,
+                offset: result.len(),
+            })?;
 
         // Move past the block header to the compressed data
         let compressed_start = offset + block_header.header_size;
@@ -200,11 +199,9 @@ pub fn decompress_classic(data: &[u8], header: &ClassicHeader) -> Result<Vec<u8>
 
         // Ensure we have enough data
         if compressed_end > data.len() {
-            return Err(ParserError::DecompressionError {
-                reason: format!(
-                    "Block {} at offset 0x{:X} extends beyond file (needs {} bytes, file has {})",
-                    block_index, offset, compressed_end, data.len()
-                ),
+            return Err(ParserError::TruncatedBlock {
+                block_index: block_index as usize,
+                offset: result.len(),
             });
         }
 
@@ -388,7 +385,13 @@ mod tests {
         let header = ClassicHeader::parse(&file).unwrap();
         let result = decompress_classic(&file, &header);
 
-        assert!(matches!(result, Err(ParserError::DecompressionError { .. })));
+        assert!(matches!(
+            result,
+            Err(ParserError::TruncatedBlock {
+                block_index: 0,
+                offset: 0
+            })
+        ));
     }
 
     #[test]
@@ -70,6 +70,7 @@ const BLOCK_HEADER_SIZE_B: usize = 12;
 ///
 /// - `ParserError::UnexpectedEof` if the data is shorter than expected
 /// - `ParserError::DecompressionError` if decompression fails
+/// - `ParserError::TruncatedBlock` if a Classic block extends beyond the file
 ///
 /// # Example
 ///
@@ -107,7 +108,7 @@ pub fn decompress_grbn(data: &[u8], _header: &GrbnHeader) -> Result<Vec<u8>> {
 /// Decompresses the metadata zlib stream at offset 0x80.
 ///
 /// This small stream contains game metadata like player names, game settings, etc.
-fn decompress_metadata_zlib(data: &[u8]) -> Result<Vec<u8>> {
+pub(crate) fn decompress_metadata_zlib(data: &[u8]) -> Result<Vec<u8>> {
     let compressed = &data[GRBN_DATA_OFFSET..];
 
     let mut decoder = ZlibDecoder::new(compressed);
@@ -173,12 +174,9 @@ fn decompress_embedded_classic(data: &[u8]) -> Result<Vec<u8>> {
 
     for block_index in 0..block_count {
         // Read compressed size from block header
-        let compressed_size = read_u16_le(data, offset).map_err(|_| {
-            ParserError::DecompressionError {
-                reason: format!(
-                    "Failed to read block header {block_index} at offset 0x{offset:X}"
-                ),
-            }
+        let compressed_size = read_u16_le(data, offset).map_err(|_| ParserError::TruncatedBlock {
+            block_index: block_index as usize,
+            offset: result.len(),
         })? as usize;
 
         let compressed_start = offset + block_header_size;
@@ -186,10 +184,9 @@ fn decompress_embedded_classic(data: &[u8]) -> Result<Vec<u8>> {
 
         // Ensure we have enough data
         if compressed_end > data.len() {
-            return Err(ParserError::DecompressionError {
-                reason: format!(
-                    "Embedded Classic block {block_index} extends beyond file (offset 0x{offset:X}, needs {compressed_end} bytes)"
-                ),
+            return Err(ParserError::TruncatedBlock {
+                block_index: block_index as usize,
+                offset: result.len(),
             });
         }
 
@@ -214,7 +211,7 @@ fn decompress_embedded_classic(data: &[u8]) -> Result<Vec<u8>> {
 /// Searches for the Classic header magic string in the data.
 ///
 /// Returns the offset of the Classic header if found, or None otherwise.
-fn find_classic_header(data: &[u8]) -> Option<usize> {
+pub(crate) fn find_classic_header(data: &[u8]) -> Option<usize> {
     // Start searching after the GRBN header and metadata
     let search_start = GRBN_DATA_OFFSET + 100; // Skip past metadata area
 
@@ -71,6 +71,7 @@ use crate::header::Header;
 ///
 /// - `ParserError::UnexpectedEof` if the data is truncated
 /// - `ParserError::DecompressionError` if the zlib data is invalid
+/// - `ParserError::TruncatedBlock` if a Classic block extends beyond the file
 ///
 /// # Example
 ///
@@ -0,0 +1,201 @@
+//! Parse modes and diagnostics for recoverable problems.
+//!
+//! Many problems found while parsing a replay do not prevent the rest of it
+//! from being read: an action that fails to decode, an unknown action type or
+//! a `TimeFrame` stream that stops before the end of the data. In
+//! [`ParseMode::Lenient`] mode these are collected as [`Diagnostic`]s and
+//! returned alongside the result. In [`ParseMode::Strict`] mode the first
+//! error aborts parsing.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::diagnostic::{ParseMode, Severity};
+//! use w3g_parser::replay::{ParseOptions, Replay};
+//!
+//! fn ingest(path: &str) -> w3g_parser::Result<()> {
+//!     let options = ParseOptions::new().mode(ParseMode::Lenient);
+//!     let replay = Replay::from_path_with(path, &options)?;
+//!     for diagnostic in &replay.diagnostics {
+//!         if diagnostic.severity == Severity::Error {
+//!             eprintln!("{diagnostic}");
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::error::{ParserError, Result};
+
+/// How recoverable parse problems are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseMode {
+    /// Fail on the first error.
+    Strict,
+
+    /// Skip over errors and record them as diagnostics.
+    #[default]
+    Lenient,
+}
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Data was decoded, but possibly not as intended (e.g. an unknown action).
+    Warning,
+
+    /// Data was skipped. In strict mode this aborts parsing.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A recoverable problem found while parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// How severe the problem is.
+    pub severity: Severity,
+
+    /// Absolute byte offset into the decompressed data.
+    pub offset: usize,
+
+    /// Where the problem occurred (e.g. "timeframe #812 / action #4").
+    pub context: String,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a warning diagnostic.
+    #[must_use]
+    pub fn warning(offset: usize, context: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            offset,
+            context: context.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates an error diagnostic.
+    #[must_use]
+    pub fn error(offset: usize, context: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            offset,
+            context: context.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at 0x{:X} ({}): {}",
+            self.severity, self.offset, self.context, self.message
+        )
+    }
+}
+
+/// Collects diagnostics according to a parse mode.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    mode: ParseMode,
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Creates an empty collector for the given mode.
+    pub(crate) fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Records a warning.
+    pub(crate) fn warning(
+        &mut self,
+        offset: usize,
+        context: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.entries
+            .push(Diagnostic::warning(offset, context, message));
+    }
+
+    /// Records a recoverable error, or returns it in strict mode.
+    ///
+    /// `skipped` is the number of bytes that are skipped because of the error.
+    pub(crate) fn error(
+        &mut self,
+        error: ParserError,
+        offset: usize,
+        context: impl Into<String>,
+        skipped: usize,
+    ) -> Result<()> {
+        match self.mode {
+            ParseMode::Strict => Err(error),
+            ParseMode::Lenient => {
+                let message = format!("{error} ({skipped} bytes skipped)");
+                self.entries
+                    .push(Diagnostic::error(offset, context, message));
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the collected diagnostics.
+    pub(crate) fn into_vec(self) -> Vec<Diagnostic> {
+        self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_lenient_collects_errors() {
+        let mut diagnostics = Diagnostics::new(ParseMode::Lenient);
+        diagnostics.warning(4, "timeframe #1", "unknown action");
+        assert!(diagnostics
+            .error(ParserError::unexpected_eof(2, 1), 10, "timeframe #2", 1)
+            .is_ok());
+
+        let entries = diagnostics.into_vec();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].severity, Severity::Error);
+        assert_eq!(entries[1].offset, 10);
+        assert_eq!(
+            entries[1].to_string(),
+            "error at 0xA (timeframe #2): Unexpected end of data: expected 2 bytes, \
+             but only 1 available (1 bytes skipped)"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_strict_fails() {
+        let mut diagnostics = Diagnostics::new(ParseMode::Strict);
+        diagnostics.warning(4, "timeframe #1", "unknown action");
+        let result = diagnostics.error(ParserError::unexpected_eof(2, 1), 10, "timeframe #2", 1);
+
+        assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
+        assert_eq!(diagnostics.into_vec().len(), 1);
+    }
+}
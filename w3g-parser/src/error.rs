@@ -14,6 +14,16 @@ use thiserror::Error;
 /// - Malformed header structures
 /// - Decompression failures
 /// - Truncated or incomplete data
+/// - Unexpected records and invalid actions in the decompressed stream
+/// - Replays that cannot be written back
+///
+/// The record-level variants carry the byte offset of the failure and a
+/// record path such as `"timeframe #812 / player 3 / action #4"`. Parsers of
+/// single records report offsets relative to the record; the iterators that
+/// call them add their own offset and path segment with
+/// [`ParserError::with_context`], so errors returned by
+/// [`Replay`](crate::replay::Replay) are absolute offsets into the
+/// decompressed data.
 ///
 /// # Example
 ///
@@ -80,6 +90,66 @@ pub enum ParserError {
         /// The actual number of bytes available.
         available: usize,
     },
+
+    /// A record or action started with a marker byte other than the expected one.
+    #[error(
+        "Unexpected record 0x{found:02X} at offset 0x{offset:X}{}: expected {expected}",
+        display_path(.path)
+    )]
+    UnexpectedRecord {
+        /// Byte offset of the marker.
+        offset: usize,
+        /// Record path (e.g. "timeframe #812 / action #4"), empty if unknown.
+        path: String,
+        /// The marker byte found.
+        found: u8,
+        /// Description of the expected marker(s) (e.g. "0x1E or 0x1F").
+        expected: String,
+    },
+
+    /// An action could not be decoded.
+    ///
+    /// This is returned when a command block's player ID is out of range, or
+    /// when an action does not fit in its command block.
+    #[error(
+        "Invalid action at offset 0x{offset:X}{}: player ID {player_id}, type 0x{type_byte:02X}",
+        display_path(.path)
+    )]
+    InvalidAction {
+        /// Byte offset of the action.
+        offset: usize,
+        /// Record path (e.g. "timeframe #812 / action #4"), empty if unknown.
+        path: String,
+        /// The player ID byte of the action.
+        player_id: u8,
+        /// The action type byte (0 if the data ends before the first action).
+        type_byte: u8,
+    },
+
+    /// A compressed block extends beyond the end of the file.
+    ///
+    /// Decompression stops at this block, so `offset` is also the number of
+    /// bytes that were decompressed successfully.
+    #[error(
+        "Truncated block #{block_index}: file ends before the block \
+         (decompressed offset 0x{offset:X})"
+    )]
+    TruncatedBlock {
+        /// Zero-based index of the block.
+        block_index: usize,
+        /// Offset in the decompressed data at which the block's data would start.
+        offset: usize,
+    },
+
+    /// A replay could not be written.
+    ///
+    /// Returned by [`ReplayWriter`](crate::writer::ReplayWriter) and the tools
+    /// that rewrite replays with it when the result cannot be encoded.
+    #[error("Cannot write replay: {reason}")]
+    WriteError {
+        /// Description of the problem.
+        reason: String,
+    },
 }
 
 impl ParserError {
@@ -118,6 +188,114 @@ impl ParserError {
     pub fn unexpected_eof(expected: usize, available: usize) -> Self {
         ParserError::UnexpectedEof { expected, available }
     }
+
+    /// Creates an `UnexpectedRecord` error with an empty record path.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Offset of the marker, relative to the record being parsed
+    /// * `found` - The marker byte found
+    /// * `expected` - Description of the expected marker(s)
+    #[must_use]
+    pub fn unexpected_record(offset: usize, found: u8, expected: impl Into<String>) -> Self {
+        ParserError::UnexpectedRecord {
+            offset,
+            path: String::new(),
+            found,
+            expected: expected.into(),
+        }
+    }
+
+    /// Adds the offset and path segment of an enclosing record.
+    ///
+    /// `base_offset` is added to the error's offset and `segment` is prepended
+    /// to its record path. An empty segment leaves the path unchanged. Errors
+    /// without an offset are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use w3g_parser::error::ParserError;
+    ///
+    /// let err = ParserError::unexpected_record(0, 0x42, "0x22")
+    ///     .with_context(3, "action #4")
+    ///     .with_context(0x100, "timeframe #812");
+    /// assert_eq!(err.offset(), Some(0x103));
+    /// assert_eq!(err.path(), Some("timeframe #812 / action #4"));
+    /// ```
+    #[must_use]
+    pub fn with_context(mut self, base_offset: usize, segment: &str) -> Self {
+        if let ParserError::UnexpectedRecord { offset, path, .. }
+        | ParserError::InvalidAction { offset, path, .. } = &mut self
+        {
+            *offset += base_offset;
+            if path.is_empty() {
+                segment.clone_into(path);
+            } else if !segment.is_empty() {
+                *path = format!("{segment} / {path}");
+            }
+        }
+        self
+    }
+
+    /// Returns the byte offset of the failure, if known.
+    #[must_use]
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ParserError::UnexpectedRecord { offset, .. }
+            | ParserError::InvalidAction { offset, .. }
+            | ParserError::TruncatedBlock { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the error variant, such as `"DecompressionError"`.
+    ///
+    /// Useful for grouping failures of many replays by cause.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use w3g_parser::error::ParserError;
+    ///
+    /// assert_eq!(ParserError::unexpected_eof(4, 0).kind(), "UnexpectedEof");
+    /// ```
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParserError::IoError(_) => "IoError",
+            ParserError::InvalidMagic { .. } => "InvalidMagic",
+            ParserError::InvalidHeader { .. } => "InvalidHeader",
+            ParserError::DecompressionError { .. } => "DecompressionError",
+            ParserError::UnexpectedEof { .. } => "UnexpectedEof",
+            ParserError::UnexpectedRecord { .. } => "UnexpectedRecord",
+            ParserError::InvalidAction { .. } => "InvalidAction",
+            ParserError::TruncatedBlock { .. } => "TruncatedBlock",
+            ParserError::WriteError { .. } => "WriteError",
+        }
+    }
+
+    /// Returns the record path of the failure, if known.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ParserError::UnexpectedRecord { path, .. } | ParserError::InvalidAction { path, .. }
+                if !path.is_empty() =>
+            {
+                Some(path)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Formats a record path for error messages.
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" ({path})")
+    }
 }
 
 /// Converts a byte slice to a hexadecimal string representation.
@@ -175,6 +353,12 @@ mod tests {
         let err = ParserError::unexpected_eof(128, 64);
         assert!(err.to_string().contains("expected 128 bytes"));
         assert!(err.to_string().contains("64 available"));
+
+        let err = ParserError::WriteError {
+            reason: "too large".to_string(),
+        };
+        assert_eq!(err.to_string(), "Cannot write replay: too large");
+        assert_eq!(err.kind(), "WriteError");
     }
 
     #[test]
@@ -203,6 +387,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_with_context_builds_record_path() {
+        let err = ParserError::InvalidAction {
+            offset: 2,
+            path: String::new(),
+            player_id: 151,
+            type_byte: 0x1A,
+        }
+        .with_context(10, "action #4")
+        .with_context(0, "")
+        .with_context(0x400, "timeframe #812");
+
+        assert_eq!(err.offset(), Some(0x40C));
+        assert_eq!(err.path(), Some("timeframe #812 / action #4"));
+        assert_eq!(
+            err.to_string(),
+            "Invalid action at offset 0x40C (timeframe #812 / action #4): player ID 151, type 0x1A"
+        );
+
+        // Errors without an offset are unchanged
+        let err = ParserError::unexpected_eof(2, 1).with_context(10, "action #4");
+        assert_eq!(err.offset(), None);
+        assert_eq!(err.path(), None);
+    }
+
+    #[test]
+    fn test_record_error_display() {
+        let err = ParserError::unexpected_record(0x20, 0x42, "0x1E or 0x1F");
+        assert_eq!(
+            err.to_string(),
+            "Unexpected record 0x42 at offset 0x20: expected 0x1E or 0x1F"
+        );
+
+        let err = ParserError::TruncatedBlock {
+            block_index: 3,
+            offset: 0x6000,
+        };
+        assert!(err.to_string().contains("block #3"));
+        assert_eq!(err.offset(), Some(0x6000));
+        assert_eq!(err.kind(), "TruncatedBlock");
+        assert_eq!(
+            ParserError::unexpected_record(0, 0x42, "0x22").kind(),
+            "UnexpectedRecord"
+        );
+    }
+
     #[test]
     fn test_error_is_send_sync() {
         // Ensure our error type can be used across threads
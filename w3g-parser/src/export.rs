@@ -0,0 +1,542 @@
+//! Tabular export of replays.
+//!
+//! [`ReplayTables`] flattens a replay into rows for analytics tools:
+//!
+//! - one [`PlayerRow`] per player, with race, team, APM and result, written
+//!   as CSV by [`PlayerCsvWriter`]
+//! - one [`ActionRow`] per action, with game time, type, object code and
+//!   target position, written as an Arrow IPC file by `ActionIpcWriter`
+//!   (requires the `arrow` feature)
+//!
+//! Both writers take any number of replays, so a whole batch ends up in one
+//! table that SQL engines and data frame libraries can load directly. Every
+//! row carries the replay ID chosen by the caller, such as the file's path in
+//! the batch.
+//!
+//! Action rows are the replay's [`Replay::actions`] of players in its
+//! roster, so they agree with every other view of the parsed replay. Results
+//! are taken from the map's W3MMD `FlagP` messages and are empty for maps
+//! that do not report them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::export::{PlayerCsvWriter, ReplayTables};
+//! use w3g_parser::Replay;
+//!
+//! let replay = Replay::from_path("replay.w3g")?;
+//! let tables = ReplayTables::from_replay(&replay, "replay.w3g")?;
+//! let mut csv = PlayerCsvWriter::new(std::io::stdout())?;
+//! csv.write(&tables)?;
+//! csv.finish()?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::actions::{AbilityCode, MmdFlag, W3mmdStats};
+use crate::error::{ParserError, Result};
+use crate::records::{PlayerRecord, Slot};
+use crate::replay::Replay;
+
+/// Column names of the player CSV.
+pub const PLAYER_CSV_COLUMNS: [&str; 9] = [
+    "replay_id",
+    "player_id",
+    "player",
+    "race",
+    "team",
+    "observer",
+    "actions",
+    "apm",
+    "result",
+];
+
+/// Action types that carry build, train, research and purchase orders.
+const BUILD_ORDER_TYPES: [&str; 4] = [
+    "UnitAbilityNoTarget",
+    "UnitAbilityGroundTarget",
+    "UnitAbilityUnitTarget",
+    "UnitAbilityTwoTargets",
+];
+
+/// Second characters of hero ability codes, by race: `AHbz` is the Archmage's
+/// Blizzard.
+const HERO_ABILITY_RACES: [char; 5] = ['H', 'O', 'U', 'E', 'N'];
+
+/// A player of a replay.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerRow {
+    /// Player ID.
+    pub player_id: u8,
+
+    /// Player name.
+    pub name: String,
+
+    /// Race picked in the lobby: `Human`, `Orc`, `Night Elf`, `Undead` or
+    /// `Random`.
+    pub race: Option<String>,
+
+    /// Team number from the slot table.
+    pub team: Option<u8>,
+
+    /// Whether the player watched the game as an observer.
+    pub observer: bool,
+
+    /// Number of actions the player issued.
+    pub actions: usize,
+
+    /// Actions per minute of game time.
+    pub apm: f64,
+
+    /// Result reported by the map (`winner`, `loser`, `drawer`, ...).
+    pub result: Option<String>,
+}
+
+/// An action of a replay.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionRow {
+    /// Player ID of the issuing player.
+    pub player_id: u8,
+
+    /// Game time in milliseconds.
+    pub time_ms: u32,
+
+    /// Action type name, as returned by `ActionType::type_name`.
+    pub action_type: String,
+
+    /// `FourCC` code of the ability, unit or item used, or the hex order ID
+    /// for abilities issued by numeric ID.
+    pub code: Option<String>,
+
+    /// Target X coordinate.
+    pub x: Option<f32>,
+
+    /// Target Y coordinate.
+    pub y: Option<f32>,
+}
+
+impl ActionRow {
+    /// Returns whether the action orders a unit, building, upgrade or item.
+    ///
+    /// These orders carry the object's `FourCC`; orders of abilities use codes
+    /// starting with `A` or numeric order IDs, which are skipped.
+    #[must_use]
+    pub fn is_build_order(&self) -> bool {
+        BUILD_ORDER_TYPES.contains(&self.action_type.as_str())
+            && self
+                .code
+                .as_deref()
+                .is_some_and(|code| !code.starts_with("0x") && !code.starts_with('A'))
+    }
+
+    /// Returns whether the action makes a hero learn a skill.
+    ///
+    /// Skills are learned by ordering the hero ability's `FourCC`, such as
+    /// `AHbz`; casting them uses numeric order IDs instead.
+    #[must_use]
+    pub fn is_hero_skill(&self) -> bool {
+        BUILD_ORDER_TYPES.contains(&self.action_type.as_str())
+            && self.code.as_deref().is_some_and(|code| {
+                let mut chars = code.chars();
+                code.len() == 4
+                    && chars.next() == Some('A')
+                    && chars.next().is_some_and(|c| HERO_ABILITY_RACES.contains(&c))
+            })
+    }
+}
+
+/// The player and action rows of one replay.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayTables {
+    /// Replay ID written in every row.
+    pub replay_id: String,
+
+    /// Players sorted by player ID.
+    pub players: Vec<PlayerRow>,
+
+    /// Actions in game order.
+    pub actions: Vec<ActionRow>,
+
+    /// Game duration in milliseconds.
+    pub duration_ms: u32,
+}
+
+impl ReplayTables {
+    /// Builds the rows of `replay`, identified by `replay_id`.
+    ///
+    /// The action rows are taken from [`Replay::actions`], so `replay` must
+    /// have been parsed with actions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `replay` was parsed without actions or the slot
+    /// table is malformed.
+    pub fn from_replay(replay: &Replay, replay_id: &str) -> Result<Self> {
+        if !replay.options().includes_actions() {
+            return Err(ParserError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "replay was parsed without actions",
+            )));
+        }
+        let slots = replay.slot_table()?;
+        let duration_ms = replay.duration_ms;
+
+        let header = &replay.game.header;
+        let mut roster: Vec<(u8, String)> = replay
+            .game
+            .players
+            .players()
+            .filter_map(|record| match record {
+                PlayerRecord::PlayerSlot(slot) => Some((slot.slot_id, slot.player_name.clone())),
+                PlayerRecord::SlotRecord(_) => None,
+            })
+            .chain(std::iter::once((header.host_slot, header.host_name.clone())))
+            .collect();
+        roster.sort();
+
+        // Only actions of players in the roster become rows
+        let mut action_counts: HashMap<u8, usize> =
+            roster.iter().map(|(player_id, _)| (*player_id, 0)).collect();
+        let mut actions = Vec::new();
+        let mut w3mmd = W3mmdStats::new();
+        for action in &replay.actions {
+            w3mmd.record(action);
+            let Some(count) = action_counts.get_mut(&action.player_id) else {
+                continue;
+            };
+            *count += 1;
+            let position = action.action_type.position();
+            actions.push(ActionRow {
+                player_id: action.player_id,
+                time_ms: action.timestamp_ms,
+                action_type: action.action_type.type_name().to_string(),
+                code: action.action_type.object_code().map(AbilityCode::label),
+                x: position.map(|p| p.x),
+                y: position.map(|p| p.y),
+            });
+        }
+
+        let results: HashMap<&str, &MmdFlag> = w3mmd
+            .players
+            .values()
+            .filter_map(|player| Some((player.name.as_str(), player.result()?)))
+            .collect();
+        let minutes = f64::from(duration_ms) / 60_000.0;
+
+        let players = roster
+            .into_iter()
+            .map(|(player_id, name)| {
+                let slot = slots.as_ref().and_then(|table| {
+                    let slot = table.player_slot(player_id)?;
+                    Some((slot, table.is_observer(slot)))
+                });
+                let count = action_counts.get(&player_id).copied().unwrap_or(0);
+                #[allow(clippy::cast_precision_loss)]
+                let apm = if minutes > 0.0 { count as f64 / minutes } else { 0.0 };
+                PlayerRow {
+                    player_id,
+                    race: slot.and_then(|(slot, _)| race_label(slot)),
+                    team: slot.map(|(slot, _)| slot.team),
+                    observer: slot.is_some_and(|(_, observer)| observer),
+                    actions: count,
+                    apm,
+                    result: results.get(name.as_str()).map(|flag| flag_label(flag)),
+                    name,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            replay_id: replay_id.to_string(),
+            players,
+            actions,
+            duration_ms,
+        })
+    }
+}
+
+/// Returns the CSV label for a slot's race.
+fn race_label(slot: &Slot) -> Option<String> {
+    if slot.is_random_race() {
+        return Some("Random".to_string());
+    }
+    slot.race().map(|race| race.to_string())
+}
+
+/// Returns the protocol name of a W3MMD result flag.
+fn flag_label(flag: &MmdFlag) -> String {
+    match flag {
+        MmdFlag::Winner => "winner".to_string(),
+        MmdFlag::Loser => "loser".to_string(),
+        MmdFlag::Drawer => "drawer".to_string(),
+        MmdFlag::Leaver => "leaver".to_string(),
+        MmdFlag::Practicing => "practicing".to_string(),
+        MmdFlag::Other(other) => other.clone(),
+    }
+}
+
+/// Writes player rows of any number of replays as CSV.
+#[derive(Debug)]
+pub struct PlayerCsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PlayerCsvWriter<W> {
+    /// Creates a writer and writes the header row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if writing fails.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writeln!(writer, "{}", PLAYER_CSV_COLUMNS.join(","))?;
+        Ok(Self { writer })
+    }
+
+    /// Writes the player rows of one replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if writing fails.
+    pub fn write(&mut self, tables: &ReplayTables) -> Result<()> {
+        for player in &tables.players {
+            writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{},{:.1},{}",
+                csv_field(&tables.replay_id),
+                player.player_id,
+                csv_field(&player.name),
+                csv_field(player.race.as_deref().unwrap_or_default()),
+                player.team.map(|team| team.to_string()).unwrap_or_default(),
+                player.observer,
+                player.actions,
+                player.apm,
+                csv_field(player.result.as_deref().unwrap_or_default()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if flushing fails.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+#[cfg(feature = "arrow")]
+pub use self::arrow::{action_schema, ActionIpcWriter};
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use arrow_array::builder::{Float32Builder, StringBuilder, UInt32Builder, UInt8Builder};
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+    use super::ReplayTables;
+    use crate::error::{ParserError, Result};
+
+    /// Returns the schema of the action table.
+    ///
+    /// | Column | Type |
+    /// |--------|------|
+    /// | `replay_id` | Utf8 |
+    /// | `player_id` | `UInt8` |
+    /// | `time_ms` | `UInt32` |
+    /// | `type` | Utf8 |
+    /// | `code` | Utf8, nullable |
+    /// | `x` | `Float32`, nullable |
+    /// | `y` | `Float32`, nullable |
+    #[must_use]
+    pub fn action_schema() -> Schema {
+        Schema::new(vec![
+            Field::new("replay_id", DataType::Utf8, false),
+            Field::new("player_id", DataType::UInt8, false),
+            Field::new("time_ms", DataType::UInt32, false),
+            Field::new("type", DataType::Utf8, false),
+            Field::new("code", DataType::Utf8, true),
+            Field::new("x", DataType::Float32, true),
+            Field::new("y", DataType::Float32, true),
+        ])
+    }
+
+    /// Writes action rows of any number of replays as an Arrow IPC file.
+    ///
+    /// Each replay becomes one record batch.
+    pub struct ActionIpcWriter<W: Write> {
+        writer: FileWriter<W>,
+        schema: Arc<Schema>,
+    }
+
+    impl<W: Write> ActionIpcWriter<W> {
+        /// Creates a writer and writes the file header.
+        ///
+        /// # Errors
+        ///
+        /// Returns `ParserError::IoError` if writing fails.
+        pub fn new(writer: W) -> Result<Self> {
+            let schema = Arc::new(action_schema());
+            let writer = FileWriter::try_new(writer, &schema).map_err(arrow_error)?;
+            Ok(Self { writer, schema })
+        }
+
+        /// Writes the action rows of one replay.
+        ///
+        /// # Errors
+        ///
+        /// Returns `ParserError::IoError` if writing fails.
+        pub fn write(&mut self, tables: &ReplayTables) -> Result<()> {
+            if tables.actions.is_empty() {
+                return Ok(());
+            }
+
+            let rows = tables.actions.len();
+            let mut replay_ids = StringBuilder::with_capacity(rows, rows * tables.replay_id.len());
+            let mut player_ids = UInt8Builder::with_capacity(rows);
+            let mut times = UInt32Builder::with_capacity(rows);
+            let mut types = StringBuilder::new();
+            let mut codes = StringBuilder::new();
+            let mut xs = Float32Builder::with_capacity(rows);
+            let mut ys = Float32Builder::with_capacity(rows);
+            for action in &tables.actions {
+                replay_ids.append_value(&tables.replay_id);
+                player_ids.append_value(action.player_id);
+                times.append_value(action.time_ms);
+                types.append_value(&action.action_type);
+                codes.append_option(action.code.as_deref());
+                xs.append_option(action.x);
+                ys.append_option(action.y);
+            }
+
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(replay_ids.finish()),
+                Arc::new(player_ids.finish()),
+                Arc::new(times.finish()),
+                Arc::new(types.finish()),
+                Arc::new(codes.finish()),
+                Arc::new(xs.finish()),
+                Arc::new(ys.finish()),
+            ];
+            let batch =
+                RecordBatch::try_new(Arc::clone(&self.schema), columns).map_err(arrow_error)?;
+            self.writer.write(&batch).map_err(arrow_error)
+        }
+
+        /// Writes the file footer and returns the underlying writer.
+        ///
+        /// # Errors
+        ///
+        /// Returns `ParserError::IoError` if writing fails.
+        pub fn finish(mut self) -> Result<W> {
+            self.writer.finish().map_err(arrow_error)?;
+            self.writer.into_inner().map_err(arrow_error)
+        }
+    }
+
+    impl<W: Write> std::fmt::Debug for ActionIpcWriter<W> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ActionIpcWriter").finish_non_exhaustive()
+        }
+    }
+
+    fn arrow_error(e: ArrowError) -> ParserError {
+        match e {
+            ArrowError::IoError(_, e) => ParserError::IoError(e),
+            other => ParserError::IoError(std::io::Error::other(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables() -> ReplayTables {
+        ReplayTables {
+            replay_id: "season 1/game,1.w3g".to_string(),
+            players: vec![PlayerRow {
+                player_id: 2,
+                name: "Say \"hi\"".to_string(),
+                race: Some("Night Elf".to_string()),
+                team: Some(1),
+                observer: false,
+                actions: 300,
+                apm: 150.04,
+                result: None,
+            }],
+            actions: Vec::new(),
+            duration_ms: 120_000,
+        }
+    }
+
+    fn action(action_type: &str, code: Option<&str>) -> ActionRow {
+        ActionRow {
+            player_id: 1,
+            time_ms: 0,
+            action_type: action_type.to_string(),
+            code: code.map(str::to_string),
+            x: None,
+            y: None,
+        }
+    }
+
+    #[test]
+    fn test_is_build_order() {
+        assert!(action("UnitAbilityNoTarget", Some("hpea")).is_build_order());
+        assert!(action("UnitAbilityGroundTarget", Some("ogre")).is_build_order());
+        assert!(action("UnitAbilityNoTarget", Some("Rhde")).is_build_order());
+        assert!(!action("UnitAbilityNoTarget", Some("AHbz")).is_build_order());
+        assert!(!action("UnitAbilityNoTarget", Some("0x000D0012")).is_build_order());
+        assert!(!action("SelectSubgroup", Some("hpea")).is_build_order());
+        assert!(!action("UnitAbilityNoTarget", None).is_build_order());
+    }
+
+    #[test]
+    fn test_is_hero_skill() {
+        assert!(action("UnitAbilityNoTarget", Some("AHbz")).is_hero_skill());
+        assert!(action("UnitAbilityNoTarget", Some("ANsg")).is_hero_skill());
+        assert!(!action("UnitAbilityNoTarget", Some("Aatk")).is_hero_skill());
+        assert!(!action("UnitAbilityNoTarget", Some("Hpal")).is_hero_skill());
+        assert!(!action("SelectSubgroup", Some("AHbz")).is_hero_skill());
+    }
+
+    #[test]
+    fn test_player_csv() {
+        let mut csv = PlayerCsvWriter::new(Vec::new()).unwrap();
+        csv.write(&tables()).unwrap();
+        let text = String::from_utf8(csv.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], PLAYER_CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "\"season 1/game,1.w3g\",2,\"Say \"\"hi\"\"\",Night Elf,1,false,300,150.0,"
+        );
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+}
@@ -48,6 +48,7 @@ pub const CLASSIC_TYPE_B_THRESHOLD: u32 = 10000;
 /// - `Grbn`: Magic bytes `GRBN` at offset 0x00
 /// - `Classic`: Magic string `Warcraft III recorded game\x1A\x00` at offset 0x00
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReplayFormat {
     /// GRBN format used by Warcraft III: Reforged (patch 1.32+).
     ///
@@ -93,6 +94,7 @@ impl ReplayFormat {
 /// - **Type A**: Build version < 10000 (e.g., version 26)
 /// - **Type B**: Build version >= 10000 (e.g., versions 10032, 10036)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClassicVersion {
     /// Type A format with 8-byte block headers.
     ///
@@ -18,7 +18,7 @@
 //! | 0x34 | 4 | `build_version` | 26 (Type A) or 10000+ (Type B) |
 //! | 0x38 | 4 | `flags` | Unknown flags (high bit set) |
 //! | 0x3C | 4 | `duration_ms` | Game duration in milliseconds |
-//! | 0x40 | 4 | `checksum` | Unknown checksum |
+//! | 0x40 | 4 | `checksum` | CRC32 of the header with this field zeroed |
 //!
 //! Data blocks start at offset 0x44 (68 bytes).
 //!
@@ -65,6 +65,7 @@ pub const TFT_SUB_HEADER_MAGIC: &[u8; 4] = b"PX3W";
 /// println!("Block format: {:?}", header.version_type());
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClassicHeader {
     /// Magic string at offset 0x00 (28 bytes).
     /// "Warcraft III recorded game\x1A\x00"
@@ -106,7 +107,7 @@ pub struct ClassicHeader {
     pub duration_ms: u32,
 
     /// Checksum at offset 0x40.
-    /// Algorithm unknown.
+    /// CRC32 of the 68 header bytes with the checksum field set to zero.
     pub checksum: [u8; 4],
 }
 
@@ -187,6 +188,46 @@ impl ClassicHeader {
         })
     }
 
+    /// Encodes the header into its 68-byte on-disk representation.
+    ///
+    /// The stored [`ClassicHeader::checksum`] is written as-is; use
+    /// [`ClassicHeader::compute_checksum`] to refresh it after changing fields.
+    #[must_use]
+    pub fn encode(&self) -> [u8; CLASSIC_HEADER_SIZE] {
+        let mut out = [0u8; CLASSIC_HEADER_SIZE];
+        out[0x00..0x1C].copy_from_slice(&self.magic);
+        out[0x1C..0x20].copy_from_slice(&self.header_size.to_le_bytes());
+        out[0x20..0x24].copy_from_slice(&self.file_size.to_le_bytes());
+        out[0x24..0x28].copy_from_slice(&self.header_version.to_le_bytes());
+        out[0x28..0x2C].copy_from_slice(&self.decompressed_size.to_le_bytes());
+        out[0x2C..0x30].copy_from_slice(&self.block_count.to_le_bytes());
+        out[0x30..0x34].copy_from_slice(&self.sub_header_magic);
+        out[0x34..0x38].copy_from_slice(&self.build_version.to_le_bytes());
+        out[0x38..0x3C].copy_from_slice(&self.flags.to_le_bytes());
+        out[0x3C..0x40].copy_from_slice(&self.duration_ms.to_le_bytes());
+        out[0x40..0x44].copy_from_slice(&self.checksum);
+        out
+    }
+
+    /// Computes the header checksum from the current field values.
+    ///
+    /// This is the CRC32 of the encoded header with the checksum field zeroed,
+    /// stored little-endian.
+    #[must_use]
+    pub fn compute_checksum(&self) -> [u8; 4] {
+        let mut bytes = self.encode();
+        bytes[0x40..0x44].fill(0);
+        let mut crc = flate2::Crc::new();
+        crc.update(&bytes);
+        crc.sum().to_le_bytes()
+    }
+
+    /// Returns whether the stored checksum matches the header fields.
+    #[must_use]
+    pub fn checksum_is_valid(&self) -> bool {
+        self.checksum == self.compute_checksum()
+    }
+
     /// Determines the Classic version type based on build version.
     ///
     /// - **Type A**: Build version < 10000 (uses 8-byte block headers)
@@ -467,4 +508,21 @@ mod tests {
         let header = ClassicHeader::parse(&data).unwrap();
         assert_eq!(header.file_size, 100_646);
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let data = create_test_header();
+        let header = ClassicHeader::parse(&data).unwrap();
+        assert_eq!(header.encode().as_slice(), &data[..CLASSIC_HEADER_SIZE]);
+    }
+
+    #[test]
+    fn test_compute_checksum() {
+        let mut header = ClassicHeader::parse(&create_test_header()).unwrap();
+        header.checksum = header.compute_checksum();
+        assert!(header.checksum_is_valid());
+
+        header.duration_ms += 1;
+        assert!(!header.checksum_is_valid());
+    }
 }
@@ -49,6 +49,7 @@ pub const GRBN_EXPECTED_VERSION: u32 = 2;
 /// println!("Decompressed size: {} bytes", header.decompressed_size);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GrbnHeader {
     /// Magic bytes "GRBN" (0x4752424E) at offset 0x00.
     pub magic: [u8; 4],
@@ -70,6 +70,8 @@ use crate::format::{detect_format, ClassicVersion, ReplayFormat};
 /// # Ok::<(), w3g_parser::error::ParserError>(())
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
 pub enum Header {
     /// A GRBN (Reforged) format header.
     Grbn(GrbnHeader),
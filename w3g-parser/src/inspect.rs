@@ -0,0 +1,743 @@
+//! Record tree with byte ranges for inspecting replays.
+//!
+//! [`RecordTree`] breaks a replay down into nested [`Node`]s, each covering a
+//! byte range: the file header with its fields, and the decompressed stream
+//! with the game record header, player records, slot table, `TimeFrame`s,
+//! their command blocks and the actions within them. It backs the CLI's
+//! `inspect` command and is meant for reverse engineering new patches.
+//!
+//! [`Node::annotations`] flattens a node into a map of labelled byte ranges
+//! that covers every byte, reporting the bytes no structure accounts for as
+//! unclaimed. It backs the CLI's `annotate` command.
+//!
+//! Command blocks are framed by their `u16` length and the actions in a block
+//! are parsed back to back, so an action that is parsed with the wrong length
+//! only affects the rest of its block. Bytes the parsers cannot account for
+//! are kept as [`NodeKind::Unparsed`] nodes, and nodes whose meaning is not
+//! known (unknown actions, unknown header fields) are flagged with
+//! [`Node::unknown`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::inspect::RecordTree;
+//! use w3g_parser::Replay;
+//!
+//! let data = std::fs::read("replay.w3g")?;
+//! let replay = Replay::from_bytes(&data)?;
+//! let tree = RecordTree::build(&data, &replay);
+//! for node in tree.stream.children.iter().take(5) {
+//!     println!("0x{:06X} {:>5}  {}", node.offset, node.len, node.label);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::actions::{ActionContext, ActionIterator, ActionType};
+use crate::binary::{read_u16_le, read_u32_le};
+use crate::header::Header;
+use crate::records::{
+    find_game_record_start, Record, RecordStream, SlotTable, TIMEFRAME_HEADER_SIZE,
+};
+use crate::replay::Replay;
+
+/// Classic header fields as (offset, size, name).
+const CLASSIC_HEADER_FIELDS: [(usize, usize, &str); 11] = [
+    (0x00, 28, "magic"),
+    (0x1C, 4, "header_size"),
+    (0x20, 4, "file_size"),
+    (0x24, 4, "header_version"),
+    (0x28, 4, "decompressed_size"),
+    (0x2C, 4, "block_count"),
+    (0x30, 4, "sub_header_magic"),
+    (0x34, 4, "build_version"),
+    (0x38, 4, "flags"),
+    (0x3C, 4, "duration_ms"),
+    (0x40, 4, "checksum"),
+];
+
+/// GRBN header fields as (offset, size, name).
+const GRBN_HEADER_FIELDS: [(usize, usize, &str); 10] = [
+    (0x00, 4, "magic"),
+    (0x04, 4, "version"),
+    (0x08, 4, "unknown_1"),
+    (0x0C, 4, "unknown_2"),
+    (0x10, 8, "reserved_1"),
+    (0x18, 4, "unknown_3"),
+    (0x1C, 4, "unknown_4"),
+    (0x20, 4, "reserved_2"),
+    (0x24, 4, "decompressed_size"),
+    (0x28, 88, "reserved_3"),
+];
+
+/// Kind of structure a node covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeKind {
+    /// The file header.
+    Header,
+    /// The decompressed data.
+    Stream,
+    /// The game record header with host and encoded settings.
+    GameRecordHeader,
+    /// Player record (0x16).
+    Player,
+    /// Slot table record (0x19).
+    SlotTable,
+    /// One slot of the slot table.
+    Slot,
+    /// Extended metadata record (0x39).
+    ExtendedMetadata,
+    /// Game start record (0x1A-0x1C).
+    GameStart,
+    /// `TimeFrame` record (0x1E, 0x1F).
+    TimeFrame,
+    /// A player's command block within a `TimeFrame`.
+    CommandBlock,
+    /// A single action within a command block.
+    Action,
+    /// Chat record (0x20).
+    Chat,
+    /// Checksum record (0x22).
+    Checksum,
+    /// Desync record (0x23).
+    Desync,
+    /// Leave record (0x17).
+    Leave,
+    /// A field of a record.
+    Field,
+    /// Bytes no parser accounts for.
+    Unparsed,
+    /// Zero padding at the end of the decompressed data.
+    Padding,
+}
+
+impl NodeKind {
+    /// Returns the name of the kind, such as `"TimeFrame"`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            NodeKind::Header => "Header",
+            NodeKind::Stream => "Stream",
+            NodeKind::GameRecordHeader => "GameRecordHeader",
+            NodeKind::Player => "Player",
+            NodeKind::SlotTable => "SlotTable",
+            NodeKind::Slot => "Slot",
+            NodeKind::ExtendedMetadata => "ExtendedMetadata",
+            NodeKind::GameStart => "GameStart",
+            NodeKind::TimeFrame => "TimeFrame",
+            NodeKind::CommandBlock => "CommandBlock",
+            NodeKind::Action => "Action",
+            NodeKind::Chat => "Chat",
+            NodeKind::Checksum => "Checksum",
+            NodeKind::Desync => "Desync",
+            NodeKind::Leave => "Leave",
+            NodeKind::Field => "Field",
+            NodeKind::Unparsed => "Unparsed",
+            NodeKind::Padding => "Padding",
+        }
+    }
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A structure in the replay and the bytes it covers.
+///
+/// Children lie within the parent's range, in offset order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    /// Kind of structure.
+    pub kind: NodeKind,
+
+    /// Human-readable description, such as `"TimeFrame #12 @ 00:01.300"`.
+    pub label: String,
+
+    /// Offset of the first byte.
+    pub offset: usize,
+
+    /// Number of bytes covered.
+    pub len: usize,
+
+    /// Player the structure belongs to, if any.
+    pub player_id: Option<u8>,
+
+    /// Action type name for action nodes, such as `"UnitAbilityNoTarget"`.
+    pub action_type: Option<String>,
+
+    /// Whether the meaning of the bytes is not known.
+    pub unknown: bool,
+
+    /// Nested structures.
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn new(kind: NodeKind, label: impl Into<String>, offset: usize, len: usize) -> Self {
+        Self {
+            kind,
+            label: label.into(),
+            offset,
+            len,
+            player_id: None,
+            action_type: None,
+            unknown: false,
+            children: Vec::new(),
+        }
+    }
+
+    fn field(name: &str, offset: usize, len: usize) -> Self {
+        let mut node = Self::new(NodeKind::Field, name, offset, len);
+        node.unknown = name.starts_with("unknown");
+        node
+    }
+
+    fn unparsed(label: impl Into<String>, offset: usize, len: usize) -> Self {
+        let mut node = Self::new(NodeKind::Unparsed, label, offset, len);
+        node.unknown = true;
+        node
+    }
+
+    fn player(mut self, player_id: u8) -> Self {
+        self.player_id = Some(player_id);
+        self
+    }
+
+    fn with_children(mut self, children: Vec<Node>) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// Returns the offset after the last byte.
+    #[must_use]
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    /// Returns whether this node or any node below it is flagged unknown.
+    #[must_use]
+    pub fn has_unknown(&self) -> bool {
+        self.unknown || self.children.iter().any(Node::has_unknown)
+    }
+
+    /// Returns the leaves of the node as labelled byte ranges.
+    ///
+    /// The ranges are in offset order and cover the node without gaps:
+    /// bytes of a structure that none of its children cover are returned as
+    /// unclaimed ranges.
+    #[must_use]
+    pub fn annotations(&self) -> Vec<Annotation> {
+        let mut annotations = Vec::new();
+        annotate(self, "", &mut annotations);
+        annotations
+    }
+
+    /// Returns the innermost node containing `offset`, with its ancestors.
+    ///
+    /// The path starts with this node. Returns an empty path if `offset` is
+    /// outside the node.
+    #[must_use]
+    pub fn path_to(&self, offset: usize) -> Vec<&Node> {
+        let mut path = Vec::new();
+        let mut node = self;
+        if !(node.offset..node.end()).contains(&offset) {
+            return path;
+        }
+        loop {
+            path.push(node);
+            match node.children.iter().find(|c| (c.offset..c.end()).contains(&offset)) {
+                Some(child) => node = child,
+                None => return path,
+            }
+        }
+    }
+}
+
+/// The record tree of a replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordTree {
+    /// The file header; offsets are into the replay file.
+    pub header: Node,
+
+    /// The decompressed data; offsets are into [`Replay::decompressed`].
+    pub stream: Node,
+}
+
+impl RecordTree {
+    /// Builds the tree of a replay parsed from `file`.
+    ///
+    /// Parsing stops at the first record that cannot be framed; the rest of
+    /// the data becomes an unparsed node.
+    #[must_use]
+    pub fn build(file: &[u8], replay: &Replay) -> Self {
+        Self {
+            header: header_node(file, &replay.header),
+            stream: stream_node(replay),
+        }
+    }
+}
+
+/// A labelled byte range of a node's annotation map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    /// Offset of the first byte.
+    pub offset: usize,
+
+    /// Number of bytes covered.
+    pub len: usize,
+
+    /// Structures the range belongs to, such as
+    /// `"TimeFrame[12]/CommandBlock[0].length"`. Structures are numbered per
+    /// kind within their parent and fields are appended with a dot.
+    pub path: String,
+
+    /// Kind of the innermost structure; unclaimed ranges are
+    /// [`NodeKind::Unparsed`].
+    pub kind: NodeKind,
+
+    /// Label of the innermost structure.
+    pub label: String,
+
+    /// Player the range belongs to, if any.
+    pub player_id: Option<u8>,
+
+    /// Whether a parser accounts for the bytes.
+    pub claimed: bool,
+
+    /// Whether the meaning of the bytes is not known.
+    pub unknown: bool,
+}
+
+impl Annotation {
+    fn unclaimed(offset: usize, len: usize, path: &str) -> Self {
+        Self {
+            offset,
+            len,
+            path: path.to_string(),
+            kind: NodeKind::Unparsed,
+            label: "Unclaimed".to_string(),
+            player_id: None,
+            claimed: false,
+            unknown: true,
+        }
+    }
+
+    /// Returns the offset after the last byte.
+    #[must_use]
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+}
+
+fn annotate(node: &Node, path: &str, annotations: &mut Vec<Annotation>) {
+    if node.children.is_empty() {
+        annotations.push(Annotation {
+            offset: node.offset,
+            len: node.len,
+            path: path.to_string(),
+            kind: node.kind,
+            label: node.label.clone(),
+            player_id: node.player_id,
+            claimed: node.kind != NodeKind::Unparsed,
+            unknown: node.unknown,
+        });
+        return;
+    }
+
+    let mut counts: HashMap<NodeKind, usize> = HashMap::new();
+    let mut cursor = node.offset;
+    for child in &node.children {
+        if child.offset > cursor {
+            annotations.push(Annotation::unclaimed(cursor, child.offset - cursor, path));
+        }
+        let child_path = if child.kind == NodeKind::Field {
+            // Field labels start with the field name, followed by its value
+            let name = child.label.split_whitespace().next().unwrap_or_default();
+            if path.is_empty() { name.to_string() } else { format!("{path}.{name}") }
+        } else {
+            let index = counts.entry(child.kind).or_default();
+            let segment = format!("{}[{index}]", child.kind);
+            *index += 1;
+            if path.is_empty() { segment } else { format!("{path}/{segment}") }
+        };
+        annotate(child, &child_path, annotations);
+        cursor = cursor.max(child.end());
+    }
+    if cursor < node.end() {
+        annotations.push(Annotation::unclaimed(cursor, node.end() - cursor, path));
+    }
+}
+
+fn header_node(file: &[u8], header: &Header) -> Node {
+    let (label, fields): (_, &[(usize, usize, &str)]) = match header {
+        Header::Classic(h) => (
+            format!("Classic header (build {})", h.build_version),
+            &CLASSIC_HEADER_FIELDS,
+        ),
+        Header::Grbn(_) => ("GRBN header".to_string(), &GRBN_HEADER_FIELDS),
+    };
+    let len = header.header_size().min(file.len());
+    let children = fields
+        .iter()
+        .filter(|(offset, size, _)| offset + size <= len)
+        .map(|&(offset, size, name)| Node::field(name, offset, size))
+        .collect();
+    Node::new(NodeKind::Header, label, 0, len).with_children(children)
+}
+
+pub(crate) fn stream_node(replay: &Replay) -> Node {
+    let data = replay.decompressed();
+    let mut children = Vec::new();
+
+    let Ok(start) = find_game_record_start(data) else {
+        children.push(Node::unparsed("No game record found", 0, data.len()));
+        return Node::new(NodeKind::Stream, "Decompressed data", 0, data.len())
+            .with_children(children);
+    };
+    if start > 0 {
+        children.push(Node::unparsed("Data before the game record", 0, start));
+    }
+    children.push(game_record_header_node(replay, start));
+
+    let mut stream = RecordStream::new(data, start + replay.game.header.byte_length);
+    let mut frame_index = 0;
+    let mut time_ms = 0u32;
+    while let Some(Ok((offset, record))) = stream.next() {
+        if let Record::TimeFrame { time_increment_ms, .. } = record {
+            frame_index += 1;
+            time_ms = time_ms.saturating_add(u32::from(time_increment_ms));
+        }
+        let len = stream.current_offset() - offset;
+        children.push(record_node(offset, len, &record, frame_index, time_ms));
+    }
+
+    // The stream stops at zero padding or at a record it cannot frame
+    let end = stream.current_offset().min(data.len());
+    if end < data.len() {
+        if data[end..].iter().all(|&b| b == 0) {
+            children.push(Node::new(NodeKind::Padding, "Zero padding", end, data.len() - end));
+        } else {
+            let marker = data[end];
+            let label = format!("Unframed data (marker 0x{marker:02X})");
+            children.push(Node::unparsed(label, end, data.len() - end));
+        }
+    }
+
+    Node::new(NodeKind::Stream, "Decompressed data", 0, data.len()).with_children(children)
+}
+
+fn game_record_header_node(replay: &Replay, start: usize) -> Node {
+    let header = &replay.game.header;
+    let host_name = start + 6;
+    let host_flags = host_name + header.host_name.len() + 1;
+    let additional = host_flags + 1;
+    let settings = additional + header.additional_data.len() + 1;
+    let end = start + header.byte_length;
+    let children = vec![
+        Node::field("record_type", start, 4),
+        Node::field("unknown_1", start + 4, 1),
+        Node::field("host_slot", start + 5, 1),
+        Node::field(
+            &format!("host_name \"{}\"", header.host_name),
+            host_name,
+            host_flags - host_name,
+        ),
+        Node::field("host_flags", host_flags, 1),
+        Node::field("additional_data", additional, settings - additional),
+        Node::field("encoded_settings", settings, end.saturating_sub(settings)),
+    ];
+    Node::new(NodeKind::GameRecordHeader, "Game record header", start, header.byte_length)
+        .with_children(children)
+        .player(header.host_slot)
+}
+
+#[allow(clippy::too_many_lines)]
+fn record_node(offset: usize, len: usize, record: &Record, frame: usize, time_ms: u32) -> Node {
+    let field = |name: &str, at: usize, size: usize| Node::field(name, offset + at, size);
+    match record {
+        Record::Player(slot) => {
+            let name_len = slot.player_name.len() + 1;
+            let data_len = slot.trailing_data.len() - 5;
+            let label = format!("Player {} \"{}\"", slot.slot_id, slot.player_name);
+            Node::new(NodeKind::Player, label, offset, len)
+                .player(slot.slot_id)
+                .with_children(vec![
+                    field("marker", 0, 1),
+                    field("player_id", 1, 1),
+                    field("name", 2, name_len),
+                    field("data_length", 2 + name_len, 1),
+                    field("data", 3 + name_len, data_len),
+                    field("unknown", 3 + name_len + data_len, 4),
+                ])
+        }
+        Record::SlotTable { data: body } => slot_table_node(offset, len, body),
+        Record::ExtendedMetadata { subtype, data: body } => {
+            let label = format!("Extended metadata (subtype 0x{subtype:02X})");
+            Node::new(NodeKind::ExtendedMetadata, label, offset, len).with_children(vec![
+                field("marker", 0, 1),
+                field("subtype", 1, 1),
+                field("length", 2, 4),
+                field("payload", 6, body.len()),
+            ])
+        }
+        Record::GameStart { marker, .. } => {
+            let label = format!("Game start (0x{marker:02X})");
+            Node::new(NodeKind::GameStart, label, offset, len)
+                .with_children(vec![field("marker", 0, 1), field("value", 1, 4)])
+        }
+        Record::TimeFrame {
+            time_increment_ms,
+            commands,
+            ..
+        } => {
+            let label = format!(
+                "TimeFrame #{frame} @ {} (+{time_increment_ms} ms)",
+                format_time(time_ms)
+            );
+            let mut children = vec![
+                field("marker", 0, 1),
+                field("length", 1, 2),
+                field("time_increment", 3, 2),
+            ];
+            children.extend(command_block_nodes(commands, offset + TIMEFRAME_HEADER_SIZE));
+            Node::new(NodeKind::TimeFrame, label, offset, len).with_children(children)
+        }
+        Record::Chat {
+            player_id, mode, ..
+        } => {
+            let text = record.chat_text().unwrap_or_default();
+            let label = format!("Chat from {player_id}: {text:?}");
+            let mut children = vec![
+                field("marker", 0, 1),
+                field("player_id", 1, 1),
+                field("length", 2, 2),
+                field("flags", 4, 1),
+            ];
+            let text_start = if mode.is_some() {
+                children.push(field("mode", 5, 4));
+                9
+            } else {
+                5
+            };
+            children.push(field("message", text_start, len - text_start));
+            Node::new(NodeKind::Chat, label, offset, len)
+                .player(*player_id)
+                .with_children(children)
+        }
+        Record::Checksum { data: body } => {
+            let label = match read_u32_le(body, 0) {
+                Ok(value) if body.len() == 4 => format!("Checksum 0x{value:08X}"),
+                _ => format!("Checksum ({} bytes)", body.len()),
+            };
+            Node::new(NodeKind::Checksum, label, offset, len).with_children(vec![
+                field("marker", 0, 1),
+                field("length", 1, 1),
+                field("checksum", 2, body.len()),
+            ])
+        }
+        Record::Desync(desync) => {
+            let label = format!("Desync (checksum 0x{:08X})", desync.checksum);
+            Node::new(NodeKind::Desync, label, offset, len).with_children(vec![
+                field("marker", 0, 1),
+                field("unknown_1", 1, 4),
+                field("unknown_2", 5, 1),
+                field("checksum", 6, 4),
+                field("unknown_3", 10, 1),
+            ])
+        }
+        Record::Leave(leave) => {
+            let label =
+                format!("Leave: player {} (reason 0x{:02X})", leave.player_id, leave.reason);
+            Node::new(NodeKind::Leave, label, offset, len)
+                .player(leave.player_id)
+                .with_children(vec![
+                    field("marker", 0, 1),
+                    field("reason", 1, 4),
+                    field("player_id", 5, 1),
+                    field("result", 6, 4),
+                    field("unknown", 10, 4),
+                ])
+        }
+    }
+}
+
+fn slot_table_node(offset: usize, len: usize, body: &[u8]) -> Node {
+    let mut children = vec![
+        Node::field("marker", offset, 1),
+        Node::field("length", offset + 1, 2),
+    ];
+    let Ok(table) = SlotTable::parse(body) else {
+        children.push(Node::unparsed("Malformed slot table", offset + 3, body.len()));
+        return Node::new(NodeKind::SlotTable, "Slot table", offset, len).with_children(children);
+    };
+
+    let start = offset + 3;
+    let slot_size = (body.len() - 7) / table.slots.len().max(1);
+    children.push(Node::field("slot_count", start, 1));
+    for (index, slot) in table.slots.iter().enumerate() {
+        let observer = if table.is_observer(slot) { ", observer" } else { "" };
+        let label = format!(
+            "Slot {index}: {:?}, team {}{observer}",
+            slot.status, slot.team
+        );
+        let mut node = Node::new(NodeKind::Slot, label, start + 1 + index * slot_size, slot_size);
+        if slot.is_player() {
+            node = node.player(slot.player_id);
+        }
+        children.push(node);
+    }
+    let trailer = offset + len - 6;
+    children.push(Node::field("random_seed", trailer, 4));
+    children.push(Node::field("select_mode", trailer + 4, 1));
+    children.push(Node::field("start_spot_count", trailer + 5, 1));
+
+    let label = format!("Slot table ({} slots)", table.slots.len());
+    Node::new(NodeKind::SlotTable, label, offset, len).with_children(children)
+}
+
+/// Builds the command block nodes of a `TimeFrame` whose commands start at `base`.
+pub(crate) fn command_block_nodes(commands: &[u8], base: usize) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut offset = 0;
+    while offset < commands.len() {
+        let player_id = commands[offset];
+        let block_len = read_u16_le(commands, offset + 1).map(usize::from);
+        let Some(block) = block_len
+            .ok()
+            .and_then(|len| commands.get(offset + 3..offset + 3 + len))
+        else {
+            let label = "Command data that does not frame as blocks";
+            nodes.push(Node::unparsed(label, base + offset, commands.len() - offset));
+            break;
+        };
+
+        let start = base + offset;
+        let mut children = vec![
+            Node::field("player_id", start, 1),
+            Node::field("length", start + 1, 2),
+        ];
+        children.extend(action_nodes(&commands[offset..offset + 3 + block.len()], start, player_id));
+        let label = format!("Commands of player {player_id} ({} bytes)", block.len());
+        nodes.push(
+            Node::new(NodeKind::CommandBlock, label, start, 3 + block.len())
+                .player(player_id)
+                .with_children(children),
+        );
+        offset += 3 + block.len();
+    }
+    nodes
+}
+
+/// Builds the action nodes of a command block, including its header, that
+/// starts at `base`.
+///
+/// The actions are decoded by [`ActionIterator`], like [`Replay::actions`].
+fn action_nodes(block: &[u8], base: usize, player_id: u8) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut actions = ActionIterator::new(block, ActionContext::default());
+    while let Some(result) = actions.next() {
+        // The block header is covered by the block's own field nodes
+        let start = actions.action_offset().max(3);
+        let Ok(action) = result else {
+            let label = format!("Unparsed actions (type 0x{:02X})", block[start]);
+            nodes.push(Node::unparsed(label, base + start, block.len() - start).player(player_id));
+            break;
+        };
+
+        let len = actions.current_offset() - start;
+        let action_type = action.action_type;
+        let mut node = Node::new(NodeKind::Action, action_type.to_string(), base + start, len)
+            .player(player_id);
+        node.unknown = matches!(action_type, ActionType::Unknown { .. });
+        node.action_type = Some(action_type.type_name().to_string());
+        nodes.push(node);
+    }
+    nodes
+}
+
+/// Formats a game time as `MM:SS.mmm`.
+fn format_time(ms: u32) -> String {
+    format!("{:02}:{:02}.{:03}", ms / 60_000, (ms / 1000) % 60, ms % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_block_nodes() {
+        // Player 1: TriggerEscapeKey (0x61); player 2: an arrow key action with a bad event
+        let commands = [0x01, 0x01, 0x00, 0x61, 0x02, 0x02, 0x00, 0x75, 0xFF];
+        let nodes = command_block_nodes(&commands, 0x100);
+        assert_eq!(nodes.len(), 2);
+
+        let first = &nodes[0];
+        assert_eq!((first.offset, first.len, first.player_id), (0x100, 4, Some(1)));
+        let action = first.children.last().unwrap();
+        assert_eq!(action.kind, NodeKind::Action);
+        assert_eq!(action.action_type.as_deref(), Some("TriggerEscapeKey"));
+        assert_eq!((action.offset, action.len), (0x103, 1));
+        assert!(!first.has_unknown());
+
+        assert_eq!(nodes[1].offset, 0x104);
+        assert!(nodes[1].has_unknown());
+        assert_eq!(nodes[1].children.last().unwrap().end(), 0x109);
+    }
+
+    #[test]
+    fn test_unframed_command_data() {
+        let nodes = command_block_nodes(&[0x01, 0x10, 0x00, 0x18], 0);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].kind, NodeKind::Unparsed);
+        assert_eq!(nodes[0].len, 4);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let record = Node::new(NodeKind::Checksum, "Checksum", 0x10, 8).with_children(vec![
+            Node::field("marker", 0x10, 1),
+            Node::field("length", 0x11, 1),
+            Node::field("unknown_1", 0x14, 2),
+        ]);
+        let stream = Node::new(NodeKind::Stream, "Decompressed data", 0x10, 9)
+            .with_children(vec![record, Node::unparsed("Unframed data", 0x18, 1)]);
+
+        let annotations = stream.annotations();
+        let ranges: Vec<_> = annotations
+            .iter()
+            .map(|a| (a.offset, a.len, a.path.as_str(), a.claimed))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (0x10, 1, "Checksum[0].marker", true),
+                (0x11, 1, "Checksum[0].length", true),
+                (0x12, 2, "Checksum[0]", false),
+                (0x14, 2, "Checksum[0].unknown_1", true),
+                (0x16, 2, "Checksum[0]", false),
+                (0x18, 1, "Unparsed[0]", false),
+            ]
+        );
+        assert!(annotations[3].unknown);
+        assert_eq!(annotations[2].label, "Unclaimed");
+    }
+
+    #[test]
+    fn test_path_to() {
+        let nodes = command_block_nodes(&[0x01, 0x01, 0x00, 0x61], 0x10);
+        let block = &nodes[0];
+        let path = block.path_to(0x13);
+        assert_eq!(path.len(), 2);
+        assert_eq!(path[1].kind, NodeKind::Action);
+        assert_eq!(block.path_to(0x11).last().unwrap().label, "length");
+        assert!(block.path_to(0x14).is_empty());
+    }
+}
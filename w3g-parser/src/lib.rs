@@ -42,7 +42,34 @@
 //! - [`format`] - Format detection and type definitions
 //! - [`header`] - Header parsing for GRBN and Classic formats
 //! - [`decompress`] - Decompression for GRBN and Classic formats
+//! - [`diagnostic`] - Strict/lenient parse modes and collected diagnostics
 //! - [`records`] - Decompressed data record parsing (game header, players, timeframes)
+//! - [`replay`] - One-call parsing of a whole replay into a [`Replay`]
+//! - [`writer`] - Writing Classic replay files with [`ReplayWriter`]
+//! - [`anonymize`] - Replacing player identities with salted pseudonyms
+//! - [`trim`] - Cutting a replay to a window of game time
+//! - [`compare`] - Checking two perspectives of one game against each other
+//! - [`batch`] - Finding replays in directories and archives for batch runs
+//! - [`export`] - Player CSV and Arrow action tables for analytics
+//! - `sqlite` - Normalised `SQLite` database of replays (requires the `sqlite` feature)
+//! - [`cache`] - Reusing batch results for replays that have not changed
+//! - [`inspect`] - Record tree with byte ranges for reverse engineering
+//! - [`census`] - Grouping the unknown actions of many replays by pattern
+//! - [`report`] - Self-contained HTML match reports
+//!
+//! ## Cargo Features
+//!
+//! - `serde` (default) - Derives `Serialize`/`Deserialize` for all public model
+//!   types. Enums carrying records (`Header`, `PlayerRecord`, `ActionType`) are
+//!   internally tagged with a `"type"` field holding the variant name.
+//! - `archives` - Reads replays from `.zip`, `.tar` and `.tar.gz` archives in
+//!   [`batch`] input.
+//! - `arrow` - Writes action tables as Arrow IPC files in [`export`].
+//! - `sqlite` - Writes replays into a `SQLite` database with the `sqlite`
+//!   module, using a bundled `SQLite`.
+//! - `tui` - Builds the CLI's interactive `inspect` command.
+//! - `cli` - Enables all of the above for the full `w3g-parser` binary, which
+//!   itself only requires `serde`.
 //!
 //! ## Format Reference
 //!
@@ -63,24 +90,46 @@
 #![allow(clippy::module_name_repetitions)]
 
 pub mod actions;
+pub mod anonymize;
+pub mod batch;
 pub mod binary;
+pub mod cache;
+pub mod census;
+pub mod clock;
+pub mod compare;
 pub mod decompress;
+pub mod diagnostic;
 pub mod error;
+pub mod export;
 pub mod format;
 pub mod header;
+pub mod inspect;
 pub mod records;
+pub mod replay;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod trim;
+pub mod writer;
 
 // Re-export commonly used types at the crate root
 pub use actions::{
-    AbilityAction, AbilityCode, Action, ActionContext, ActionIterator, ActionStatistics,
-    ActionType, HotkeyAction, HotkeyOperation, MovementAction, Position, SelectionAction,
+    AbilityCode, Action, ActionContext, ActionFilter, ActionIterator, ActionStatistics,
+    ActionType, FilterError, HotkeyAction, HotkeyOperation, Position, SelectionAction,
     SelectionMode,
 };
+pub use anonymize::{anonymize, AnonymizeOptions, ChatPolicy};
+pub use clock::GameClock;
+pub use compare::{compare, ReplayComparison};
 pub use decompress::decompress;
+pub use diagnostic::{Diagnostic, ParseMode, Severity};
 pub use error::{ParserError, Result};
 pub use format::{detect_format, ClassicVersion, ReplayFormat};
 pub use header::Header;
 pub use records::{
-    ChatMessage, GameRecord, GameRecordHeader, PlayerRoster, TimeFrame, TimeFrameIterator,
-    CHAT_MARKER,
+    ChatMessage, ChecksumSeries, DesyncReport, GameRecord, GameRecordHeader, PlayerRoster,
+    TimeFrame, TimeFrameIterator, CHAT_MARKER,
 };
+pub use replay::{ParseOptions, Replay, GAME_ID_FRAMES};
+pub use trim::{trim, LeadIn, TrimOptions};
+pub use writer::ReplayWriter;
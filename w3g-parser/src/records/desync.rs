@@ -0,0 +1,262 @@
+//! Checksum series and desync analysis for decompressed W3G replay data.
+//!
+//! Every `TimeFrame` is normally followed by a checksum record (0x22) holding a
+//! hash of the game state at that point. When a client detects that its state
+//! diverged from the other clients, desync records (0x23) are written as well.
+//!
+//! This module collects both into analysis structures:
+//!
+//! - [`ChecksumSeries`]: the checksum of every `TimeFrame`, in order
+//! - [`DesyncReport`]: all desync records with the game time they occurred at
+//!
+//! # Example
+//!
+//! ```ignore
+//! use w3g_parser::records::{DesyncReport, GameRecord};
+//!
+//! let game = GameRecord::parse(&decompressed)?;
+//! let report = DesyncReport::from_iterator(game.timeframes(&decompressed))?;
+//!
+//! if let Some(time) = report.first_divergence_string() {
+//!     println!("Game desynced at {time}");
+//! }
+//! ```
+
+use super::timeframe::{DesyncRecord, TimeFrameIterator};
+use crate::error::Result;
+
+/// The checksum of a single `TimeFrame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChecksumPoint {
+    /// Index of the `TimeFrame` (0-based) the checksum belongs to.
+    pub frame_index: usize,
+
+    /// Accumulated game time of the `TimeFrame` in milliseconds.
+    pub time_ms: u32,
+
+    /// Checksum value.
+    pub checksum: u32,
+}
+
+/// Per-frame series of game state checksums.
+///
+/// Two replays of the same game recorded by different players should have
+/// identical series. The first differing point marks where the games diverged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChecksumSeries {
+    /// Checksum points in stream order.
+    points: Vec<ChecksumPoint>,
+}
+
+impl ChecksumSeries {
+    /// Collects the checksum series from an iterator of `TimeFrame` records.
+    ///
+    /// Frames without a trailing checksum record are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any `TimeFrame` in the iterator fails to parse.
+    pub fn from_iterator(iter: TimeFrameIterator<'_>) -> Result<Self> {
+        Ok(DesyncReport::from_iterator(iter)?.checksums)
+    }
+
+    /// Returns the number of checksums in the series.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns whether the series is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns a slice of all checksum points.
+    #[must_use]
+    pub fn as_slice(&self) -> &[ChecksumPoint] {
+        &self.points
+    }
+
+    /// Returns an iterator over all checksum points.
+    pub fn iter(&self) -> impl Iterator<Item = &ChecksumPoint> {
+        self.points.iter()
+    }
+
+    /// Returns the checksum of the last `TimeFrame` at or before `time_ms`.
+    #[must_use]
+    pub fn checksum_at(&self, time_ms: u32) -> Option<u32> {
+        let index = self.points.partition_point(|p| p.time_ms <= time_ms);
+        index.checked_sub(1).map(|i| self.points[i].checksum)
+    }
+
+    /// Finds the first point where this series differs from another.
+    ///
+    /// Points are compared in order. Returns the point of `self` at the first
+    /// mismatch, or `None` if the overlapping parts of both series agree.
+    #[must_use]
+    pub fn first_mismatch(&self, other: &ChecksumSeries) -> Option<ChecksumPoint> {
+        self.points
+            .iter()
+            .zip(&other.points)
+            .find(|(a, b)| a.checksum != b.checksum)
+            .map(|(a, _)| *a)
+    }
+}
+
+/// A desync record together with where in the game it occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesyncEvent {
+    /// Index of the `TimeFrame` (0-based) the record followed.
+    pub frame_index: usize,
+
+    /// Accumulated game time in milliseconds when the desync was recorded.
+    pub time_ms: u32,
+
+    /// The raw desync record.
+    pub record: DesyncRecord,
+}
+
+/// Summary of out-of-sync records found in a replay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesyncReport {
+    /// All desync events in stream order.
+    pub events: Vec<DesyncEvent>,
+
+    /// Per-frame checksum series.
+    pub checksums: ChecksumSeries,
+
+    /// Total number of `TimeFrame` records inspected.
+    pub frame_count: usize,
+}
+
+impl DesyncReport {
+    /// Builds a desync report from an iterator of `TimeFrame` records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any `TimeFrame` in the iterator fails to parse.
+    pub fn from_iterator(iter: TimeFrameIterator<'_>) -> Result<Self> {
+        let mut report = DesyncReport::default();
+
+        for (frame_index, result) in iter.enumerate() {
+            let frame = result?;
+            report.frame_count += 1;
+
+            if let Some(record) = frame.checksum {
+                report.checksums.points.push(ChecksumPoint {
+                    frame_index,
+                    time_ms: frame.accumulated_time_ms,
+                    checksum: record.checksum,
+                });
+            }
+
+            for record in &frame.desync_records {
+                report.events.push(DesyncEvent {
+                    frame_index,
+                    time_ms: frame.accumulated_time_ms,
+                    record: *record,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Returns whether the replay contains any desync records.
+    #[must_use]
+    pub fn has_desync(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// Returns the game time in milliseconds of the first desync record.
+    #[must_use]
+    pub fn first_divergence_ms(&self) -> Option<u32> {
+        self.events.first().map(|e| e.time_ms)
+    }
+
+    /// Returns the game time of the first desync record formatted as MM:SS.
+    #[must_use]
+    pub fn first_divergence_string(&self) -> Option<String> {
+        self.first_divergence_ms().map(|ms| {
+            let total_seconds = ms / 1000;
+            format!("{}:{:02}", total_seconds / 60, total_seconds % 60)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::timeframe::{CHECKSUM_MARKER, DESYNC_MARKER, TIMEFRAME_MARKER_1F};
+
+    /// Builds a `TimeFrame` stream with one checksum per frame and an optional desync.
+    fn create_stream(checksums: &[u32], desync_after: Option<usize>) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        for (i, checksum) in checksums.iter().enumerate() {
+            data.push(TIMEFRAME_MARKER_1F);
+            data.extend_from_slice(&[0x02, 0x00, 0xE8, 0x03]); // 1000ms
+            data.extend_from_slice(&[CHECKSUM_MARKER, 0x04]);
+            data.extend_from_slice(&checksum.to_le_bytes());
+
+            if desync_after == Some(i) {
+                data.push(DESYNC_MARKER);
+                data.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00]);
+                data.extend_from_slice(&checksum.to_le_bytes());
+                data.push(0x00);
+            }
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_checksum_series() {
+        let data = create_stream(&[1, 2, 3], None);
+        let series = ChecksumSeries::from_iterator(TimeFrameIterator::new(&data, 0)).unwrap();
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.as_slice()[1].time_ms, 2000);
+        assert_eq!(series.checksum_at(2500), Some(2));
+        assert_eq!(series.checksum_at(500), None);
+    }
+
+    #[test]
+    fn test_checksum_series_first_mismatch() {
+        let a_data = create_stream(&[1, 2, 3, 4], None);
+        let b_data = create_stream(&[1, 2, 9, 4], None);
+        let a = ChecksumSeries::from_iterator(TimeFrameIterator::new(&a_data, 0)).unwrap();
+        let b = ChecksumSeries::from_iterator(TimeFrameIterator::new(&b_data, 0)).unwrap();
+
+        let mismatch = a.first_mismatch(&b).unwrap();
+        assert_eq!(mismatch.frame_index, 2);
+        assert_eq!(mismatch.time_ms, 3000);
+        assert!(a.first_mismatch(&a).is_none());
+    }
+
+    #[test]
+    fn test_desync_report() {
+        let data = create_stream(&[1, 2, 3, 4], Some(2));
+        let report = DesyncReport::from_iterator(TimeFrameIterator::new(&data, 0)).unwrap();
+
+        assert!(report.has_desync());
+        assert_eq!(report.frame_count, 4);
+        assert_eq!(report.checksums.len(), 4);
+        assert_eq!(report.first_divergence_ms(), Some(3000));
+        assert_eq!(report.first_divergence_string().as_deref(), Some("0:03"));
+    }
+
+    #[test]
+    fn test_desync_report_clean() {
+        let data = create_stream(&[1, 2], None);
+        let report = DesyncReport::from_iterator(TimeFrameIterator::new(&data, 0)).unwrap();
+
+        assert!(!report.has_desync());
+        assert_eq!(report.first_divergence_ms(), None);
+    }
+}
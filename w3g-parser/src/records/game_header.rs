@@ -29,18 +29,27 @@
 //! println!("Host: {} (slot {})", header.host_name, header.host_slot);
 //! ```
 
+use std::ops::Range;
+
 use crate::binary::{read_string, read_u32_le};
 use crate::error::{ParserError, Result};
 
 /// Magic value for the game record header (0x10 0x01 0x00 0x00 as little-endian u32).
 pub const GAME_RECORD_MAGIC: u32 = 0x0000_0110;
 
+/// Size of the game settings flags at the start of the decoded settings string.
+const SETTINGS_FLAGS_SIZE: usize = 13;
+
+/// Size of the player count, game type and language fields after the settings string.
+const SETTINGS_TRAILER_SIZE: usize = 12;
+
 /// The initial game record that appears at the start of decompressed data.
 ///
 /// This contains the host player information and encoded game settings.
 /// The header is followed by player slot records (0x16 marker) and then
 /// the action stream.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameRecordHeader {
     /// Record type marker (always 0x00000110).
     pub record_type: u32,
@@ -139,6 +148,22 @@ impl GameRecordHeader {
         })
     }
 
+    /// Encodes this header in the layout read by [`GameRecordHeader::parse`].
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.byte_length);
+        out.extend_from_slice(&self.record_type.to_le_bytes());
+        out.push(self.unknown_1);
+        out.push(self.host_slot);
+        out.extend_from_slice(self.host_name.as_bytes());
+        out.push(0);
+        out.push(self.host_flags);
+        out.extend_from_slice(self.additional_data.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&self.encoded_settings);
+        out
+    }
+
     /// Returns whether this is a valid game record header.
     #[must_use]
     pub fn is_valid(&self) -> bool {
@@ -266,6 +291,137 @@ impl GameRecordHeader {
         // Variant B or fallback
         12.min(self.encoded_settings.len())
     }
+
+    /// Locates the encoded settings string within `encoded_settings`.
+    ///
+    /// The string is the last null-terminated run before the trailing
+    /// player count, game type and language fields (3 x `u32`).
+    pub(crate) fn settings_string_range(&self) -> Option<Range<usize>> {
+        let end = self.encoded_settings.len().checked_sub(SETTINGS_TRAILER_SIZE + 1)?;
+        if self.encoded_settings[end] != 0 {
+            return None;
+        }
+        let start = self.encoded_settings[..end]
+            .iter()
+            .rposition(|&b| b == 0)
+            .map_or(0, |i| i + 1);
+        (start < end).then_some(start..end)
+    }
+
+    /// Returns the decoded settings string.
+    ///
+    /// The decoded string holds 13 bytes of game settings, the map path, the
+    /// creator name and the map checksum. Returns `None` if the string cannot be
+    /// located.
+    #[must_use]
+    pub fn decoded_settings(&self) -> Option<Vec<u8>> {
+        let range = self.settings_string_range()?;
+        Some(decode_settings_string(&self.encoded_settings[range]))
+    }
+
+    /// Replaces the settings string with the encoding of `decoded`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::InvalidHeader` if the settings string cannot be located.
+    pub fn set_decoded_settings(&mut self, decoded: &[u8]) -> Result<()> {
+        let range = self.settings_string_range().ok_or_else(|| ParserError::InvalidHeader {
+            reason: "encoded settings string not found".to_string(),
+        })?;
+        let encoded = encode_settings_string(decoded);
+        self.byte_length = (self.byte_length + encoded.len()).saturating_sub(range.len());
+        self.encoded_settings.splice(range, encoded);
+        Ok(())
+    }
+
+    /// Returns the map path from the decoded settings string.
+    ///
+    /// Unlike [`map_path_raw`](Self::map_path_raw) this is the exact path, such
+    /// as `Maps\(2)EchoIsles.w3x`.
+    #[must_use]
+    pub fn map_path(&self) -> Option<String> {
+        let decoded = self.decoded_settings()?;
+        let path = decoded.get(SETTINGS_FLAGS_SIZE..)?.split(|&b| b == 0).next()?;
+        (!path.is_empty()).then(|| String::from_utf8_lossy(path).into_owned())
+    }
+
+    /// Returns the name of the player or bot that created the game.
+    ///
+    /// This is read from the decoded settings string and may differ from the
+    /// host name, for example for games hosted by a bot.
+    #[must_use]
+    pub fn creator_name(&self) -> Option<String> {
+        let decoded = self.decoded_settings()?;
+        let creator = decoded.get(SETTINGS_FLAGS_SIZE..)?.split(|&b| b == 0).nth(1)?;
+        Some(String::from_utf8_lossy(creator).into_owned())
+    }
+
+    /// Replaces the creator name in the settings string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::InvalidHeader` if the settings string cannot be
+    /// located or has no creator name field.
+    pub fn set_creator_name(&mut self, name: &str) -> Result<()> {
+        let missing = || ParserError::InvalidHeader {
+            reason: "creator name not found in settings string".to_string(),
+        };
+        let mut decoded = self.decoded_settings().ok_or_else(missing)?;
+        let fields = decoded.get(SETTINGS_FLAGS_SIZE..).ok_or_else(missing)?;
+        let map_end = fields.iter().position(|&b| b == 0).ok_or_else(missing)?;
+        let creator_start = SETTINGS_FLAGS_SIZE + map_end + 1;
+        let creator_len = decoded[creator_start..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(missing)?;
+
+        decoded.splice(creator_start..creator_start + creator_len, name.bytes());
+        self.set_decoded_settings(&decoded)
+    }
+}
+
+/// Decodes a settings string.
+///
+/// Every 8th byte is a mask for the following 7 bytes: a byte whose mask bit
+/// is clear was stored incremented by one, so the encoding contains no zeros.
+#[must_use]
+pub fn decode_settings_string(encoded: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    for block in encoded.chunks(8) {
+        let mask = block[0];
+        for (i, &byte) in block.iter().enumerate().skip(1) {
+            if mask & (1 << i) == 0 {
+                decoded.push(byte.wrapping_sub(1));
+            } else {
+                decoded.push(byte);
+            }
+        }
+    }
+    decoded
+}
+
+/// Encodes a settings string, the inverse of [`decode_settings_string`].
+///
+/// Even bytes are stored incremented by one with their mask bit clear; odd
+/// bytes are stored as-is with their mask bit set.
+#[must_use]
+pub fn encode_settings_string(decoded: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(decoded.len() + decoded.len() / 7 + 1);
+    for block in decoded.chunks(7) {
+        let mut mask = 1u8;
+        let mask_at = encoded.len();
+        encoded.push(0);
+        for (i, &byte) in block.iter().enumerate() {
+            if byte % 2 == 0 {
+                encoded.push(byte + 1);
+            } else {
+                mask |= 1 << (i + 1);
+                encoded.push(byte);
+            }
+        }
+        encoded[mask_at] = mask;
+    }
+    encoded
 }
 
 /// Finds the boundary where encoded settings end and player records begin.
@@ -513,4 +669,42 @@ mod tests {
         let boundary = find_settings_boundary(&data, 0);
         assert_eq!(boundary, data.len());
     }
+
+    #[test]
+    fn test_settings_string_round_trip() {
+        let decoded: Vec<u8> = (0..=255).collect();
+        let encoded = encode_settings_string(&decoded);
+        assert!(!encoded.contains(&0));
+        assert_eq!(decode_settings_string(&encoded), decoded);
+    }
+
+    #[test]
+    fn test_creator_name() {
+        let mut decoded = vec![0x02; SETTINGS_FLAGS_SIZE];
+        decoded.extend_from_slice(b"Maps\\(2)EchoIsles.w3x\0Karen\0\0");
+
+        let mut settings = b"rich\0\0".to_vec();
+        settings.extend(encode_settings_string(&decoded));
+        settings.push(0);
+        settings.extend_from_slice(&[0x0C, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut header = GameRecordHeader {
+            record_type: GAME_RECORD_MAGIC,
+            unknown_1: 0,
+            host_slot: 1,
+            host_name: "kaiseris".to_string(),
+            host_flags: 1,
+            additional_data: String::new(),
+            byte_length: 0,
+            encoded_settings: settings,
+        };
+        assert_eq!(header.decoded_settings(), Some(decoded.clone()));
+        assert_eq!(header.creator_name().as_deref(), Some("Karen"));
+        assert_eq!(header.map_path().as_deref(), Some("Maps\\(2)EchoIsles.w3x"));
+
+        header.set_creator_name("Player-1").unwrap();
+        assert_eq!(header.creator_name().as_deref(), Some("Player-1"));
+        assert!(header.encoded_settings.starts_with(b"rich\0\0"));
+        assert_eq!(header.map_path().as_deref(), Some("Maps\\(2)EchoIsles.w3x"));
+    }
 }
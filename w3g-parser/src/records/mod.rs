@@ -5,9 +5,12 @@
 //!
 //! - **Game Record Header**: Initial record with host player info and game settings
 //! - **Player Slot Records**: Player names and slot assignments
+//! - **Slot Table**: Lobby teams, races and the random seed (see [`slot_table`])
 //! - **`TimeFrame` Records**: Game actions with timestamps
 //! - **Checksum Records**: Game state verification
+//! - **Desync Records**: Out-of-sync detection (see [`desync`] for analysis)
 //! - **Chat Messages**: In-game chat
+//! - **Record Stream**: Exact, length-framed records for lossless rewriting (see [`stream`])
 //!
 //! # Record Structure Overview
 //!
@@ -25,6 +28,7 @@
 //! 3. **Action Stream**
 //!    - `TimeFrame` records (0x1F or 0x1E markers) with time deltas and action data
 //!    - Checksum records (0x22 0x04) for state verification
+//!    - Desync records (0x23) when clients went out of sync
 //!    - Chat messages (0x20) and leave records (0x17)
 //!
 //! # Example
@@ -57,18 +61,24 @@
 //! }
 //! ```
 
+pub mod desync;
 pub mod game_header;
 pub mod player;
+pub mod slot_table;
+pub mod stream;
 pub mod timeframe;
 
+pub use desync::{ChecksumPoint, ChecksumSeries, DesyncEvent, DesyncReport};
 pub use game_header::{GameRecordHeader, GAME_RECORD_MAGIC};
 pub use player::{
     PlayerRecord, PlayerRoster, PlayerSlot, SlotRecord, PLAYER_SLOT_MARKER, SLOT_RECORD_MARKER,
 };
+pub use slot_table::{Slot, SlotStatus, SlotTable};
+pub use stream::{Record, RecordStream, GAME_START_MARKERS};
 pub use timeframe::{
-    find_timeframe_start, ChatMessage, ChecksumRecord, LeaveRecord, TimeFrame, TimeFrameIterator,
-    TimeFrameStats, CHAT_MARKER, CHECKSUM_MARKER, LEAVE_MARKER, TIMEFRAME_MARKER_1E,
-    TIMEFRAME_MARKER_1F,
+    find_chat_messages, find_timeframe_start, ChatMessage, ChecksumRecord, DesyncRecord, LeaveRecord, TimeFrame,
+    TimeFrameIterator, TimeFrameStats, CHAT_MARKER, CHECKSUM_MARKER, DESYNC_MARKER, LEAVE_MARKER,
+    TIMEFRAME_HEADER_SIZE, TIMEFRAME_MARKER_1E, TIMEFRAME_MARKER_1F,
 };
 
 use crate::error::{ParserError, Result};
@@ -117,6 +127,7 @@ pub fn find_game_record_start(data: &[u8]) -> Result<usize> {
 /// This struct provides a unified view of the game record header, player roster,
 /// and access to the `TimeFrame` action stream.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameRecord {
     /// Game record header with host player info.
     pub header: GameRecordHeader,
@@ -168,10 +179,15 @@ impl GameRecord {
         let players = PlayerRoster::parse(&game_data[header_end..])?;
         let players_end = header_end + players.byte_length;
 
-        // Find where TimeFrames actually begin
-        // The TimeFrame offset is after players, but there may be additional
-        // padding or game start records
-        let timeframe_offset = find_timeframe_start(game_data, players_end).unwrap_or(game_data.len());
+        // Find where TimeFrames actually begin by framing the records after the
+        // header (player list, slot table, game start records). If they do not
+        // frame, fall back to the first TimeFrame marker after the players.
+        let timeframe_offset = RecordStream::new(game_data, header_end)
+            .map_while(Result::ok)
+            .find(|(_, record)| matches!(record, Record::TimeFrame { .. }))
+            .map(|(offset, _)| offset)
+            .or_else(|| find_timeframe_start(game_data, players_end))
+            .unwrap_or(game_data.len());
 
         // Adjust offset to be relative to original data
         let timeframe_offset = start_offset + timeframe_offset;
@@ -215,6 +231,18 @@ impl GameRecord {
         self.players.player_names()
     }
 
+    /// Encodes the game record header followed by the player records.
+    ///
+    /// Only the parsed records are written: any protobuf metadata before the
+    /// game record and the bytes between the last player record and
+    /// [`GameRecord::timeframe_offset`] are not part of the model.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.header.encode();
+        out.extend(self.players.encode());
+        out
+    }
+
     /// Returns the number of players in the game.
     #[must_use]
     pub fn player_count(&self) -> usize {
@@ -258,15 +286,15 @@ mod tests {
 
         // TimeFrame
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]); // Length
         data.extend_from_slice(&[0x64, 0x00]); // 100ms
-        data.extend_from_slice(&[0x00, 0x00]); // Length hint
         data.push(CHECKSUM_MARKER);
         data.extend_from_slice(&[0x04, 0x00, 0x00, 0x00, 0x00]);
 
         // Another TimeFrame
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x32, 0x00]); // 50ms
-        data.extend_from_slice(&[0x00, 0x00]);
 
         data
     }
@@ -323,7 +351,7 @@ mod tests {
 
         // Immediately TimeFrame (no player slots)
         data.push(TIMEFRAME_MARKER_1F);
-        data.extend_from_slice(&[0x10, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0x02, 0x00, 0x10, 0x00]);
 
         let record = GameRecord::parse(&data).unwrap();
 
@@ -42,6 +42,7 @@ pub const SLOT_RECORD_MARKER: u8 = 0x19;
 /// These records appear after the game header and define all players
 /// participating in the game.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerSlot {
     /// Slot number (typically 1-24 for larger games).
     pub slot_id: u8,
@@ -70,7 +71,7 @@ impl PlayerSlot {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x16
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x16
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.is_empty() {
@@ -79,12 +80,11 @@ impl PlayerSlot {
 
         // Validate marker byte
         if data[0] != PLAYER_SLOT_MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid player slot marker: expected 0x{PLAYER_SLOT_MARKER:02X}, found 0x{:02X}",
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{PLAYER_SLOT_MARKER:02X}"),
+            ));
         }
 
         if data.len() < 3 {
@@ -114,6 +114,12 @@ impl PlayerSlot {
         })
     }
 
+    /// Encodes this record in the layout read by [`PlayerSlot::parse`].
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        encode_record(PLAYER_SLOT_MARKER, self.slot_id, &self.player_name, &self.trailing_data)
+    }
+
     /// Returns whether this appears to be a valid player slot.
     #[must_use]
     pub fn is_valid(&self) -> bool {
@@ -125,6 +131,7 @@ impl PlayerSlot {
 ///
 /// Some replays use this format for additional player information.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlotRecord {
     /// Slot number.
     pub slot_id: u8,
@@ -152,7 +159,7 @@ impl SlotRecord {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x19
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x19
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.is_empty() {
@@ -161,12 +168,11 @@ impl SlotRecord {
 
         // Validate marker byte
         if data[0] != SLOT_RECORD_MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid slot record marker: expected 0x{SLOT_RECORD_MARKER:02X}, found 0x{:02X}",
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{SLOT_RECORD_MARKER:02X}"),
+            ));
         }
 
         if data.len() < 3 {
@@ -195,10 +201,18 @@ impl SlotRecord {
             byte_length: additional_end,
         })
     }
+
+    /// Encodes this record in the layout read by [`SlotRecord::parse`].
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        encode_record(SLOT_RECORD_MARKER, self.slot_id, &self.player_name, &self.additional_data)
+    }
 }
 
 /// Unified player record that can be either format.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
 pub enum PlayerRecord {
     /// Standard player slot (0x16).
     PlayerSlot(PlayerSlot),
@@ -226,6 +240,15 @@ impl PlayerRecord {
         }
     }
 
+    /// Encodes this record in the layout it was parsed from.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            PlayerRecord::PlayerSlot(p) => p.encode(),
+            PlayerRecord::SlotRecord(s) => s.encode(),
+        }
+    }
+
     /// Returns the byte length of this record.
     #[must_use]
     pub fn byte_length(&self) -> usize {
@@ -238,6 +261,7 @@ impl PlayerRecord {
 
 /// Collection of all players in a game.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlayerRoster {
     /// All player records found.
     players: Vec<PlayerRecord>,
@@ -269,14 +293,16 @@ impl PlayerRoster {
         let mut offset = 0;
 
         while offset < data.len() {
+            let path = format!("player record #{}", players.len() + 1);
+            let in_record = |e: ParserError| e.with_context(offset, &path);
             match data[offset] {
                 PLAYER_SLOT_MARKER => {
-                    let slot = PlayerSlot::parse(&data[offset..])?;
+                    let slot = PlayerSlot::parse(&data[offset..]).map_err(in_record)?;
                     offset += slot.byte_length;
                     players.push(PlayerRecord::PlayerSlot(slot));
                 }
                 SLOT_RECORD_MARKER => {
-                    let record = SlotRecord::parse(&data[offset..])?;
+                    let record = SlotRecord::parse(&data[offset..]).map_err(in_record)?;
                     offset += record.byte_length;
                     players.push(PlayerRecord::SlotRecord(record));
                 }
@@ -302,6 +328,15 @@ impl PlayerRoster {
         })
     }
 
+    /// Encodes all player records, in order.
+    ///
+    /// Padding bytes between records and the extended metadata section are not
+    /// part of the roster and are not written.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        self.players.iter().flat_map(PlayerRecord::encode).collect()
+    }
+
     /// Returns the number of players in the roster.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -386,6 +421,17 @@ fn find_extended_metadata_end(data: &[u8], start: usize) -> usize {
     start
 }
 
+/// Encodes a player or slot record: marker, slot ID, null-terminated name and trailing data.
+fn encode_record(marker: u8, slot_id: u8, name: &str, trailing: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(3 + name.len() + trailing.len());
+    out.push(marker);
+    out.push(slot_id);
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(trailing);
+    out
+}
+
 /// Finds the end of trailing data for a player record.
 ///
 /// In W3G format, trailing data is typically 7 bytes after the null-terminated name:
@@ -464,7 +510,7 @@ mod tests {
     fn test_player_slot_invalid_marker() {
         let data = [0x17, 0x04, b'T', b'e', b's', b't', 0x00];
         let result = PlayerSlot::parse(&data);
-        assert!(matches!(result, Err(ParserError::InvalidHeader { .. })));
+        assert!(matches!(result, Err(ParserError::UnexpectedRecord { .. })));
     }
 
     #[test]
@@ -0,0 +1,285 @@
+//! Slot table record parsing.
+//!
+//! The slot table (0x19) follows the player list and describes the lobby:
+//! one entry per slot, then the game's random seed, the select mode and the
+//! number of start spots. [`Record::SlotTable`](super::Record::SlotTable)
+//! carries the record body after its `u16` length:
+//!
+//! | Size | Field |
+//! |------|-------|
+//! | 1 | Slot count |
+//! | 9 × count | Slots |
+//! | 4 | Random seed |
+//! | 1 | Select mode |
+//! | 1 | Start spot count |
+//!
+//! Each slot is laid out as:
+//!
+//! | Offset | Field |
+//! |--------|-------|
+//! | 0 | Player ID (0 for computer and empty slots) |
+//! | 1 | Map download percent (0xFF for the Reforged observer slot) |
+//! | 2 | Status: 0 open, 1 closed, 2 used |
+//! | 3 | Computer flag |
+//! | 4 | Team (the slot count for observers) |
+//! | 5 | Color |
+//! | 6 | Race flags |
+//! | 7 | AI strength |
+//! | 8 | Handicap percent |
+//!
+//! Replays from before patch 1.07 have shorter slots without the AI strength
+//! and handicap; the slot size is derived from the record length.
+
+use crate::actions::Race;
+use crate::binary::read_u32_le;
+use crate::error::{ParserError, Result};
+
+/// Size of a slot entry in current replays.
+pub const SLOT_SIZE: usize = 9;
+
+/// Smallest slot entry size, used by the earliest replays.
+const MIN_SLOT_SIZE: usize = 7;
+
+/// Bytes after the slots: random seed, select mode and start spot count.
+const TRAILER_SIZE: usize = 6;
+
+/// Race flag marking a random race pick.
+const RACE_FLAG_RANDOM: u8 = 0x20;
+
+/// Status of a lobby slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SlotStatus {
+    /// Open slot without a player.
+    Open,
+    /// Closed slot.
+    Closed,
+    /// Slot taken by a player or computer.
+    Used,
+    /// A status byte not defined by the format.
+    Unknown(u8),
+}
+
+impl SlotStatus {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => SlotStatus::Open,
+            1 => SlotStatus::Closed,
+            2 => SlotStatus::Used,
+            other => SlotStatus::Unknown(other),
+        }
+    }
+}
+
+/// A single slot of the lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Slot {
+    /// Player ID of the slot's occupant (0 for computer and empty slots).
+    pub player_id: u8,
+
+    /// Map download progress in percent.
+    pub download_percent: u8,
+
+    /// Slot status.
+    pub status: SlotStatus,
+
+    /// Whether the slot is taken by a computer player.
+    pub is_computer: bool,
+
+    /// Team number; observers are on the team equal to the slot count.
+    pub team: u8,
+
+    /// Player color.
+    pub color: u8,
+
+    /// Raw race flags (0x01 Human, 0x02 Orc, 0x04 Night Elf, 0x08 Undead,
+    /// 0x20 random, 0x40 selectable).
+    pub race_flags: u8,
+
+    /// Computer difficulty (missing before patch 1.07).
+    pub ai_strength: Option<u8>,
+
+    /// Handicap in percent (missing before patch 1.07).
+    pub handicap: Option<u8>,
+}
+
+impl Slot {
+    /// Parses a slot entry of `MIN_SLOT_SIZE` or more bytes.
+    fn parse(data: &[u8]) -> Self {
+        Self {
+            player_id: data[0],
+            download_percent: data[1],
+            status: SlotStatus::from_byte(data[2]),
+            is_computer: data[3] != 0,
+            team: data[4],
+            color: data[5],
+            race_flags: data[6],
+            ai_strength: data.get(7).copied(),
+            handicap: data.get(8).copied(),
+        }
+    }
+
+    /// Returns the race chosen in the lobby.
+    ///
+    /// Returns `None` for random picks, whose race is only decided when the
+    /// game starts, and for slots without a race.
+    #[must_use]
+    pub fn race(&self) -> Option<Race> {
+        if self.is_random_race() {
+            return None;
+        }
+        match self.race_flags & 0x0F {
+            0x01 => Some(Race::Human),
+            0x02 => Some(Race::Orc),
+            0x04 => Some(Race::NightElf),
+            0x08 => Some(Race::Undead),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the race was picked at random.
+    #[must_use]
+    pub fn is_random_race(&self) -> bool {
+        self.race_flags & RACE_FLAG_RANDOM != 0
+    }
+
+    /// Returns whether a human player occupies the slot.
+    #[must_use]
+    pub fn is_player(&self) -> bool {
+        self.status == SlotStatus::Used && !self.is_computer
+    }
+}
+
+/// The parsed slot table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlotTable {
+    /// Lobby slots in order.
+    pub slots: Vec<Slot>,
+
+    /// Random seed of the game.
+    pub random_seed: u32,
+
+    /// Team and race selection mode.
+    pub select_mode: u8,
+
+    /// Number of start spots on the map.
+    pub start_spot_count: u8,
+}
+
+impl SlotTable {
+    /// Parses the body of a slot table record.
+    ///
+    /// # Errors
+    ///
+    /// - `ParserError::UnexpectedEof` if the data is shorter than its slots
+    /// - `ParserError::UnexpectedRecord` at the first byte after the slots if
+    ///   the slot entries do not have a consistent size
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let Some(&count) = data.first() else {
+            return Err(ParserError::unexpected_eof(1, 0));
+        };
+        let count = usize::from(count);
+        let minimum = 1 + count * MIN_SLOT_SIZE + TRAILER_SIZE;
+        if data.len() < minimum {
+            return Err(ParserError::unexpected_eof(minimum, data.len()));
+        }
+
+        let slot_bytes = &data[1..data.len() - TRAILER_SIZE];
+        let slot_size = slot_bytes.len().checked_div(count).unwrap_or(SLOT_SIZE);
+        if slot_bytes.len() != count * slot_size {
+            // Bytes left over after the last slot
+            let offset = 1 + count * slot_size;
+            return Err(ParserError::unexpected_record(
+                offset,
+                data[offset],
+                format!("{count} slots of {slot_size} bytes"),
+            ));
+        }
+
+        let trailer = data.len() - TRAILER_SIZE;
+        Ok(Self {
+            slots: slot_bytes.chunks(slot_size).map(Slot::parse).collect(),
+            random_seed: read_u32_le(data, trailer)?,
+            select_mode: data[trailer + 4],
+            start_spot_count: data[trailer + 5],
+        })
+    }
+
+    /// Returns the team number used for observers.
+    #[must_use]
+    pub fn observer_team(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the slot of the human player with ID `player_id`.
+    #[must_use]
+    pub fn player_slot(&self, player_id: u8) -> Option<&Slot> {
+        self.slots
+            .iter()
+            .find(|slot| slot.is_player() && slot.player_id == player_id)
+    }
+
+    /// Returns whether `slot` is an observer slot.
+    #[must_use]
+    pub fn is_observer(&self, slot: &Slot) -> bool {
+        usize::from(slot.team) == self.observer_team()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(slots: &[[u8; SLOT_SIZE]]) -> Vec<u8> {
+        let mut data = vec![u8::try_from(slots.len()).unwrap()];
+        for slot in slots {
+            data.extend_from_slice(slot);
+        }
+        data.extend_from_slice(&0x1234_5678u32.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x02]);
+        data
+    }
+
+    #[test]
+    fn test_parse_slot_table() {
+        let data = table(&[
+            [0x01, 0x64, 0x02, 0x00, 0x00, 0x01, 0x44, 0x01, 0x64],
+            [0x02, 0x64, 0x02, 0x00, 0x01, 0x04, 0x60, 0x01, 0x64],
+            [0x00, 0x64, 0x00, 0x00, 0x02, 0x02, 0x60, 0x01, 0x64],
+        ]);
+        let table = SlotTable::parse(&data).unwrap();
+        assert_eq!(table.slots.len(), 3);
+        assert_eq!(table.random_seed, 0x1234_5678);
+        assert_eq!(table.start_spot_count, 2);
+
+        let first = table.player_slot(1).unwrap();
+        assert_eq!(first.race(), Some(Race::NightElf));
+        assert_eq!(first.handicap, Some(100));
+        let second = table.player_slot(2).unwrap();
+        assert!(second.is_random_race());
+        assert_eq!(second.race(), None);
+        assert!(table.player_slot(0).is_none());
+    }
+
+    #[test]
+    fn test_parse_short_slots_and_errors() {
+        // Pre-1.07 layout: 7-byte slots
+        let mut data = vec![1, 0x01, 0x64, 0x02, 0x00, 0x00, 0x01, 0x02];
+        data.extend_from_slice(&[0; TRAILER_SIZE]);
+        let table = SlotTable::parse(&data).unwrap();
+        assert_eq!(table.slots[0].race(), Some(Race::Orc));
+        assert_eq!(table.slots[0].handicap, None);
+
+        assert!(SlotTable::parse(&[]).is_err());
+        assert!(SlotTable::parse(&[2, 0, 0, 0]).is_err());
+
+        // Two slots in 15 bytes
+        let mut data = vec![2];
+        data.extend_from_slice(&[0; 15 + TRAILER_SIZE]);
+        let err = SlotTable::parse(&data).unwrap_err();
+        assert!(matches!(err, ParserError::UnexpectedRecord { .. }));
+        assert_eq!(err.offset(), Some(15));
+    }
+}
@@ -0,0 +1,486 @@
+//! Length-framed record stream for lossless reading and writing.
+//!
+//! The records after the game record header all carry enough information to
+//! be framed exactly, so the stream can be read and written back without
+//! losing bytes. [`TimeFrameIterator`](super::TimeFrameIterator) builds on
+//! this framing:
+//!
+//! | Marker | Record | Size |
+//! |--------|--------|------|
+//! | 0x16 | Player | 2 + name + 1 + `u8` length + data + 4 |
+//! | 0x19 | Slot table | 3 + `u16` length at offset 1 |
+//! | 0x39 | Extended metadata (Reforged) | 6 + `u32` length at offset 2 |
+//! | 0x17 | Leave | 14 bytes |
+//! | 0x1A, 0x1B, 0x1C | Game start | 5 bytes |
+//! | 0x1E, 0x1F | `TimeFrame` | 3 + `u16` length at offset 1 |
+//! | 0x20 | Chat | 4 + `u16` length at offset 2 |
+//! | 0x22 | Checksum | 2 + `u8` length at offset 1 |
+//! | 0x23 | Desync | 11 bytes |
+//!
+//! A player record holds the slot ID, the null-terminated name, a data block
+//! prefixed with its length and a trailing `u32`. Extended metadata records
+//! carry a subtype byte and a protobuf payload with `BattleTags`.
+//!
+//! A `TimeFrame` body starts with the `u16` time increment, followed by the
+//! command data. A chat body starts with a flags byte; flags 0x20 are followed
+//! by a `u32` chat mode, then the null-terminated message.
+//!
+//! [`RecordStream`] yields [`Record`]s until the zero padding at the end of
+//! the decompressed data, and [`Record::encode`] writes them back byte for
+//! byte.
+
+use std::borrow::Cow;
+
+use crate::binary::{read_bytes, read_string, read_u16_le, read_u32_le};
+use crate::error::{ParserError, Result};
+
+use super::player::{PlayerSlot, PLAYER_SLOT_MARKER, SLOT_RECORD_MARKER};
+use super::timeframe::{
+    DesyncRecord, LeaveRecord, CHAT_MARKER, CHECKSUM_MARKER, DESYNC_MARKER, LEAVE_MARKER,
+    TIMEFRAME_HEADER_SIZE, TIMEFRAME_MARKER_1E, TIMEFRAME_MARKER_1F,
+};
+
+/// Game start record markers (0x1A, 0x1B, 0x1C).
+pub const GAME_START_MARKERS: [u8; 3] = [0x1A, 0x1B, 0x1C];
+
+/// Extended metadata record marker used by Reforged replays (0x39).
+pub const EXTENDED_METADATA_MARKER: u8 = 0x39;
+
+/// Chat flags value indicating that a `u32` chat mode follows.
+pub const CHAT_FLAGS_WITH_MODE: u8 = 0x20;
+
+/// A single record from the decompressed action stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
+pub enum Record {
+    /// Player record (0x16) from the player list.
+    ///
+    /// The trailing data holds the length-prefixed data block and the `u32`
+    /// that follows it, so the record encodes back to the original bytes.
+    Player(PlayerSlot),
+
+    /// Slot table (0x19) with its raw slot, seed and start spot data.
+    SlotTable {
+        /// Record body after the `u16` length.
+        data: Vec<u8>,
+    },
+
+    /// Extended metadata record (0x39) from Reforged replays.
+    ExtendedMetadata {
+        /// Record subtype.
+        subtype: u8,
+        /// Protobuf payload.
+        data: Vec<u8>,
+    },
+
+    /// Game start record (0x1A, 0x1B or 0x1C) with its `u32` payload.
+    GameStart {
+        /// Record marker.
+        marker: u8,
+        /// Payload value (0x01 in all observed replays).
+        value: u32,
+    },
+
+    /// `TimeFrame` record (0x1E or 0x1F) with its raw command data.
+    TimeFrame {
+        /// Record marker.
+        marker: u8,
+        /// Game time elapsed since the previous `TimeFrame`, in milliseconds.
+        time_increment_ms: u16,
+        /// Command blocks for this frame.
+        commands: Vec<u8>,
+    },
+
+    /// Chat record (0x20).
+    Chat {
+        /// Sending player ID.
+        player_id: u8,
+        /// Flags byte (0x10 for startup messages, 0x20 for in-game chat).
+        flags: u8,
+        /// Chat mode (all, allies, observers, ...) when flags are 0x20.
+        mode: Option<u32>,
+        /// Raw message bytes without the null terminator.
+        message: Vec<u8>,
+    },
+
+    /// Checksum record (0x22) with its raw payload.
+    Checksum {
+        /// Checksum payload (usually a single `u32`).
+        data: Vec<u8>,
+    },
+
+    /// Desync record (0x23).
+    Desync(DesyncRecord),
+
+    /// Leave record (0x17).
+    Leave(LeaveRecord),
+}
+
+impl Record {
+    /// Parses one record from the start of `data`.
+    ///
+    /// Returns the record and the number of bytes it occupies.
+    ///
+    /// # Errors
+    ///
+    /// - `ParserError::UnexpectedRecord` if the marker byte is unknown or a
+    ///   `TimeFrame`'s length does not cover its time field
+    /// - `ParserError::UnexpectedEof` if the record is truncated
+    pub fn parse(data: &[u8]) -> Result<(Self, usize)> {
+        let Some(&marker) = data.first() else {
+            return Err(ParserError::unexpected_eof(1, 0));
+        };
+
+        match marker {
+            PLAYER_SLOT_MARKER => {
+                let player_name = read_string(data, 2, 256)?;
+                let data_len_at = 2 + player_name.len() + 1;
+                let data_len = usize::from(read_bytes(data, data_len_at, 1)?[0]);
+                let len = data_len_at + 1 + data_len + 4;
+                let body = read_bytes(data, 0, len)?;
+                let slot = PlayerSlot {
+                    slot_id: body[1],
+                    player_name,
+                    trailing_data: body[data_len_at..].to_vec(),
+                    byte_length: len,
+                };
+                Ok((Record::Player(slot), len))
+            }
+            SLOT_RECORD_MARKER => {
+                let len = 3 + usize::from(read_u16_le(data, 1)?);
+                let body = read_bytes(data, 0, len)?;
+                Ok((Record::SlotTable { data: body[3..].to_vec() }, len))
+            }
+            EXTENDED_METADATA_MARKER => {
+                let payload_len = read_u32_le(data, 2)? as usize;
+                let body = read_bytes(data, 0, 6 + payload_len)?;
+                let record = Record::ExtendedMetadata {
+                    subtype: body[1],
+                    data: body[6..].to_vec(),
+                };
+                Ok((record, body.len()))
+            }
+            TIMEFRAME_MARKER_1E | TIMEFRAME_MARKER_1F => {
+                let len = 3 + usize::from(read_u16_le(data, 1)?);
+                if len < TIMEFRAME_HEADER_SIZE {
+                    // The length does not cover the time field
+                    return Err(ParserError::unexpected_record(
+                        0,
+                        marker,
+                        "a TimeFrame of at least 5 bytes",
+                    ));
+                }
+                let body = read_bytes(data, 0, len)?;
+                let record = Record::TimeFrame {
+                    marker,
+                    time_increment_ms: u16::from_le_bytes([body[3], body[4]]),
+                    commands: body[TIMEFRAME_HEADER_SIZE..].to_vec(),
+                };
+                Ok((record, len))
+            }
+            CHAT_MARKER => {
+                let len = 4 + usize::from(read_u16_le(data, 2)?);
+                let body = read_bytes(data, 0, len)?;
+                if len < 6 {
+                    return Err(ParserError::unexpected_eof(6, len));
+                }
+                let flags = body[4];
+                let (mode, text_start) = if flags == CHAT_FLAGS_WITH_MODE {
+                    (Some(read_u32_le(body, 5)?), 9)
+                } else {
+                    (None, 5)
+                };
+                let text = body.get(text_start..len - 1).ok_or_else(|| {
+                    ParserError::unexpected_eof(text_start + 1, len)
+                })?;
+                let record = Record::Chat {
+                    player_id: body[1],
+                    flags,
+                    mode,
+                    message: text.to_vec(),
+                };
+                Ok((record, len))
+            }
+            CHECKSUM_MARKER => {
+                let len = 2 + usize::from(*data.get(1).unwrap_or(&0));
+                let body = read_bytes(data, 0, len)?;
+                Ok((Record::Checksum { data: body[2..].to_vec() }, len))
+            }
+            DESYNC_MARKER => Ok((Record::Desync(DesyncRecord::parse(data)?), DesyncRecord::SIZE)),
+            LEAVE_MARKER => Ok((Record::Leave(LeaveRecord::parse(data)?), LeaveRecord::SIZE)),
+            m if GAME_START_MARKERS.contains(&m) => {
+                let value = read_u32_le(data, 1)?;
+                Ok((Record::GameStart { marker, value }, 5))
+            }
+            _ => Err(ParserError::unexpected_record(
+                0,
+                marker,
+                "0x16, 0x17, 0x19-0x1C, 0x1E, 0x1F, 0x20, 0x22, 0x23 or 0x39",
+            )),
+        }
+    }
+
+    /// Encodes the record back into its on-disk representation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a slot table, `TimeFrame` or chat body exceeds its `u16`
+    /// length field, or a checksum payload exceeds 255 bytes.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Record::Player(slot) => out.extend(slot.encode()),
+            Record::SlotTable { data } => {
+                let len = u16::try_from(data.len()).expect("slot table too long");
+                out.push(SLOT_RECORD_MARKER);
+                out.extend_from_slice(&len.to_le_bytes());
+                out.extend_from_slice(data);
+            }
+            Record::ExtendedMetadata { subtype, data } => {
+                let len = u32::try_from(data.len()).expect("metadata record too long");
+                out.push(EXTENDED_METADATA_MARKER);
+                out.push(*subtype);
+                out.extend_from_slice(&len.to_le_bytes());
+                out.extend_from_slice(data);
+            }
+            Record::GameStart { marker, value } => {
+                out.push(*marker);
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            Record::TimeFrame {
+                marker,
+                time_increment_ms,
+                commands,
+            } => {
+                let len = u16::try_from(commands.len() + 2).expect("TimeFrame too long");
+                out.push(*marker);
+                out.extend_from_slice(&len.to_le_bytes());
+                out.extend_from_slice(&time_increment_ms.to_le_bytes());
+                out.extend_from_slice(commands);
+            }
+            Record::Chat {
+                player_id,
+                flags,
+                mode,
+                message,
+            } => {
+                let body_len = 1 + mode.map_or(0, |_| 4) + message.len() + 1;
+                let len = u16::try_from(body_len).expect("chat message too long");
+                out.push(CHAT_MARKER);
+                out.push(*player_id);
+                out.extend_from_slice(&len.to_le_bytes());
+                out.push(*flags);
+                if let Some(mode) = mode {
+                    out.extend_from_slice(&mode.to_le_bytes());
+                }
+                out.extend_from_slice(message);
+                out.push(0);
+            }
+            Record::Checksum { data } => {
+                out.push(CHECKSUM_MARKER);
+                out.push(u8::try_from(data.len()).expect("checksum payload too long"));
+                out.extend_from_slice(data);
+            }
+            Record::Desync(desync) => out.extend(desync.encode()),
+            Record::Leave(leave) => out.extend(leave.encode()),
+        }
+        out
+    }
+
+    /// Returns the record's marker byte.
+    #[must_use]
+    pub fn marker(&self) -> u8 {
+        match self {
+            Record::Player(_) => PLAYER_SLOT_MARKER,
+            Record::SlotTable { .. } => SLOT_RECORD_MARKER,
+            Record::ExtendedMetadata { .. } => EXTENDED_METADATA_MARKER,
+            Record::GameStart { marker, .. } | Record::TimeFrame { marker, .. } => *marker,
+            Record::Chat { .. } => CHAT_MARKER,
+            Record::Checksum { .. } => CHECKSUM_MARKER,
+            Record::Desync(_) => DESYNC_MARKER,
+            Record::Leave(_) => LEAVE_MARKER,
+        }
+    }
+
+    /// Returns the time increment for `TimeFrame` records and 0 otherwise.
+    #[must_use]
+    pub fn time_increment_ms(&self) -> u32 {
+        match self {
+            Record::TimeFrame {
+                time_increment_ms, ..
+            } => u32::from(*time_increment_ms),
+            _ => 0,
+        }
+    }
+
+    /// Returns the chat message as text, replacing invalid UTF-8.
+    ///
+    /// Returns `None` for records other than chat.
+    #[must_use]
+    pub fn chat_text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Record::Chat { message, .. } => Some(String::from_utf8_lossy(message)),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over length-framed records in decompressed replay data.
+///
+/// Yields `(offset, record)` pairs, where `offset` is relative to the data the
+/// stream was created with. Iteration ends at the end of the data or at the
+/// all-zero padding that fills the last block. After the first error the
+/// iterator is finished.
+///
+/// # Example
+///
+/// ```ignore
+/// use w3g_parser::records::RecordStream;
+///
+/// for result in RecordStream::new(&decompressed, game.timeframe_offset) {
+///     let (offset, record) = result?;
+///     println!("0x{offset:X}: 0x{:02X}", record.marker());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordStream<'a> {
+    data: &'a [u8],
+    offset: usize,
+    finished: bool,
+}
+
+impl<'a> RecordStream<'a> {
+    /// Creates a record stream starting at `start_offset`.
+    #[must_use]
+    pub fn new(data: &'a [u8], start_offset: usize) -> Self {
+        Self {
+            data,
+            offset: start_offset,
+            finished: false,
+        }
+    }
+
+    /// Returns the offset of the next record.
+    #[must_use]
+    pub fn current_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Iterator for RecordStream<'_> {
+    type Item = Result<(usize, Record)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let rest = self.data.get(self.offset..).unwrap_or_default();
+        if rest.iter().all(|&b| b == 0) {
+            self.finished = true;
+            return None;
+        }
+
+        let start = self.offset;
+        match Record::parse(rest) {
+            Ok((record, len)) => {
+                self.offset += len;
+                Some(Ok((start, record)))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e.with_context(start, "record stream")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> Record {
+        let (record, len) = Record::parse(bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        assert_eq!(record.encode(), bytes);
+        record
+    }
+
+    #[test]
+    fn test_timeframe_round_trip() {
+        let bytes = [0x1F, 0x05, 0x00, 0x64, 0x00, 0xAA, 0xBB, 0xCC];
+        let record = round_trip(&bytes);
+        assert_eq!(record.time_increment_ms(), 100);
+        assert!(matches!(record, Record::TimeFrame { ref commands, .. } if commands.len() == 3));
+    }
+
+    #[test]
+    fn test_chat_round_trip() {
+        let mut bytes = vec![0x20, 0x02, 0x08, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00];
+        bytes.extend_from_slice(b"gg\0");
+        let record = round_trip(&bytes);
+        assert_eq!(record.chat_text().as_deref(), Some("gg"));
+
+        let startup = [0x20, 0x01, 0x04, 0x00, 0x10, b'h', b'i', 0x00];
+        let record = round_trip(&startup);
+        assert!(matches!(record, Record::Chat { mode: None, .. }));
+    }
+
+    #[test]
+    fn test_checksum_desync_leave_and_game_start_round_trip() {
+        round_trip(&[0x22, 0x04, 0x01, 0x02, 0x03, 0x04]);
+        round_trip(&[0x1A, 0x01, 0x00, 0x00, 0x00]);
+        round_trip(&[0x23, 0x10, 0x00, 0x00, 0x00, 0x02, 0xEF, 0xBE, 0xAD, 0xDE, 0x00]);
+        round_trip(&[
+            0x17, 0x01, 0x00, 0x00, 0x00, 0x02, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn test_preamble_records_round_trip() {
+        let mut player = vec![0x16, 0x02];
+        player.extend_from_slice(b"Player1\0");
+        player.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let record = round_trip(&player);
+        assert!(matches!(record, Record::Player(ref p) if p.player_name == "Player1"));
+
+        round_trip(&[0x19, 0x03, 0x00, 0x01, 0x02, 0x03]);
+        let record = round_trip(&[0x39, 0x04, 0x02, 0x00, 0x00, 0x00, 0x08, 0x02]);
+        assert!(matches!(record, Record::ExtendedMetadata { subtype: 4, .. }));
+    }
+
+    #[test]
+    fn test_stream_stops_at_padding() {
+        let mut data = vec![0x1F, 0x02, 0x00, 0x64, 0x00, 0x22, 0x01, 0xFF];
+        data.extend_from_slice(&[0; 16]);
+        let records: Vec<_> = RecordStream::new(&data, 0)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].0, 5);
+    }
+
+    #[test]
+    fn test_stream_unknown_marker() {
+        let data = [0x1F, 0x02, 0x00, 0x64, 0x00, 0x99, 0x00];
+        let mut stream = RecordStream::new(&data, 0);
+        assert!(stream.next().unwrap().is_ok());
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(matches!(err, ParserError::UnexpectedRecord { found: 0x99, .. }));
+        assert_eq!(err.offset(), Some(5));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_stream_short_timeframe() {
+        let data = [0x1F, 0x02, 0x00, 0x64, 0x00, 0x1E, 0x01, 0x00, 0x00];
+        let mut stream = RecordStream::new(&data, 0);
+        assert!(stream.next().unwrap().is_ok());
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(matches!(err, ParserError::UnexpectedRecord { found: 0x1E, .. }));
+        assert_eq!(err.offset(), Some(5));
+        assert_eq!(err.path(), Some("record stream"));
+    }
+}
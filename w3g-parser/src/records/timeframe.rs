@@ -10,9 +10,12 @@
 //! | Offset | Size | Type | Field |
 //! |--------|------|------|-------|
 //! | 0 | 1 | u8 | Record type (0x1F or 0x1E) |
-//! | 1 | 2 | u16 LE | Time increment (milliseconds) |
-//! | 3 | 2 | u16 LE | Action data length hint |
-//! | 5 | var | bytes | Action data |
+//! | 1 | 2 | u16 LE | Length of the rest of the record |
+//! | 3 | 2 | u16 LE | Time increment (milliseconds) |
+//! | 5 | var | bytes | Action data (command blocks) |
+//!
+//! The action data is a sequence of command blocks, each holding a player ID,
+//! a `u16` length and that player's actions.
 //!
 //! # Example
 //!
@@ -32,15 +35,21 @@
 //! ```
 
 use crate::actions::{ActionContext, ActionIterator};
-use crate::binary::read_u16_le;
+use crate::binary::{read_u16_le, read_u32_le};
 use crate::error::{ParserError, Result};
 
+use super::stream::{Record, RecordStream};
+
 /// `TimeFrame` record marker (primary).
 pub const TIMEFRAME_MARKER_1F: u8 = 0x1F;
 
 /// `TimeFrame` record marker (alternate, possibly for different game modes).
 pub const TIMEFRAME_MARKER_1E: u8 = 0x1E;
 
+/// Size of a `TimeFrame` record's header: the marker, the `u16` length and
+/// the `u16` time increment. The command blocks follow it.
+pub const TIMEFRAME_HEADER_SIZE: usize = 5;
+
 /// Checksum record marker.
 pub const CHECKSUM_MARKER: u8 = 0x22;
 
@@ -50,12 +59,16 @@ pub const CHAT_MARKER: u8 = 0x20;
 /// Leave game marker.
 pub const LEAVE_MARKER: u8 = 0x17;
 
+/// Desync (out-of-sync) record marker.
+pub const DESYNC_MARKER: u8 = 0x23;
+
 /// A single `TimeFrame` record containing game actions.
 ///
 /// `TimeFrame` records represent a slice of game time and contain all player actions
 /// that occurred during that period. The time delta represents milliseconds
 /// since the previous `TimeFrame`.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeFrame {
     /// Time increment in milliseconds since the previous `TimeFrame`.
     pub time_delta_ms: u16,
@@ -65,6 +78,14 @@ pub struct TimeFrame {
 
     /// Accumulated game time in milliseconds from game start.
     pub accumulated_time_ms: u32,
+
+    /// Checksum record (0x22) immediately following this `TimeFrame`, if any.
+    pub checksum: Option<ChecksumRecord>,
+
+    /// Desync records (0x23) immediately following this `TimeFrame`.
+    ///
+    /// These are only present when the game detected an out-of-sync state.
+    pub desync_records: Vec<DesyncRecord>,
 }
 
 impl TimeFrame {
@@ -109,6 +130,7 @@ impl TimeFrame {
 ///
 /// Checksum records (0x22 0x04) contain game state verification data.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChecksumRecord {
     /// Checksum type (usually 0x04).
     pub checksum_type: u8,
@@ -133,7 +155,7 @@ impl ChecksumRecord {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x22
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x22
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.len() < Self::SIZE {
@@ -141,12 +163,11 @@ impl ChecksumRecord {
         }
 
         if data[0] != CHECKSUM_MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid checksum marker: expected 0x{CHECKSUM_MARKER:02X}, found 0x{:02X}",
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{CHECKSUM_MARKER:02X}"),
+            ));
         }
 
         let checksum_type = data[1];
@@ -157,6 +178,94 @@ impl ChecksumRecord {
             checksum,
         })
     }
+
+    /// Creates a checksum record from the payload of a framed checksum record.
+    ///
+    /// Returns `None` if the payload is shorter than a `u32`.
+    #[must_use]
+    pub fn from_payload(data: &[u8]) -> Option<Self> {
+        let checksum = data.get(..4)?;
+        Some(ChecksumRecord {
+            checksum_type: u8::try_from(data.len()).unwrap_or(u8::MAX),
+            checksum: u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]),
+        })
+    }
+}
+
+/// A desync (out-of-sync) record.
+///
+/// Desync records (0x23 marker) are written when the game detects that the
+/// game state of the local client diverged from another client. They follow
+/// the checksum record of the `TimeFrame` in which the divergence was noticed.
+///
+/// # Format
+///
+/// ```text
+/// 23 [unknown_1: u32] [unknown_2: u8] [checksum: u32] [unknown_3: u8]
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesyncRecord {
+    /// Unknown field (possibly a game tick counter).
+    pub unknown_1: u32,
+
+    /// Unknown byte.
+    pub unknown_2: u8,
+
+    /// Checksum value reported alongside the desync.
+    pub checksum: u32,
+
+    /// Unknown byte.
+    pub unknown_3: u8,
+}
+
+impl DesyncRecord {
+    /// Size of a desync record in bytes.
+    pub const SIZE: usize = 11;
+
+    /// Parses a desync record from decompressed replay data.
+    ///
+    /// # Errors
+    ///
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x23
+    /// - `ParserError::UnexpectedEof` if the data is truncated
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < Self::SIZE {
+            return Err(ParserError::unexpected_eof(Self::SIZE, data.len()));
+        }
+
+        if data[0] != DESYNC_MARKER {
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{DESYNC_MARKER:02X}"),
+            ));
+        }
+
+        let unknown_1 = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+        let unknown_2 = data[5];
+        let checksum = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+        let unknown_3 = data[10];
+
+        Ok(DesyncRecord {
+            unknown_1,
+            unknown_2,
+            checksum,
+            unknown_3,
+        })
+    }
+
+    /// Encodes the desync record, including its 0x23 marker.
+    #[must_use]
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0] = DESYNC_MARKER;
+        out[1..5].copy_from_slice(&self.unknown_1.to_le_bytes());
+        out[5] = self.unknown_2;
+        out[6..10].copy_from_slice(&self.checksum.to_le_bytes());
+        out[10] = self.unknown_3;
+        out
+    }
 }
 
 /// A chat message record.
@@ -168,6 +277,7 @@ impl ChecksumRecord {
 /// The `sender_slot` field contains the player slot ID (1-24) for player messages,
 /// or `None` for system messages (flags = 0x03).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChatMessage {
     /// Player slot ID (1-24) for player messages, None for system messages.
     ///
@@ -181,6 +291,11 @@ pub struct ChatMessage {
     /// Message ID (often correlates with message length).
     pub message_id: u16,
 
+    /// Chat mode: 0 for all players, 1 for allies, 2 for observers, and
+    /// 3 + N for a private message to player N.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mode: u32,
+
     /// The message content.
     pub message: String,
 
@@ -208,7 +323,7 @@ impl ChatMessage {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x20
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x20
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.len() < 5 {
@@ -216,38 +331,32 @@ impl ChatMessage {
         }
 
         if data[0] != CHAT_MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid chat marker: expected 0x{CHAT_MARKER:02X}, found 0x{:02X}",
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{CHAT_MARKER:02X}"),
+            ));
         }
 
         let flags = data[1];
         let message_id = read_u16_le(data, 2)?;
+        let mode = read_u32_le(data, 5).unwrap_or(0);
 
         // Chat message format (reverse engineered from replay data):
         // - Offset 0: 0x20 (marker)
         // - Offset 1: flags/sender_slot (0x03 for system, 1-24 for player slot ID)
         // - Offset 2-3: message_id (u16 little-endian)
-        // - Offset 4-8: padding (0x20 0x00 0x00 0x00 0x00)
+        // - Offset 4: 0x20 (in-game chat)
+        // - Offset 5-8: chat mode (u32 little-endian)
         // - Offset 9+: null-terminated message
         //
         // The flags byte serves dual purpose:
         // - 0x03: System message (game events, player leave notifications)
         // - 1-24: Player slot ID for player chat messages
 
-        // Determine sender_slot from flags
-        let sender_slot = if flags == 0x03 {
-            None // System message
-        } else if flags >= 1 && flags <= 24 {
-            Some(flags) // Player slot ID
-        } else {
-            None // Unknown flags value, treat as system
-        };
+        let sender_slot = sender_slot(flags);
 
-        // The message always starts at offset 9 (after the 5-byte padding block)
+        // The message always starts at offset 9 (after the chat mode)
         let msg_start = 9;
 
         // Find the message end (null terminator)
@@ -273,16 +382,45 @@ impl ChatMessage {
             sender_slot,
             flags,
             message_id,
+            mode,
             message,
             byte_length,
         })
     }
+
+    /// Builds a chat message from the fields of a framed chat record.
+    ///
+    /// `player_id` is the byte [`ChatMessage::parse`] reads as flags, and
+    /// `mode` is `None` for records without a chat mode, such as startup
+    /// messages. As with [`ChatMessage::parse`], the text ends at the first
+    /// null byte.
+    pub(crate) fn from_record(player_id: u8, mode: Option<u32>, message: &[u8]) -> Self {
+        let body_len = 1 + mode.map_or(0, |_| 4) + message.len() + 1;
+        let text = message.split(|&b| b == 0).next().unwrap_or_default();
+        ChatMessage {
+            sender_slot: sender_slot(player_id),
+            flags: player_id,
+            message_id: u16::try_from(body_len).unwrap_or(u16::MAX),
+            mode: mode.unwrap_or(0),
+            message: String::from_utf8_lossy(text).into_owned(),
+            byte_length: 4 + body_len,
+        }
+    }
+}
+
+/// Returns the sender slot encoded in a chat flags byte.
+///
+/// 0x03 marks system messages; values outside 1-24 are treated as system
+/// messages too.
+fn sender_slot(flags: u8) -> Option<u8> {
+    (flags != 0x03 && (1..=24).contains(&flags)).then_some(flags)
 }
 
 /// A leave game record.
 ///
 /// Leave game records (0x17 marker) indicate a player leaving the game.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LeaveRecord {
     /// Reason code for leaving.
     pub reason: u32,
@@ -305,7 +443,7 @@ impl LeaveRecord {
     ///
     /// # Errors
     ///
-    /// - `ParserError::InvalidHeader` if the marker byte is not 0x17
+    /// - `ParserError::UnexpectedRecord` if the marker byte is not 0x17
     /// - `ParserError::UnexpectedEof` if the data is truncated
     pub fn parse(data: &[u8]) -> Result<Self> {
         if data.len() < Self::SIZE {
@@ -313,12 +451,11 @@ impl LeaveRecord {
         }
 
         if data[0] != LEAVE_MARKER {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid leave marker: expected 0x{LEAVE_MARKER:02X}, found 0x{:02X}",
-                    data[0]
-                ),
-            });
+            return Err(ParserError::unexpected_record(
+                0,
+                data[0],
+                format!("0x{LEAVE_MARKER:02X}"),
+            ));
         }
 
         let reason = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
@@ -333,12 +470,40 @@ impl LeaveRecord {
             unknown,
         })
     }
+
+    /// Encodes the leave record, including its 0x17 marker.
+    #[must_use]
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0] = LEAVE_MARKER;
+        out[1..5].copy_from_slice(&self.reason.to_le_bytes());
+        out[5] = self.player_id;
+        out[6..10].copy_from_slice(&self.result.to_le_bytes());
+        out[10..14].copy_from_slice(&self.unknown.to_le_bytes());
+        out
+    }
+
+    /// Returns whether the reason, player ID and result codes are in their known ranges.
+    ///
+    /// Known reasons are 0x01 (connection closed by remote game), 0x07 (left
+    /// the game), 0x0B, 0x0C (connection closed by local game) and 0x0E.
+    /// Known results range from 0x01 (disconnected) to 0x0D.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        matches!(self.reason, 0x01 | 0x07 | 0x0B | 0x0C | 0x0E)
+            && (1..=24).contains(&self.player_id)
+            && (0x01..=0x0D).contains(&self.result)
+    }
 }
 
 /// Iterator over `TimeFrame` records in decompressed replay data.
 ///
-/// This iterator yields `TimeFrame` records sequentially, handling the interleaved
-/// checksum records, chat messages, and leave records automatically.
+/// The records are framed by their length fields through a [`RecordStream`],
+/// so the iterator must start at a record boundary, such as
+/// [`GameRecord::timeframe_offset`](super::GameRecord::timeframe_offset).
+/// Checksum and desync records directly following a `TimeFrame` are attached
+/// to it; other records are skipped. Skipped leave records that look valid
+/// are kept and can be retrieved with [`TimeFrameIterator::take_leave_records`].
 ///
 /// # Example
 ///
@@ -352,11 +517,14 @@ impl LeaveRecord {
 /// }
 /// ```
 pub struct TimeFrameIterator<'a> {
-    /// Reference to the decompressed data.
+    /// The decompressed data.
     data: &'a [u8],
 
-    /// Current position in the data.
-    offset: usize,
+    /// Length-framed records of the decompressed data.
+    records: RecordStream<'a>,
+
+    /// Record read past the end of the previous `TimeFrame`'s trailing records.
+    peeked: Option<Result<(usize, Record)>>,
 
     /// Accumulated game time in milliseconds.
     accumulated_time: u32,
@@ -364,8 +532,15 @@ pub struct TimeFrameIterator<'a> {
     /// Number of `TimeFrame` records yielded so far.
     frame_count: usize,
 
+    /// Offset of the most recently parsed `TimeFrame` record.
+    frame_offset: usize,
+
     /// Whether iteration has completed.
     finished: bool,
+
+    /// Valid leave records skipped between `TimeFrame` records, with the game
+    /// time at which they appeared.
+    leave_records: Vec<(u32, LeaveRecord)>,
 }
 
 impl<'a> TimeFrameIterator<'a> {
@@ -374,7 +549,7 @@ impl<'a> TimeFrameIterator<'a> {
     /// # Arguments
     ///
     /// * `data` - The decompressed replay data
-    /// * `start_offset` - The byte offset where `TimeFrame` records begin
+    /// * `start_offset` - The byte offset of the first record to read
     ///
     /// # Returns
     ///
@@ -383,10 +558,13 @@ impl<'a> TimeFrameIterator<'a> {
     pub fn new(data: &'a [u8], start_offset: usize) -> Self {
         TimeFrameIterator {
             data,
-            offset: start_offset,
+            records: RecordStream::new(data, start_offset),
+            peeked: None,
             accumulated_time: 0,
             frame_count: 0,
+            frame_offset: start_offset,
             finished: false,
+            leave_records: Vec::new(),
         }
     }
 
@@ -402,10 +580,31 @@ impl<'a> TimeFrameIterator<'a> {
         self.frame_count
     }
 
-    /// Returns the current byte offset in the data.
+    /// Returns the offset of the next record to read.
+    ///
+    /// After an error this is the offset of the record that failed to parse.
     #[must_use]
     pub fn current_offset(&self) -> usize {
-        self.offset
+        match &self.peeked {
+            Some(Ok((offset, _))) => *offset,
+            _ => self.records.current_offset(),
+        }
+    }
+
+    /// Returns the byte offset of the most recently parsed `TimeFrame` record.
+    ///
+    /// The frame's action data starts [`TIMEFRAME_HEADER_SIZE`] bytes after
+    /// this offset, at [`TimeFrameIterator::action_data_offset`].
+    #[must_use]
+    pub fn frame_offset(&self) -> usize {
+        self.frame_offset
+    }
+
+    /// Returns the byte offset of the most recently parsed `TimeFrame`'s
+    /// action data.
+    #[must_use]
+    pub fn action_data_offset(&self) -> usize {
+        self.frame_offset + TIMEFRAME_HEADER_SIZE
     }
 
     /// Returns whether iteration is complete.
@@ -414,154 +613,56 @@ impl<'a> TimeFrameIterator<'a> {
         self.finished
     }
 
-    /// Skips over non-TimeFrame records (checksums, chat messages, leave records).
+    /// Moves past a record that failed to parse to the next `TimeFrame`.
     ///
-    /// Returns `true` if we should continue iteration, `false` if we're done.
-    fn skip_non_timeframe_records(&mut self) -> bool {
-        // Maximum bytes to scan before giving up (avoid infinite loops)
-        let scan_limit = 10000;
-        let mut bytes_scanned = 0;
-
-        loop {
-            if self.offset >= self.data.len() {
-                self.finished = true;
-                return false;
-            }
-
-            if bytes_scanned > scan_limit {
-                // Give up after scanning too many bytes without finding a TimeFrame
-                self.finished = true;
-                return false;
-            }
-
-            match self.data[self.offset] {
-                CHECKSUM_MARKER => {
-                    // Skip checksum record (6 bytes)
-                    self.offset += ChecksumRecord::SIZE;
-                    bytes_scanned += ChecksumRecord::SIZE;
-                }
-                CHAT_MARKER => {
-                    // Parse and skip chat message
-                    if let Ok(msg) = ChatMessage::parse(&self.data[self.offset..]) {
-                        self.offset += msg.byte_length;
-                        bytes_scanned += msg.byte_length;
-                    } else {
-                        // If parsing fails, skip 1 byte and continue
-                        self.offset += 1;
-                        bytes_scanned += 1;
-                    }
-                }
-                LEAVE_MARKER => {
-                    // Skip leave record (14 bytes)
-                    self.offset += LeaveRecord::SIZE;
-                    bytes_scanned += LeaveRecord::SIZE;
-                }
-                TIMEFRAME_MARKER_1E | TIMEFRAME_MARKER_1F => {
-                    // Verify this looks like a real TimeFrame (not just a random byte)
-                    if self.is_valid_timeframe_at(self.offset) {
-                        return true;
-                    }
-                    // Not a valid TimeFrame, skip this byte
-                    self.offset += 1;
-                    bytes_scanned += 1;
-                }
-                0x00 => {
-                    // Padding byte, skip it
-                    self.offset += 1;
-                    bytes_scanned += 1;
-                }
-                _ => {
-                    // Unknown marker - try to find the next TimeFrame marker
-                    // Build 10100+ may have additional record types we don't know
-                    if let Some(next_tf) = self.find_next_timeframe() {
-                        self.offset = next_tf;
-                        bytes_scanned += next_tf - self.offset;
-                    } else {
-                        self.finished = true;
-                        return false;
-                    }
-                }
-            }
-        }
+    /// After an error, scans forward from the failed record for a `TimeFrame`
+    /// whose length leads to another well-formed record or to the end of the
+    /// data, and continues iteration there. Returns the offset iteration
+    /// resumes at, or `None` if no such `TimeFrame` follows.
+    pub fn resync(&mut self) -> Option<usize> {
+        let start = self.current_offset() + 1;
+        let offset = (start..self.data.len()).find(|&offset| is_frame_start(self.data, offset))?;
+        self.records = RecordStream::new(self.data, offset);
+        self.peeked = None;
+        self.finished = false;
+        Some(offset)
     }
 
-    /// Checks if the byte at the given offset is a valid TimeFrame start.
-    fn is_valid_timeframe_at(&self, offset: usize) -> bool {
-        if offset + 5 > self.data.len() {
-            return false;
-        }
-
-        let marker = self.data[offset];
-        if marker != TIMEFRAME_MARKER_1E && marker != TIMEFRAME_MARKER_1F {
-            return false;
-        }
-
-        // Check for reasonable time delta (< 5000ms) and length hint (< 8000)
-        let time_delta = u16::from_le_bytes([self.data[offset + 1], self.data[offset + 2]]);
-        let length_hint = u16::from_le_bytes([self.data[offset + 3], self.data[offset + 4]]);
-
-        time_delta < 5000 && length_hint < 8000
+    /// Takes the leave records encountered so far.
+    ///
+    /// Each record is paired with the accumulated game time in milliseconds
+    /// at which it appeared in the stream.
+    pub fn take_leave_records(&mut self) -> Vec<(u32, LeaveRecord)> {
+        std::mem::take(&mut self.leave_records)
     }
 
-    /// Finds the next valid TimeFrame marker from current offset.
-    fn find_next_timeframe(&self) -> Option<usize> {
-        // Limit search to avoid scanning entire file
-        let search_limit = 1000;
-        let end = (self.offset + search_limit).min(self.data.len());
-
-        for i in (self.offset + 1)..end {
-            if self.is_valid_timeframe_at(i) {
-                return Some(i);
-            }
-        }
-        None
+    /// Returns the next record, including one read ahead earlier.
+    fn next_record(&mut self) -> Option<Result<(usize, Record)>> {
+        self.peeked.take().or_else(|| self.records.next())
     }
 
-    /// Parses a single `TimeFrame` record at the current offset.
-    fn parse_timeframe(&mut self) -> Result<TimeFrame> {
-        let data = &self.data[self.offset..];
-
-        if data.len() < 5 {
-            return Err(ParserError::unexpected_eof(5, data.len()));
-        }
+    /// Reads the checksum (0x22) and desync (0x23) records directly following
+    /// a `TimeFrame`.
+    ///
+    /// The first other record is kept for the next call to `next`.
+    fn take_trailing_records(&mut self) -> (Option<ChecksumRecord>, Vec<DesyncRecord>) {
+        let mut checksum = None;
+        let mut desync_records = Vec::new();
 
-        let marker = data[0];
-        if marker != TIMEFRAME_MARKER_1E && marker != TIMEFRAME_MARKER_1F {
-            return Err(ParserError::InvalidHeader {
-                reason: format!(
-                    "Invalid TimeFrame marker: expected 0x1E or 0x1F, found 0x{marker:02X}"
-                ),
-            });
+        loop {
+            match self.records.next() {
+                Some(Ok((_, Record::Checksum { data }))) if checksum.is_none() => {
+                    checksum = ChecksumRecord::from_payload(&data);
+                }
+                Some(Ok((_, Record::Desync(record)))) => desync_records.push(record),
+                other => {
+                    self.peeked = other;
+                    break;
+                }
+            }
         }
 
-        // Read time delta (2 bytes, little-endian)
-        let time_delta_ms = read_u16_le(data, 1)?;
-
-        // Read action data length hint (2 bytes) - but we use marker-based detection
-        // The length hint is not reliable, so we scan for the next record marker
-        let _length_hint = read_u16_le(data, 3)?;
-
-        // Find the end of action data by scanning for next record marker
-        let action_start = 5;
-        let action_end = find_action_boundary(data, action_start);
-
-        let action_data = if action_end > action_start {
-            data[action_start..action_end].to_vec()
-        } else {
-            Vec::new()
-        };
-
-        // Update accumulated time
-        self.accumulated_time = self.accumulated_time.saturating_add(u32::from(time_delta_ms));
-
-        // Update offset to point past the TimeFrame
-        self.offset += action_end;
-
-        Ok(TimeFrame {
-            time_delta_ms,
-            action_data,
-            accumulated_time_ms: self.accumulated_time,
-        })
+        (checksum, desync_records)
     }
 }
 
@@ -573,42 +674,95 @@ impl Iterator for TimeFrameIterator<'_> {
             return None;
         }
 
-        // Skip any non-TimeFrame records
-        if !self.skip_non_timeframe_records() {
-            return None;
-        }
-
-        // Parse the TimeFrame
-        match self.parse_timeframe() {
-            Ok(frame) => {
-                self.frame_count += 1;
-                Some(Ok(frame))
-            }
-            Err(e) => {
-                self.finished = true;
-                Some(Err(e))
+        loop {
+            let (offset, record) = match self.next_record() {
+                Some(Ok(next)) => next,
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            };
+
+            match record {
+                Record::TimeFrame {
+                    time_increment_ms,
+                    commands,
+                    ..
+                } => {
+                    self.frame_offset = offset;
+                    self.frame_count += 1;
+                    self.accumulated_time =
+                        self.accumulated_time.saturating_add(u32::from(time_increment_ms));
+                    let (checksum, desync_records) = self.take_trailing_records();
+                    return Some(Ok(TimeFrame {
+                        time_delta_ms: time_increment_ms,
+                        action_data: commands,
+                        accumulated_time_ms: self.accumulated_time,
+                        checksum,
+                        desync_records,
+                    }));
+                }
+                Record::Leave(record) if record.is_valid() => {
+                    self.leave_records.push((self.accumulated_time, record));
+                }
+                _ => {}
             }
         }
     }
 }
 
-/// Finds the boundary where action data ends within a `TimeFrame`.
+/// Returns whether a `TimeFrame` starts at `offset` whose length leads to
+/// another record or to the zero padding at the end of the data.
+fn is_frame_start(data: &[u8], offset: usize) -> bool {
+    let Ok((Record::TimeFrame { .. }, len)) = Record::parse(&data[offset..]) else {
+        return false;
+    };
+    let rest = &data[offset + len..];
+    rest.iter().all(|&b| b == 0) || Record::parse(rest).is_ok()
+}
+
+/// Finds all chat messages in decompressed replay data.
+///
+/// Scans byte by byte from `start` for 0x20 markers followed by the chat
+/// padding signature (0x20 XX 0x00 0x00 0x00), which filters out most false
+/// positives inside action data. Messages shorter than 2 characters or with
+/// non-printable content are dropped.
 ///
-/// Scans for known record markers: 0x1E, 0x1F (`TimeFrame`), 0x22 (Checksum),
-/// 0x20 (Chat), 0x17 (Leave).
-fn find_action_boundary(data: &[u8], start: usize) -> usize {
-    for (i, &byte) in data.iter().enumerate().skip(start) {
-        match byte {
-            TIMEFRAME_MARKER_1E
-            | TIMEFRAME_MARKER_1F
-            | CHECKSUM_MARKER
-            | CHAT_MARKER
-            | LEAVE_MARKER => return i,
-            _ => {}
+/// Returns each message together with its offset in `data`.
+#[must_use]
+pub fn find_chat_messages(data: &[u8], start: usize) -> Vec<(usize, ChatMessage)> {
+    let mut messages = Vec::new();
+    let mut offset = start;
+
+    while offset + 9 < data.len() {
+        // Flags should be small (0x00-0x1F) and the padding signature must match
+        if data[offset] == CHAT_MARKER
+            && data[offset + 1] <= 0x1F
+            && data[offset + 4] == 0x20
+            && data[offset + 6..offset + 9] == [0x00, 0x00, 0x00]
+        {
+            if let Ok(chat) = ChatMessage::parse(&data[offset..]) {
+                let byte_length = chat.byte_length;
+                if chat.message.len() >= 2
+                    && chat
+                        .message
+                        .chars()
+                        .all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+                {
+                    messages.push((offset, chat));
+                }
+                offset += byte_length;
+                continue;
+            }
         }
+        offset += 1;
     }
 
-    data.len()
+    messages
 }
 
 /// Finds the offset where `TimeFrame` records begin in decompressed data.
@@ -622,6 +776,7 @@ pub fn find_timeframe_start(data: &[u8], start: usize) -> Option<usize> {
 
 /// Summary statistics from `TimeFrame` iteration.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeFrameStats {
     /// Total number of `TimeFrame` records.
     pub frame_count: usize,
@@ -692,12 +847,12 @@ mod tests {
         // TimeFrame marker
         data.push(TIMEFRAME_MARKER_1F);
 
+        // Length: time field and 5 bytes of action data
+        data.extend_from_slice(&[0x07, 0x00]);
+
         // Time delta: 60ms (0x003C)
         data.extend_from_slice(&[0x3C, 0x00]);
 
-        // Length hint (ignored)
-        data.extend_from_slice(&[0x64, 0x00]);
-
         // Some action data
         data.extend_from_slice(&[0x01, 0x1A, 0x00, 0x16, 0x01]);
 
@@ -721,11 +876,11 @@ mod tests {
         // TimeFrame marker
         data.push(TIMEFRAME_MARKER_1F);
 
-        // Time delta: 2ms
+        // Length: only the time field
         data.extend_from_slice(&[0x02, 0x00]);
 
-        // Length hint: 0
-        data.extend_from_slice(&[0x00, 0x00]);
+        // Time delta: 2ms
+        data.extend_from_slice(&[0x02, 0x00]);
 
         // Immediately followed by checksum
         data.push(CHECKSUM_MARKER);
@@ -745,22 +900,22 @@ mod tests {
 
         // First TimeFrame
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x10, 0x00]); // 16ms
-        data.extend_from_slice(&[0x00, 0x00]);
         data.push(CHECKSUM_MARKER);
         data.extend_from_slice(&[0x04, 0x00, 0x00, 0x00, 0x00]);
 
         // Second TimeFrame
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x20, 0x00]); // 32ms
-        data.extend_from_slice(&[0x00, 0x00]);
         data.push(CHECKSUM_MARKER);
         data.extend_from_slice(&[0x04, 0x00, 0x00, 0x00, 0x00]);
 
         // Third TimeFrame
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x0A, 0x00]); // 10ms
-        data.extend_from_slice(&[0x00, 0x00]);
 
         let iter = TimeFrameIterator::new(&data, 0);
         let frames: Vec<_> = iter.collect::<Result<Vec<_>>>().unwrap();
@@ -788,7 +943,110 @@ mod tests {
     fn test_checksum_record_invalid_marker() {
         let data = [0x21, 0x04, 0x00, 0x00, 0x00, 0x00];
         let result = ChecksumRecord::parse(&data);
-        assert!(matches!(result, Err(ParserError::InvalidHeader { .. })));
+        assert!(matches!(
+            result,
+            Err(ParserError::UnexpectedRecord { found: 0x21, .. })
+        ));
+    }
+
+    #[test]
+    fn test_timeframe_trailing_checksum() {
+        let mut data = Vec::new();
+
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00, 0x64, 0x00]);
+        data.extend_from_slice(&[CHECKSUM_MARKER, 0x04, 0x78, 0x56, 0x34, 0x12]);
+
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00, 0x64, 0x00]);
+
+        let frames: Vec<_> = TimeFrameIterator::new(&data, 0)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].checksum.map(|c| c.checksum), Some(0x1234_5678));
+        assert!(frames[0].desync_records.is_empty());
+        assert_eq!(frames[1].checksum, None);
+    }
+
+    #[test]
+    fn test_timeframe_trailing_desync() {
+        let mut data = Vec::new();
+
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00, 0x64, 0x00]);
+        data.extend_from_slice(&[CHECKSUM_MARKER, 0x04, 0x01, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[
+            DESYNC_MARKER, 0x10, 0x00, 0x00, 0x00, 0x02, 0xEF, 0xBE, 0xAD, 0xDE, 0x00,
+        ]);
+
+        data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00, 0x32, 0x00]);
+
+        let frames: Vec<_> = TimeFrameIterator::new(&data, 0)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].desync_records.len(), 1);
+        assert_eq!(frames[0].desync_records[0].checksum, 0xDEAD_BEEF);
+        assert_eq!(frames[1].accumulated_time_ms, 150);
+    }
+
+    #[test]
+    fn test_desync_record_parse() {
+        let data = [
+            0x23, 0x10, 0x00, 0x00, 0x00, 0x02, 0xEF, 0xBE, 0xAD, 0xDE, 0x01,
+        ];
+
+        let record = DesyncRecord::parse(&data).unwrap();
+
+        assert_eq!(record.unknown_1, 0x10);
+        assert_eq!(record.unknown_2, 0x02);
+        assert_eq!(record.checksum, 0xDEAD_BEEF);
+        assert_eq!(record.unknown_3, 0x01);
+
+        let result = DesyncRecord::parse(&data[..5]);
+        assert!(matches!(result, Err(ParserError::UnexpectedEof { .. })));
+    }
+
+    #[test]
+    fn test_iterator_collects_leave_records() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[TIMEFRAME_MARKER_1F, 0x02, 0x00, 0x64, 0x00]);
+        // Player 2 left the game (reason 0x0C, result 0x09)
+        data.extend_from_slice(&[LEAVE_MARKER, 0x0C, 0x00, 0x00, 0x00, 0x02]);
+        data.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        // Implausible reason code
+        data.extend_from_slice(&[LEAVE_MARKER, 0x00, 0x10, 0x04, 0x00, 0x02]);
+        data.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[TIMEFRAME_MARKER_1F, 0x02, 0x00, 0x64, 0x00]);
+
+        let mut iter = TimeFrameIterator::new(&data, 0);
+        assert_eq!(iter.by_ref().count(), 2);
+
+        let leaves = iter.take_leave_records();
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(leaves[0].0, 100);
+        assert_eq!(leaves[0].1.player_id, 2);
+        assert!(iter.take_leave_records().is_empty());
+    }
+
+    #[test]
+    fn test_find_chat_messages() {
+        let mut data = vec![0x20, 0x00];
+        // Player chat from slot 2, then a lone marker byte
+        data.extend_from_slice(&[CHAT_MARKER, 0x02, 0x05, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(b"gl hf\0");
+        data.extend_from_slice(&[CHAT_MARKER, 0x00, 0x00]);
+
+        let messages = find_chat_messages(&data, 0);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, 2);
+        assert_eq!(messages[0].1.sender_slot, Some(2));
+        assert_eq!(messages[0].1.message, "gl hf");
     }
 
     #[test]
@@ -814,8 +1072,8 @@ mod tests {
         // Three TimeFrames with varying time deltas
         for time in &[100u16, 200u16, 150u16] {
             data.push(TIMEFRAME_MARKER_1F);
+            data.extend_from_slice(&[0x02, 0x00]);
             data.extend_from_slice(&time.to_le_bytes());
-            data.extend_from_slice(&[0x00, 0x00]);
             data.push(CHECKSUM_MARKER);
             data.extend_from_slice(&[0x04, 0x00, 0x00, 0x00, 0x00]);
         }
@@ -838,8 +1096,8 @@ mod tests {
 
         // TimeFrame at offset 4
         data.push(TIMEFRAME_MARKER_1F);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x64, 0x00]); // 100ms
-        data.extend_from_slice(&[0x00, 0x00]);
 
         let iter = TimeFrameIterator::new(&data, 4);
         let frames: Vec<_> = iter.collect::<Result<Vec<_>>>().unwrap();
@@ -854,8 +1112,8 @@ mod tests {
 
         // TimeFrame with 0x1E marker
         data.push(TIMEFRAME_MARKER_1E);
+        data.extend_from_slice(&[0x02, 0x00]);
         data.extend_from_slice(&[0x32, 0x00]); // 50ms
-        data.extend_from_slice(&[0x00, 0x00]);
 
         let mut iter = TimeFrameIterator::new(&data, 0);
         let frame = iter.next().unwrap().unwrap();
@@ -867,9 +1125,9 @@ mod tests {
     fn test_iterator_helper_methods() {
         let data = [
             TIMEFRAME_MARKER_1F,
-            0x10,
-            0x00,
+            0x02,
             0x00,
+            0x10,
             0x00,
             CHECKSUM_MARKER,
             0x04,
@@ -890,4 +1148,26 @@ mod tests {
         assert_eq!(iter.frame_count(), 1);
         assert_eq!(iter.accumulated_time_ms(), 16);
     }
+
+    #[test]
+    fn test_iterator_resync() {
+        let mut data = vec![TIMEFRAME_MARKER_1F, 0x02, 0x00, 0x64, 0x00];
+        // A TimeFrame too short for its time field, then garbage
+        data.extend_from_slice(&[TIMEFRAME_MARKER_1F, 0x01, 0x00, 0x99]);
+        data.extend_from_slice(&[TIMEFRAME_MARKER_1F, 0x02, 0x00, 0x32, 0x00]);
+
+        let mut iter = TimeFrameIterator::new(&data, 0);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(iter.current_offset(), 5);
+        assert_eq!(iter.resync(), Some(9));
+        let frame = iter.next().unwrap().unwrap();
+        assert_eq!(frame.accumulated_time_ms, 150);
+        assert!(iter.next().is_none());
+
+        // Nothing to resync to
+        let mut iter = TimeFrameIterator::new(&data[..9], 0);
+        assert!(iter.nth(1).unwrap().is_err());
+        assert_eq!(iter.resync(), None);
+    }
 }
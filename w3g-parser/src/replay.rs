@@ -0,0 +1,543 @@
+//! High-level replay parsing.
+//!
+//! [`Replay`] runs the full parsing pipeline in one call: header parsing,
+//! decompression, game record parsing and iteration over the `TimeFrame`
+//! stream. The result holds the header, game settings, players, chat messages,
+//! leave events and a flat list of timestamped actions.
+//!
+//! [`Replay::game_id`] fingerprints the game itself, so replays of one game
+//! saved by different players can be recognised as duplicates.
+//!
+//! [`ParseOptions`] controls which parts of the action stream are collected
+//! and how recoverable problems are handled (see [`crate::diagnostic`]).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::replay::{ParseOptions, Replay};
+//!
+//! fn print_replay(path: &str) -> w3g_parser::Result<()> {
+//!     let replay = Replay::from_path(path)?;
+//!     for player in replay.game.players.players() {
+//!         println!("Slot {}: {}", player.slot_id(), player.player_name());
+//!     }
+//!     println!("{} actions", replay.actions.len());
+//!
+//!     // Only the metadata and chat, without decoding actions
+//!     let options = ParseOptions::new().actions(false);
+//!     let replay = Replay::from_path_with(path, &options)?;
+//!     for chat in &replay.chat {
+//!         println!("[{}ms] {}", chat.timestamp_ms, chat.message.message);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::actions::{Action, ActionType};
+use crate::compare::GameSummary;
+use crate::decompress::decompress;
+use crate::diagnostic::{Diagnostic, Diagnostics, ParseMode};
+use crate::error::Result;
+use crate::header::Header;
+use crate::records::{
+    find_game_record_start, ChatMessage, GameRecord, GameRecordHeader,
+    LeaveRecord, Record, RecordStream, SlotTable, TimeFrame, TimeFrameIterator,
+};
+
+/// Number of leading `TimeFrame`s whose commands are hashed by [`Replay::game_id`].
+pub const GAME_ID_FRAMES: usize = 256;
+
+/// Options controlling what [`Replay`] collects from the action stream.
+///
+/// All parts are collected by default, in [`ParseMode::Lenient`] mode.
+///
+/// # Example
+///
+/// ```
+/// use w3g_parser::replay::ParseOptions;
+///
+/// let options = ParseOptions::new().actions(false).chat(false);
+/// assert!(!options.includes_actions());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Whether to decode player actions.
+    actions: bool,
+
+    /// Whether to collect chat messages.
+    chat: bool,
+
+    /// How recoverable problems are handled.
+    mode: ParseMode,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            actions: true,
+            chat: true,
+            mode: ParseMode::default(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Creates options that collect everything.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether player actions are decoded.
+    #[must_use]
+    pub fn actions(mut self, enabled: bool) -> Self {
+        self.actions = enabled;
+        self
+    }
+
+    /// Sets whether chat messages are collected.
+    #[must_use]
+    pub fn chat(mut self, enabled: bool) -> Self {
+        self.chat = enabled;
+        self
+    }
+
+    /// Sets how recoverable problems are handled.
+    #[must_use]
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns whether player actions are decoded.
+    #[must_use]
+    pub fn includes_actions(&self) -> bool {
+        self.actions
+    }
+
+    /// Returns whether chat messages are collected.
+    #[must_use]
+    pub fn includes_chat(&self) -> bool {
+        self.chat
+    }
+
+    /// Returns the parse mode.
+    #[must_use]
+    pub fn parse_mode(&self) -> ParseMode {
+        self.mode
+    }
+}
+
+/// A chat message with the game time at which it was sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChatEvent {
+    /// Accumulated game time in milliseconds.
+    pub timestamp_ms: u32,
+
+    /// The chat message.
+    pub message: ChatMessage,
+}
+
+/// A player leaving the game, with the game time at which it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeaveEvent {
+    /// Accumulated game time in milliseconds.
+    pub timestamp_ms: u32,
+
+    /// The leave record.
+    pub record: LeaveRecord,
+}
+
+/// A fully parsed replay.
+///
+/// The decompressed data is kept so the `TimeFrame` stream can be iterated
+/// again (see [`Replay::timeframes`]). It is not serialized.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    /// Replay file header.
+    pub header: Header,
+
+    /// Game record with host, game settings and player roster.
+    pub game: GameRecord,
+
+    /// Chat messages in stream order (empty if chat was not collected).
+    ///
+    /// Chat messages are timestamped with the time of the preceding `TimeFrame`.
+    pub chat: Vec<ChatEvent>,
+
+    /// Players leaving the game, in stream order.
+    pub leaves: Vec<LeaveEvent>,
+
+    /// All player actions in stream order (empty if actions were not decoded).
+    pub actions: Vec<Action>,
+
+    /// Number of `TimeFrame` records in the replay.
+    pub frame_count: usize,
+
+    /// Accumulated game time of the last `TimeFrame` in milliseconds.
+    pub duration_ms: u32,
+
+    /// Recoverable problems found while parsing, in stream order.
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Decompressed replay data.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    decompressed: Vec<u8>,
+
+    /// Options the replay was parsed with.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    options: ParseOptions,
+}
+
+impl Replay {
+    /// Parses a replay from raw file contents, collecting everything.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header, decompression or game record fails to
+    /// parse.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_bytes_with(data, &ParseOptions::default())
+    }
+
+    /// Parses a replay from raw file contents with the given options.
+    ///
+    /// In lenient mode, a record that fails to parse is skipped up to the next
+    /// `TimeFrame` that frames cleanly, an action that fails to parse is
+    /// skipped with the rest of its command block, and both are reported in
+    /// [`Replay::diagnostics`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header, decompression or game record fails to
+    /// parse, or in strict mode if any `TimeFrame` or action fails to parse.
+    pub fn from_bytes_with(data: &[u8], options: &ParseOptions) -> Result<Self> {
+        let header = Header::parse(data)?;
+        let decompressed = decompress(data, &header)?;
+        let game = GameRecord::parse(&decompressed)?;
+
+        let mut diagnostics = Diagnostics::new(options.mode);
+        if decompressed.len() < header.decompressed_size() as usize {
+            diagnostics.warning(
+                0,
+                "header",
+                format!(
+                    "decompressed {} bytes, header declares {}",
+                    decompressed.len(),
+                    header.decompressed_size()
+                ),
+            );
+        }
+
+        let mut replay = Replay {
+            header,
+            game,
+            chat: Vec::new(),
+            leaves: Vec::new(),
+            actions: Vec::new(),
+            frame_count: 0,
+            duration_ms: 0,
+            diagnostics: Vec::new(),
+            decompressed: Vec::new(),
+            options: *options,
+        };
+
+        // End offset and accumulated time of every frame, for timestamping chat
+        let mut frame_ends = Vec::new();
+
+        let mut iter = replay.game.timeframes(&decompressed);
+        while let Some(result) = iter.next() {
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(e) => {
+                    // Continue at the next TimeFrame that frames cleanly
+                    let offset = iter.current_offset();
+                    let context = format!("timeframe #{}", replay.frame_count + 1);
+                    let resumed = iter.resync();
+                    let skipped = resumed.unwrap_or(decompressed.len()) - offset;
+                    diagnostics.error(e, offset, context, skipped)?;
+                    if resumed.is_none() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            replay.frame_count += 1;
+            replay.duration_ms = frame.accumulated_time_ms;
+            frame_ends.push((iter.current_offset(), frame.accumulated_time_ms));
+            if options.actions {
+                replay.collect_actions(&frame, iter.action_data_offset(), &mut diagnostics)?;
+            }
+        }
+
+        // The stream ends at the zero padding of the last block
+        let end = iter.current_offset();
+        if decompressed[end.min(decompressed.len())..].iter().any(|&b| b != 0) {
+            diagnostics.warning(
+                end,
+                format!("after timeframe #{}", replay.frame_count),
+                format!(
+                    "TimeFrame stream stopped early; {} bytes not parsed",
+                    decompressed.len() - end
+                ),
+            );
+        }
+
+        replay.leaves = iter
+            .take_leave_records()
+            .into_iter()
+            .map(|(timestamp_ms, record)| LeaveEvent {
+                timestamp_ms,
+                record,
+            })
+            .collect();
+
+        replay.decompressed = decompressed;
+        if options.chat {
+            replay.chat = replay.collect_chat(&frame_ends)?;
+        }
+
+        replay.diagnostics = diagnostics.into_vec();
+        Ok(replay)
+    }
+
+    /// Reads and parses a replay file, collecting everything.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or fails to parse.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_path_with(path, &ParseOptions::default())
+    }
+
+    /// Reads and parses a replay file with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or fails to parse.
+    pub fn from_path_with(path: impl AsRef<Path>, options: &ParseOptions) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes_with(&data, options)
+    }
+
+    /// Returns the game settings record (host, game name, map, encoded settings).
+    #[must_use]
+    pub fn settings(&self) -> &GameRecordHeader {
+        &self.game.header
+    }
+
+    /// Returns the options the replay was parsed with.
+    ///
+    /// A replay restored through serde reports the default options.
+    #[must_use]
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    /// Returns the decompressed replay data.
+    ///
+    /// This is empty for a replay restored through serde.
+    #[must_use]
+    pub fn decompressed(&self) -> &[u8] {
+        &self.decompressed
+    }
+
+    /// Creates a new iterator over the `TimeFrame` records.
+    #[must_use]
+    pub fn timeframes(&self) -> TimeFrameIterator<'_> {
+        self.game.timeframes(&self.decompressed)
+    }
+
+    /// Creates a length-framed stream over every record after the game record header.
+    ///
+    /// Unlike [`Replay::timeframes`] this covers the player list, slot table and
+    /// startup records as well, and reproduces the data byte for byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the game record header cannot be located, which
+    /// is the case for a replay restored through serde.
+    pub fn records(&self) -> Result<RecordStream<'_>> {
+        let start = find_game_record_start(&self.decompressed)?;
+        Ok(RecordStream::new(&self.decompressed, start + self.game.header.byte_length))
+    }
+
+    /// Returns the lobby slot table, with teams, races and the random seed.
+    ///
+    /// Returns `None` if the replay has no slot table record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record stream cannot be framed or the slot
+    /// table is malformed.
+    pub fn slot_table(&self) -> Result<Option<SlotTable>> {
+        for result in self.records()? {
+            match result? {
+                (offset, Record::SlotTable { data }) => {
+                    // The slots start after the marker and length
+                    return SlotTable::parse(&data)
+                        .map(Some)
+                        .map_err(|e| e.with_context(offset + 3, "slot table"));
+                }
+                // The slot table comes before the first `TimeFrame`
+                (_, Record::TimeFrame { .. }) => break,
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns a stable identifier for the game this replay recorded.
+    ///
+    /// The identifier is the hex SHA-256 of content that every player's replay
+    /// of the game shares: the decoded game settings (map path, flags, creator
+    /// and map checksum), the random seed, the roster sorted by player ID, and
+    /// the game times and commands of the first [`GAME_ID_FRAMES`] `TimeFrame`s.
+    /// Replays of one game saved by different players get the same ID as long
+    /// as both cover those frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record stream cannot be framed.
+    pub fn game_id(&self) -> Result<String> {
+        let summary = GameSummary::collect(self, GAME_ID_FRAMES)?;
+
+        let mut hasher = Sha256::new();
+        let mut field = |bytes: &[u8]| {
+            // Length prefixes keep adjacent fields from running into each other
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        field(&self.game.header.decoded_settings().unwrap_or_default());
+        field(&summary.seed.map(u32::to_le_bytes).unwrap_or_default());
+        for (player_id, name) in &summary.roster {
+            field(&[*player_id]);
+            field(name.as_bytes());
+        }
+        for frame in &summary.frames {
+            field(&frame.time_ms.to_le_bytes());
+            field(&frame.commands);
+        }
+
+        let mut id = String::with_capacity(64);
+        for byte in hasher.finalize() {
+            let _ = write!(id, "{byte:02x}");
+        }
+        Ok(id)
+    }
+
+    /// Returns whether any problem was recorded while parsing.
+    #[must_use]
+    pub fn has_diagnostics(&self) -> bool {
+        !self.diagnostics.is_empty()
+    }
+
+    /// Returns the actions issued by a player.
+    pub fn player_actions(&self, player_id: u8) -> impl Iterator<Item = &Action> {
+        self.actions.iter().filter(move |a| a.player_id == player_id)
+    }
+
+    /// Collects the chat records of the record stream.
+    ///
+    /// `frame_ends` holds the end offset and accumulated time of every
+    /// `TimeFrame`; a message is timestamped with the frame before it. The
+    /// stream resumes after the next `TimeFrame` when a record fails to
+    /// frame, which the `TimeFrame` walk has already reported.
+    fn collect_chat(&self, frame_ends: &[(usize, u32)]) -> Result<Vec<ChatEvent>> {
+        let mut chat = Vec::new();
+        let mut stream = self.records()?;
+        loop {
+            match stream.next() {
+                Some(Ok((offset, Record::Chat { player_id, mode, message, .. }))) => {
+                    let index = frame_ends.partition_point(|&(end, _)| end <= offset);
+                    chat.push(ChatEvent {
+                        timestamp_ms: index.checked_sub(1).map_or(0, |i| frame_ends[i].1),
+                        message: ChatMessage::from_record(player_id, mode, &message),
+                    });
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) => {
+                    let failed = stream.current_offset();
+                    let Some(&(resume, _)) = frame_ends.iter().find(|&&(end, _)| end > failed)
+                    else {
+                        break;
+                    };
+                    stream = RecordStream::new(&self.decompressed, resume);
+                }
+                None => break,
+            }
+        }
+        Ok(chat)
+    }
+
+    /// Decodes the actions of one `TimeFrame`.
+    ///
+    /// `data_offset` is the absolute offset of the frame's action data. Action
+    /// errors are rebased onto it so they carry absolute offsets.
+    fn collect_actions(
+        &mut self,
+        frame: &TimeFrame,
+        data_offset: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<()> {
+        let frame_path = format!("timeframe #{}", self.frame_count);
+        let mut iter = frame.actions();
+        let mut index = 0;
+
+        while let Some(result) = iter.next() {
+            let offset = data_offset + iter.action_offset();
+            index += 1;
+
+            match result {
+                Ok(action) => {
+                    if let ActionType::Unknown { type_id, .. } = action.action_type {
+                        diagnostics.warning(
+                            offset,
+                            format!("{frame_path} / player {} / action #{index}", action.player_id),
+                            format!("unknown action type 0x{type_id:02X}"),
+                        );
+                    }
+                    self.actions.push(action);
+                }
+                Err(e) => {
+                    let e = e.with_context(data_offset, &frame_path);
+                    let context = e
+                        .path()
+                        .map_or_else(|| format!("{frame_path} / action #{index}"), str::to_string);
+                    let skipped = iter.current_offset() - iter.action_offset();
+                    diagnostics.error(e, offset, context, skipped)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options_builder() {
+        let options = ParseOptions::new();
+        assert!(options.includes_actions());
+        assert!(options.includes_chat());
+
+        let options = options.actions(false).chat(false);
+        assert!(!options.includes_actions());
+        assert!(!options.includes_chat());
+    }
+
+    #[test]
+    fn test_replay_from_bytes_invalid() {
+        assert!(Replay::from_bytes(b"not a replay").is_err());
+    }
+}
@@ -0,0 +1,646 @@
+//! Self-contained HTML match reports.
+//!
+//! [`MatchReport`] gathers what a caster wants to show about one game: the
+//! match header, players by team, APM over time, build orders, hero skill
+//! orders, the chat log and leave events. [`MatchReport::to_html`] renders it
+//! as a single static HTML page with inline CSS, an inline SVG APM chart and a
+//! few lines of inline JavaScript, so the file can be shared and opened
+//! without any server.
+//!
+//! Players, APM, build orders and hero skills come from
+//! [`ReplayTables`](crate::export::ReplayTables), and chat and leaves from
+//! [`Replay::chat`] and [`Replay::leaves`], so all times follow the same game
+//! clock.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::report::MatchReport;
+//! use w3g_parser::Replay;
+//!
+//! let replay = Replay::from_path("replay.w3g")?;
+//! let report = MatchReport::from_replay(&replay, "Grand final, game 3")?;
+//! std::fs::write("match.html", report.to_html())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt::Write;
+
+use crate::error::Result;
+use crate::export::{PlayerRow, ReplayTables};
+use crate::census::build_key;
+use crate::replay::Replay;
+
+/// Line colours of the players in the APM chart, in player order.
+const PLAYER_COLORS: [&str; 12] = [
+    "#e53935", "#1e88e5", "#00897b", "#8e24aa", "#fdd835", "#fb8c00", "#43a047", "#d81b60",
+    "#757575", "#64b5f6", "#2e7d32", "#6d4c41",
+];
+
+/// Size of the APM chart's drawing area in SVG units.
+const CHART_WIDTH: f64 = 760.0;
+const CHART_HEIGHT: f64 = 240.0;
+
+/// Margin around the APM chart's drawing area for the axis labels.
+const CHART_MARGIN: f64 = 40.0;
+
+/// Everything shown in a match report.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchReport {
+    /// Title of the page.
+    pub title: String,
+
+    /// Game version, such as `"1.26 (build 6059)"` or `"Reforged"`.
+    pub version: String,
+
+    /// Lobby name, if the game has one.
+    pub game_name: Option<String>,
+
+    /// Map path from the game settings.
+    pub map: Option<String>,
+
+    /// Name of the player who hosted the game.
+    pub host: String,
+
+    /// Fingerprint shared by every player's replay of the game.
+    pub game_id: Option<String>,
+
+    /// Game duration in milliseconds.
+    pub duration_ms: u32,
+
+    /// Players sorted by player ID.
+    pub players: Vec<PlayerReport>,
+
+    /// Chat messages in game order.
+    pub chat: Vec<ChatLine>,
+
+    /// Players leaving the game, in game order.
+    pub leaves: Vec<LeaveLine>,
+}
+
+/// A player's part of a match report.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerReport {
+    /// Name, race, team, APM and result.
+    pub player: PlayerRow,
+
+    /// Actions issued in each minute of game time; the last minute may be
+    /// partial.
+    pub apm_by_minute: Vec<u32>,
+
+    /// Units, buildings, upgrades and items ordered, in game order.
+    pub build_order: Vec<TimedCode>,
+
+    /// Hero skills learned, in game order.
+    pub hero_skills: Vec<TimedCode>,
+}
+
+/// A `FourCC` object code ordered at a game time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedCode {
+    /// Game time in milliseconds.
+    pub time_ms: u32,
+
+    /// `FourCC` code, such as `"hpea"`.
+    pub code: String,
+}
+
+/// A chat message of a match report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChatLine {
+    /// Game time in milliseconds.
+    pub time_ms: u32,
+
+    /// Sending player ID, or `None` for system messages.
+    pub player_id: Option<u8>,
+
+    /// Who the message was sent to: `All`, `Allies`, `Observers` or
+    /// `Private`.
+    pub channel: String,
+
+    /// Message text.
+    pub message: String,
+}
+
+/// A leave event of a match report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeaveLine {
+    /// Game time in milliseconds.
+    pub time_ms: u32,
+
+    /// Player who left.
+    pub player_id: u8,
+
+    /// Reason code of the leave record.
+    pub reason: u32,
+
+    /// Result code of the leave record.
+    pub result: u32,
+}
+
+impl MatchReport {
+    /// Builds the report of `replay` under the page title `title`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `replay` was parsed without actions or the slot
+    /// table is malformed.
+    pub fn from_replay(replay: &Replay, title: &str) -> Result<Self> {
+        let tables = ReplayTables::from_replay(replay, title)?;
+        let minutes = tables.duration_ms.div_ceil(60_000).max(1) as usize;
+
+        let players = tables
+            .players
+            .iter()
+            .map(|player| {
+                let actions = tables.actions.iter().filter(|a| a.player_id == player.player_id);
+                let mut apm_by_minute = vec![0; minutes];
+                let mut build_order = Vec::new();
+                let mut hero_skills = Vec::new();
+                for action in actions {
+                    let minute = (action.time_ms / 60_000) as usize;
+                    apm_by_minute[minute.min(minutes - 1)] += 1;
+
+                    let timed = || TimedCode {
+                        time_ms: action.time_ms,
+                        code: action.code.clone().unwrap_or_default(),
+                    };
+                    if action.is_build_order() {
+                        build_order.push(timed());
+                    } else if action.is_hero_skill() {
+                        hero_skills.push(timed());
+                    }
+                }
+                PlayerReport {
+                    player: player.clone(),
+                    apm_by_minute,
+                    build_order,
+                    hero_skills,
+                }
+            })
+            .collect();
+
+        let (chat, leaves) = events(replay);
+        let settings = replay.settings();
+        let game_name = settings.game_name();
+        Ok(Self {
+            title: title.to_string(),
+            version: build_key(&replay.header),
+            game_name: (!game_name.is_empty()).then_some(game_name),
+            map: settings.map_path(),
+            host: settings.host_name.clone(),
+            game_id: replay.game_id().ok(),
+            duration_ms: tables.duration_ms,
+            players,
+            chat,
+            leaves,
+        })
+    }
+
+    /// Returns the name of a player, or `Player N` if the ID is not in the
+    /// roster.
+    #[must_use]
+    pub fn player_name(&self, player_id: u8) -> String {
+        self.players
+            .iter()
+            .find(|p| p.player.player_id == player_id)
+            .map_or_else(|| format!("Player {player_id}"), |p| p.player.name.clone())
+    }
+
+    /// Renders the report as a self-contained HTML page.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = self.write_html(&mut html);
+        html
+    }
+
+    fn write_html(&self, html: &mut String) -> std::fmt::Result {
+        let title = escape(&self.title);
+        writeln!(html, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>")?;
+        writeln!(html, "<meta charset=\"utf-8\">")?;
+        writeln!(html, "<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>")?;
+        writeln!(html, "<h1>{title}</h1>")?;
+
+        writeln!(html, "<dl class=\"meta\">")?;
+        let mut meta = |name: &str, value: &str| {
+            writeln!(html, "<div><dt>{name}</dt><dd>{}</dd></div>", escape(value))
+        };
+        meta("Version", &self.version)?;
+        meta("Duration", &clock(self.duration_ms))?;
+        if let Some(name) = &self.game_name {
+            meta("Game", name)?;
+        }
+        if let Some(map) = &self.map {
+            meta("Map", map)?;
+        }
+        meta("Host", &self.host)?;
+        if let Some(game_id) = &self.game_id {
+            meta("Game ID", game_id)?;
+        }
+        writeln!(html, "</dl>")?;
+
+        self.write_teams(html)?;
+        self.write_apm_chart(html)?;
+        self.write_orders(html)?;
+        self.write_chat(html)?;
+        self.write_leaves(html)?;
+
+        writeln!(html, "<script>{SCRIPT}</script>\n</body>\n</html>")
+    }
+
+    fn write_teams(&self, html: &mut String) -> std::fmt::Result {
+        writeln!(html, "<section>\n<h2>Players</h2>\n<div class=\"teams\">")?;
+        let mut teams: Vec<Option<u8>> = self
+            .players
+            .iter()
+            .filter(|p| !p.player.observer)
+            .map(|p| p.player.team)
+            .collect();
+        teams.sort_unstable();
+        teams.dedup();
+
+        let mut write_team = |heading: String, players: Vec<(usize, &PlayerReport)>| {
+            writeln!(html, "<table>\n<caption>{heading}</caption>")?;
+            writeln!(
+                html,
+                "<tr><th>Player</th><th>Race</th><th>APM</th><th>Actions</th><th>Result</th></tr>"
+            )?;
+            for (index, report) in players {
+                let player = &report.player;
+                writeln!(
+                    html,
+                    "<tr><td><span class=\"swatch\" style=\"background:{}\"></span>{}</td>\
+                     <td>{}</td><td>{:.0}</td><td>{}</td><td>{}</td></tr>",
+                    PLAYER_COLORS[index % PLAYER_COLORS.len()],
+                    escape(&player.name),
+                    escape(player.race.as_deref().unwrap_or("-")),
+                    player.apm,
+                    player.actions,
+                    escape(player.result.as_deref().unwrap_or("-"))
+                )?;
+            }
+            writeln!(html, "</table>")
+        };
+        for team in teams {
+            let players = self
+                .indexed_players()
+                .filter(|(_, p)| !p.player.observer && p.player.team == team)
+                .collect();
+            let heading = team.map_or_else(|| "No team".to_string(), |t| format!("Team {}", t + 1));
+            write_team(heading, players)?;
+        }
+        let observers: Vec<_> = self.indexed_players().filter(|(_, p)| p.player.observer).collect();
+        if !observers.is_empty() {
+            write_team("Observers".to_string(), observers)?;
+        }
+        writeln!(html, "</div>\n</section>")
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn write_apm_chart(&self, html: &mut String) -> std::fmt::Result {
+        let players: Vec<_> = self.indexed_players().filter(|(_, p)| !p.player.observer).collect();
+        let minutes = players.iter().map(|(_, p)| p.apm_by_minute.len()).max().unwrap_or(0);
+        let peak = players
+            .iter()
+            .flat_map(|(_, p)| p.apm_by_minute.iter().copied())
+            .max()
+            .unwrap_or(0);
+        let step = if peak > 400 { 100 } else { 50 };
+        let top = peak.div_ceil(step).max(1) * step;
+
+        let x = |minute: f64| CHART_MARGIN + minute * CHART_WIDTH / minutes.max(1) as f64;
+        let y = |apm: u32| CHART_MARGIN + CHART_HEIGHT * (1.0 - f64::from(apm) / f64::from(top));
+
+        writeln!(html, "<section>\n<h2>APM</h2>")?;
+        writeln!(
+            html,
+            "<svg class=\"chart\" viewBox=\"0 0 {} {}\" role=\"img\" \
+             aria-label=\"APM per minute\">",
+            CHART_WIDTH + 2.0 * CHART_MARGIN,
+            CHART_HEIGHT + 2.0 * CHART_MARGIN
+        )?;
+        for apm in (0..=top).step_by(step as usize) {
+            writeln!(
+                html,
+                "<line class=\"grid\" x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{2:.1}\" y2=\"{2:.1}\"/>\
+                 <text class=\"axis\" x=\"{3:.1}\" y=\"{4:.1}\" text-anchor=\"end\">{apm}</text>",
+                x(0.0),
+                x(minutes as f64),
+                y(apm),
+                CHART_MARGIN - 6.0,
+                y(apm) + 4.0
+            )?;
+        }
+        let label_every = minutes.div_ceil(15).max(1);
+        for minute in (0..=minutes).step_by(label_every) {
+            writeln!(
+                html,
+                "<text class=\"axis\" x=\"{:.1}\" y=\"{:.1}\" \
+                 text-anchor=\"middle\">{minute}</text>",
+                x(minute as f64),
+                CHART_MARGIN + CHART_HEIGHT + 18.0
+            )?;
+        }
+
+        for (index, report) in &players {
+            let points: Vec<String> = report
+                .apm_by_minute
+                .iter()
+                .enumerate()
+                .map(|(minute, &apm)| format!("{:.1},{:.1}", x(minute as f64 + 0.5), y(apm)))
+                .collect();
+            writeln!(
+                html,
+                "<g class=\"series\" data-player=\"{}\"><title>{}</title>\
+                 <polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/></g>",
+                report.player.player_id,
+                escape(&report.player.name),
+                PLAYER_COLORS[index % PLAYER_COLORS.len()],
+                points.join(" ")
+            )?;
+        }
+        writeln!(html, "</svg>\n<p class=\"legend\">")?;
+        for (index, report) in &players {
+            writeln!(
+                html,
+                "<button type=\"button\" data-player=\"{}\"><span class=\"swatch\" \
+                 style=\"background:{}\"></span>{} ({:.0})</button>",
+                report.player.player_id,
+                PLAYER_COLORS[index % PLAYER_COLORS.len()],
+                escape(&report.player.name),
+                report.player.apm
+            )?;
+        }
+        writeln!(html, "</p>\n<p class=\"note\">Actions per minute of game time.</p>\n</section>")
+    }
+
+    fn write_orders(&self, html: &mut String) -> std::fmt::Result {
+        let players: Vec<_> = self
+            .players
+            .iter()
+            .filter(|p| !p.build_order.is_empty() || !p.hero_skills.is_empty())
+            .collect();
+        writeln!(html, "<section>\n<h2>Build and Skill Orders</h2>")?;
+        if players.is_empty() {
+            return writeln!(
+                html,
+                "<p class=\"note\">No build or skill orders were decoded.</p>\n</section>"
+            );
+        }
+        writeln!(html, "<div class=\"orders\">")?;
+        for report in players {
+            writeln!(html, "<div>\n<h3>{}</h3>", escape(&report.player.name))?;
+            for (heading, orders) in
+                [("Hero skills", &report.hero_skills), ("Build order", &report.build_order)]
+            {
+                if orders.is_empty() {
+                    continue;
+                }
+                writeln!(html, "<h4>{heading}</h4>\n<ol>")?;
+                for order in orders {
+                    writeln!(
+                        html,
+                        "<li><time>{}</time> <code>{}</code></li>",
+                        clock(order.time_ms),
+                        escape(&order.code)
+                    )?;
+                }
+                writeln!(html, "</ol>")?;
+            }
+            writeln!(html, "</div>")?;
+        }
+        writeln!(html, "</div>\n</section>")
+    }
+
+    fn write_chat(&self, html: &mut String) -> std::fmt::Result {
+        writeln!(html, "<section>\n<h2>Chat</h2>")?;
+        if self.chat.is_empty() {
+            return writeln!(html, "<p class=\"note\">No chat messages.</p>\n</section>");
+        }
+        writeln!(html, "<table class=\"log\">")?;
+        for line in &self.chat {
+            let sender = line
+                .player_id
+                .map_or_else(|| "System".to_string(), |id| self.player_name(id));
+            writeln!(
+                html,
+                "<tr><td><time>{}</time></td><td>{}</td>\
+                 <td class=\"channel\">{}</td><td>{}</td></tr>",
+                clock(line.time_ms),
+                escape(&sender),
+                escape(&line.channel),
+                escape(&line.message)
+            )?;
+        }
+        writeln!(html, "</table>\n</section>")
+    }
+
+    fn write_leaves(&self, html: &mut String) -> std::fmt::Result {
+        writeln!(html, "<section>\n<h2>Leaves</h2>")?;
+        if self.leaves.is_empty() {
+            return writeln!(html, "<p class=\"note\">No leave events.</p>\n</section>");
+        }
+        writeln!(html, "<table class=\"log\">")?;
+        for leave in &self.leaves {
+            writeln!(
+                html,
+                "<tr><td><time>{}</time></td><td>{}</td>\
+                 <td class=\"channel\">reason 0x{:02X}, result 0x{:02X}</td></tr>",
+                clock(leave.time_ms),
+                escape(&self.player_name(leave.player_id)),
+                leave.reason,
+                leave.result
+            )?;
+        }
+        writeln!(html, "</table>\n</section>")
+    }
+
+    /// Returns the players with their index, which picks their colour.
+    fn indexed_players(&self) -> impl Iterator<Item = (usize, &PlayerReport)> {
+        self.players.iter().enumerate()
+    }
+}
+
+/// Returns the chat lines and leave lines of [`Replay::chat`] and
+/// [`Replay::leaves`].
+fn events(replay: &Replay) -> (Vec<ChatLine>, Vec<LeaveLine>) {
+    let chat = replay
+        .chat
+        .iter()
+        .map(|event| ChatLine {
+            time_ms: event.timestamp_ms,
+            player_id: event.message.sender_slot,
+            channel: channel(event.message.mode).to_string(),
+            message: event.message.message.clone(),
+        })
+        .collect();
+    let leaves = replay
+        .leaves
+        .iter()
+        .map(|event| LeaveLine {
+            time_ms: event.timestamp_ms,
+            player_id: event.record.player_id,
+            reason: event.record.reason,
+            result: event.record.result,
+        })
+        .collect();
+    (chat, leaves)
+}
+
+/// Returns the name of a chat mode.
+fn channel(mode: u32) -> &'static str {
+    match mode {
+        0 => "All",
+        1 => "Allies",
+        2 => "Observers",
+        _ => "Private",
+    }
+}
+
+/// Formats a game time as `M:SS`, or `H:MM:SS` from one hour on.
+fn clock(ms: u32) -> String {
+    let seconds = ms / 1000;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Escapes text for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body { font: 15px/1.5 system-ui, sans-serif; margin: 2rem auto; max-width: 62rem;
+  padding: 0 1rem; color: #222; background: #fafafa; }
+h1 { margin-bottom: .5rem; }
+h2 { border-bottom: 1px solid #ddd; padding-bottom: .25rem; margin-top: 2rem; }
+h4 { margin: .75rem 0 .25rem; font-size: .9rem; color: #555; }
+.meta { display: flex; flex-wrap: wrap; gap: .5rem 2rem; margin: 0; }
+.meta dt { font-size: .8rem; color: #666; }
+.meta dd { margin: 0; font-weight: 600; word-break: break-all; }
+.teams, .orders { display: flex; flex-wrap: wrap; gap: 1.5rem; align-items: flex-start; }
+table { border-collapse: collapse; }
+caption { text-align: left; font-weight: 600; padding-bottom: .25rem; }
+th, td { text-align: left; padding: .2rem .75rem .2rem 0; vertical-align: top; }
+th { font-size: .8rem; color: #666; }
+.log td:first-child, time { font-variant-numeric: tabular-nums; color: #666; }
+.channel { color: #888; font-size: .85rem; }
+.swatch { display: inline-block; width: .8em; height: .8em; border-radius: 2px;
+  margin-right: .4em; vertical-align: -.05em; }
+.chart { width: 100%; height: auto; background: #fff; border: 1px solid #eee; }
+.chart .grid { stroke: #eee; }
+.chart .axis { font-size: 11px; fill: #888; }
+.chart .hidden { display: none; }
+.legend button { font: inherit; border: 1px solid #ddd; background: #fff;
+  border-radius: 4px; margin: 0 .4rem .4rem 0; cursor: pointer; }
+.legend button.off { opacity: .4; }
+.orders ol { list-style: none; padding: 0; margin: 0; }
+.note { color: #888; font-size: .85rem; }
+";
+
+const SCRIPT: &str = "
+document.querySelectorAll('.legend button').forEach(function (button) {
+  button.addEventListener('click', function () {
+    var player = button.dataset.player;
+    button.classList.toggle('off');
+    document.querySelectorAll('.series[data-player=\"' + player + '\"]').forEach(function (g) {
+      g.classList.toggle('hidden');
+    });
+  });
+});
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> MatchReport {
+        let player = |player_id: u8, name: &str, team: u8| PlayerReport {
+            player: PlayerRow {
+                player_id,
+                name: name.to_string(),
+                race: Some("Orc".to_string()),
+                team: Some(team),
+                observer: false,
+                actions: 120,
+                apm: 60.0,
+                result: None,
+            },
+            apm_by_minute: vec![40, 80],
+            build_order: vec![TimedCode { time_ms: 5_000, code: "opeo".to_string() }],
+            hero_skills: vec![TimedCode { time_ms: 65_000, code: "AOcl".to_string() }],
+        };
+        MatchReport {
+            title: "Final <game 3>".to_string(),
+            version: "1.26 (build 6059)".to_string(),
+            game_name: None,
+            map: Some("Maps\\(2)EchoIsles.w3x".to_string()),
+            host: "host".to_string(),
+            game_id: None,
+            duration_ms: 120_000,
+            players: vec![player(1, "Grubby", 0), player(2, "<b>Moon</b>", 1)],
+            chat: vec![
+                ChatLine {
+                    time_ms: 3_600_000,
+                    player_id: Some(2),
+                    channel: "All".to_string(),
+                    message: "<script>alert(1)</script>".to_string(),
+                },
+                ChatLine {
+                    time_ms: 3_601_000,
+                    player_id: None,
+                    channel: "All".to_string(),
+                    message: "Grubby has left the game voluntarily.".to_string(),
+                },
+            ],
+            leaves: vec![LeaveLine { time_ms: 119_000, player_id: 9, reason: 1, result: 8 }],
+        }
+    }
+
+    #[test]
+    fn test_to_html() {
+        let html = report().to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Final &lt;game 3&gt;</title>"));
+        assert!(html.contains("<caption>Team 1</caption>"));
+        assert!(html.contains("<caption>Team 2</caption>"));
+        assert!(html.contains("&lt;b&gt;Moon&lt;/b&gt;"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("<time>1:00:00</time>"));
+        assert!(html.contains("<code>AOcl</code>"));
+        assert!(html.contains("Player 9"));
+        assert!(html.contains("<td>System</td>"));
+        assert_eq!(html.matches("<polyline").count(), 2);
+        assert_eq!(html.matches("<script>").count(), 1);
+    }
+
+    #[test]
+    fn test_channel_and_clock() {
+        assert_eq!(channel(0), "All");
+        assert_eq!(channel(1), "Allies");
+        assert_eq!(channel(5), "Private");
+        assert_eq!(clock(65_000), "1:05");
+        assert_eq!(clock(3_725_000), "1:02:05");
+        assert_eq!(escape("a&'\""), "a&amp;&#39;&quot;");
+    }
+}
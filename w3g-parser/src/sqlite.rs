@@ -0,0 +1,441 @@
+//! `SQLite` export of replays.
+//!
+//! [`ReplayDatabase`] writes replays into a normalised `SQLite` schema so they
+//! can be queried with SQL from an embedded database file:
+//!
+//! | Table | Rows |
+//! |-------|------|
+//! | `replays` | One per game, keyed by [`Replay::game_id`] |
+//! | `players` | Roster with race, team, APM and result |
+//! | `slots` | Lobby slot table |
+//! | `chat` | Chat messages, if collected (see [`crate::ParseOptions::chat`]) |
+//! | `leaves` | Players leaving the game |
+//! | `actions` | Every action of a roster player |
+//! | `build_orders` | Build, train, research and purchase orders |
+//!
+//! Child tables reference `replays.id` through their `replay_id` column. All
+//! times are game time in milliseconds from exact record framing. The full
+//! definition is in [`SCHEMA`].
+//!
+//! Replays are upserted by fingerprint: importing another perspective of a
+//! game that is already in the database, or the same file again, replaces
+//! that game's rows instead of adding a duplicate. A perspective shorter than
+//! the stored one, such as the replay of a player who left early, is not
+//! imported, so the database keeps the longest record of each game.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::sqlite::ReplayDatabase;
+//! use w3g_parser::Replay;
+//!
+//! let mut db = ReplayDatabase::open("replays.db")?;
+//! let replay = Replay::from_path("replay.w3g")?;
+//! db.upsert(&replay, "replay.w3g")?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::error::{ParserError, Result};
+use crate::export::ReplayTables;
+use crate::header::Header;
+use crate::records::SlotStatus;
+use crate::replay::Replay;
+
+/// Version of [`SCHEMA`], stored in the database's `user_version`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// SQL definition of the database schema.
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS replays (
+    id INTEGER PRIMARY KEY,
+    game_id TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    format TEXT NOT NULL,
+    build_version INTEGER,
+    version TEXT,
+    duration_ms INTEGER NOT NULL,
+    game_name TEXT NOT NULL,
+    map_path TEXT,
+    host_name TEXT NOT NULL,
+    random_seed INTEGER
+);
+CREATE TABLE IF NOT EXISTS players (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    player_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    race TEXT,
+    team INTEGER,
+    observer INTEGER NOT NULL,
+    actions INTEGER NOT NULL,
+    apm REAL NOT NULL,
+    result TEXT,
+    PRIMARY KEY (replay_id, player_id)
+);
+CREATE TABLE IF NOT EXISTS slots (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    slot INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    computer INTEGER NOT NULL,
+    team INTEGER NOT NULL,
+    color INTEGER NOT NULL,
+    race_flags INTEGER NOT NULL,
+    ai_strength INTEGER,
+    handicap INTEGER,
+    PRIMARY KEY (replay_id, slot)
+);
+CREATE TABLE IF NOT EXISTS chat (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    seq INTEGER NOT NULL,
+    time_ms INTEGER NOT NULL,
+    player_id INTEGER,
+    mode INTEGER,
+    message TEXT NOT NULL,
+    PRIMARY KEY (replay_id, seq)
+);
+CREATE TABLE IF NOT EXISTS leaves (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    seq INTEGER NOT NULL,
+    time_ms INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    reason INTEGER NOT NULL,
+    result INTEGER NOT NULL,
+    PRIMARY KEY (replay_id, seq)
+);
+CREATE TABLE IF NOT EXISTS actions (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    seq INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    time_ms INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    code TEXT,
+    x REAL,
+    y REAL,
+    PRIMARY KEY (replay_id, seq)
+);
+CREATE TABLE IF NOT EXISTS build_orders (
+    replay_id INTEGER NOT NULL REFERENCES replays(id),
+    player_id INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    time_ms INTEGER NOT NULL,
+    code TEXT NOT NULL,
+    PRIMARY KEY (replay_id, player_id, seq)
+);
+";
+
+/// Tables holding rows of a single replay, cleared before it is re-imported.
+const CHILD_TABLES: [&str; 6] = ["players", "slots", "chat", "leaves", "actions", "build_orders"];
+
+/// Outcome of [`ReplayDatabase::upsert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upsert {
+    /// The game was not in the database yet.
+    Inserted,
+    /// The game was already in the database and its rows were replaced.
+    Replaced,
+    /// The game was already in the database from a longer perspective, which
+    /// was kept.
+    Kept,
+}
+
+/// A `SQLite` database of replays.
+#[derive(Debug)]
+pub struct ReplayDatabase {
+    conn: Connection,
+}
+
+impl ReplayDatabase {
+    /// Opens the database at `path`, creating it and the schema if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if the database cannot be opened or was
+    /// created with a different schema version.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Opens a new in-memory database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if the schema cannot be created.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        let version: u32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        if version != 0 && version != SCHEMA_VERSION {
+            return Err(ParserError::IoError(std::io::Error::other(format!(
+                "database has schema version {version}, expected {SCHEMA_VERSION}"
+            ))));
+        }
+        conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(sqlite_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Returns the underlying connection, for queries.
+    #[must_use]
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Writes `replay` into the database, replacing the rows of the same game
+    /// if it was imported before.
+    ///
+    /// A game imported before from a longer perspective (a longer
+    /// `duration_ms`) is kept as it is and [`Upsert::Kept`] is returned.
+    ///
+    /// `source` is stored with the replay, such as the file's path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record stream cannot be framed, or
+    /// `ParserError::IoError` if `replay` was parsed without actions or the
+    /// database cannot be written. Nothing is written if an error occurs.
+    pub fn upsert(&mut self, replay: &Replay, source: &str) -> Result<Upsert> {
+        let game_id = replay.game_id()?;
+        let tables = ReplayTables::from_replay(replay, source)?;
+        let tx = self.conn.transaction().map_err(sqlite_error)?;
+
+        let stored: Option<(i64, i64)> = tx
+            .query_row(
+                "SELECT id, duration_ms FROM replays WHERE game_id = ?1",
+                [&game_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        if stored.is_some_and(|(_, duration)| duration > i64::from(replay.duration_ms)) {
+            return Ok(Upsert::Kept);
+        }
+
+        let existing = stored.map(|(id, _)| id);
+        if let Some(id) = existing {
+            for table in CHILD_TABLES {
+                tx.execute(&format!("DELETE FROM {table} WHERE replay_id = ?1"), [id])
+                    .map_err(sqlite_error)?;
+            }
+        }
+
+        insert_replay(&tx, replay, &game_id, &tables, existing)
+            .and_then(|id| insert_rows(&tx, id, replay, &tables))
+            .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+
+        Ok(if existing.is_some() {
+            Upsert::Replaced
+        } else {
+            Upsert::Inserted
+        })
+    }
+
+    /// Returns the number of games in the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParserError::IoError` if the query fails.
+    pub fn replay_count(&self) -> Result<usize> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM replays", [], |row| row.get(0))
+            .map_err(sqlite_error)
+    }
+}
+
+/// Inserts or updates the `replays` row, returning its ID.
+fn insert_replay(
+    tx: &Transaction<'_>,
+    replay: &Replay,
+    game_id: &str,
+    tables: &ReplayTables,
+    existing: Option<i64>,
+) -> rusqlite::Result<i64> {
+    let (build_version, version) = match &replay.header {
+        Header::Classic(header) => (Some(header.build_version), Some(header.version_string())),
+        Header::Grbn(_) => (None, None),
+    };
+    let settings = replay.settings();
+    let seed = replay.slot_table().ok().flatten().map(|table| table.random_seed);
+
+    tx.execute(
+        "INSERT INTO replays (id, game_id, source, format, build_version, version, duration_ms,
+             game_name, map_path, host_name, random_seed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT (id) DO UPDATE SET
+             source = excluded.source, format = excluded.format,
+             build_version = excluded.build_version, version = excluded.version,
+             duration_ms = excluded.duration_ms, game_name = excluded.game_name,
+             map_path = excluded.map_path, host_name = excluded.host_name,
+             random_seed = excluded.random_seed",
+        params![
+            existing,
+            game_id,
+            tables.replay_id,
+            format!("{:?}", replay.header.format()),
+            build_version,
+            version,
+            tables.duration_ms,
+            settings.game_name(),
+            settings.map_path(),
+            settings.host_name,
+            seed,
+        ],
+    )?;
+    Ok(existing.unwrap_or_else(|| tx.last_insert_rowid()))
+}
+
+/// Inserts the child table rows of the replay with ID `id`.
+fn insert_rows(
+    tx: &Transaction<'_>,
+    id: i64,
+    replay: &Replay,
+    tables: &ReplayTables,
+) -> rusqlite::Result<()> {
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO players (replay_id, player_id, name, race, team, observer, actions, apm,
+             result)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for player in &tables.players {
+        insert.execute(params![
+            id,
+            player.player_id,
+            player.name,
+            player.race,
+            player.team,
+            player.observer,
+            player.actions,
+            player.apm,
+            player.result,
+        ])?;
+    }
+
+    if let Some(table) = replay.slot_table().ok().flatten() {
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO slots (replay_id, slot, player_id, status, computer, team, color,
+                 race_flags, ai_strength, handicap)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for (index, slot) in table.slots.iter().enumerate() {
+            insert.execute(params![
+                id,
+                index,
+                slot.player_id,
+                status_label(slot.status),
+                slot.is_computer,
+                slot.team,
+                slot.color,
+                slot.race_flags,
+                slot.ai_strength,
+                slot.handicap,
+            ])?;
+        }
+    }
+
+    insert_events(tx, id, replay)?;
+
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO actions (replay_id, seq, player_id, time_ms, type, code, x, y)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?;
+    for (seq, action) in tables.actions.iter().enumerate() {
+        insert.execute(params![
+            id,
+            seq,
+            action.player_id,
+            action.time_ms,
+            action.action_type,
+            action.code,
+            action.x,
+            action.y,
+        ])?;
+    }
+
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO build_orders (replay_id, player_id, seq, time_ms, code)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut counts = [0usize; 256];
+    for action in tables.actions.iter().filter(|action| action.is_build_order()) {
+        let seq = &mut counts[usize::from(action.player_id)];
+        insert.execute(params![id, action.player_id, *seq, action.time_ms, action.code])?;
+        *seq += 1;
+    }
+    Ok(())
+}
+
+/// Inserts the chat and leave rows of [`Replay::chat`] and [`Replay::leaves`].
+fn insert_events(tx: &Transaction<'_>, id: i64, replay: &Replay) -> rusqlite::Result<()> {
+    let mut chat = tx.prepare_cached(
+        "INSERT INTO chat (replay_id, seq, time_ms, player_id, mode, message)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut leaves = tx.prepare_cached(
+        "INSERT INTO leaves (replay_id, seq, time_ms, player_id, reason, result)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+
+    for (seq, event) in replay.chat.iter().enumerate() {
+        let message = &event.message;
+        chat.execute(params![
+            id,
+            seq,
+            event.timestamp_ms,
+            message.sender_slot,
+            message.mode,
+            message.message,
+        ])?;
+    }
+    for (seq, event) in replay.leaves.iter().enumerate() {
+        let leave = &event.record;
+        leaves.execute(params![
+            id,
+            seq,
+            event.timestamp_ms,
+            leave.player_id,
+            leave.reason,
+            leave.result,
+        ])?;
+    }
+    Ok(())
+}
+
+fn status_label(status: SlotStatus) -> String {
+    match status {
+        SlotStatus::Open => "open".to_string(),
+        SlotStatus::Closed => "closed".to_string(),
+        SlotStatus::Used => "used".to_string(),
+        SlotStatus::Unknown(byte) => format!("0x{byte:02X}"),
+    }
+}
+
+fn sqlite_error(e: rusqlite::Error) -> ParserError {
+    ParserError::IoError(std::io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_is_created_once() {
+        let db = ReplayDatabase::open_in_memory().unwrap();
+        assert_eq!(db.replay_count().unwrap(), 0);
+        db.connection().execute_batch(SCHEMA).unwrap();
+
+        let version: u32 = db
+            .connection()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+}
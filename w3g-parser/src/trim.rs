@@ -0,0 +1,241 @@
+//! Replay trimming.
+//!
+//! [`trim`] cuts a replay down to a window of game time, for example
+//! 10:00–15:00 for a highlight clip or a bug report. The game record header,
+//! player list, slot table and game start records are always kept, and
+//! `TimeFrame`s that end after the window are dropped together with every
+//! record that follows them.
+//!
+//! Warcraft III replays are deterministic simulations, so the game state at
+//! the start of the window depends on every action before it. What happens
+//! to the lead-in before the window is chosen with [`LeadIn`]:
+//!
+//! | Policy | Lead-in records | Playback |
+//! |--------|-----------------|----------|
+//! | [`LeadIn::Keep`] | unchanged | stays in sync |
+//! | [`LeadIn::Empty`] | collapsed into empty `TimeFrame`s | timestamps kept, state diverges |
+//! | [`LeadIn::Drop`] | removed | window starts at 00:00 |
+//!
+//! With [`LeadIn::Empty`] and [`LeadIn::Drop`], chat and checksum records from
+//! the lead-in are removed and leave records are kept, so the roster is still
+//! correct at the start of the window.
+//!
+//! The result is written with [`ReplayWriter`]. For GRBN replays the header and
+//! metadata are kept and the embedded Classic replay is replaced; the GRBN
+//! header's own duration is not updated.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::trim::{trim, LeadIn, TrimOptions};
+//!
+//! let data = std::fs::read("replay.w3g")?;
+//! let options = TrimOptions::new(600_000..=900_000).lead_in(LeadIn::Empty);
+//! std::fs::write("clip.w3g", trim(&data, &options)?)?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::ops::RangeInclusive;
+
+use crate::decompress::grbn::find_classic_header;
+use crate::error::{ParserError, Result};
+use crate::header::Header;
+use crate::records::Record;
+use crate::replay::{ParseOptions, Replay};
+use crate::writer::ReplayWriter;
+
+/// `TimeFrame` marker used for the empty frames written by [`LeadIn::Empty`].
+const EMPTY_FRAME_MARKER: u8 = 0x1F;
+
+/// What to do with the records before the start of the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LeadIn {
+    /// Keep every record, so playback reaches the window in sync.
+    #[default]
+    Keep,
+    /// Replace the lead-in with empty `TimeFrame`s of the same total duration.
+    ///
+    /// Action timestamps inside the window are unchanged, but the actions that
+    /// built up the game state are gone.
+    Empty,
+    /// Remove the lead-in, so the window starts at game time zero.
+    Drop,
+}
+
+/// Options controlling [`trim`].
+#[derive(Debug, Clone)]
+pub struct TrimOptions {
+    start_ms: u32,
+    end_ms: u32,
+    lead_in: LeadIn,
+}
+
+impl TrimOptions {
+    /// Creates options keeping the game time window `range`, in milliseconds.
+    ///
+    /// A `TimeFrame` belongs to the window when the game time at its end lies
+    /// in `range`.
+    #[must_use]
+    pub fn new(range: RangeInclusive<u32>) -> Self {
+        Self {
+            start_ms: *range.start(),
+            end_ms: *range.end(),
+            lead_in: LeadIn::default(),
+        }
+    }
+
+    /// Sets the lead-in policy.
+    #[must_use]
+    pub fn lead_in(mut self, policy: LeadIn) -> Self {
+        self.lead_in = policy;
+        self
+    }
+
+    /// Returns the window start in milliseconds.
+    #[must_use]
+    pub fn start_ms(&self) -> u32 {
+        self.start_ms
+    }
+
+    /// Returns the window end in milliseconds, which is part of the window.
+    #[must_use]
+    pub fn end_ms(&self) -> u32 {
+        self.end_ms
+    }
+
+    /// Returns the lead-in policy.
+    #[must_use]
+    pub fn lead_in_policy(&self) -> LeadIn {
+        self.lead_in
+    }
+}
+
+/// Trims a replay file to a window of game time and returns the new file.
+///
+/// # Errors
+///
+/// - Any error from parsing the replay or framing its records
+/// - `ParserError::DecompressionError` if a GRBN file has no embedded Classic replay
+pub fn trim(data: &[u8], options: &TrimOptions) -> Result<Vec<u8>> {
+    match Header::parse(data)? {
+        Header::Classic(_) => trim_classic(data, options),
+        Header::Grbn(_) => {
+            let classic_offset = find_classic_header(data).ok_or_else(|| {
+                ParserError::DecompressionError {
+                    reason: "No embedded Classic replay found in GRBN file".to_string(),
+                }
+            })?;
+            let mut out = data[..classic_offset].to_vec();
+            out.extend(trim_classic(&data[classic_offset..], options)?);
+            Ok(out)
+        }
+    }
+}
+
+/// Trims a Classic replay.
+fn trim_classic(data: &[u8], options: &TrimOptions) -> Result<Vec<u8>> {
+    let parse_options = ParseOptions::new().actions(false).chat(false);
+    let replay = Replay::from_bytes_with(data, &parse_options)?;
+    let Header::Classic(header) = &replay.header else {
+        return Err(ParserError::WriteError {
+            reason: "expected a Classic format replay".to_string(),
+        });
+    };
+
+    let mut writer = ReplayWriter::new(header.clone());
+    writer.write_game_header(&replay.game.header);
+
+    let mut time_ms = 0u32;
+    // Lead-in time not yet written as empty frames
+    let mut pending_ms = 0u32;
+    for result in replay.records()? {
+        let (_, record) = result?;
+        if let Record::TimeFrame {
+            time_increment_ms, ..
+        } = &record
+        {
+            time_ms = time_ms.saturating_add(u32::from(*time_increment_ms));
+            if time_ms > options.end_ms {
+                break;
+            }
+        }
+
+        let in_lead_in = time_ms < options.start_ms && !is_pre_game(&record);
+        if !in_lead_in || options.lead_in == LeadIn::Keep {
+            flush_lead_in(&mut writer, &mut pending_ms);
+            writer.write_record(&record);
+            continue;
+        }
+
+        match record {
+            Record::TimeFrame {
+                time_increment_ms, ..
+            } if options.lead_in == LeadIn::Empty => {
+                pending_ms += u32::from(time_increment_ms);
+            }
+            Record::Leave(_) => {
+                flush_lead_in(&mut writer, &mut pending_ms);
+                writer.write_record(&record);
+            }
+            _ => {}
+        }
+    }
+
+    writer.finish()
+}
+
+/// Returns whether `record` belongs to the player list and startup sequence.
+fn is_pre_game(record: &Record) -> bool {
+    matches!(
+        record,
+        Record::Player(_)
+            | Record::SlotTable { .. }
+            | Record::ExtendedMetadata { .. }
+            | Record::GameStart { .. }
+    )
+}
+
+/// Writes `pending_ms` of game time as empty `TimeFrame`s and resets it.
+fn flush_lead_in(writer: &mut ReplayWriter, pending_ms: &mut u32) {
+    for frame in empty_frames(*pending_ms) {
+        writer.write_record(&frame);
+    }
+    *pending_ms = 0;
+}
+
+/// Returns as few empty `TimeFrame`s as possible covering `duration_ms`.
+fn empty_frames(mut duration_ms: u32) -> Vec<Record> {
+    let mut frames = Vec::new();
+    while duration_ms > 0 {
+        let increment = u16::try_from(duration_ms).unwrap_or(u16::MAX);
+        frames.push(Record::TimeFrame {
+            marker: EMPTY_FRAME_MARKER,
+            time_increment_ms: increment,
+            commands: Vec::new(),
+        });
+        duration_ms -= u32::from(increment);
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_frames_split_long_durations() {
+        let frames = empty_frames(150_000);
+        let increments: Vec<u32> = frames.iter().map(Record::time_increment_ms).collect();
+        assert_eq!(increments, [65_535, 65_535, 18_930]);
+        assert!(empty_frames(0).is_empty());
+    }
+
+    #[test]
+    fn test_is_pre_game() {
+        assert!(is_pre_game(&Record::GameStart {
+            marker: 0x1A,
+            value: 1
+        }));
+        assert!(!is_pre_game(&Record::Checksum { data: vec![0; 4] }));
+    }
+}
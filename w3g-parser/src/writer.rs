@@ -0,0 +1,296 @@
+//! Classic replay writer.
+//!
+//! [`ReplayWriter`] builds a Classic format `.w3g` file from a
+//! [`ClassicHeader`], a [`GameRecord`] and a stream of [`Record`]s. The
+//! records are encoded into the decompressed data, which is split into
+//! 8 KiB blocks, zero-padded, zlib-compressed and given Type A or Type B block
+//! headers depending on the header's build version.
+//!
+//! On [`ReplayWriter::finish`] the header's `file_size`, `decompressed_size`,
+//! `block_count`, `duration_ms` and `checksum` are recomputed, so the header
+//! passed in only needs to supply the magic, build version and flags.
+//!
+//! # Checksums
+//!
+//! The header checksum is the CRC32 of the 68 header bytes with the checksum
+//! field zeroed. Each block checksum combines two CRC32s folded to 16 bits:
+//! the low half covers the block header with its checksum zeroed, the high
+//! half covers the compressed data (see [`block_checksum`]).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use w3g_parser::{Replay, ReplayWriter};
+//!
+//! let replay = Replay::from_path("replay.w3g")?;
+//! let bytes = ReplayWriter::from_replay(&replay)?.finish()?;
+//! std::fs::write("copy.w3g", bytes)?;
+//! # Ok::<(), w3g_parser::error::ParserError>(())
+//! ```
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+
+use crate::decompress::classic::{BLOCK_DECOMPRESSED_SIZE, BLOCK_HEADER_SIZE_A};
+use crate::error::{ParserError, Result};
+use crate::format::ClassicVersion;
+use crate::header::classic::{ClassicHeader, CLASSIC_HEADER_SIZE};
+use crate::header::Header;
+use crate::records::{GameRecord, GameRecordHeader, Record};
+use crate::replay::Replay;
+
+/// Computes the checksum stored in a Classic block header.
+///
+/// `block_header` is the encoded block header with its trailing 4-byte
+/// checksum field zeroed, and `compressed` is the block's compressed data.
+#[must_use]
+pub fn block_checksum(block_header: &[u8], compressed: &[u8]) -> u32 {
+    let fold = |bytes: &[u8]| {
+        let mut crc = Crc::new();
+        crc.update(bytes);
+        let sum = crc.sum();
+        (sum ^ (sum >> 16)) & 0xFFFF
+    };
+    fold(block_header) | (fold(compressed) << 16)
+}
+
+/// Builds a Classic format replay file.
+///
+/// Data is written in file order: the game record header, the player list and
+/// startup records, then the action stream records.
+#[derive(Debug, Clone)]
+pub struct ReplayWriter {
+    header: ClassicHeader,
+    data: Vec<u8>,
+    duration_ms: u32,
+}
+
+impl ReplayWriter {
+    /// Creates a writer that will use `header` as the template for the output header.
+    #[must_use]
+    pub fn new(header: ClassicHeader) -> Self {
+        Self {
+            header,
+            data: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+
+    /// Creates a writer holding all records of a parsed Classic replay.
+    ///
+    /// The game record header is re-encoded and followed by every record of
+    /// [`Replay::records`].
+    ///
+    /// # Errors
+    ///
+    /// - `ParserError::WriteError` if the replay is not in Classic format
+    /// - `ParserError::UnexpectedRecord` if the record stream cannot be framed
+    pub fn from_replay(replay: &Replay) -> Result<Self> {
+        let Header::Classic(header) = &replay.header else {
+            return Err(ParserError::WriteError {
+                reason: "only Classic format replays can be written".to_string(),
+            });
+        };
+
+        let mut writer = Self::new(header.clone());
+        writer.write_game_header(&replay.game.header);
+        for result in replay.records()? {
+            let (_, record) = result?;
+            writer.write_record(&record);
+        }
+
+        Ok(writer)
+    }
+
+    /// Appends the encoded game record header.
+    ///
+    /// The player list and slot table follow as [`Record`]s.
+    pub fn write_game_header(&mut self, header: &GameRecordHeader) {
+        self.data.extend(header.encode());
+    }
+
+    /// Appends the encoded game record header and player records.
+    ///
+    /// This writes the parsed [`GameRecord`] model only; use
+    /// [`ReplayWriter::write_game_header`] and [`Record::Player`] records to
+    /// reproduce an existing replay exactly.
+    pub fn write_game_record(&mut self, game: &GameRecord) {
+        self.data.extend(game.encode());
+    }
+
+    /// Appends raw decompressed bytes, such as records not covered by the model.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Appends an encoded record, adding `TimeFrame` increments to the duration.
+    pub fn write_record(&mut self, record: &Record) {
+        self.duration_ms = self.duration_ms.saturating_add(record.time_increment_ms());
+        self.data.extend(record.encode());
+    }
+
+    /// Returns the decompressed data written so far.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the game duration accumulated from the `TimeFrame` records.
+    #[must_use]
+    pub fn duration_ms(&self) -> u32 {
+        self.duration_ms
+    }
+
+    /// Compresses the data and returns the complete replay file.
+    ///
+    /// # Errors
+    ///
+    /// - `ParserError::WriteError` if the data exceeds the header's 32-bit size fields
+    /// - `ParserError::IoError` if compression fails
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let Self {
+            mut header,
+            data,
+            duration_ms,
+        } = self;
+
+        let too_large = |_| ParserError::WriteError {
+            reason: "replay data exceeds the 32-bit Classic size fields".to_string(),
+        };
+        let version = header.version_type();
+
+        let mut blocks = Vec::new();
+        for chunk in data.chunks(BLOCK_DECOMPRESSED_SIZE) {
+            let mut block = chunk.to_vec();
+            block.resize(BLOCK_DECOMPRESSED_SIZE, 0);
+
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&block)?;
+            let compressed = encoder.finish()?;
+
+            blocks.extend(encode_block(version, &compressed));
+        }
+
+        header.block_count = u32::try_from(data.len().div_ceil(BLOCK_DECOMPRESSED_SIZE))
+            .map_err(too_large)?;
+        header.decompressed_size = u32::try_from(data.len()).map_err(too_large)?;
+        header.file_size = u32::try_from(CLASSIC_HEADER_SIZE + blocks.len()).map_err(too_large)?;
+        header.duration_ms = duration_ms;
+        header.checksum = header.compute_checksum();
+
+        let mut out = header.encode().to_vec();
+        out.extend(blocks);
+        Ok(out)
+    }
+}
+
+/// Encodes one block header followed by its compressed data.
+fn encode_block(version: ClassicVersion, compressed: &[u8]) -> Vec<u8> {
+    // A zlib stream of 8 KiB input never comes close to 64 KiB.
+    let compressed_size = u16::try_from(compressed.len()).expect("compressed block too large");
+    #[allow(clippy::cast_possible_truncation)]
+    let decompressed_size = BLOCK_DECOMPRESSED_SIZE as u16;
+
+    let mut block = Vec::with_capacity(version.block_header_size() + compressed.len());
+    block.extend_from_slice(&compressed_size.to_le_bytes());
+    if version.block_header_size() > BLOCK_HEADER_SIZE_A {
+        block.extend_from_slice(&[0, 0]);
+    }
+    block.extend_from_slice(&decompressed_size.to_le_bytes());
+    if version.block_header_size() > BLOCK_HEADER_SIZE_A {
+        block.extend_from_slice(&[0, 0]);
+    }
+    block.extend_from_slice(&[0; 4]);
+
+    let checksum_at = block.len() - 4;
+    let checksum = block_checksum(&block, compressed);
+    block[checksum_at..].copy_from_slice(&checksum.to_le_bytes());
+    block.extend_from_slice(compressed);
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompress::classic::decompress_classic;
+    use crate::format::CLASSIC_MAGIC;
+    use crate::header::classic::TFT_SUB_HEADER_MAGIC;
+
+    fn template(build_version: u32) -> ClassicHeader {
+        let mut magic = [0u8; 28];
+        magic.copy_from_slice(CLASSIC_MAGIC);
+        ClassicHeader {
+            magic,
+            header_size: 68,
+            file_size: 0,
+            header_version: 1,
+            decompressed_size: 0,
+            block_count: 0,
+            sub_header_magic: *TFT_SUB_HEADER_MAGIC,
+            build_version,
+            flags: 0x8000_0000,
+            duration_ms: 0,
+            checksum: [0; 4],
+        }
+    }
+
+    fn write_frames(build_version: u32) -> (ReplayWriter, Vec<u8>) {
+        let mut writer = ReplayWriter::new(template(build_version));
+        for _ in 0..3000 {
+            writer.write_record(&Record::TimeFrame {
+                marker: 0x1F,
+                time_increment_ms: 100,
+                commands: vec![0x01, 0x02, 0x03],
+            });
+        }
+        let data = writer.data().to_vec();
+        (writer, data)
+    }
+
+    #[test]
+    fn test_block_checksum_halves() {
+        let header = [0x10, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00];
+        let checksum = block_checksum(&header, b"data");
+        assert_eq!(checksum & 0xFFFF, block_checksum(&header, b"other") & 0xFFFF);
+        assert_ne!(checksum >> 16, block_checksum(&header, b"other") >> 16);
+    }
+
+    #[test]
+    fn test_finish_type_a() {
+        let (writer, data) = write_frames(26);
+        assert_eq!(writer.duration_ms(), 300_000);
+        let bytes = writer.finish().unwrap();
+
+        let header = ClassicHeader::parse(&bytes).unwrap();
+        assert_eq!(header.file_size as usize, bytes.len());
+        assert_eq!(header.decompressed_size as usize, data.len());
+        assert_eq!(header.block_count, 3);
+        assert_eq!(header.duration_ms, 300_000);
+        assert!(header.checksum_is_valid());
+
+        let decompressed = decompress_classic(&bytes, &header).unwrap();
+        assert_eq!(&decompressed[..data.len()], data.as_slice());
+        assert!(decompressed[data.len()..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_finish_type_b() {
+        let (writer, data) = write_frames(10032);
+        let bytes = writer.finish().unwrap();
+
+        let header = ClassicHeader::parse(&bytes).unwrap();
+        assert!(header.is_type_b());
+        let decompressed = decompress_classic(&bytes, &header).unwrap();
+        assert_eq!(&decompressed[..data.len()], data.as_slice());
+    }
+
+    #[test]
+    fn test_finish_empty() {
+        let bytes = ReplayWriter::new(template(26)).finish().unwrap();
+        let header = ClassicHeader::parse(&bytes).unwrap();
+        assert_eq!(header.block_count, 0);
+        assert_eq!(bytes.len(), CLASSIC_HEADER_SIZE);
+    }
+}
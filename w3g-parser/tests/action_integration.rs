@@ -242,9 +242,9 @@ fn test_movement_coordinates_reasonable() {
             if let Ok(frame) = frame_result {
                 for action_result in frame.actions() {
                     if let Ok(action) = action_result {
-                        if let ActionType::Movement(mov) = &action.action_type {
+                        if let Some(position) = action.action_type.position() {
                             movement_count += 1;
-                            if mov.is_valid_position() {
+                            if position.is_valid() {
                                 valid_count += 1;
                             }
                         }
@@ -304,42 +304,6 @@ fn test_selection_action_parsing() {
     assert_eq!(consumed, 12);
 }
 
-/// Test ability action parsing.
-#[test]
-fn test_ability_action_parsing() {
-    use w3g_parser::AbilityAction;
-
-    // Direct ability
-    let data: &[u8] = &[
-        0x1A, 0x19, // Ability command
-        0x77, 0x6F, 0x74, 0x68, // "woth"
-        0x3B, 0x3A, 0x00, 0x00, 0x3B, 0x3A, 0x00, 0x00, // Target
-    ];
-    let (action, consumed) = AbilityAction::parse(data).unwrap();
-    assert_eq!(action.ability_code.as_string(), "htow");
-    assert_eq!(consumed, 14);
-}
-
-/// Test movement action parsing.
-#[test]
-fn test_movement_action_parsing() {
-    use w3g_parser::MovementAction;
-
-    // Ground movement
-    let data: &[u8] = &[
-        0x00, 0x0D, 0x00, 0x00, // Move command
-        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // No target
-        0x00, 0x00, 0xB0, 0xC5, // X = -5632.0
-        0x00, 0x00, 0x60, 0x45, // Y = 3584.0
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Extra
-    ];
-    let (mov, consumed) = MovementAction::parse(data).unwrap();
-    assert!(mov.is_ground_target());
-    assert!((mov.x - (-5632.0)).abs() < 0.1);
-    assert!((mov.y - 3584.0).abs() < 0.1);
-    assert_eq!(consumed, 28);
-}
-
 /// Test action statistics tracking.
 #[test]
 fn test_action_statistics() {
@@ -352,7 +316,6 @@ fn test_action_statistics() {
         ActionType::Selection(SelectionAction {
             unit_count: 1,
             mode: 1,
-            flags: 0,
             unit_ids: vec![0x1234],
         }),
         1000,
@@ -364,3 +327,134 @@ fn test_action_statistics() {
     assert_eq!(stats.selection_actions, 1);
     assert_eq!(stats.actions_per_player.get(&1), Some(&1));
 }
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+/// Loads and decompresses a fixture replay.
+fn load_fixture(filename: &str) -> Vec<u8> {
+    let path = Path::new(FIXTURES_DIR).join(filename);
+    let data = fs::read(&path).unwrap_or_else(|_| panic!("Failed to read {path:?}"));
+    let header = Header::parse(&data).unwrap_or_else(|_| panic!("Failed to parse {path:?}"));
+    decompress(&data, &header).unwrap_or_else(|_| panic!("Failed to decompress {path:?}"))
+}
+
+#[test]
+fn test_w3mmd_stats_from_fixture() {
+    use w3g_parser::actions::{MmdFlag, W3mmdStats};
+
+    let decompressed = load_fixture("replay_10000.w3g");
+    let game_record = GameRecord::parse(&decompressed).expect("Failed to parse game record");
+    let stats = W3mmdStats::from_iterator(game_record.timeframes(&decompressed))
+        .expect("Failed to collect W3MMD stats");
+
+    assert!(!stats.is_empty());
+    assert_eq!(stats.players[&3].name, "JUSTANOTHERORC");
+    assert!(stats.players[&0].flags.contains(&MmdFlag::Winner));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip_from_fixture() {
+    use w3g_parser::Action;
+
+    let data = fs::read(Path::new(FIXTURES_DIR).join("replay_1.w3g")).unwrap();
+    let header = Header::parse(&data).unwrap();
+    let json = serde_json::to_string(&header).unwrap();
+    assert_eq!(serde_json::from_str::<Header>(&json).unwrap(), header);
+
+    let decompressed = load_fixture("replay_1.w3g");
+    let game_record = GameRecord::parse(&decompressed).expect("Failed to parse game record");
+    let json = serde_json::to_string(&game_record.players).unwrap();
+    assert_eq!(
+        serde_json::from_str::<w3g_parser::PlayerRoster>(&json).unwrap(),
+        game_record.players
+    );
+
+    let actions: Vec<Action> = game_record
+        .timeframes(&decompressed)
+        .flatten()
+        .flat_map(|frame| frame.actions().flatten().collect::<Vec<_>>())
+        .collect();
+    assert!(!actions.is_empty());
+
+    let json = serde_json::to_value(&actions).unwrap();
+    let restored: Vec<Action> = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+}
+
+#[test]
+fn test_action_filter_on_fixture() {
+    use w3g_parser::{ActionFilter, Replay};
+
+    let replay = Replay::from_path(Path::new(FIXTURES_DIR).join("replay_10000.w3g")).unwrap();
+    let filter: ActionFilter = "type=UnitAbilityNoTarget,Selection and player=2".parse().unwrap();
+    let selected: Vec<_> = replay.actions.iter().filter(|a| filter.matches(a)).collect();
+    assert!(!selected.is_empty());
+    assert!(selected.iter().all(|a| a.player_id == 2));
+    assert_eq!(
+        selected.len(),
+        replay
+            .actions
+            .iter()
+            .filter(|a| a.player_id == 2)
+            .filter(|a| {
+                matches!(
+                    a.action_type,
+                    ActionType::UnitAbilityNoTarget { .. } | ActionType::Selection(_)
+                )
+            })
+            .count()
+    );
+
+    let first = selected[0];
+    let code = first
+        .action_type
+        .object_code()
+        .filter(|code| code.is_valid_fourcc())
+        .map(|code| code.as_string());
+    let window = ActionFilter::new()
+        .players([2])
+        .from_ms(first.timestamp_ms)
+        .to_ms(first.timestamp_ms + 1);
+    assert!(window.matches(first));
+    if let Some(code) = code {
+        assert!(ActionFilter::new().codes([code]).matches(first));
+    }
+}
+
+#[test]
+fn test_action_filter_build_orders_on_fixture() {
+    use w3g_parser::{ActionFilter, Replay};
+
+    let replay = Replay::from_path(Path::new(FIXTURES_DIR).join("replay_10000.w3g")).unwrap();
+    let code = replay
+        .actions
+        .iter()
+        .find_map(|a| match a.action_type {
+            ActionType::UnitAbilityNoTarget { ability_code, .. } => {
+                Some(w3g_parser::AbilityCode::from_raw(ability_code))
+            }
+            _ => None,
+        })
+        .filter(|code| code.is_valid_fourcc())
+        .expect("fixture has a FourCC build order")
+        .as_string();
+
+    let query = format!("type=UnitAbilityNoTarget and code={code}");
+    let filter: ActionFilter = query.parse().unwrap();
+    let selected = replay.actions.iter().filter(|a| filter.matches(a)).count();
+    let expected = replay
+        .actions
+        .iter()
+        .filter(|a| {
+            matches!(a.action_type, ActionType::UnitAbilityNoTarget { .. })
+                && a.action_type.object_code().map(|c| c.as_string()) == Some(code.clone())
+        })
+        .count();
+    assert!(selected > 0);
+    assert_eq!(selected, expected);
+
+    let err = "type=BuildTrain".parse::<ActionFilter>().unwrap_err();
+    assert!(err.to_string().contains("BuildTrain"));
+}
@@ -0,0 +1,88 @@
+//! Integration tests for replay anonymisation.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::records::Record;
+use w3g_parser::{anonymize, AnonymizeOptions, ChatPolicy, Replay};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture_bytes(filename: &str) -> Vec<u8> {
+    std::fs::read(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to read fixture")
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle.as_bytes())
+}
+
+fn chat_records(replay: &Replay) -> Vec<Record> {
+    replay
+        .records()
+        .unwrap()
+        .map(|result| result.unwrap().1)
+        .filter(|record| matches!(record, Record::Chat { .. }))
+        .collect()
+}
+
+/// Anonymises a fixture and checks that no original name survives.
+fn assert_anonymized(filename: &str) {
+    let data = fixture_bytes(filename);
+    let original = Replay::from_bytes(&data).expect("Failed to parse original");
+    let options = AnonymizeOptions::new("test-salt");
+
+    let output = anonymize(&data, &options).expect("Failed to anonymize");
+    let replay = Replay::from_bytes(&output).expect("Failed to parse anonymised replay");
+
+    assert_eq!(
+        replay.game.host_name(),
+        options.pseudonym(original.game.host_name())
+    );
+    assert_eq!(replay.game.player_count(), original.game.player_count());
+    assert_eq!(replay.frame_count, original.frame_count);
+    assert_eq!(chat_records(&replay).len(), chat_records(&original).len());
+
+    let mut names = original.game.player_names();
+    names.push(original.game.host_name());
+    for name in names.into_iter().filter(|name| name.len() >= 3) {
+        assert!(
+            !contains(replay.decompressed(), name),
+            "{filename}: {name:?} survived anonymisation"
+        );
+    }
+
+    assert_eq!(anonymize(&data, &options).unwrap(), output);
+}
+
+#[test]
+fn test_anonymize_classic() {
+    assert_anonymized("replay_10000.w3g");
+}
+
+#[test]
+fn test_anonymize_reforged_battle_tags() {
+    assert_anonymized("replay_85000.w3g");
+}
+
+#[test]
+fn test_anonymize_grbn() {
+    assert_anonymized("replay_1.w3g");
+}
+
+#[test]
+fn test_anonymize_chat_policies() {
+    let data = fixture_bytes("replay_10000.w3g");
+
+    let options = AnonymizeOptions::new("test-salt").chat(ChatPolicy::Drop);
+    let replay = Replay::from_bytes(&anonymize(&data, &options).unwrap()).unwrap();
+    assert!(chat_records(&replay).is_empty());
+
+    let options = AnonymizeOptions::new("test-salt").chat(ChatPolicy::Redact);
+    let replay = Replay::from_bytes(&anonymize(&data, &options).unwrap()).unwrap();
+    let chat = chat_records(&replay);
+    assert!(!chat.is_empty());
+    assert!(chat
+        .iter()
+        .all(|record| record.chat_text().as_deref() == Some("[redacted]")));
+}
@@ -0,0 +1,211 @@
+//! Integration tests for finding and reading batch inputs.
+//!
+//! Tests copy fixture replays from ../tests/fixtures/ into a nested temporary
+//! directory and pack some of them into zip and tar.gz archives.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "archives")]
+use w3g_parser::batch::read_sources;
+use w3g_parser::batch::{collect_sources, InputOptions, ReplaySource};
+#[cfg(feature = "archives")]
+use w3g_parser::Replay;
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture_bytes(filename: &str) -> Vec<u8> {
+    fs::read(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to read fixture")
+}
+
+/// Creates an empty scratch directory unique to `name`.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("w3g-batch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Lays out `season/month/replay` directories plus a stray non-replay file.
+fn nested_tree(name: &str) -> PathBuf {
+    let root = scratch_dir(name);
+    for (dir, fixture) in [
+        ("2024-01", "replay_10000.w3g"),
+        ("2024-02/finals", "replay_5000.w3g"),
+        ("2023-12", "replay_1.w3g"),
+    ] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        fs::write(root.join(dir).join(fixture), fixture_bytes(fixture)).unwrap();
+    }
+    fs::write(root.join("2024-01/notes.txt"), "not a replay").unwrap();
+    root
+}
+
+fn relative_paths(sources: &[ReplaySource]) -> Vec<String> {
+    sources
+        .iter()
+        .map(|s| s.relative.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn test_collect_sources_top_level_only() {
+    let root = nested_tree("flat");
+    fs::write(root.join("top.w3g"), fixture_bytes("replay_1.w3g")).unwrap();
+
+    let sources = collect_sources(&[root], &InputOptions::new()).unwrap();
+    assert_eq!(relative_paths(&sources), ["top.w3g"]);
+}
+
+#[test]
+fn test_collect_sources_recursive_with_globs() {
+    let root = nested_tree("recursive");
+
+    let options = InputOptions::new().recursive(true);
+    let sources = collect_sources(std::slice::from_ref(&root), &options).unwrap();
+    assert_eq!(
+        relative_paths(&sources),
+        [
+            "2023-12/replay_1.w3g",
+            "2024-01/replay_10000.w3g",
+            "2024-02/finals/replay_5000.w3g"
+        ]
+    );
+
+    let options = InputOptions::new()
+        .recursive(true)
+        .include("2024-*/*.w3g")
+        .unwrap()
+        .exclude("**/finals/**")
+        .unwrap();
+    let sources = collect_sources(&[root], &options).unwrap();
+    assert_eq!(relative_paths(&sources), ["2024-01/replay_10000.w3g"]);
+}
+
+#[test]
+fn test_collect_sources_missing_input() {
+    let missing = scratch_dir("missing").join("nope.w3g");
+    assert!(collect_sources(&[missing], &InputOptions::new()).is_err());
+}
+
+#[cfg(feature = "archives")]
+mod archives {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const PACKED: [&str; 2] = ["replay_10000.w3g", "replay_5000.w3g"];
+
+    fn write_zip(path: &Path) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.add_directory("season/", options).unwrap();
+        for fixture in PACKED {
+            zip.start_file(format!("season/{}", fixture), options).unwrap();
+            zip.write_all(&fixture_bytes(fixture)).unwrap();
+        }
+        zip.start_file("season/readme.txt", options).unwrap();
+        zip.write_all(b"not a replay").unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path) {
+        let encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for fixture in PACKED {
+            let data = fixture_bytes(fixture);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("season/{}", fixture), data.as_slice())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn read_all(sources: &[ReplaySource]) -> Vec<Vec<u8>> {
+        let mut data = vec![Vec::new(); sources.len()];
+        read_sources(sources, |index, result| {
+            data[index] = result.expect("Failed to read source");
+            true
+        })
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_read_replays_from_archives() {
+        let root = scratch_dir("archives");
+        write_zip(&root.join("pack.zip"));
+        write_tar_gz(&root.join("bundle.tar.gz"));
+
+        let sources = collect_sources(&[root], &InputOptions::new()).unwrap();
+        assert_eq!(sources.len(), 4);
+        assert!(sources.iter().all(|s| s.archive.is_some()));
+
+        for (source, data) in sources.iter().zip(read_all(&sources)) {
+            let fixture = source.path.file_name().unwrap().to_str().unwrap();
+            assert_eq!(data, fixture_bytes(fixture), "{}", source);
+            Replay::from_bytes(&data).expect("Failed to parse archived replay");
+        }
+
+        let outputs: Vec<PathBuf> = sources.iter().map(ReplaySource::output_path).collect();
+        assert!(outputs.contains(&Path::new("pack").join("season/replay_10000.w3g")));
+    }
+
+    #[test]
+    fn test_archive_entries_filtered_by_glob() {
+        let root = scratch_dir("archive-globs");
+        let archive = root.join("pack.zip");
+        write_zip(&archive);
+
+        let options = InputOptions::new().exclude("**/replay_5000.w3g").unwrap();
+        let sources = collect_sources(&[archive], &options).unwrap();
+        assert_eq!(relative_paths(&sources), ["season/replay_10000.w3g"]);
+
+        // Stopping early reads no further entries
+        let mut visited = 0;
+        let all = collect_sources(&[root], &InputOptions::new()).unwrap();
+        read_sources(&all, |_, _| {
+            visited += 1;
+            false
+        })
+        .unwrap();
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn test_archive_entries_escaping_output_dir_skipped() {
+        let root = scratch_dir("archive-traversal");
+        let data = fixture_bytes("replay_1.w3g");
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(root.join("evil.zip")).unwrap());
+        for name in ["../escape.w3g", "/tmp/absolute.w3g", "safe/game.w3g"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let mut tar = tar::Builder::new(fs::File::create(root.join("evil.tar")).unwrap());
+        for name in ["../escape.w3g", "safe/game.w3g"] {
+            // `Header::set_path` refuses `..`, so the name is written raw
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append(&header, data.as_slice()).unwrap();
+        }
+        tar.into_inner().unwrap();
+
+        let sources = collect_sources(&[root], &InputOptions::new()).unwrap();
+        assert_eq!(relative_paths(&sources), ["safe/game.w3g", "safe/game.w3g"]);
+        for (source, data) in sources.iter().zip(read_all(&sources)) {
+            assert_eq!(data, fixture_bytes("replay_1.w3g"), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Integration tests for the unknown action census.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::collections::HashSet;
+use std::path::Path;
+use w3g_parser::census::UnknownCensus;
+use w3g_parser::{ActionStatistics, Replay};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+/// Classic and Reforged fixtures of different builds.
+const FIXTURES: &[&str] = &["replay_5000.w3g", "replay_10000.w3g", "replay_1000.w3g"];
+
+fn census(samples: usize) -> UnknownCensus {
+    let mut census = UnknownCensus::new().samples(samples);
+    for filename in FIXTURES {
+        let replay = Replay::from_path(Path::new(FIXTURES_DIR).join(filename))
+            .expect("Failed to parse replay");
+        census.add(filename, &replay);
+    }
+    census
+}
+
+#[test]
+fn test_census_totals() {
+    let census = census(2);
+    assert_eq!(census.totals.replays, FIXTURES.len());
+    // Every action in the fixtures has a known layout
+    assert_eq!(census.totals.unknown_actions, 0);
+    assert!(census.totals.actions > 0);
+
+    let builds = census.builds.values();
+    assert_eq!(builds.clone().map(|b| b.replays).sum::<usize>(), FIXTURES.len());
+    assert_eq!(builds.clone().map(|b| b.actions).sum::<usize>(), census.totals.actions);
+    assert!(census.builds.contains_key("Reforged"));
+
+    let patterns = census.patterns();
+    let unknown: usize = patterns.iter().map(|p| p.count).sum();
+    assert_eq!(unknown, census.totals.unknown_actions);
+    assert!(patterns.windows(2).all(|w| w[0].count >= w[1].count));
+}
+
+#[test]
+fn test_census_matches_action_statistics() {
+    for filename in FIXTURES {
+        let replay = Replay::from_path(Path::new(FIXTURES_DIR).join(filename))
+            .expect("Failed to parse replay");
+        let mut stats = ActionStatistics::new();
+        replay.actions.iter().for_each(|action| stats.record(action));
+
+        let mut census = UnknownCensus::new();
+        census.add(filename, &replay);
+        assert_eq!(census.totals.actions, stats.total_actions as usize, "{filename}");
+        assert_eq!(census.totals.unknown_actions, stats.unknown_actions as usize, "{filename}");
+        assert!((census.totals.unknown_rate() - stats.error_rate()).abs() < f64::EPSILON);
+    }
+}
+
+#[test]
+fn test_census_patterns() {
+    let census = census(2);
+    for pattern in census.patterns() {
+        assert!(pattern.replays >= 1 && pattern.replays <= FIXTURES.len());
+        assert_eq!(pattern.builds.values().sum::<usize>(), pattern.count);
+        assert_eq!(pattern.preceding.values().sum::<usize>(), pattern.count);
+        assert_eq!(pattern.following.values().sum::<usize>(), pattern.count);
+
+        assert_eq!(pattern.samples.len(), pattern.replays.min(2));
+        let sources: HashSet<_> = pattern.samples.iter().map(|s| &s.source).collect();
+        assert_eq!(sources.len(), pattern.samples.len());
+        let type_byte = format!("{:02X}", pattern.key.type_id);
+        assert!(pattern.samples.iter().all(|s| s.hex.starts_with(&type_byte)));
+    }
+}
@@ -0,0 +1,183 @@
+//! Integration tests for comparing two replays of the same game.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/. Doctored
+//! perspectives are built by rewriting a fixture's records with the writer.
+
+use std::path::Path;
+use w3g_parser::compare::{DivergenceKind, IdentityMismatch, Side};
+use w3g_parser::records::Record;
+use w3g_parser::{compare, trim, Header, Replay, ReplayWriter, TrimOptions};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture_bytes(filename: &str) -> Vec<u8> {
+    std::fs::read(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to read fixture")
+}
+
+/// Rewrites every record of a Classic replay with `edit`.
+fn rewrite(data: &[u8], mut edit: impl FnMut(usize, Record) -> Vec<Record>) -> Vec<u8> {
+    let replay = Replay::from_bytes(data).unwrap();
+    let Header::Classic(header) = &replay.header else {
+        panic!("expected a Classic replay");
+    };
+    let mut writer = ReplayWriter::new(header.clone());
+    writer.write_game_header(&replay.game.header);
+    for (index, result) in replay.records().unwrap().enumerate() {
+        for record in edit(index, result.unwrap().1) {
+            writer.write_record(&record);
+        }
+    }
+    writer.finish().unwrap()
+}
+
+/// Returns the index of the first `TimeFrame` record with command data.
+fn first_frame_with_commands(data: &[u8]) -> usize {
+    let replay = Replay::from_bytes(data).unwrap();
+    replay
+        .records()
+        .unwrap()
+        .position(|result| {
+            matches!(result.unwrap().1, Record::TimeFrame { commands, .. } if !commands.is_empty())
+        })
+        .expect("No TimeFrame with commands")
+}
+
+#[test]
+fn test_compare_identical_replays() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let replay = Replay::from_bytes(&data).unwrap();
+
+    let comparison = compare(&replay, &replay).unwrap();
+    assert!(comparison.is_consistent());
+    assert_eq!(comparison.frames_compared, comparison.left_frame_count);
+    assert_eq!(comparison.overlap_ms, 691_680);
+}
+
+#[test]
+fn test_compare_different_games() {
+    let left = Replay::from_bytes(&fixture_bytes("replay_10000.w3g")).unwrap();
+    let right = Replay::from_bytes(&fixture_bytes("replay_5000.w3g")).unwrap();
+
+    let comparison = compare(&left, &right).unwrap();
+    assert!(!comparison.is_same_game());
+    assert!(comparison
+        .identity_mismatches
+        .iter()
+        .any(|m| matches!(m, IdentityMismatch::RandomSeed { .. })));
+    assert!(comparison
+        .identity_mismatches
+        .iter()
+        .any(|m| matches!(m, IdentityMismatch::Roster { .. })));
+}
+
+#[test]
+fn test_compare_shorter_perspective_is_consistent() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let short = trim(&data, &TrimOptions::new(0..=60_000)).unwrap();
+
+    let left = Replay::from_bytes(&data).unwrap();
+    let right = Replay::from_bytes(&short).unwrap();
+    let comparison = compare(&left, &right).unwrap();
+    assert!(comparison.is_consistent());
+    assert!(comparison.overlap_ms <= 60_000);
+    assert!(comparison.left_frame_count > comparison.right_frame_count);
+}
+
+#[test]
+fn test_compare_doctored_actions() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let target = first_frame_with_commands(&data);
+    let doctored = rewrite(&data, |index, record| match record {
+        Record::TimeFrame {
+            marker,
+            time_increment_ms,
+            mut commands,
+        } if index == target => {
+            *commands.last_mut().unwrap() ^= 0xFF;
+            vec![Record::TimeFrame {
+                marker,
+                time_increment_ms,
+                commands,
+            }]
+        }
+        other => vec![other],
+    });
+
+    let left = Replay::from_bytes(&data).unwrap();
+    let right = Replay::from_bytes(&doctored).unwrap();
+    let comparison = compare(&left, &right).unwrap();
+    assert!(comparison.is_same_game());
+    assert_eq!(comparison.divergences.len(), 1);
+    let DivergenceKind::ActionsDiffer { player_ids } = &comparison.divergences[0].kind else {
+        panic!("expected differing actions");
+    };
+    // Only the last player's block of the frame was changed
+    assert_eq!(player_ids.len(), 1);
+}
+
+#[test]
+fn test_compare_checksum_mismatch() {
+    // replay_10000 predates per-frame checksum records
+    let data = fixture_bytes("replay_85000.w3g");
+    let mut changed = false;
+    let doctored = rewrite(&data, |_, record| match record {
+        Record::Checksum { mut data } if !changed && data.len() == 4 => {
+            changed = true;
+            data[0] ^= 0xFF;
+            vec![Record::Checksum { data }]
+        }
+        other => vec![other],
+    });
+
+    let left = Replay::from_bytes(&data).unwrap();
+    let right = Replay::from_bytes(&doctored).unwrap();
+    let comparison = compare(&left, &right).unwrap();
+    assert_eq!(comparison.divergences.len(), 1);
+    assert!(matches!(
+        comparison.divergences[0].kind,
+        DivergenceKind::ChecksumMismatch { left, right } if left ^ right == 0xFF
+    ));
+}
+
+#[test]
+fn test_compare_missing_frame() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let mut split = false;
+    // Split the first empty frame in two, adding a frame the original lacks
+    let doctored = rewrite(&data, |_, record| match record {
+        Record::TimeFrame {
+            marker,
+            time_increment_ms,
+            commands,
+        } if !split && commands.is_empty() && time_increment_ms >= 2 => {
+            split = true;
+            let half = time_increment_ms / 2;
+            vec![
+                Record::TimeFrame {
+                    marker,
+                    time_increment_ms: half,
+                    commands: Vec::new(),
+                },
+                Record::TimeFrame {
+                    marker,
+                    time_increment_ms: time_increment_ms - half,
+                    commands,
+                },
+            ]
+        }
+        other => vec![other],
+    });
+
+    let left = Replay::from_bytes(&data).unwrap();
+    let right = Replay::from_bytes(&doctored).unwrap();
+    let comparison = compare(&left, &right).unwrap();
+    assert_eq!(comparison.right_frame_count, comparison.left_frame_count + 1);
+    assert_eq!(comparison.divergences.len(), 1);
+    assert_eq!(
+        comparison.divergences[0].kind,
+        DivergenceKind::MissingFrame {
+            missing_from: Side::Left
+        }
+    );
+}
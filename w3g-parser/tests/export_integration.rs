@@ -0,0 +1,127 @@
+//! Integration tests for the player and action tables.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/.
+
+use std::path::Path;
+use w3g_parser::export::{PlayerCsvWriter, ReplayTables, PLAYER_CSV_COLUMNS};
+use w3g_parser::Replay;
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn tables(filename: &str) -> ReplayTables {
+    let replay =
+        Replay::from_path(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to parse fixture");
+    ReplayTables::from_replay(&replay, filename).expect("Failed to build tables")
+}
+
+#[test]
+fn test_slot_table_matches_players() {
+    let replay = Replay::from_path(Path::new(FIXTURES_DIR).join("replay_10000.w3g")).unwrap();
+    let slots = replay.slot_table().unwrap().expect("replay has a slot table");
+    assert_eq!(slots.observer_team(), slots.slots.len());
+
+    let tables = ReplayTables::from_replay(&replay, "replay_10000.w3g").unwrap();
+    for player in &tables.players {
+        let slot = slots.player_slot(player.player_id).expect("player has a slot");
+        assert_eq!(slots.is_observer(slot), player.observer, "{}", player.name);
+    }
+    assert!(tables.players.iter().any(|p| p.observer));
+}
+
+#[test]
+fn test_player_rows() {
+    let tables = tables("replay_10000.w3g");
+    assert_eq!(tables.duration_ms, 691_680);
+    assert!(tables.players.windows(2).all(|w| w[0].player_id < w[1].player_id));
+
+    let total: usize = tables.players.iter().map(|p| p.actions).sum();
+    assert_eq!(total, tables.actions.len());
+
+    for player in tables.players.iter().filter(|p| !p.observer) {
+        assert!(player.race.is_some(), "{} has no race", player.name);
+        assert!(player.team.is_some());
+        assert!(player.apm > 0.0);
+    }
+}
+
+#[test]
+fn test_action_rows_in_game_order() {
+    for fixture in ["replay_1.w3g", "replay_10000.w3g", "replay_5000.w3g"] {
+        let tables = tables(fixture);
+        assert!(!tables.actions.is_empty(), "{}", fixture);
+        assert!(tables.actions.windows(2).all(|w| w[0].time_ms <= w[1].time_ms));
+        assert!(tables.actions.iter().all(|a| a.time_ms <= tables.duration_ms));
+        assert!(tables.actions.iter().any(|a| a.code.is_some()));
+        assert!(tables.actions.iter().any(|a| a.x.is_some() && a.y.is_some()));
+    }
+}
+
+#[test]
+fn test_action_rows_match_replay_actions() {
+    for fixture in ["replay_1.w3g", "replay_10000.w3g", "replay_5000.w3g"] {
+        let replay = Replay::from_path(Path::new(FIXTURES_DIR).join(fixture)).unwrap();
+        let tables = ReplayTables::from_replay(&replay, fixture).unwrap();
+
+        for player in &tables.players {
+            let actions = replay.player_actions(player.player_id).count();
+            assert_eq!(player.actions, actions, "{fixture}: {}", player.name);
+        }
+        let rows: Vec<_> = replay
+            .actions
+            .iter()
+            .filter(|action| tables.players.iter().any(|p| p.player_id == action.player_id))
+            .collect();
+        assert_eq!(rows.len(), tables.actions.len(), "{fixture}");
+        assert!(rows
+            .iter()
+            .zip(&tables.actions)
+            .all(|(action, row)| action.timestamp_ms == row.time_ms
+                && action.action_type.type_name() == row.action_type));
+    }
+}
+
+#[test]
+fn test_player_csv() {
+    let first = tables("replay_10000.w3g");
+    let second = tables("replay_5000.w3g");
+
+    let mut csv = PlayerCsvWriter::new(Vec::new()).unwrap();
+    csv.write(&first).unwrap();
+    csv.write(&second).unwrap();
+    let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(PLAYER_CSV_COLUMNS.join(",").as_str()));
+    assert_eq!(lines.count(), first.players.len() + second.players.len());
+    assert!(csv.contains("\nreplay_5000.w3g,"));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_action_ipc_round_trip() {
+    use arrow_array::{Array, StringArray, UInt32Array};
+    use arrow_ipc::reader::FileReader;
+    use std::io::Cursor;
+    use w3g_parser::export::{action_schema, ActionIpcWriter};
+
+    let tables = tables("replay_10000.w3g");
+    let mut writer = ActionIpcWriter::new(Vec::new()).unwrap();
+    writer.write(&tables).unwrap();
+    let data = writer.finish().unwrap();
+
+    let reader = FileReader::try_new(Cursor::new(data), None).unwrap();
+    assert_eq!(*reader.schema(), action_schema());
+    let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert_eq!(batches.len(), 1);
+
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), tables.actions.len());
+    let times = batch.column(2).as_any().downcast_ref::<UInt32Array>().unwrap();
+    assert_eq!(times.value(0), tables.actions[0].time_ms);
+    let codes = batch.column(4).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(
+        codes.iter().filter(Option::is_some).count(),
+        tables.actions.iter().filter(|a| a.code.is_some()).count()
+    );
+}
@@ -0,0 +1,133 @@
+//! Integration tests for the record tree behind the inspect subcommand.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::inspect::{Node, NodeKind, RecordTree};
+use w3g_parser::Replay;
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+/// One Classic and one Reforged fixture.
+const FIXTURES: &[&str] = &["replay_5000.w3g", "replay_1000.w3g"];
+
+fn build_tree(filename: &str) -> (Vec<u8>, Replay, RecordTree) {
+    let data = std::fs::read(Path::new(FIXTURES_DIR).join(filename))
+        .expect("Failed to read fixture");
+    let replay = Replay::from_bytes(&data).expect("Failed to parse replay");
+    let tree = RecordTree::build(&data, &replay);
+    (data, replay, tree)
+}
+
+/// Asserts that the children of `node` lie inside it, in order, without
+/// overlapping, and recurses into them.
+fn assert_nested(node: &Node) {
+    let mut cursor = node.offset;
+    for child in &node.children {
+        assert!(
+            child.offset >= cursor && child.end() <= node.end(),
+            "{} (0x{:X}+{}) is misplaced in {} (0x{:X}+{})",
+            child.label,
+            child.offset,
+            child.len,
+            node.label,
+            node.offset,
+            node.len
+        );
+        cursor = child.end();
+        assert_nested(child);
+    }
+}
+
+#[test]
+fn test_tree_nesting() {
+    for filename in FIXTURES {
+        let (data, replay, tree) = build_tree(filename);
+        assert_eq!(tree.header.kind, NodeKind::Header);
+        assert_eq!(tree.header.offset, 0);
+        assert!(tree.header.end() <= data.len());
+        assert_nested(&tree.header);
+
+        assert_eq!(tree.stream.kind, NodeKind::Stream);
+        assert_eq!(tree.stream.len, replay.decompressed().len());
+        assert_nested(&tree.stream);
+    }
+}
+
+#[test]
+fn test_stream_coverage() {
+    for filename in FIXTURES {
+        let (_, replay, tree) = build_tree(filename);
+
+        // The top-level nodes of the stream leave no gaps
+        let mut cursor = 0;
+        for child in &tree.stream.children {
+            assert_eq!(child.offset, cursor, "{filename}: gap before {}", child.label);
+            cursor = child.end();
+        }
+        assert_eq!(cursor, replay.decompressed().len());
+
+        let kinds: Vec<NodeKind> = tree.stream.children.iter().map(|n| n.kind).collect();
+        assert!(kinds.contains(&NodeKind::GameRecordHeader), "{filename}: no record header");
+        assert!(kinds.contains(&NodeKind::TimeFrame), "{filename}: no time frames");
+    }
+}
+
+#[test]
+fn test_path_to_action() {
+    let (_, _, tree) = build_tree("replay_1000.w3g");
+
+    let action = find(&tree.stream, &|node| {
+        node.kind == NodeKind::Action && !node.unknown
+    })
+    .expect("No known action in the replay");
+    let path = tree.stream.path_to(action.offset);
+    let kinds: Vec<NodeKind> = path.iter().map(|n| n.kind).collect();
+    assert_eq!(
+        kinds,
+        [NodeKind::Stream, NodeKind::TimeFrame, NodeKind::CommandBlock, NodeKind::Action]
+    );
+    assert!(path[2].player_id.is_some());
+    assert_eq!(path[3].player_id, path[2].player_id);
+}
+
+#[test]
+fn test_annotations_cover_stream() {
+    for filename in FIXTURES {
+        let (_, replay, tree) = build_tree(filename);
+        let annotations = tree.stream.annotations();
+
+        let mut cursor = 0;
+        for annotation in &annotations {
+            assert_eq!(annotation.offset, cursor, "{filename}: gap at {}", annotation.path);
+            assert!(annotation.len > 0, "{filename}: empty range at {}", annotation.path);
+            assert_eq!(annotation.claimed, annotation.kind != NodeKind::Unparsed);
+            cursor = annotation.end();
+        }
+        assert_eq!(cursor, replay.decompressed().len());
+
+        let header = annotations
+            .iter()
+            .find(|a| a.path == "GameRecordHeader[0].host_slot")
+            .expect("No host slot annotation");
+        assert!(header.claimed && !header.unknown);
+        assert!(annotations.iter().any(|a| a.path.contains("/CommandBlock[0]/Action[0]")));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_tree_serializes() {
+    let (_, _, tree) = build_tree("replay_5000.w3g");
+    let json = serde_json::to_value(&tree).expect("Failed to serialize the tree");
+    assert_eq!(json["header"]["kind"], "Header");
+    assert_eq!(json["stream"]["offset"], 0);
+}
+
+fn find<'n>(node: &'n Node, predicate: &dyn Fn(&Node) -> bool) -> Option<&'n Node> {
+    if predicate(node) {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| find(child, predicate))
+}
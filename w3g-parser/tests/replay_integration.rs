@@ -0,0 +1,258 @@
+//! Integration tests for the high-level `Replay` facade.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::records::Record;
+use w3g_parser::{
+    trim, ParseMode, ParseOptions, ParserError, Replay, ReplayWriter, Severity, TrimOptions,
+};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture_path(filename: &str) -> std::path::PathBuf {
+    Path::new(FIXTURES_DIR).join(filename)
+}
+
+/// Rewrites replay_10000 with the first action of the `frame`th `TimeFrame`
+/// with commands replaced by a selection that overruns its command block.
+fn corrupted_fixture(frame: usize) -> Vec<u8> {
+    let replay = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+    let header = replay.header.as_classic().expect("Expected a Classic fixture");
+
+    let mut writer = ReplayWriter::new(header.clone());
+    writer.write_game_header(&replay.game.header);
+    let mut index = 0;
+    for result in replay.records().expect("Failed to frame records") {
+        let (_, mut record) = result.expect("Failed to frame records");
+        if let Record::TimeFrame { commands, .. } = &mut record {
+            if commands.len() >= 7 {
+                index += 1;
+                if index == frame {
+                    // A selection of 0xFFFF units overruns any command block
+                    commands[3..7].copy_from_slice(&[0x16, 0x01, 0xFF, 0xFF]);
+                }
+            }
+        }
+        writer.write_record(&record);
+    }
+    writer.finish().expect("Failed to write replay")
+}
+
+/// Rewrites replay_10000 with a `TimeFrame` too short for its time field
+/// inserted before the `frame`th `TimeFrame`.
+fn broken_frame_fixture(frame: usize) -> Vec<u8> {
+    let replay = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+    let header = replay.header.as_classic().expect("Expected a Classic fixture");
+
+    let mut writer = ReplayWriter::new(header.clone());
+    writer.write_game_header(&replay.game.header);
+    let mut index = 0;
+    for result in replay.records().expect("Failed to frame records") {
+        let (_, record) = result.expect("Failed to frame records");
+        if let Record::TimeFrame { .. } = record {
+            index += 1;
+            if index == frame {
+                writer.write_bytes(&[0x1F, 0x01, 0x00]);
+            }
+        }
+        writer.write_record(&record);
+    }
+    writer.finish().expect("Failed to write replay")
+}
+
+#[test]
+fn test_replay_matches_manual_pipeline() {
+    let replay = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+
+    let manual_actions: usize = replay
+        .game
+        .timeframes(replay.decompressed())
+        .flatten()
+        .map(|frame| frame.actions().flatten().count())
+        .sum();
+
+    assert_eq!(replay.actions.len(), manual_actions);
+    assert!(replay.frame_count > 0);
+    assert!(replay
+        .actions
+        .iter()
+        .all(|a| a.timestamp_ms <= replay.duration_ms));
+}
+
+#[test]
+fn test_replay_duration_matches_header() {
+    for filename in ["replay_5000.w3g", "replay_10000.w3g"] {
+        let replay = Replay::from_path(fixture_path(filename)).expect("Failed to parse");
+        let header = replay.header.as_classic().expect("Expected a Classic fixture");
+
+        assert_eq!(replay.duration_ms, header.duration_ms, "{filename}");
+        let last_action = replay.actions.last().expect("Expected actions");
+        assert!(last_action.timestamp_ms <= replay.duration_ms, "{filename}");
+    }
+}
+
+#[test]
+fn test_replay_chat_and_leaves() {
+    let replay = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+
+    assert_eq!(replay.chat.len(), 33);
+    let framed = replay
+        .records()
+        .unwrap()
+        .filter(|r| matches!(r, Ok((_, Record::Chat { .. }))))
+        .count();
+    assert_eq!(replay.chat.len(), framed);
+    let first = &replay.chat[0];
+    assert!(first.message.is_system_message());
+    assert_eq!(
+        first.message.message,
+        "Shortest load by player [JUSTANOTHERORC] was 2.51 seconds."
+    );
+    assert!(replay.chat.iter().any(|c| c.message.message == "1 элем такой же баланс"));
+    assert!(replay
+        .chat
+        .windows(2)
+        .all(|w| w[0].timestamp_ms <= w[1].timestamp_ms));
+
+    assert!(!replay.leaves.is_empty());
+    assert!(replay.leaves.iter().all(|l| l.record.is_valid()));
+}
+
+#[test]
+fn test_replay_parse_options_skip() {
+    let options = ParseOptions::new().actions(false).chat(false);
+    let replay = Replay::from_path_with(fixture_path("replay_10000.w3g"), &options)
+        .expect("Failed to parse");
+    let full = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+
+    assert!(replay.actions.is_empty());
+    assert!(replay.chat.is_empty());
+    assert_eq!(replay.frame_count, full.frame_count);
+    assert_eq!(replay.duration_ms, full.duration_ms);
+    assert_eq!(replay.game.player_count(), full.game.player_count());
+}
+
+#[test]
+fn test_replay_lenient_and_strict_modes() {
+    let data = corrupted_fixture(100);
+    let lenient = Replay::from_bytes(&data).expect("Lenient parsing should succeed");
+
+    let errors: Vec<_> = lenient
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+    assert!(!errors.is_empty());
+    let data_len = lenient.decompressed().len();
+    assert!(lenient
+        .diagnostics
+        .iter()
+        .all(|d| d.offset < data_len && d.context.starts_with("timeframe #")));
+
+    let options = ParseOptions::new().mode(ParseMode::Strict);
+    assert!(Replay::from_bytes_with(&data, &options).is_err());
+
+    // Without actions, the TimeFrame stream itself parses cleanly
+    let options = options.actions(false);
+    let strict = Replay::from_bytes_with(&data, &options).expect("Strict parsing should succeed");
+    assert!(!strict.has_diagnostics());
+    assert_eq!(strict.frame_count, lenient.frame_count);
+}
+
+#[test]
+fn test_lenient_mode_resyncs_after_broken_timeframe() {
+    let original = Replay::from_path(fixture_path("replay_10000.w3g")).expect("Failed to parse");
+    let data = broken_frame_fixture(100);
+    let lenient = Replay::from_bytes(&data).expect("Lenient parsing should succeed");
+
+    let errors: Vec<_> = lenient
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].context, "timeframe #100");
+    assert!(errors[0].message.ends_with("(3 bytes skipped)"));
+
+    assert_eq!(lenient.frame_count, original.frame_count);
+    assert_eq!(lenient.duration_ms, original.duration_ms);
+    assert_eq!(lenient.actions.len(), original.actions.len());
+    assert_eq!(lenient.leaves.len(), original.leaves.len());
+
+    let options = ParseOptions::new().mode(ParseMode::Strict);
+    let err = Replay::from_bytes_with(&data, &options).expect_err("Strict parsing should fail");
+    assert!(matches!(err, ParserError::UnexpectedRecord { found: 0x1F, .. }));
+    assert_eq!(err.offset(), Some(errors[0].offset));
+}
+
+#[test]
+fn test_strict_error_has_absolute_offset_and_path() {
+    let data = corrupted_fixture(100);
+    let lenient = Replay::from_bytes(&data).expect("Lenient parsing should succeed");
+
+    let options = ParseOptions::new().mode(ParseMode::Strict);
+    let err = Replay::from_bytes_with(&data, &options).expect_err("Strict parsing should fail");
+
+    match &err {
+        ParserError::InvalidAction {
+            offset,
+            path,
+            player_id,
+            type_byte,
+        } => {
+            assert_eq!(lenient.decompressed()[*offset], *type_byte);
+            assert!(path.starts_with("timeframe #"));
+            assert!(path.contains(&format!(" / player {player_id} / action #")));
+        }
+        other => panic!("Expected InvalidAction, got {other:?}"),
+    }
+
+    // The same failure is the first error collected in lenient mode
+    let first = lenient
+        .diagnostics
+        .iter()
+        .find(|d| d.severity == Severity::Error)
+        .expect("Expected an error diagnostic");
+    assert_eq!(Some(first.offset), err.offset());
+    assert_eq!(Some(first.context.as_str()), err.path());
+}
+
+#[test]
+fn test_game_id_is_stable_across_perspectives() {
+    let path = fixture_path("replay_10000.w3g");
+    let data = std::fs::read(&path).unwrap();
+    let replay = Replay::from_bytes(&data).unwrap();
+    let id = replay.game_id().expect("Failed to compute game ID");
+    assert_eq!(id.len(), 64);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+
+    // A perspective that ends earlier still covers the hashed frames
+    let shorter = trim(&data, &TrimOptions::new(0..=120_000)).unwrap();
+    assert_eq!(Replay::from_bytes(&shorter).unwrap().game_id().unwrap(), id);
+
+    // Parse options do not affect the record stream
+    let options = ParseOptions::new().actions(false).chat(false);
+    let metadata_only = Replay::from_path_with(&path, &options).unwrap();
+    assert_eq!(metadata_only.game_id().unwrap(), id);
+}
+
+#[test]
+fn test_game_id_differs_between_games() {
+    let fixtures = [
+        "replay_1.w3g",
+        "replay_1000.w3g",
+        "replay_5000.w3g",
+        "replay_10000.w3g",
+        "replay_50000.w3g",
+        "replay_85000.w3g",
+    ];
+    let mut ids: Vec<String> = fixtures
+        .iter()
+        .map(|name| Replay::from_path(fixture_path(name)).unwrap().game_id().unwrap())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), fixtures.len());
+}
@@ -0,0 +1,114 @@
+//! Integration tests for HTML match reports.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::report::MatchReport;
+use w3g_parser::Replay;
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn report(filename: &str) -> MatchReport {
+    let replay = Replay::from_path(Path::new(FIXTURES_DIR).join(filename))
+        .expect("Failed to parse replay");
+    MatchReport::from_replay(&replay, filename).expect("Failed to build report")
+}
+
+#[test]
+fn test_report_contents() {
+    for filename in ["replay_5000.w3g", "replay_1000.w3g"] {
+        let report = report(filename);
+        assert!(report.duration_ms > 0);
+        assert!(report.game_id.is_some());
+        assert!(!report.players.is_empty());
+
+        let minutes = report.duration_ms.div_ceil(60_000) as usize;
+        for player in &report.players {
+            assert_eq!(player.apm_by_minute.len(), minutes);
+            let actions: u32 = player.apm_by_minute.iter().sum();
+            assert_eq!(actions as usize, player.player.actions);
+        }
+
+        let mut last = 0;
+        for line in &report.chat {
+            assert!(line.time_ms >= last && line.time_ms <= report.duration_ms);
+            assert!(!line.message.ends_with('\0'));
+            last = line.time_ms;
+        }
+    }
+}
+
+#[test]
+fn test_report_events_match_replay() {
+    let replay = Replay::from_path(Path::new(FIXTURES_DIR).join("replay_10000.w3g"))
+        .expect("Failed to parse replay");
+    let report = MatchReport::from_replay(&replay, "replay_10000.w3g").unwrap();
+
+    assert_eq!(report.chat.len(), replay.chat.len());
+    assert!(report.chat.iter().zip(&replay.chat).all(|(line, event)| {
+        line.time_ms == event.timestamp_ms
+            && line.player_id == event.message.sender_slot
+            && line.message == event.message.message
+    }));
+    assert!(report.chat.iter().any(|line| line.player_id.is_none()));
+    assert_eq!(report.leaves.len(), replay.leaves.len());
+    assert!(report
+        .leaves
+        .iter()
+        .zip(&replay.leaves)
+        .all(|(line, event)| line.time_ms == event.timestamp_ms
+            && line.player_id == event.record.player_id));
+}
+
+#[test]
+fn test_report_html() {
+    let report = report("replay_5000.w3g");
+    let html = report.to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    for heading in ["Players", "APM", "Build and Skill Orders", "Chat", "Leaves"] {
+        assert!(html.contains(&format!("<h2>{heading}</h2>")), "missing {heading}");
+    }
+
+    // Self-contained: no external scripts, styles or images
+    assert!(!html.contains(" src="));
+    assert!(!html.contains("<link"));
+    let players = report.players.iter().filter(|p| !p.player.observer).count();
+    assert_eq!(html.matches("<polyline").count(), players);
+}
+
+#[test]
+fn test_report_without_actions_fails() {
+    use w3g_parser::replay::ParseOptions;
+
+    let options = ParseOptions::new().actions(false);
+    let replay = Replay::from_path_with(Path::new(FIXTURES_DIR).join("replay_5000.w3g"), &options)
+        .expect("Failed to parse replay");
+    assert!(MatchReport::from_replay(&replay, "replay_5000.w3g").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_report_command() {
+    use std::process::Command;
+
+    let filename = "replay_10000.w3g";
+    let output = std::env::temp_dir().join(format!("w3g-report-{}.html", std::process::id()));
+    let run = Command::new(env!("CARGO_BIN_EXE_w3g-parser"))
+        .arg("report")
+        .arg(Path::new(FIXTURES_DIR).join(filename))
+        .arg("--output")
+        .arg(&output)
+        .output()
+        .expect("Failed to run w3g-parser");
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    let html = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_file(&output).ok();
+
+    let report = report(filename);
+    assert!(report.players.iter().any(|p| p.player.apm > 0.0));
+    assert!(report.players.iter().any(|p| !p.build_order.is_empty()));
+    assert!(!report.chat.is_empty());
+    assert_eq!(html, report.to_html());
+}
@@ -0,0 +1,146 @@
+//! Integration tests for the SQLite export.
+//!
+//! Tests import fixture replays from ../tests/fixtures/ into in-memory and
+//! temporary databases.
+
+#![cfg(feature = "sqlite")]
+
+use std::path::Path;
+use w3g_parser::export::ReplayTables;
+use w3g_parser::sqlite::{ReplayDatabase, Upsert};
+use w3g_parser::trim::{trim, TrimOptions};
+use w3g_parser::Replay;
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture(filename: &str) -> Replay {
+    Replay::from_path(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to parse fixture")
+}
+
+fn count(db: &ReplayDatabase, sql: &str) -> usize {
+    db.connection().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn test_rows_match_parsed_replay() {
+    let replay = fixture("replay_5000.w3g");
+    let tables = ReplayTables::from_replay(&replay, "replay_5000.w3g").unwrap();
+    let slots = replay.slot_table().unwrap().unwrap();
+
+    let mut db = ReplayDatabase::open_in_memory().unwrap();
+    assert_eq!(db.upsert(&replay, "replay_5000.w3g").unwrap(), Upsert::Inserted);
+
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM players"), tables.players.len());
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM slots"), slots.slots.len());
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM actions"), tables.actions.len());
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM leaves"), replay.leaves.len());
+    assert!(count(&db, "SELECT COUNT(*) FROM chat") > 0);
+    // System messages have no sender
+    assert_eq!(
+        count(&db, "SELECT COUNT(*) FROM chat WHERE player_id IS NULL"),
+        replay.chat.iter().filter(|c| c.message.sender_slot.is_none()).count()
+    );
+    assert!(count(&db, "SELECT COUNT(*) FROM chat WHERE player_id IS NULL") > 0);
+
+    let (game_id, map_path, duration): (String, String, u32) = db
+        .connection()
+        .query_row("SELECT game_id, map_path, duration_ms FROM replays", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .unwrap();
+    assert_eq!(game_id, replay.game_id().unwrap());
+    assert!(map_path.ends_with("EchoIsles.w3x"), "{}", map_path);
+    assert_eq!(duration, tables.duration_ms);
+
+    // Action counts per player agree with the players table
+    let mismatched = count(
+        &db,
+        "SELECT COUNT(*) FROM players p WHERE p.actions != (SELECT COUNT(*) FROM actions a
+             WHERE a.replay_id = p.replay_id AND a.player_id = p.player_id)",
+    );
+    assert_eq!(mismatched, 0);
+}
+
+#[test]
+fn test_upsert_replaces_game_by_fingerprint() {
+    let path = std::env::temp_dir().join(format!("w3g-sqlite-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let first = fixture("replay_10000.w3g");
+    let second = fixture("replay_1.w3g");
+    let mut db = ReplayDatabase::open(&path).unwrap();
+    assert_eq!(db.upsert(&first, "a/replay_10000.w3g").unwrap(), Upsert::Inserted);
+    assert_eq!(db.upsert(&second, "replay_1.w3g").unwrap(), Upsert::Inserted);
+    let actions = count(&db, "SELECT COUNT(*) FROM actions");
+    drop(db);
+
+    // Re-importing the game, here from another path, replaces its rows
+    let mut db = ReplayDatabase::open(&path).unwrap();
+    assert_eq!(db.upsert(&first, "b/replay_10000.w3g").unwrap(), Upsert::Replaced);
+    assert_eq!(db.replay_count().unwrap(), 2);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM actions"), actions);
+    assert_eq!(
+        count(&db, "SELECT COUNT(*) FROM replays WHERE source = 'b/replay_10000.w3g'"),
+        1
+    );
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_upsert_keeps_longest_perspective() {
+    let data = std::fs::read(Path::new(FIXTURES_DIR).join("replay_10000.w3g")).unwrap();
+    let full = Replay::from_bytes(&data).unwrap();
+    // A trimmed copy stands in for the replay of a player who left early
+    let short = trim(&data, &TrimOptions::new(0..=60_000)).unwrap();
+    let short = Replay::from_bytes(&short).unwrap();
+    assert_eq!(short.game_id().unwrap(), full.game_id().unwrap());
+    assert!(short.duration_ms < full.duration_ms);
+
+    let mut db = ReplayDatabase::open_in_memory().unwrap();
+    assert_eq!(db.upsert(&full, "full.w3g").unwrap(), Upsert::Inserted);
+    assert_eq!(db.upsert(&short, "short.w3g").unwrap(), Upsert::Kept);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM replays WHERE source = 'full.w3g'"), 1);
+
+    let mut db = ReplayDatabase::open_in_memory().unwrap();
+    assert_eq!(db.upsert(&short, "short.w3g").unwrap(), Upsert::Inserted);
+    assert_eq!(db.upsert(&full, "full.w3g").unwrap(), Upsert::Replaced);
+    assert_eq!(count(&db, "SELECT COUNT(*) FROM replays WHERE source = 'full.w3g'"), 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_export_sqlite_command() {
+    use std::process::Command;
+
+    let path = std::env::temp_dir().join(format!("w3g-sqlite-cli-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let run = Command::new(env!("CARGO_BIN_EXE_w3g-parser"))
+        .arg("export-sqlite")
+        .arg(Path::new(FIXTURES_DIR).join("replay_5000.w3g"))
+        .arg("--db")
+        .arg(&path)
+        .output()
+        .expect("Failed to run w3g-parser");
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+
+    let replay = fixture("replay_5000.w3g");
+    let mut expected = ReplayDatabase::open_in_memory().unwrap();
+    expected.upsert(&replay, "replay_5000.w3g").unwrap();
+
+    let db = ReplayDatabase::open(&path).unwrap();
+    for table in ["players", "slots", "chat", "leaves", "actions", "build_orders"] {
+        let sql = format!("SELECT COUNT(*) FROM {table}");
+        assert_eq!(count(&db, &sql), count(&expected, &sql), "{table}");
+    }
+    for sql in [
+        "SELECT COUNT(*) FROM actions",
+        "SELECT COUNT(*) FROM build_orders",
+        "SELECT COUNT(*) FROM players WHERE apm > 0",
+    ] {
+        assert!(count(&db, sql) > 0, "{sql}");
+    }
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,129 @@
+//! Integration tests for trimming replays to a time range.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::header::classic::ClassicHeader;
+use w3g_parser::records::Record;
+use w3g_parser::{trim, LeadIn, Replay, TrimOptions};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+/// Window used by most tests: 01:00 to 03:00.
+const START_MS: u32 = 60_000;
+const END_MS: u32 = 180_000;
+
+fn fixture_bytes(filename: &str) -> Vec<u8> {
+    std::fs::read(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to read fixture")
+}
+
+/// Returns the records of a replay paired with the game time after each one.
+///
+/// The record stream is used instead of `Replay::actions` because its
+/// timestamps come from exact record framing.
+fn timed_records(data: &[u8]) -> Vec<(u32, Record)> {
+    let replay = Replay::from_bytes(data).expect("Failed to parse replay");
+    let mut time_ms = 0;
+    replay
+        .records()
+        .unwrap()
+        .map(|result| {
+            let (_, record) = result.expect("Failed to frame record");
+            time_ms += record.time_increment_ms();
+            (time_ms, record)
+        })
+        .collect()
+}
+
+fn trimmed(data: &[u8], lead_in: LeadIn) -> Vec<u8> {
+    let options = TrimOptions::new(START_MS..=END_MS).lead_in(lead_in);
+    trim(data, &options).expect("Failed to trim")
+}
+
+#[test]
+fn test_trim_full_range_is_identity() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let output = trim(&data, &TrimOptions::new(0..=u32::MAX)).unwrap();
+
+    let original = Replay::from_bytes(&data).unwrap();
+    let replay = Replay::from_bytes(&output).unwrap();
+    assert_eq!(replay.decompressed(), original.decompressed());
+}
+
+#[test]
+fn test_trim_keep_lead_in() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let original = timed_records(&data);
+    let output = trimmed(&data, LeadIn::Keep);
+
+    let header = ClassicHeader::parse(&output).unwrap();
+    assert!(header.checksum_is_valid());
+    assert!(header.duration_ms <= END_MS);
+    assert!(header.duration_ms > END_MS - 1000);
+
+    // Everything up to the end of the window is kept unchanged
+    let records = timed_records(&output);
+    assert_eq!(records, original[..records.len()]);
+    assert!(records.iter().all(|(ms, _)| *ms <= END_MS));
+    assert!(original[records.len()].0 > END_MS);
+}
+
+#[test]
+fn test_trim_empty_lead_in_keeps_timestamps() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let output = trimmed(&data, LeadIn::Empty);
+
+    let keep = ClassicHeader::parse(&trimmed(&data, LeadIn::Keep)).unwrap();
+    let header = ClassicHeader::parse(&output).unwrap();
+    assert_eq!(header.duration_ms, keep.duration_ms);
+    assert!(output.len() < data.len());
+
+    for (ms, record) in timed_records(&output) {
+        match record {
+            Record::TimeFrame { commands, .. } if !commands.is_empty() => {
+                assert!((START_MS..=END_MS).contains(&ms));
+            }
+            Record::Chat { .. } | Record::Checksum { .. } => assert!(ms >= START_MS),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_trim_drop_lead_in_starts_at_zero() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let original = timed_records(&data);
+    let output = trimmed(&data, LeadIn::Drop);
+
+    // The first kept frame's increment may start before the window
+    let header = ClassicHeader::parse(&output).unwrap();
+    assert!(header.duration_ms < END_MS - START_MS + 1000);
+    assert!(header.duration_ms > END_MS - START_MS - 1000);
+
+    let frames = |records: &[(u32, Record)]| {
+        records
+            .iter()
+            .filter(|(_, record)| matches!(record, Record::TimeFrame { .. }))
+            .count()
+    };
+    let in_window = original
+        .iter()
+        .filter(|(_, record)| matches!(record, Record::TimeFrame { .. }))
+        .filter(|(ms, _)| (START_MS..=END_MS).contains(ms))
+        .count();
+    assert_eq!(frames(&timed_records(&output)), in_window);
+}
+
+#[test]
+fn test_trim_grbn_keeps_container() {
+    let data = fixture_bytes("replay_1.w3g");
+    let original = Replay::from_bytes(&data).unwrap();
+    let output = trimmed(&data, LeadIn::Keep);
+
+    let replay = Replay::from_bytes(&output).expect("Failed to parse trimmed GRBN replay");
+    assert_eq!(replay.header.format(), original.header.format());
+    assert_eq!(replay.game.player_names(), original.game.player_names());
+    let records = timed_records(&output);
+    assert!(records.last().unwrap().0 <= END_MS);
+}
@@ -0,0 +1,117 @@
+//! Integration tests for writing replays back to Classic `.w3g` files.
+//!
+//! Tests run against the fixture replays in ../tests/fixtures/
+
+use std::path::Path;
+use w3g_parser::decompress::classic::BlockHeader;
+use w3g_parser::format::CLASSIC_MAGIC;
+use w3g_parser::header::classic::{ClassicHeader, CLASSIC_DATA_OFFSET};
+use w3g_parser::records::{Record, RecordStream};
+use w3g_parser::writer::block_checksum;
+use w3g_parser::{ParserError, Replay, ReplayWriter};
+
+/// Path to the fixture replays shipped with the repository.
+const FIXTURES_DIR: &str = "../tests/fixtures";
+
+fn fixture_bytes(filename: &str) -> Vec<u8> {
+    std::fs::read(Path::new(FIXTURES_DIR).join(filename)).expect("Failed to read fixture")
+}
+
+/// Returns the embedded Classic replay inside a GRBN file.
+fn embedded_classic(data: &[u8]) -> &[u8] {
+    let start = data
+        .windows(CLASSIC_MAGIC.len())
+        .position(|w| w == CLASSIC_MAGIC)
+        .expect("No embedded Classic replay");
+    &data[start..]
+}
+
+/// Checks that every stored checksum in a Classic file matches the computed one.
+fn assert_checksums_valid(data: &[u8]) {
+    let header = ClassicHeader::parse(data).unwrap();
+    assert!(header.checksum_is_valid());
+
+    let mut offset = CLASSIC_DATA_OFFSET;
+    for _ in 0..header.block_count {
+        let block = BlockHeader::parse(data, offset, header.version_type()).unwrap();
+        let checksum_at = offset + block.header_size - 4;
+        let mut block_header = data[offset..checksum_at + 4].to_vec();
+        block_header[block.header_size - 4..].fill(0);
+
+        let compressed_start = offset + block.header_size;
+        let compressed = &data[compressed_start..compressed_start + block.compressed_size as usize];
+        let stored = u32::from_le_bytes(data[checksum_at..checksum_at + 4].try_into().unwrap());
+        assert_eq!(block_checksum(&block_header, compressed), stored);
+
+        offset = compressed_start + compressed.len();
+    }
+}
+
+fn assert_round_trip(original: &[u8]) {
+    let replay = Replay::from_bytes(original).expect("Failed to parse original");
+    let writer = ReplayWriter::from_replay(&replay).expect("Failed to build writer");
+    let expected_duration = writer.duration_ms();
+    let written = writer.finish().expect("Failed to write");
+
+    assert_checksums_valid(&written);
+
+    let before = ClassicHeader::parse(original).unwrap();
+    let after = ClassicHeader::parse(&written).unwrap();
+    assert_eq!(after.file_size as usize, written.len());
+    assert_eq!(after.decompressed_size, before.decompressed_size);
+    assert_eq!(after.block_count, before.block_count);
+    assert_eq!(after.build_version, before.build_version);
+    assert_eq!(after.duration_ms, expected_duration);
+
+    let rewritten = Replay::from_bytes(&written).expect("Failed to parse written replay");
+    assert_eq!(rewritten.decompressed(), replay.decompressed());
+    assert_eq!(rewritten.game.player_names(), replay.game.player_names());
+    assert_eq!(rewritten.actions.len(), replay.actions.len());
+}
+
+#[test]
+fn test_fixture_checksums_match_computed() {
+    assert_checksums_valid(&fixture_bytes("replay_10000.w3g"));
+    assert_checksums_valid(embedded_classic(&fixture_bytes("replay_1000.w3g")));
+}
+
+#[test]
+fn test_round_trip_classic_type_a() {
+    assert_round_trip(&fixture_bytes("replay_10000.w3g"));
+}
+
+#[test]
+fn test_round_trip_classic_type_b() {
+    // replay_50000 has Reforged extended metadata records before the slot table
+    assert_round_trip(&fixture_bytes("replay_50000.w3g"));
+    assert_round_trip(&fixture_bytes("replay_85000.w3g"));
+}
+
+#[test]
+fn test_round_trip_embedded_classic() {
+    assert_round_trip(embedded_classic(&fixture_bytes("replay_1.w3g")));
+}
+
+#[test]
+fn test_record_stream_covers_action_stream() {
+    let data = fixture_bytes("replay_10000.w3g");
+    let header = ClassicHeader::parse(&data).unwrap();
+    let replay = Replay::from_bytes(&data).unwrap();
+    let records: Vec<_> = RecordStream::new(replay.decompressed(), replay.game.timeframe_offset)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to frame records");
+
+    let duration: u32 = records.iter().map(|(_, r)| r.time_increment_ms()).sum();
+    let chats = records.iter().filter(|(_, r)| matches!(r, Record::Chat { .. })).count();
+    assert_eq!(duration, header.duration_ms);
+    assert_eq!(chats, 31);
+}
+
+#[test]
+fn test_writer_rejects_grbn() {
+    let replay = Replay::from_bytes(&fixture_bytes("replay_1.w3g")).unwrap();
+    assert!(matches!(
+        ReplayWriter::from_replay(&replay),
+        Err(ParserError::WriteError { .. })
+    ));
+}